@@ -4,8 +4,14 @@ export PATH=~/dev/sdk/vulkansdk-macos-1.2.131.2/macOS/bin/glslc:$PATH
 glslc sprite.vert -o sprite.vert.spv
 glslc sprite.frag -o sprite.frag.spv
 
+glslc text.vert -o text.vert.spv
+glslc text.frag -o text.frag.spv
+
 glslc mesh.vert -o mesh.vert.spv
 glslc mesh.frag -o mesh.frag.spv
+glslc mesh_depth_prepass.vert -o mesh_depth_prepass.vert.spv
+glslc mesh_wireframe.frag -o mesh_wireframe.frag.spv
+glslc mesh_cull.comp -o mesh_cull.comp.spv
 
 glslc debug.vert -o debug.vert.spv
 glslc debug.frag -o debug.frag.spv
@@ -19,5 +25,12 @@ glslc bloom_blur.frag -o bloom_blur.frag.spv
 glslc bloom_combine.vert -o bloom_combine.vert.spv
 glslc bloom_combine.frag -o bloom_combine.frag.spv
 
+glslc outline_mask.vert -o outline_mask.vert.spv
+glslc outline_mask.frag -o outline_mask.frag.spv
+glslc outline_composite.vert -o outline_composite.vert.spv
+glslc outline_composite.frag -o outline_composite.frag.spv
+glslc picking.vert -o picking.vert.spv
+glslc picking.frag -o picking.frag.spv
+
 glslc imgui.vert -o imgui.vert.spv
 glslc imgui.frag -o imgui.frag.spv
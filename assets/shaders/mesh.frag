@@ -15,6 +15,19 @@
 
 const float PI = 3.14159265359;
 
+// Must match MeshDebugViewMode
+const uint DEBUG_VIEW_MODE_NONE = 0;
+const uint DEBUG_VIEW_MODE_BASE_COLOR = 1;
+const uint DEBUG_VIEW_MODE_VERTEX_NORMALS = 2;
+const uint DEBUG_VIEW_MODE_MAPPED_NORMALS = 3;
+const uint DEBUG_VIEW_MODE_ROUGHNESS = 4;
+const uint DEBUG_VIEW_MODE_METALLIC = 5;
+const uint DEBUG_VIEW_MODE_OCCLUSION = 6;
+const uint DEBUG_VIEW_MODE_EMISSIVE = 7;
+const uint DEBUG_VIEW_MODE_UVS = 8;
+const uint DEBUG_VIEW_MODE_LIGHT_COUNT = 9;
+const uint DEBUG_VIEW_MODE_WIREFRAME = 10;
+
 //
 // Per-Frame Pass
 //
@@ -49,6 +62,7 @@ layout (set = 0, binding = 0) uniform PerFrameData {
     uint point_light_count;
     uint directional_light_count;
     uint spot_light_count;
+    uint debug_view_mode;
     PointLight point_lights[16];
     DirectionalLight directional_lights[16];
     SpotLight spot_lights[16];
@@ -92,6 +106,7 @@ layout (location = 1) in vec3 in_normal_vs;
 layout (location = 2) in vec3 in_tangent_vs;
 layout (location = 3) in vec3 in_binormal_vs;
 layout (location = 4) in vec2 in_uv;
+layout (location = 5) in vec4 in_debug_color;
 
 // Force early depth testing, this is likely not strictly necessary
 layout(early_fragment_tests) in;
@@ -588,6 +603,47 @@ vec4 pbr_path(
 
 
 
+//
+// Debug views
+//
+uint count_lights(vec3 surface_position_vs) {
+    uint light_count = per_frame_data.directional_light_count;
+
+    for (uint i = 0; i < per_frame_data.point_light_count; ++i) {
+        PointLight light = per_frame_data.point_lights[i];
+        if (distance(light.position_vs, surface_position_vs) < light.range) {
+            light_count += 1;
+        }
+    }
+
+    for (uint i = 0; i < per_frame_data.spot_light_count; ++i) {
+        SpotLight light = per_frame_data.spot_lights[i];
+        vec3 surface_to_light_dir = light.position_vs - surface_position_vs;
+        float distance = length(surface_to_light_dir);
+        surface_to_light_dir = surface_to_light_dir / distance;
+
+        float cone_falloff = spotlight_cone_falloff(
+            surface_to_light_dir,
+            light.direction_vs,
+            light.spotlight_half_angle
+        );
+
+        if (distance < light.range && cone_falloff > 0.0) {
+            light_count += 1;
+        }
+    }
+
+    return light_count;
+}
+
+// Blue for no lights, through green and yellow, to red for 8 or more
+vec3 heatmap(float t) {
+    t = clamp(t, 0.0, 1.0);
+    vec3 low = mix(vec3(0.0, 0.0, 1.0), vec3(0.0, 1.0, 0.0), clamp(t * 3.0, 0.0, 1.0));
+    vec3 mid = mix(low, vec3(1.0, 1.0, 0.0), clamp(t * 3.0 - 1.0, 0.0, 1.0));
+    return mix(mid, vec3(1.0, 0.0, 0.0), clamp(t * 3.0 - 2.0, 0.0, 1.0));
+}
+
 void main() {
     // Sample the base color, if it exists
     vec4 base_color = material_data_ubo.data.base_color_factor;
@@ -627,6 +683,39 @@ void main() {
 
     //TOOD: AO
 
+    // Output a single input of the lighting instead of the shaded color
+    uint debug_view_mode = per_frame_data.debug_view_mode;
+    if (debug_view_mode != DEBUG_VIEW_MODE_NONE && debug_view_mode != DEBUG_VIEW_MODE_WIREFRAME) {
+        vec3 debug_color = vec3(0.0);
+        if (debug_view_mode == DEBUG_VIEW_MODE_BASE_COLOR) {
+            debug_color = base_color.rgb;
+        } else if (debug_view_mode == DEBUG_VIEW_MODE_VERTEX_NORMALS) {
+            debug_color = normalize(in_normal_vs) * 0.5 + 0.5;
+        } else if (debug_view_mode == DEBUG_VIEW_MODE_MAPPED_NORMALS) {
+            debug_color = normal_vs * 0.5 + 0.5;
+        } else if (debug_view_mode == DEBUG_VIEW_MODE_ROUGHNESS) {
+            debug_color = vec3(roughness);
+        } else if (debug_view_mode == DEBUG_VIEW_MODE_METALLIC) {
+            debug_color = vec3(metalness);
+        } else if (debug_view_mode == DEBUG_VIEW_MODE_OCCLUSION) {
+            float occlusion = 1.0;
+            if (material_data_ubo.data.has_occlusion_texture) {
+                float sampled = texture(sampler2D(occlusion_texture, smp), in_uv).r;
+                occlusion = mix(1.0, sampled, material_data_ubo.data.occlusion_texture_strength);
+            }
+            debug_color = vec3(occlusion);
+        } else if (debug_view_mode == DEBUG_VIEW_MODE_EMISSIVE) {
+            debug_color = emissive_color.rgb;
+        } else if (debug_view_mode == DEBUG_VIEW_MODE_UVS) {
+            debug_color = vec3(fract(in_uv), 0.0);
+        } else if (debug_view_mode == DEBUG_VIEW_MODE_LIGHT_COUNT) {
+            debug_color = heatmap(float(count_lights(in_position_vs)) / 8.0);
+        }
+
+        out_color = vec4(mix(debug_color, in_debug_color.rgb, in_debug_color.a), 1.0);
+        return;
+    }
+
     vec3 eye_position_vs = vec3(0, 0, 0);
     vec3 surface_to_eye_vs = normalize(eye_position_vs - in_position_vs);
 
@@ -645,4 +734,6 @@ void main() {
         roughness,
         normal_vs
     );
+
+    out_color.rgb = mix(out_color.rgb, in_debug_color.rgb, in_debug_color.a);
 }
@@ -2,10 +2,17 @@
 #extension GL_ARB_separate_shader_objects : enable
 #extension GL_ARB_shading_language_420pack : enable
 
-layout(set = 2, binding = 0) uniform PerObjectData {
+struct PerObjectData {
     mat4 model_view;
     mat4 model_view_proj;
-} per_object_data;
+    // Mixed into the shaded color by alpha, used by debug views
+    vec4 debug_color;
+};
+
+// Instances are drawn in batches, gl_InstanceIndex includes the batch's first instance
+layout(std430, set = 2, binding = 0) readonly buffer PerInstanceData {
+    PerObjectData objects[];
+} per_instance_data;
 
 layout (location = 0) in vec3 in_pos;
 layout (location = 1) in vec3 in_normal;
@@ -21,8 +28,13 @@ layout (location = 1) out vec3 out_normal_vs;
 layout (location = 2) out vec3 out_tangent_vs;
 layout (location = 3) out vec3 out_binormal_vs;
 layout (location = 4) out vec2 out_uv;
+layout (location = 5) out vec4 out_debug_color;
+
+// Must match mesh_depth_prepass.vert so that depth-equal testing works when the prepass is enabled
+invariant gl_Position;
 
 void main() {
+    PerObjectData per_object_data = per_instance_data.objects[gl_InstanceIndex];
     gl_Position = per_object_data.model_view_proj * vec4(in_pos, 1.0);
     out_position_vs = (per_object_data.model_view * vec4(in_pos, 1.0)).xyz;
 
@@ -33,4 +45,5 @@ void main() {
     out_binormal_vs = mat3(per_object_data.model_view) * binormal;
 
     out_uv = in_uv;
+    out_debug_color = per_object_data.debug_color;
 }
@@ -0,0 +1,26 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+// Position-only variant of mesh.vert used to lay down depth before the opaque pass. The opaque
+// pass tests for depth equality, so gl_Position must be computed exactly the same way in both.
+struct PerObjectData {
+    mat4 model_view;
+    mat4 model_view_proj;
+    // Mixed into the shaded color by alpha, used by debug views
+    vec4 debug_color;
+};
+
+// Instances are drawn in batches, gl_InstanceIndex includes the batch's first instance
+layout(std430, set = 2, binding = 0) readonly buffer PerInstanceData {
+    PerObjectData objects[];
+} per_instance_data;
+
+layout (location = 0) in vec3 in_pos;
+
+invariant gl_Position;
+
+void main() {
+    PerObjectData per_object_data = per_instance_data.objects[gl_InstanceIndex];
+    gl_Position = per_object_data.model_view_proj * vec4(in_pos, 1.0);
+}
@@ -0,0 +1,16 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+// Used with mesh.vert and a pipeline that rasterizes triangle edges as lines. Only the interpolated
+// debug color is used, everything else mesh.vert outputs is ignored
+
+layout (location = 5) in vec4 in_debug_color;
+
+layout (location = 0) out vec4 out_color;
+
+void main() {
+    // Edges take on the LOD color when that debug view is also enabled
+    vec3 wireframe_color = vec3(0.9, 0.9, 0.9);
+    out_color = vec4(mix(wireframe_color, in_debug_color.rgb, in_debug_color.a), 1.0);
+}
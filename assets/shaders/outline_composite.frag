@@ -0,0 +1,44 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+
+layout (set = 0, binding = 0) uniform texture2D in_mask;
+layout (set = 0, binding = 1) uniform sampler smp;
+layout (set = 0, binding = 2) uniform OutlineParams {
+    vec4 selected_color;
+    vec4 hovered_color;
+    // How far outside the mask the outline extends
+    int thickness_in_pixels;
+} outline_params;
+
+layout (location = 0) in vec2 inUV;
+
+layout (location = 0) out vec4 out_color;
+
+void main()
+{
+    // The mask has red where selected objects were drawn and green where hovered objects were
+    // drawn. A pixel is on the outline if it is outside an object's mask but a nearby pixel is
+    // inside it
+    vec2 texel_size = 1.0 / vec2(textureSize(sampler2D(in_mask, smp), 0));
+    vec2 center = texture(sampler2D(in_mask, smp), inUV).rg;
+
+    int thickness = outline_params.thickness_in_pixels;
+    vec2 nearby = vec2(0.0);
+    for (int y = -thickness; y <= thickness; ++y) {
+        for (int x = -thickness; x <= thickness; ++x) {
+            vec2 offset = vec2(x, y) * texel_size;
+            nearby = max(nearby, texture(sampler2D(in_mask, smp), inUV + offset).rg);
+        }
+    }
+
+    vec2 edge = nearby * (vec2(1.0) - center);
+
+    // Selection takes priority over hover
+    if (edge.r > 0.0) {
+        out_color = vec4(outline_params.selected_color.rgb, outline_params.selected_color.a * edge.r);
+    } else if (edge.g > 0.0) {
+        out_color = vec4(outline_params.hovered_color.rgb, outline_params.hovered_color.a * edge.g);
+    } else {
+        out_color = vec4(0.0);
+    }
+}
@@ -0,0 +1,11 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout (location = 0) in vec4 in_mask_color;
+
+layout (location = 0) out vec4 out_mask;
+
+void main() {
+    out_mask = in_mask_color;
+}
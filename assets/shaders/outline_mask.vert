@@ -0,0 +1,20 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+// Draws a selected or hovered mesh into the outline mask. Uses the mesh vertex buffer, but only
+// the position is read
+layout(set = 0, binding = 0) uniform PerObjectData {
+    mat4 model_view_proj;
+    // Red for selected objects, green for hovered objects
+    vec4 mask_color;
+} per_object_data;
+
+layout (location = 0) in vec3 in_pos;
+
+layout (location = 0) out vec4 out_mask_color;
+
+void main() {
+    out_mask_color = per_object_data.mask_color;
+    gl_Position = per_object_data.model_view_proj * vec4(in_pos, 1.0);
+}
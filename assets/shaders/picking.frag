@@ -0,0 +1,13 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout (location = 0) flat in uint in_picking_id;
+
+// The ID and the depth, so the editor can tell whether the mesh is in front of other things under
+// the cursor
+layout (location = 0) out uvec2 out_picking_id;
+
+void main() {
+    out_picking_id = uvec2(in_picking_id, floatBitsToUint(gl_FragCoord.z));
+}
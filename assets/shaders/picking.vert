@@ -0,0 +1,20 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+// Draws a mesh with the ID the editor uses to find which entity is under the cursor. Uses the
+// mesh vertex buffer, but only the position is read
+layout(set = 0, binding = 0) uniform PerObjectData {
+    mat4 model_view_proj;
+    // Index into the frame's picking entity list plus one, so that 0 means nothing
+    uint picking_id;
+} per_object_data;
+
+layout (location = 0) in vec3 in_pos;
+
+layout (location = 0) flat out uint out_picking_id;
+
+void main() {
+    out_picking_id = per_object_data.picking_id;
+    gl_Position = per_object_data.model_view_proj * vec4(in_pos, 1.0);
+}
@@ -11,11 +11,13 @@ layout (set = 0, binding = 1) uniform sampler smp;
 layout (set = 1, binding = 0) uniform texture2D tex;
 
 layout (location = 0) in vec2 o_uv;
+// Sprite tint
+layout (location = 1) in vec4 o_color;
 
 layout (location = 0) out vec4 uFragColor;
 
 void main() {
     //vec4 color = texture(tex[0], o_uv);
     vec4 color = texture(sampler2D(tex, smp), o_uv);
-    uFragColor = color;
+    uFragColor = color * o_color;
 }
@@ -6,12 +6,15 @@ layout(set = 0, binding = 0) uniform Args {
     mat4 mvp;
 } uniform_buffer;
 
-layout (location = 0) in vec4 pos;
+layout (location = 0) in vec3 pos;
 layout (location = 1) in vec2 uv;
+layout (location = 2) in vec4 color;
 
 layout (location = 0) out vec2 o_uv;
+layout (location = 1) out vec4 o_color;
 
 void main() {
     o_uv = uv;
-    gl_Position = uniform_buffer.mvp * pos;
+    o_color = color;
+    gl_Position = uniform_buffer.mvp * vec4(pos, 1.0);
 }
@@ -0,0 +1,24 @@
+#version 450
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout (set = 0, binding = 1) uniform sampler smp;
+
+// Signed distance field, 0.5 is the edge of the glyph and higher values are inside
+layout (set = 1, binding = 0) uniform texture2D font_atlas;
+
+layout (location = 0) in vec2 o_uv;
+// Text color
+layout (location = 1) in vec4 o_color;
+
+layout (location = 0) out vec4 uFragColor;
+
+void main() {
+    float distance = texture(sampler2D(font_atlas, smp), o_uv).r;
+
+    // Antialias over roughly one pixel regardless of how large the text is on screen
+    float width = fwidth(distance);
+    float alpha = smoothstep(0.5 - width, 0.5 + width, distance);
+
+    uFragColor = vec4(o_color.rgb, o_color.a * alpha);
+}
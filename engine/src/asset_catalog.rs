@@ -0,0 +1,350 @@
+use atelier_assets::core::{AssetRef, AssetTypeId, AssetUuid};
+use atelier_assets::loader::handle::RefOp;
+use atelier_assets::loader::{LoadHandle, Loader};
+use crossbeam_channel::Sender;
+use fnv::{FnvHashMap, FnvHashSet};
+use legion::prelude::*;
+use minimum::resources::AssetResource;
+use serde::Deserialize;
+use std::cell::RefCell;
+use std::path::Path;
+use std::sync::Arc;
+use type_uuid::TypeUuid;
+use crate::assets::gltf::{GltfMaterialAsset, MeshAssetData};
+use crate::assets::sprite::SpriteSheetAsset;
+use crate::assets::font::FontAsset;
+use renderer::assets::{
+    ShaderAssetData, PipelineAssetData, RenderpassAssetData, MaterialAssetData,
+    MaterialInstanceAssetData, ImageAssetData, BufferAssetData,
+};
+use minimum::pipeline::PrefabAsset;
+
+// The daemon's default asset directory, relative to the working directory
+const ASSET_DIRECTORY: &str = "assets";
+
+// Type of the (empty) imgui drag-drop payload used when dragging an asset. The dragged asset is
+// stored in the catalog while the drag is in progress.
+pub const ASSET_DRAG_DROP_TYPE: &str = "ASSET";
+
+// The parts of a .meta file that we need. The daemon writes one of these next to every source
+// file it imports, listing the assets produced from it.
+#[derive(Deserialize)]
+struct SourceMetadata {
+    #[serde(default)]
+    assets: Vec<AssetMetadata>,
+}
+
+#[derive(Deserialize)]
+struct AssetMetadata {
+    id: AssetUuid,
+    #[serde(default)]
+    search_tags: Vec<(String, Option<String>)>,
+    #[serde(default)]
+    artifact: Option<ArtifactMetadata>,
+}
+
+#[derive(Deserialize)]
+struct ArtifactMetadata {
+    type_id: AssetTypeId,
+    #[serde(default)]
+    load_deps: Vec<AssetRef>,
+}
+
+// Names shown for the asset types this crate registers
+pub fn asset_type_name(asset_type: AssetTypeId) -> Option<&'static str> {
+    let names = [
+        (MeshAssetData::UUID, "Mesh"),
+        (ImageAssetData::UUID, "Image"),
+        (MaterialAssetData::UUID, "Material"),
+        (MaterialInstanceAssetData::UUID, "Material Instance"),
+        (GltfMaterialAsset::UUID, "glTF Material"),
+        (PrefabAsset::UUID, "Prefab"),
+        (SpriteSheetAsset::UUID, "Sprite Sheet"),
+        (FontAsset::UUID, "Font"),
+        (ShaderAssetData::UUID, "Shader"),
+        (PipelineAssetData::UUID, "Pipeline"),
+        (RenderpassAssetData::UUID, "Renderpass"),
+        (BufferAssetData::UUID, "Buffer"),
+    ];
+
+    names
+        .iter()
+        .find(|(uuid, _)| *uuid == asset_type.0)
+        .map(|(_, name)| *name)
+}
+
+pub struct AssetCatalogEntry {
+    pub id: AssetUuid,
+    pub asset_type: AssetTypeId,
+    // Source file the asset was imported from, relative to the asset directory
+    pub path: String,
+    // Name of the asset within the source file (e.g. a gltf mesh name) if it has one
+    pub name: Option<String>,
+    pub search_tags: Vec<(String, Option<String>)>,
+    // Assets that are loaded along with this one
+    pub load_deps: Vec<AssetUuid>,
+}
+
+impl AssetCatalogEntry {
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => format!("{} ({})", name, self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+// A load started on behalf of a picker widget. The widget takes ownership of the ref when it
+// creates its handle.
+struct PendingAssetLoad {
+    widget_id: AssetPickerWidgetId,
+    asset_id: AssetUuid,
+    load_handle: Option<LoadHandle>,
+    // Whether the widget was drawn since the last update. If it isn't (i.e. the selection
+    // changed) the load is dropped and its ref released.
+    drawn: bool,
+}
+
+// Picker widgets are identified by their full imgui ID (the hash of the ID stack they are drawn
+// with) and their asset type
+pub type AssetPickerWidgetId = (imgui::sys::ImGuiID, AssetTypeId);
+
+// Lists the assets in the daemon's metadata and creates handles for them. imgui-inspect does not
+// give render functions access to resources, so this is thread-local state that the inspector
+// widgets use directly and that editor_update_asset_catalog updates once per frame.
+#[derive(Default)]
+pub struct AssetCatalog {
+    entries: Vec<AssetCatalogEntry>,
+    scanned: bool,
+    rescan_requested: bool,
+    ref_op_sender: Option<Arc<Sender<RefOp>>>,
+    asset_ids: FnvHashMap<LoadHandle, AssetUuid>,
+    unresolved_load_handles: FnvHashSet<LoadHandle>,
+    pending_loads: Vec<PendingAssetLoad>,
+    dragged_asset: Option<AssetUuid>,
+    // Updates since a drag source last reported the dragged asset
+    dragged_asset_age: u32,
+}
+
+thread_local! {
+    static ASSET_CATALOG: RefCell<AssetCatalog> = RefCell::new(AssetCatalog::default());
+}
+
+impl AssetCatalog {
+    pub fn with<R, F: FnOnce(&mut AssetCatalog) -> R>(f: F) -> R {
+        ASSET_CATALOG.with(|catalog| (f)(&mut *catalog.borrow_mut()))
+    }
+
+    pub fn entries(&self) -> &[AssetCatalogEntry] {
+        &self.entries
+    }
+
+    pub fn entry(
+        &self,
+        asset_id: AssetUuid,
+    ) -> Option<&AssetCatalogEntry> {
+        self.entries.iter().find(|entry| entry.id == asset_id)
+    }
+
+    // Re-read the metadata on the next update so that newly imported assets are listed
+    pub fn request_rescan(&mut self) {
+        self.rescan_requested = true;
+    }
+
+    // Returns the asset a handle refers to. If it isn't known yet, it will be looked up on the
+    // next update.
+    pub fn asset_id(
+        &mut self,
+        load_handle: LoadHandle,
+    ) -> Option<AssetUuid> {
+        let asset_id = self.asset_ids.get(&load_handle).copied();
+        if asset_id.is_none() {
+            self.unresolved_load_handles.insert(load_handle);
+        }
+
+        asset_id
+    }
+
+    // Starts loading the asset for the given widget. The widget picks up the ref with
+    // take_loaded_asset() once it has been added.
+    pub fn request_load(
+        &mut self,
+        widget_id: AssetPickerWidgetId,
+        asset_id: AssetUuid,
+    ) {
+        self.pending_loads.retain(|x| x.widget_id != widget_id);
+        self.pending_loads.push(PendingAssetLoad {
+            widget_id,
+            asset_id,
+            load_handle: None,
+            drawn: true,
+        });
+    }
+
+    // Returns a load handle that holds a ref the caller now owns, along with the channel that a
+    // handle needs to release it. Widgets call this every time they are drawn to keep their load
+    // pending.
+    pub fn take_loaded_asset(
+        &mut self,
+        widget_id: AssetPickerWidgetId,
+    ) -> Option<(Arc<Sender<RefOp>>, LoadHandle)> {
+        let index = self
+            .pending_loads
+            .iter()
+            .position(|x| x.widget_id == widget_id)?;
+        self.pending_loads[index].drawn = true;
+
+        let ref_op_sender = self.ref_op_sender.clone()?;
+        self.pending_loads[index].load_handle?;
+        let pending_load = self.pending_loads.swap_remove(index);
+        let load_handle = pending_load.load_handle.unwrap();
+        self.asset_ids.insert(load_handle, pending_load.asset_id);
+        Some((ref_op_sender, load_handle))
+    }
+
+    // Drag sources call this every frame while the drag is in progress
+    pub fn set_dragged_asset(
+        &mut self,
+        asset_id: AssetUuid,
+    ) {
+        self.dragged_asset = Some(asset_id);
+        self.dragged_asset_age = 0;
+    }
+
+    pub fn dragged_asset(&self) -> Option<AssetUuid> {
+        self.dragged_asset
+    }
+
+    fn update(
+        &mut self,
+        asset_resource: &AssetResource,
+    ) {
+        if !self.scanned || self.rescan_requested {
+            self.entries = scan_asset_metadata(Path::new(ASSET_DIRECTORY));
+            self.scanned = true;
+            self.rescan_requested = false;
+        }
+
+        // A drop may be delivered on the frame after the source last reported the drag, and the
+        // source may have been drawn before this update
+        self.dragged_asset_age += 1;
+        if self.dragged_asset_age > 2 {
+            self.dragged_asset = None;
+        }
+
+        self.ref_op_sender = Some(asset_resource.tx().clone());
+
+        let loader = asset_resource.loader();
+        for load_handle in self.unresolved_load_handles.drain() {
+            if let Some(load_info) = loader.get_load_info(load_handle) {
+                self.asset_ids.insert(load_handle, load_info.asset_id);
+            }
+        }
+
+        self.pending_loads.retain(|pending_load| {
+            if !pending_load.drawn {
+                if let Some(load_handle) = pending_load.load_handle {
+                    loader.remove_ref(load_handle);
+                }
+            }
+            pending_load.drawn
+        });
+
+        for pending_load in &mut self.pending_loads {
+            pending_load.drawn = false;
+            if pending_load.load_handle.is_none() {
+                pending_load.load_handle = Some(loader.add_ref(pending_load.asset_id));
+            }
+        }
+    }
+}
+
+// Reads every .meta file under the asset directory. Files that fail to parse are skipped.
+fn scan_asset_metadata(directory: &Path) -> Vec<AssetCatalogEntry> {
+    let mut entries = vec![];
+    let mut directories = vec![directory.to_path_buf()];
+    while let Some(dir) = directories.pop() {
+        let read_dir = match std::fs::read_dir(&dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) => {
+                log::warn!("Could not read asset directory {:?}: {:?}", dir, e);
+                continue;
+            }
+        };
+
+        for dir_entry in read_dir.filter_map(|x| x.ok()) {
+            let path = dir_entry.path();
+            if path.is_dir() {
+                directories.push(path);
+                continue;
+            }
+
+            if path.extension().map(|x| x != "meta").unwrap_or(true) {
+                continue;
+            }
+
+            let metadata = std::fs::read_to_string(&path)
+                .ok()
+                .and_then(|x| ron::de::from_str::<SourceMetadata>(&x).ok());
+            let metadata = match metadata {
+                Some(metadata) => metadata,
+                None => {
+                    log::warn!("Could not parse asset metadata {:?}", path);
+                    continue;
+                }
+            };
+
+            // The source file is the .meta path without its extension
+            let source_path = path.with_extension("");
+            let source_path = source_path
+                .strip_prefix(directory)
+                .unwrap_or(&source_path)
+                .to_string_lossy()
+                .replace('\\', "/");
+
+            for asset in metadata.assets {
+                let artifact = match asset.artifact {
+                    Some(artifact) => artifact,
+                    None => continue,
+                };
+
+                let name = asset
+                    .search_tags
+                    .iter()
+                    .find(|(key, _)| key.ends_with("_name") && key != "file_name")
+                    .and_then(|(_, value)| value.clone());
+
+                let load_deps = artifact
+                    .load_deps
+                    .into_iter()
+                    .filter_map(|dep| match dep {
+                        AssetRef::Uuid(uuid) => Some(uuid),
+                        AssetRef::Path(_) => None,
+                    })
+                    .collect();
+
+                entries.push(AssetCatalogEntry {
+                    id: asset.id,
+                    asset_type: artifact.type_id,
+                    path: source_path.clone(),
+                    name,
+                    search_tags: asset.search_tags,
+                    load_deps,
+                });
+            }
+        }
+    }
+
+    entries.sort_by(|a, b| a.display_name().cmp(&b.display_name()));
+    entries
+}
+
+// Refreshes the asset catalog and resolves the loads requested by asset picker widgets during
+// the previous frame
+pub fn editor_update_asset_catalog(
+    _world: &mut World,
+    resources: &mut Resources,
+) {
+    let asset_resource = resources.get::<AssetResource>().unwrap();
+    AssetCatalog::with(|catalog| catalog.update(&*asset_resource));
+}
@@ -0,0 +1,126 @@
+use serde::{Deserialize, Serialize};
+use type_uuid::*;
+use atelier_assets::loader::handle::Handle;
+use renderer::assets::ImageAsset;
+
+/// Horizontal alignment of each line of text relative to the text's position
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum TextAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+impl Default for TextAlignment {
+    fn default() -> Self {
+        TextAlignment::Left
+    }
+}
+
+/// A single glyph's signed distance field within the font's atlas. All sizes are in pixels at the
+/// font's glyph_pixel_size
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FontGlyph {
+    pub codepoint: char,
+    // x, y, width, height in the atlas from the top-left corner, including the SDF padding. Empty
+    // for glyphs that draw nothing (i.e. space)
+    pub atlas_rect: [u32; 4],
+    // Bottom-left corner of the glyph's quad relative to the pen position on the baseline
+    pub offset: [f32; 2],
+    // How far the pen moves after this glyph
+    pub advance: f32,
+}
+
+/// A font imported from a TTF file. Glyphs are rasterized into a signed distance field atlas at
+/// import time so that text stays sharp at any size.
+#[derive(TypeUuid, Serialize, Deserialize, Clone)]
+#[uuid = "3a8f2c6d-91e4-4b7a-a5d0-6c2e8f1b7d43"]
+pub struct FontAsset {
+    pub atlas: Handle<ImageAsset>,
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    // Pixel size the glyphs were rasterized at
+    pub glyph_pixel_size: f32,
+    // Distance in pixels covered by the field on either side of a glyph's edge
+    pub sdf_spread: f32,
+    pub ascent: f32,
+    pub line_height: f32,
+    // Sorted by codepoint
+    pub glyphs: Vec<FontGlyph>,
+}
+
+/// A positioned glyph quad produced by FontAsset::layout_text
+#[derive(Copy, Clone, Debug)]
+pub struct TextLayoutGlyph {
+    // Bottom-left and top-right corners of the quad
+    pub min: glam::Vec2,
+    pub max: glam::Vec2,
+    // Texture coordinates of the top-left and bottom-right corners of the quad
+    pub uv_min: glam::Vec2,
+    pub uv_max: glam::Vec2,
+}
+
+impl FontAsset {
+    pub fn glyph(
+        &self,
+        codepoint: char,
+    ) -> Option<&FontGlyph> {
+        self.glyphs
+            .binary_search_by_key(&codepoint, |glyph| glyph.codepoint)
+            .ok()
+            .map(|index| &self.glyphs[index])
+    }
+
+    /// Lays out the text with lines of the given height. The origin is at the top of the first
+    /// line, and each line is aligned horizontally around it. +Y is up, so lines go down. Characters
+    /// not in the font are drawn as '?'
+    pub fn layout_text(
+        &self,
+        text: &str,
+        size: f32,
+        alignment: TextAlignment,
+    ) -> Vec<TextLayoutGlyph> {
+        let scale = size / self.line_height;
+        let atlas_size = glam::Vec2::new(self.atlas_width as f32, self.atlas_height as f32);
+        let fallback_glyph = self.glyph('?');
+
+        let mut layout = Vec::with_capacity(text.len());
+        for (line_index, line) in text.lines().enumerate() {
+            let glyphs: Vec<_> = line
+                .chars()
+                .filter_map(|c| self.glyph(c).or(fallback_glyph))
+                .collect();
+
+            let line_width: f32 = glyphs.iter().map(|glyph| glyph.advance).sum::<f32>() * scale;
+            let mut pen_x = match alignment {
+                TextAlignment::Left => 0.0,
+                TextAlignment::Center => -line_width / 2.0,
+                TextAlignment::Right => -line_width,
+            };
+            let baseline_y = -(self.ascent + line_index as f32 * self.line_height) * scale;
+
+            for glyph in glyphs {
+                let rect = glyph.atlas_rect;
+                if rect[2] > 0 && rect[3] > 0 {
+                    let min = glam::Vec2::new(
+                        pen_x + glyph.offset[0] * scale,
+                        baseline_y + glyph.offset[1] * scale,
+                    );
+                    let size = glam::Vec2::new(rect[2] as f32, rect[3] as f32);
+                    let atlas_position = glam::Vec2::new(rect[0] as f32, rect[1] as f32);
+
+                    layout.push(TextLayoutGlyph {
+                        min,
+                        max: min + size * scale,
+                        uv_min: atlas_position / atlas_size,
+                        uv_max: (atlas_position + size) / atlas_size,
+                    });
+                }
+
+                pen_x += glyph.advance * scale;
+            }
+        }
+
+        layout
+    }
+}
@@ -0,0 +1,257 @@
+use atelier_assets::core::{AssetUuid, AssetRef};
+use atelier_assets::importer::{Error, ImportedAsset, Importer, ImporterValue, SourceFileImporter};
+use serde::{Deserialize, Serialize};
+use type_uuid::*;
+use std::io::Read;
+use crate::assets::font::{FontAsset, FontGlyph};
+use renderer::assets::assets::{ImageAssetData, ColorSpace};
+use renderer::assets::ImageAsset;
+use atelier_assets::loader::handle::{Handle, SerdeContext};
+
+// Glyphs are rasterized at this size. Larger sizes keep sharper corners when magnified but make
+// the atlas bigger
+const FONT_GLYPH_PIXEL_SIZE: f32 = 48.0;
+
+// Distance in pixels the field extends past a glyph's edge. Also the padding around each glyph
+const FONT_SDF_SPREAD: usize = 6;
+
+const FONT_ATLAS_WIDTH: usize = 512;
+
+#[derive(TypeUuid, Serialize, Deserialize, Default, Clone)]
+#[uuid = "5b0e7d14-2f6a-4c83-9e1b-d47a3c9f6e28"]
+pub struct FontImporterState {
+    font_id: Option<AssetUuid>,
+    atlas_id: Option<AssetUuid>,
+}
+
+// Imports the printable ASCII range of a TTF into an SDF atlas image and a FontAsset that refers
+// to it
+#[derive(TypeUuid)]
+#[uuid = "e2c94f87-6a3d-4b15-8f0c-9a71d5e3b2f4"]
+pub struct FontImporter;
+impl Importer for FontImporter {
+    fn version_static() -> u32
+    where
+        Self: Sized,
+    {
+        1
+    }
+
+    fn version(&self) -> u32 {
+        Self::version_static()
+    }
+
+    type Options = ();
+
+    type State = FontImporterState;
+
+    /// Reads the given bytes and produces assets.
+    fn import(
+        &self,
+        source: &mut dyn Read,
+        _options: Self::Options,
+        state: &mut Self::State,
+    ) -> atelier_assets::importer::Result<ImporterValue> {
+        let font_id = state
+            .font_id
+            .unwrap_or_else(|| AssetUuid(*uuid::Uuid::new_v4().as_bytes()));
+        let atlas_id = state
+            .atlas_id
+            .unwrap_or_else(|| AssetUuid(*uuid::Uuid::new_v4().as_bytes()));
+        *state = FontImporterState {
+            font_id: Some(font_id),
+            atlas_id: Some(atlas_id),
+        };
+
+        let mut bytes = Vec::new();
+        source.read_to_end(&mut bytes)?;
+        let font = match fontdue::Font::from_bytes(bytes.as_slice(), fontdue::FontSettings::default()) {
+            Ok(font) => font,
+            Err(err) => {
+                log::error!("Font import error: {:?}", err);
+                return Err(Error::Boxed(Box::new(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    err,
+                ))));
+            }
+        };
+
+        let (ascent, line_height) = font
+            .horizontal_line_metrics(FONT_GLYPH_PIXEL_SIZE)
+            .map(|line_metrics| (line_metrics.ascent, line_metrics.new_line_size))
+            .unwrap_or((FONT_GLYPH_PIXEL_SIZE, FONT_GLYPH_PIXEL_SIZE));
+
+        //
+        // Build the distance field for each glyph and pack them into rows of the atlas
+        //
+        let mut glyphs = Vec::new();
+        let mut glyph_fields = Vec::new();
+        let mut cursor_x = 0;
+        let mut cursor_y = 0;
+        let mut row_height = 0;
+        for codepoint in (' ' as u8..='~' as u8).map(|c| c as char) {
+            let (metrics, coverage) = font.rasterize(codepoint, FONT_GLYPH_PIXEL_SIZE);
+
+            let (width, height) = if metrics.width > 0 && metrics.height > 0 {
+                (
+                    metrics.width + 2 * FONT_SDF_SPREAD,
+                    metrics.height + 2 * FONT_SDF_SPREAD,
+                )
+            } else {
+                (0, 0)
+            };
+
+            if cursor_x + width > FONT_ATLAS_WIDTH {
+                cursor_x = 0;
+                cursor_y += row_height;
+                row_height = 0;
+            }
+
+            if width > 0 {
+                glyph_fields.push((
+                    cursor_x,
+                    cursor_y,
+                    width,
+                    build_signed_distance_field(&coverage, metrics.width, metrics.height),
+                ));
+            }
+
+            glyphs.push(FontGlyph {
+                codepoint,
+                atlas_rect: [cursor_x as u32, cursor_y as u32, width as u32, height as u32],
+                offset: [
+                    metrics.xmin as f32 - FONT_SDF_SPREAD as f32,
+                    metrics.ymin as f32 - FONT_SDF_SPREAD as f32,
+                ],
+                advance: metrics.advance_width,
+            });
+
+            cursor_x += width;
+            row_height = row_height.max(height);
+        }
+
+        let atlas_width = FONT_ATLAS_WIDTH;
+        let atlas_height = (cursor_y + row_height).max(1).next_power_of_two();
+
+        // The distance is written to every channel so that it can be sampled as red or alpha
+        let mut atlas_data = vec![0; atlas_width * atlas_height * 4];
+        for (x, y, width, field) in glyph_fields {
+            for (row_index, row) in field.chunks(width).enumerate() {
+                for (column_index, distance) in row.iter().enumerate() {
+                    let pixel = ((y + row_index) * atlas_width + x + column_index) * 4;
+                    for channel in &mut atlas_data[pixel..pixel + 4] {
+                        *channel = *distance;
+                    }
+                }
+            }
+        }
+
+        log::debug!(
+            "Importing font uuid {:?} with {} glyphs into a {}x{} atlas {:?}",
+            font_id,
+            glyphs.len(),
+            atlas_width,
+            atlas_height,
+            atlas_id
+        );
+
+        let atlas_handle = SerdeContext::with_active(|loader_info_provider, ref_op_sender| {
+            let load_handle = loader_info_provider
+                .get_load_handle(&AssetRef::Uuid(atlas_id))
+                .unwrap();
+            Handle::<ImageAsset>::new(ref_op_sender.clone(), load_handle)
+        });
+
+        let atlas_asset = ImageAssetData {
+            data: atlas_data,
+            width: atlas_width as u32,
+            height: atlas_height as u32,
+            color_space: ColorSpace::Linear,
+        };
+
+        let font_asset = FontAsset {
+            atlas: atlas_handle,
+            atlas_width: atlas_width as u32,
+            atlas_height: atlas_height as u32,
+            glyph_pixel_size: FONT_GLYPH_PIXEL_SIZE,
+            sdf_spread: FONT_SDF_SPREAD as f32,
+            ascent,
+            line_height,
+            glyphs,
+        };
+
+        Ok(ImporterValue {
+            assets: vec![
+                ImportedAsset {
+                    id: atlas_id,
+                    search_tags: vec![],
+                    build_deps: vec![],
+                    load_deps: vec![],
+                    build_pipeline: None,
+                    asset_data: Box::new(atlas_asset),
+                },
+                ImportedAsset {
+                    id: font_id,
+                    search_tags: vec![],
+                    build_deps: vec![],
+                    load_deps: vec![],
+                    build_pipeline: None,
+                    asset_data: Box::new(font_asset),
+                },
+            ],
+        })
+    }
+}
+
+// Produces a field FONT_SDF_SPREAD pixels larger than the coverage bitmap on each side. 0.5 (128)
+// is the glyph's edge, higher values are inside the glyph. This is a brute force search, which is
+// fine for the small glyphs in an atlas and only runs at import time.
+fn build_signed_distance_field(
+    coverage: &[u8],
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    let spread = FONT_SDF_SPREAD as isize;
+    let padded_width = width + 2 * FONT_SDF_SPREAD;
+    let padded_height = height + 2 * FONT_SDF_SPREAD;
+
+    let is_inside = |x: isize, y: isize| {
+        x >= 0
+            && y >= 0
+            && (x as usize) < width
+            && (y as usize) < height
+            && coverage[y as usize * width + x as usize] >= 128
+    };
+
+    let mut field = Vec::with_capacity(padded_width * padded_height);
+    for padded_y in 0..padded_height as isize {
+        for padded_x in 0..padded_width as isize {
+            let x = padded_x - spread;
+            let y = padded_y - spread;
+            let inside = is_inside(x, y);
+
+            // Find the nearest pixel on the other side of the edge
+            let mut nearest_distance_sq = (spread * spread) as f32;
+            for offset_y in -spread..=spread {
+                for offset_x in -spread..=spread {
+                    if is_inside(x + offset_x, y + offset_y) != inside {
+                        let distance_sq = (offset_x * offset_x + offset_y * offset_y) as f32;
+                        nearest_distance_sq = nearest_distance_sq.min(distance_sq);
+                    }
+                }
+            }
+
+            let distance = nearest_distance_sq.sqrt();
+            let signed_distance = if inside { distance } else { -distance };
+            let value = 0.5 + signed_distance / (2.0 * FONT_SDF_SPREAD as f32);
+            field.push((value.max(0.0).min(1.0) * 255.0) as u8);
+        }
+    }
+
+    field
+}
+
+inventory::submit!(SourceFileImporter {
+    extension: "ttf",
+    instantiator: || Box::new(FontImporter {}),
+});
@@ -4,12 +4,14 @@ use atelier_assets::loader::handle::Handle;
 use renderer::assets::ImageAsset;
 use renderer::assets::MaterialInstanceAsset;
 use renderer::assets::BufferAsset;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use renderer::assets::DescriptorSetArc;
 use renderer::assets::ResourceArc;
 use renderer::vulkan::VkBufferRaw;
 use minimum::math::BoundingSphere;
 use minimum::math::BoundingAabb;
+use fnv::FnvHashMap;
+use ncollide3d::shape::{ShapeHandle, TriMesh};
 
 //TODO: These are extensions that might be interesting to try supporting. In particular, lights,
 // LOD, and clearcoat
@@ -153,26 +155,93 @@ pub struct MeshPartData {
     pub material_instance: Handle<MaterialInstanceAsset>,
 }
 
+/// Where a mesh part's geometry is in the mesh's vertex/index buffers for a particular LOD
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct MeshPartRangeData {
+    pub vertex_buffer_offset_in_bytes: u32,
+    pub vertex_buffer_size_in_bytes: u32,
+    pub index_buffer_offset_in_bytes: u32,
+    pub index_buffer_size_in_bytes: u32,
+}
+
+/// A simplified version of the mesh. Parts correspond 1:1 with `MeshAssetData::mesh_parts` and use
+/// the same materials, only their geometry differs.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MeshLodData {
+    // This LOD is used once the mesh's projected bounding sphere diameter drops below this
+    // fraction of the viewport height
+    pub screen_size: f32,
+    pub mesh_parts: Vec<MeshPartRangeData>,
+}
+
+/// CPU copy of the full detail mesh's positions and triangles, with every part merged. Used for
+/// editor selection shapes since the vertex/index buffers only live on the GPU.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MeshCollisionData {
+    pub positions: Vec<[f32; 3]>,
+    pub indices: Vec<u32>,
+}
+
 #[derive(TypeUuid, Serialize, Deserialize, Clone)]
 #[uuid = "cf232526-3757-4d94-98d1-c2f7e27c979f"]
 pub struct MeshAssetData {
     pub bounding_sphere: BoundingSphere,
     pub bounding_aabb: BoundingAabb,
+    // Empty for meshes that have no triangles
+    pub collision: MeshCollisionData,
+    // LOD 0
     pub mesh_parts: Vec<MeshPartData>,
+    // LOD 1 and beyond, in order of decreasing detail (and decreasing screen_size)
+    pub lods: Vec<MeshLodData>,
     pub vertex_buffer: Handle<BufferAsset>,
     pub index_buffer: Handle<BufferAsset>,
 }
 
+impl MeshAssetData {
+    /// Number of LODs including the full detail mesh
+    pub fn lod_count(&self) -> usize {
+        self.lods.len() + 1
+    }
+
+    /// Buffer ranges of every part of the given LOD, in the same order as `mesh_parts`
+    pub fn lod_part_ranges(
+        &self,
+        lod: usize,
+    ) -> Vec<MeshPartRangeData> {
+        if lod == 0 {
+            self.mesh_parts
+                .iter()
+                .map(|mesh_part| MeshPartRangeData {
+                    vertex_buffer_offset_in_bytes: mesh_part.vertex_buffer_offset_in_bytes,
+                    vertex_buffer_size_in_bytes: mesh_part.vertex_buffer_size_in_bytes,
+                    index_buffer_offset_in_bytes: mesh_part.index_buffer_offset_in_bytes,
+                    index_buffer_size_in_bytes: mesh_part.index_buffer_size_in_bytes,
+                })
+                .collect()
+        } else {
+            self.lods[lod - 1].mesh_parts.clone()
+        }
+    }
+}
+
 pub struct MeshAssetPart {
     //pub material: ResourceArc<LoadedMaterial>,
     pub material_instance: Arc<Vec<Vec<DescriptorSetArc>>>,
 }
 
+// Scaled collision shapes are cached until there are this many, then the cache is cleared. This
+// keeps dragging a scale gizmo from growing the cache without bound
+const MAX_CACHED_COLLISION_SHAPES: usize = 16;
+
 pub struct MeshAssetInner {
     pub mesh_parts: Vec<MeshAssetPart>,
     pub vertex_buffer: ResourceArc<VkBufferRaw>,
     pub index_buffer: ResourceArc<VkBufferRaw>,
     pub asset: MeshAssetData,
+
+    // TriMesh shapes built from asset.collision, keyed by the bits of the scale they were built
+    // with since collision world isometries can't scale
+    collision_shapes: Mutex<FnvHashMap<[u32; 3], ShapeHandle<f32>>>,
 }
 
 #[derive(TypeUuid, Clone)]
@@ -193,10 +262,67 @@ impl MeshAsset {
             vertex_buffer,
             index_buffer,
             asset,
+            collision_shapes: Default::default(),
         };
 
         MeshAsset {
             inner: Arc::new(inner),
         }
     }
+
+    /// A triangle mesh shape of the full detail mesh with the given scale applied. Returns None if
+    /// the asset has no collision data. Building the shape is expensive, so it is cached.
+    pub fn collision_shape(
+        &self,
+        scale: glam::Vec3,
+    ) -> Option<ShapeHandle<f32>> {
+        let collision = &self.inner.asset.collision;
+        if collision.indices.is_empty() {
+            return None;
+        }
+
+        let key = [
+            scale.x().to_bits(),
+            scale.y().to_bits(),
+            scale.z().to_bits(),
+        ];
+
+        let mut collision_shapes = self.inner.collision_shapes.lock().unwrap();
+        if let Some(shape) = collision_shapes.get(&key) {
+            return Some(shape.clone());
+        }
+
+        let points = collision
+            .positions
+            .iter()
+            .map(|position| {
+                nalgebra::Point3::new(
+                    position[0] * scale.x(),
+                    position[1] * scale.y(),
+                    position[2] * scale.z(),
+                )
+            })
+            .collect();
+
+        let indices = collision
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                nalgebra::Point3::new(
+                    triangle[0] as usize,
+                    triangle[1] as usize,
+                    triangle[2] as usize,
+                )
+            })
+            .collect();
+
+        let shape = ShapeHandle::new(TriMesh::new(points, indices, None));
+
+        if collision_shapes.len() >= MAX_CACHED_COLLISION_SHAPES {
+            collision_shapes.clear();
+        }
+        collision_shapes.insert(key, shape.clone());
+
+        Some(shape)
+    }
 }
@@ -8,7 +8,8 @@ use gltf::image::Data as GltfImageData;
 use gltf::buffer::Data as GltfBufferData;
 use fnv::FnvHashMap;
 use atelier_assets::loader::handle::Handle;
-use crate::assets::gltf::{GltfMaterialAsset, MeshAssetData, MeshPartData, MeshVertex, GltfMaterialDataShaderParam, MeshAsset};
+use crate::assets::gltf::{GltfMaterialAsset, MeshAssetData, MeshPartData, MeshVertex, GltfMaterialDataShaderParam, MeshAsset, MeshLodData, MeshPartRangeData, MeshCollisionData};
+use super::mesh_simplification::simplify_by_vertex_clustering;
 use renderer::assets::assets::{ImageAssetData, ColorSpace};
 use renderer::assets::assets::BufferAssetData;
 use renderer::assets::push_buffer::PushBuffer;
@@ -143,7 +144,7 @@ impl Importer for GltfImporter {
     where
         Self: Sized,
     {
-        27
+        29
     }
 
     fn version(&self) -> u32 {
@@ -780,6 +781,97 @@ fn convert_to_u16_indices(
     Ok(indices_u16)
 }
 
+// Generated LODs as (grid cell size relative to the mesh's bounding box diagonal, screen size
+// below which the LOD is used)
+const GENERATED_MESH_LODS: [(f32, f32); 3] = [(1.0 / 64.0, 0.3), (1.0 / 24.0, 0.12), (1.0 / 10.0, 0.04)];
+
+// A generated LOD is discarded unless it removes at least this fraction of the triangles of the
+// previous LOD
+const MIN_LOD_TRIANGLE_REDUCTION: f32 = 0.25;
+
+fn generate_mesh_lods(
+    part_geometry: &[(Vec<MeshVertex>, Vec<u16>)],
+    bounding_aabb: &BoundingAabb,
+    all_vertices: &mut PushBuffer,
+    all_indices: &mut PushBuffer,
+) -> Vec<MeshLodData> {
+    let diagonal = (bounding_aabb.max - bounding_aabb.min).length();
+    if diagonal <= 0.0 {
+        return vec![];
+    }
+
+    let mut lods = vec![];
+    let mut previous_triangle_count: usize = part_geometry
+        .iter()
+        .map(|(_, indices)| indices.len() / 3)
+        .sum();
+
+    for &(relative_cell_size, screen_size) in &GENERATED_MESH_LODS {
+        let simplified_parts: Vec<_> = part_geometry
+            .iter()
+            .map(|(vertices, indices)| {
+                simplify_by_vertex_clustering(vertices, indices, diagonal * relative_cell_size)
+            })
+            .collect();
+
+        let triangle_count: usize = simplified_parts
+            .iter()
+            .map(|(_, indices)| indices.len() / 3)
+            .sum();
+
+        if triangle_count == 0 {
+            break;
+        }
+
+        if triangle_count as f32 > previous_triangle_count as f32 * (1.0 - MIN_LOD_TRIANGLE_REDUCTION) {
+            continue;
+        }
+
+        let mesh_parts = simplified_parts
+            .iter()
+            .map(|(vertices, indices)| {
+                let vertex_offset = all_vertices.len();
+                let indices_offset = all_indices.len();
+
+                all_vertices.push(vertices, 1);
+                all_indices.push(indices, 1);
+
+                MeshPartRangeData {
+                    vertex_buffer_offset_in_bytes: vertex_offset as u32,
+                    vertex_buffer_size_in_bytes: (all_vertices.len() - vertex_offset) as u32,
+                    index_buffer_offset_in_bytes: indices_offset as u32,
+                    index_buffer_size_in_bytes: (all_indices.len() - indices_offset) as u32,
+                }
+            })
+            .collect();
+
+        lods.push(MeshLodData {
+            screen_size,
+            mesh_parts,
+        });
+        previous_triangle_count = triangle_count;
+    }
+
+    lods
+}
+
+// Merges the full detail geometry of every part into a single triangle list for the editor's
+// selection shapes
+fn build_mesh_collision_data(part_geometry: &[(Vec<MeshVertex>, Vec<u16>)]) -> MeshCollisionData {
+    let mut collision = MeshCollisionData::default();
+    for (vertices, indices) in part_geometry {
+        let first_vertex = collision.positions.len() as u32;
+        collision
+            .positions
+            .extend(vertices.iter().map(|vertex| vertex.position));
+        collision
+            .indices
+            .extend(indices.iter().map(|index| first_vertex + *index as u32));
+    }
+
+    collision
+}
+
 fn extract_meshes_to_import(
     state: &mut GltfImporterStateUnstable,
     doc: &gltf::Document,
@@ -797,6 +889,9 @@ fn extract_meshes_to_import(
         let mut mesh_parts: Vec<MeshPartData> = Vec::with_capacity(mesh.primitives().len());
         let mut bounding_aabb : Option<BoundingAabb> = None;
 
+        // Full detail geometry of each part in mesh_parts, simplified to produce the LODs
+        let mut part_geometry: Vec<(Vec<MeshVertex>, Vec<u16>)> = Vec::with_capacity(mesh.primitives().len());
+
         //
         // Iterate all mesh parts, building a single vertex and index buffer. Each MeshPart will
         // hold offsets/lengths to their sections in the vertex/index buffers
@@ -851,6 +946,15 @@ fn extract_meshes_to_import(
 
                         all_indices.push(&part_indices, 1);
 
+                        let part_vertices: Vec<_> = (0..positions.len())
+                            .map(|i| MeshVertex {
+                                position: positions[i],
+                                normal: normals[i],
+                                tangent: tangents[i],
+                                tex_coord: tex_coords[i],
+                            })
+                            .collect();
+
                         let vertex_size = all_vertices.len() - vertex_offset;
                         let indices_size = all_indices.len() - indices_offset;
 
@@ -867,14 +971,14 @@ fn extract_meshes_to_import(
                             )));
                         };
 
-                        Some(MeshPartData {
+                        Some((MeshPartData {
                             material,
                             material_instance,
                             vertex_buffer_offset_in_bytes: vertex_offset as u32,
                             vertex_buffer_size_in_bytes: vertex_size as u32,
                             index_buffer_offset_in_bytes: indices_offset as u32,
                             index_buffer_size_in_bytes: indices_size as u32,
-                        })
+                        }, (part_vertices, part_indices)))
                     } else {
                         log::error!("indices must fit in u16");
                         None
@@ -887,11 +991,31 @@ fn extract_meshes_to_import(
                 }
             };
 
-            if let Some(mesh_part) = mesh_part {
+            if let Some((mesh_part, geometry)) = mesh_part {
                 mesh_parts.push(mesh_part);
+                part_geometry.push(geometry);
             }
         }
 
+        if bounding_aabb.is_none() {
+            bounding_aabb = Some(BoundingAabb::new(glam::Vec3::zero()));
+        }
+
+        let bounding_aabb = bounding_aabb.unwrap();
+        let bounding_sphere = bounding_aabb.calculate_bounding_sphere();
+
+        //
+        // LODs - appended to the same vertex/index buffers as the full detail mesh
+        //
+        let lods = generate_mesh_lods(
+            &part_geometry,
+            &bounding_aabb,
+            &mut all_vertices,
+            &mut all_indices,
+        );
+
+        let collision = build_mesh_collision_data(&part_geometry);
+
         //
         // Vertex Buffer
         //
@@ -948,17 +1072,12 @@ fn extract_meshes_to_import(
                 Handle::<BufferAsset>::new(ref_op_sender.clone(), load_handle)
             });
 
-        if bounding_aabb.is_none() {
-            bounding_aabb = Some(BoundingAabb::new(glam::Vec3::zero()));
-        }
-
-        let bounding_aabb = bounding_aabb.unwrap();
-        let bounding_sphere = bounding_aabb.calculate_bounding_sphere();
-
         let asset = MeshAssetData {
             bounding_sphere,
             bounding_aabb,
+            collision,
             mesh_parts,
+            lods,
             vertex_buffer: vertex_buffer_handle,
             index_buffer: index_buffer_handle,
         };
@@ -973,10 +1092,11 @@ fn extract_meshes_to_import(
         // Verify that we iterate meshes in order so that our resulting assets are in order
         assert!(mesh.index() == meshes_to_import.len());
         log::debug!(
-            "Importing Mesh name: {:?} index: {} mesh_parts count: {}",
+            "Importing Mesh name: {:?} index: {} mesh_parts count: {} lod count: {}",
             mesh.name(),
             mesh.index(),
-            mesh_to_import.asset.mesh_parts.len()
+            mesh_to_import.asset.mesh_parts.len(),
+            mesh_to_import.asset.lod_count(),
         );
 
         meshes_to_import.push(mesh_to_import);
@@ -1020,7 +1140,8 @@ fn add_nodes_to_world(
         //let transform_component = TransformComponentDef::from_matrix(local_to_world);
         let mesh_handle = mesh_index_to_handle[mesh.index()].clone();
         let mesh_component = MeshComponentDef {
-            mesh: Some(mesh_handle.into())
+            mesh: Some(mesh_handle.into()),
+            is_static: false,
         };
 
         // Temporary
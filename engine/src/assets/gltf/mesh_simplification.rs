@@ -0,0 +1,79 @@
+use crate::assets::gltf::MeshVertex;
+use fnv::FnvHashMap;
+
+/// Simplifies a triangle list by snapping vertices to a grid and merging all vertices that land in
+/// the same cell. Positions and normals of merged vertices are averaged, other attributes are
+/// taken from the first vertex in the cell. Triangles that collapse are dropped.
+///
+/// This is crude compared to edge-collapse approaches (UV seams can smear) but it is fast, robust
+/// against messy input, and good enough for meshes that only appear small on screen.
+pub fn simplify_by_vertex_clustering(
+    vertices: &[MeshVertex],
+    indices: &[u16],
+    cell_size: f32,
+) -> (Vec<MeshVertex>, Vec<u16>) {
+    struct Cluster {
+        vertex: MeshVertex,
+        position_sum: glam::Vec3,
+        normal_sum: glam::Vec3,
+        count: u32,
+    }
+
+    let mut cluster_indices = FnvHashMap::<(i32, i32, i32), u16>::default();
+    let mut clusters = Vec::<Cluster>::new();
+
+    // Maps each input vertex to its cluster
+    let remap: Vec<u16> = vertices
+        .iter()
+        .map(|vertex| {
+            // Copy out of the packed struct before using the fields
+            let position: glam::Vec3 = { vertex.position }.into();
+            let normal: glam::Vec3 = { vertex.normal }.into();
+
+            let cell = (position / cell_size).floor();
+            let key = (cell.x() as i32, cell.y() as i32, cell.z() as i32);
+
+            let cluster_index = *cluster_indices.entry(key).or_insert_with(|| {
+                clusters.push(Cluster {
+                    vertex: *vertex,
+                    position_sum: glam::Vec3::zero(),
+                    normal_sum: glam::Vec3::zero(),
+                    count: 0,
+                });
+                (clusters.len() - 1) as u16
+            });
+
+            let cluster = &mut clusters[cluster_index as usize];
+            cluster.position_sum += position;
+            cluster.normal_sum += normal;
+            cluster.count += 1;
+
+            cluster_index
+        })
+        .collect();
+
+    let simplified_vertices = clusters
+        .into_iter()
+        .map(|cluster| {
+            let mut vertex = cluster.vertex;
+            vertex.position = (cluster.position_sum / cluster.count as f32).into();
+            if cluster.normal_sum.length_squared() > 0.0 {
+                vertex.normal = cluster.normal_sum.normalize().into();
+            }
+            vertex
+        })
+        .collect();
+
+    let mut simplified_indices = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        let a = remap[triangle[0] as usize];
+        let b = remap[triangle[1] as usize];
+        let c = remap[triangle[2] as usize];
+
+        if a != b && b != c && c != a {
+            simplified_indices.extend_from_slice(&[a, b, c]);
+        }
+    }
+
+    (simplified_vertices, simplified_indices)
+}
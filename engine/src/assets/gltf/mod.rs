@@ -3,3 +3,5 @@ pub use assets::*;
 
 mod importer;
 pub use importer::*;
+
+mod mesh_simplification;
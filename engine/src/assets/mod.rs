@@ -1 +1,3 @@
 pub mod gltf;
+pub mod sprite;
+pub mod font;
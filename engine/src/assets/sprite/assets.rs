@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+use type_uuid::*;
+use atelier_assets::loader::handle::Handle;
+use renderer::assets::ImageAsset;
+
+/// A rectangle within a sprite sheet's image, in pixels from the top-left corner
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SpriteFrame {
+    pub name: String,
+    // x, y, width, height
+    pub rect: [u32; 4],
+}
+
+/// An image containing many sprites (an atlas or an animation strip) and the rects of each frame.
+/// Loaded from a .spritesheet RON file.
+#[derive(TypeUuid, Serialize, Deserialize, Clone)]
+#[uuid = "7d440e1d-bcc6-49ef-8f10-85f9ba73c9ad"]
+pub struct SpriteSheetAsset {
+    pub image: Handle<ImageAsset>,
+    // Size of the image in pixels, used to convert frame rects to texture coordinates
+    pub image_width: u32,
+    pub image_height: u32,
+    pub frames: Vec<SpriteFrame>,
+}
+
+impl SpriteSheetAsset {
+    /// Texture coordinates of the top-left and bottom-right corners of the given frame, or None if
+    /// the frame index is out of range
+    pub fn frame_uv_rect(
+        &self,
+        frame_index: usize,
+    ) -> Option<(glam::Vec2, glam::Vec2)> {
+        let frame = self.frames.get(frame_index)?;
+        let image_size = glam::Vec2::new(self.image_width as f32, self.image_height as f32);
+
+        let top_left = glam::Vec2::new(frame.rect[0] as f32, frame.rect[1] as f32);
+        let size = glam::Vec2::new(frame.rect[2] as f32, frame.rect[3] as f32);
+
+        Some((top_left / image_size, (top_left + size) / image_size))
+    }
+
+    pub fn find_frame(
+        &self,
+        name: &str,
+    ) -> Option<usize> {
+        self.frames.iter().position(|frame| frame.name == name)
+    }
+}
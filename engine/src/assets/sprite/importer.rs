@@ -0,0 +1,82 @@
+use atelier_assets::core::AssetUuid;
+use atelier_assets::importer::{Error, ImportedAsset, Importer, ImporterValue, SourceFileImporter};
+use serde::{Deserialize, Serialize};
+use type_uuid::*;
+use std::io::Read;
+use crate::assets::sprite::SpriteSheetAsset;
+
+#[derive(TypeUuid, Serialize, Deserialize, Default, Clone)]
+#[uuid = "fae63dae-5426-413a-8c3d-ef1a85092227"]
+pub struct SpriteSheetImporterState {
+    id: Option<AssetUuid>,
+}
+
+// Sprite sheets are written by hand as RON, i.e.
+// (
+//     image: "../textures/sprites.png",
+//     image_width: 256,
+//     image_height: 256,
+//     frames: [(name: "idle_0", rect: (0, 0, 32, 32))],
+// )
+#[derive(TypeUuid)]
+#[uuid = "c43d11bf-fffc-4033-ae70-6f11f8848d6a"]
+pub struct SpriteSheetImporter;
+impl Importer for SpriteSheetImporter {
+    fn version_static() -> u32
+    where
+        Self: Sized,
+    {
+        1
+    }
+
+    fn version(&self) -> u32 {
+        Self::version_static()
+    }
+
+    type Options = ();
+
+    type State = SpriteSheetImporterState;
+
+    /// Reads the given bytes and produces assets.
+    fn import(
+        &self,
+        source: &mut dyn Read,
+        _options: Self::Options,
+        state: &mut Self::State,
+    ) -> atelier_assets::importer::Result<ImporterValue> {
+        let id = state
+            .id
+            .unwrap_or_else(|| AssetUuid(*uuid::Uuid::new_v4().as_bytes()));
+        *state = SpriteSheetImporterState { id: Some(id) };
+
+        let sprite_sheet: SpriteSheetAsset = match ron::de::from_reader(source) {
+            Ok(sprite_sheet) => sprite_sheet,
+            Err(err) => {
+                log::error!("Sprite sheet import error: {:?}", err);
+                return Err(Error::Boxed(Box::new(err)));
+            }
+        };
+
+        log::debug!(
+            "Importing sprite sheet uuid {:?} with {} frames",
+            id,
+            sprite_sheet.frames.len()
+        );
+
+        Ok(ImporterValue {
+            assets: vec![ImportedAsset {
+                id,
+                search_tags: vec![],
+                build_deps: vec![],
+                load_deps: vec![],
+                build_pipeline: None,
+                asset_data: Box::new(sprite_sheet),
+            }],
+        })
+    }
+}
+
+inventory::submit!(SourceFileImporter {
+    extension: "spritesheet",
+    instantiator: || Box::new(SpriteSheetImporter {}),
+});
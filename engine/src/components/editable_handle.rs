@@ -3,7 +3,16 @@ use std::ops::{Deref, DerefMut};
 use serde::{Serialize, Deserialize, Serializer, Deserializer};
 use serde_diff::{ApplyContext, DiffContext, SerdeDiff};
 use imgui_inspect::InspectArgsDefault;
-use imgui::Ui;
+use imgui::{ImString, Ui};
+use atelier_assets::core::AssetTypeId;
+use atelier_assets::loader::handle::AssetHandle;
+use type_uuid::TypeUuid;
+use std::cell::RefCell;
+use crate::asset_catalog::{AssetCatalog, ASSET_DRAG_DROP_TYPE};
+use crate::assets::gltf::{MeshAsset, MeshAssetData};
+use crate::assets::sprite::SpriteSheetAsset;
+use crate::assets::font::FontAsset;
+use renderer::assets::{ImageAsset, ImageAssetData};
 
 
 #[derive(Eq)]
@@ -95,13 +104,244 @@ impl<T> SerdeDiff for EditableHandle<T> {
     }
 }
 
-impl<T> imgui_inspect::InspectRenderDefault<EditableHandle<T>> for EditableHandle<T> {
-    fn render(data: &[&EditableHandle<T>], label: &'static str, ui: &Ui, args: &InspectArgsDefault) {
-        ui.text(imgui::im_str!("hi {:?}", data[0].handle));
+// Asset types that can be picked in the inspector. AssetData is the type that the daemon's
+// metadata records for the asset, which differs from T for assets that have a custom loader.
+pub trait EditableAsset {
+    type AssetData: TypeUuid;
+}
+
+impl EditableAsset for MeshAsset {
+    type AssetData = MeshAssetData;
+}
+
+impl EditableAsset for ImageAsset {
+    type AssetData = ImageAssetData;
+}
+
+impl EditableAsset for SpriteSheetAsset {
+    type AssetData = SpriteSheetAsset;
+}
+
+impl EditableAsset for FontAsset {
+    type AssetData = FontAsset;
+}
+
+enum AssetPickerEdit<T> {
+    Assign(Handle<T>),
+    Clear,
+}
+
+thread_local! {
+    // Only one picker popup can be open at a time, so they share the search text
+    static PICKER_SEARCH: RefCell<ImString> = RefCell::new(ImString::with_capacity(128));
+}
+
+fn asset_display_name<T>(
+    catalog: &mut AssetCatalog,
+    handle: Option<&Handle<T>>,
+) -> String {
+    let handle = match handle {
+        Some(handle) => handle,
+        None => return "None".to_string(),
+    };
+
+    match catalog.asset_id(handle.load_handle()) {
+        Some(asset_id) => catalog
+            .entry(asset_id)
+            .map(|entry| entry.display_name())
+            .unwrap_or_else(|| format!("{:?}", asset_id)),
+        None => "Loading...".to_string(),
     }
+}
+
+fn render_asset_name<T>(
+    handle: Option<&Handle<T>>,
+    label: &'static str,
+    ui: &Ui,
+) {
+    let name = AssetCatalog::with(|catalog| asset_display_name(catalog, handle));
+    ui.text(imgui::im_str!("{}: {}", label, name));
+}
+
+// Draws a button showing the current asset that opens a searchable list of assets of type T. The
+// button also accepts assets dropped on it. Handles for picked assets are created by the asset
+// catalog, so the edit is returned on the frame after the asset was picked.
+fn render_asset_picker<T: EditableAsset>(
+    handle: Option<&Handle<T>>,
+    allow_clear: bool,
+    label: &'static str,
+    ui: &Ui,
+) -> Option<AssetPickerEdit<T>> {
+    let asset_type = AssetTypeId(T::AssetData::UUID);
+    let popup_id = imgui::im_str!("asset_picker");
+
+    // The label alone is shared by same-named fields of other components and entities, so the
+    // picked asset is matched to the widget by its full ID
+    let id_token = ui.push_id(label);
+    let imgui_id = unsafe { imgui::sys::igGetIDStr(popup_id.as_ptr()) };
+    let widget_id = (imgui_id, asset_type);
+
+    AssetCatalog::with(|catalog| {
+        if let Some((ref_op_sender, load_handle)) = catalog.take_loaded_asset(widget_id) {
+            id_token.pop(ui);
+            return Some(AssetPickerEdit::Assign(Handle::new(ref_op_sender, load_handle)));
+        }
+
+        let current_asset = handle.and_then(|handle| catalog.asset_id(handle.load_handle()));
+        let name = asset_display_name(catalog, handle);
+        let drag_drop_type = ImString::new(ASSET_DRAG_DROP_TYPE);
+        let mut edit = None;
+        let mut picked_asset = None;
+
+        if ui.button(&imgui::im_str!("{}", name), [0.0, 0.0]) {
+            catalog.request_rescan();
+            PICKER_SEARCH.with(|search| search.borrow_mut().clear());
+            ui.open_popup(popup_id);
+        }
+
+        // Allow dragging the asset to another field
+        if let Some(asset_id) = current_asset {
+            if let Some(tooltip) = imgui::DragDropSource::new(&drag_drop_type).begin(ui) {
+                catalog.set_dragged_asset(asset_id);
+                ui.text(&imgui::im_str!("{}", name));
+                tooltip.end();
+            }
+        }
 
-    fn render_mut(data: &mut [&mut EditableHandle<T>], label: &'static str, ui: &Ui, args: &InspectArgsDefault) -> bool {
-        ui.text(imgui::im_str!("hi {:?}", data[0].handle));
-        false
+        if let Some(target) = imgui::DragDropTarget::new(ui) {
+            let dropped = target
+                .accept_payload_empty(&drag_drop_type, imgui::DragDropFlags::empty())
+                .is_some();
+            if dropped {
+                picked_asset = catalog.dragged_asset();
+            }
+            target.pop();
+        }
+
+        if allow_clear && handle.is_some() {
+            ui.same_line(0.0);
+            if ui.button(imgui::im_str!("Clear"), [0.0, 0.0]) {
+                edit = Some(AssetPickerEdit::Clear);
+            }
+        }
+
+        ui.same_line(0.0);
+        ui.text(label);
+
+        ui.popup(popup_id, || {
+            PICKER_SEARCH.with(|search| {
+                let mut search = search.borrow_mut();
+                ui.input_text(imgui::im_str!("Search"), &mut *search).build();
+                let search = search.to_str().to_lowercase();
+
+                let entries = catalog
+                    .entries()
+                    .iter()
+                    .filter(|entry| entry.asset_type == asset_type);
+                for entry in entries {
+                    let entry_name = entry.display_name();
+                    if !entry_name.to_lowercase().contains(&search) {
+                        continue;
+                    }
+
+                    let clicked = imgui::Selectable::new(&imgui::im_str!(
+                        "{}##{:?}",
+                        entry_name,
+                        entry.id
+                    ))
+                    .selected(current_asset == Some(entry.id))
+                    .build(ui);
+                    if clicked {
+                        picked_asset = Some(entry.id);
+                    }
+                }
+            });
+        });
+
+        id_token.pop(ui);
+
+        // Dropped assets may be of a different type
+        let picked_asset = picked_asset.filter(|asset_id| {
+            catalog
+                .entry(*asset_id)
+                .map(|entry| entry.asset_type == asset_type)
+                .unwrap_or(false)
+        });
+        if let Some(asset_id) = picked_asset {
+            catalog.request_load(widget_id, asset_id);
+        }
+
+        edit
+    })
+}
+
+impl<T: EditableAsset> imgui_inspect::InspectRenderDefault<EditableHandle<T>>
+    for EditableHandle<T>
+{
+    fn render(
+        data: &[&EditableHandle<T>],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) {
+        render_asset_name(Some(&data[0].handle), label, ui);
     }
-}
\ No newline at end of file
+
+    fn render_mut(
+        data: &mut [&mut EditableHandle<T>],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) -> bool {
+        match render_asset_picker(Some(&data[0].handle), false, label, ui) {
+            Some(AssetPickerEdit::Assign(handle)) => {
+                for d in data {
+                    d.handle = handle.clone();
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+// imgui-inspect's impl for Option only draws the value when it is Some. Use this as the
+// proxy_type of Option<EditableHandle<T>> fields so that None can be replaced and Some cleared.
+pub struct OptionalEditableHandle;
+
+impl<T: EditableAsset> imgui_inspect::InspectRenderDefault<Option<EditableHandle<T>>>
+    for OptionalEditableHandle
+{
+    fn render(
+        data: &[&Option<EditableHandle<T>>],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) {
+        render_asset_name(data[0].as_ref().map(|x| &x.handle), label, ui);
+    }
+
+    fn render_mut(
+        data: &mut [&mut Option<EditableHandle<T>>],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) -> bool {
+        let handle = data[0].as_ref().map(|x| &x.handle);
+        match render_asset_picker(handle, true, label, ui) {
+            Some(AssetPickerEdit::Assign(handle)) => {
+                for d in data {
+                    **d = Some(EditableHandle::from(handle.clone()));
+                }
+                true
+            }
+            Some(AssetPickerEdit::Clear) => {
+                for d in data {
+                    **d = None;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::features::mesh::{MeshRenderNodeHandle, MeshRenderNodeSet, MeshRenderNode};
+use crate::features::mesh::{MeshRenderNodeHandle, MeshRenderNodeSet, MeshRenderNode, StaticMeshSet};
 use renderer::visibility::DynamicAabbVisibilityNodeHandle;
 use atelier_assets::loader::handle::Handle;
 use crate::assets::gltf::MeshAsset;
@@ -27,7 +27,7 @@ use imgui_inspect::{InspectArgsDefault, InspectArgsStruct};
 use std::ops::{Deref, DerefMut, Range};
 use legion_prefab::SpawnFrom;
 use legion_transaction::iter_components_in_storage;
-use crate::components::EditableHandle;
+use crate::components::{EditableHandle, OptionalEditableHandle};
 use ncollide3d::shape::Cuboid;
 use minimum::math::na_convert::vec3_glam_to_glm;
 
@@ -35,15 +35,23 @@ use minimum::math::na_convert::vec3_glam_to_glm;
 #[derive(TypeUuid, Serialize, Deserialize, SerdeDiff, Debug, PartialEq, Clone, Default, Inspect)]
 #[uuid = "46b6a84c-f224-48ac-a56d-46971bcaf7f1"]
 pub struct MeshComponentDef {
-    pub mesh: Option<EditableHandle<MeshAsset>>
+    #[inspect(proxy_type = "OptionalEditableHandle")]
+    pub mesh: Option<EditableHandle<MeshAsset>>,
+    // Static meshes are uploaded to the GPU once and culled/drawn indirectly instead of being
+    // extracted every frame. Moving one rebuilds the GPU data of every static mesh, so they are
+    // meant for objects that rarely move.
+    #[serde(default)]
+    pub is_static: bool,
 }
 
 legion_prefab::register_component_type!(MeshComponentDef);
 
 pub struct MeshComponent {
-    pub mesh_handle: MeshRenderNodeHandle,
-    pub visibility_handle: DynamicAabbVisibilityNodeHandle,
+    // Static meshes are not registered as render/visibility nodes, see StaticMeshSet
+    pub mesh_handle: Option<MeshRenderNodeHandle>,
+    pub visibility_handle: Option<DynamicAabbVisibilityNodeHandle>,
     pub mesh: Option<Handle<MeshAsset>>,
+    pub is_static: bool,
 }
 
 impl EditorSelectableTransformed<MeshComponent> for MeshComponentDef {
@@ -67,6 +75,27 @@ impl EditorSelectableTransformed<MeshComponent> for MeshComponentDef {
                 use ncollide3d::shape::ShapeHandle;
                 use ncollide3d::shape::Ball;
                 if let Some(transform) = prefab_world.get_component::<TransformComponentDef>(prefab_entity) {
+                    let rotation = transform.rotation_quat();
+                    let rotation = nalgebra::Quaternion::new(rotation.w(), rotation.x(), rotation.y(), rotation.z());
+                    let rotation = nalgebra::UnitQuaternion::from_quaternion(rotation);
+
+                    // Select by the mesh's triangles so that clicking empty space inside its
+                    // bounds doesn't select it. Meshes imported without collision data fall back
+                    // to their bounding box
+                    if let Some(shape_handle) = mesh.collision_shape(transform.scale()) {
+                        collision_world.add(
+                            ncollide3d::math::Isometry::from_parts(
+                                nalgebra::Translation::from(vec3_glam_to_glm(transform.position())),
+                                rotation,
+                            ),
+                            shape_handle,
+                            CollisionGroups::new(),
+                            GeometricQueryType::Proximity(0.001),
+                            transformed_entity,
+                        );
+                        return;
+                    }
+
                     let x = bounding_aabb.max.x() - bounding_aabb.min.x();
                     let y = bounding_aabb.max.y() - bounding_aabb.min.y();
                     let z = bounding_aabb.max.z() - bounding_aabb.min.z();
@@ -78,7 +107,6 @@ impl EditorSelectableTransformed<MeshComponent> for MeshComponentDef {
                     let center = glam::Vec3::new(x, y, z) / 2.0;
 
                     half_extents *= transform.scale();
-                    let rotation = transform.rotation_quat();
 
                     half_extents.set_x(half_extents.x().abs().max(0.001));
                     half_extents.set_y(half_extents.y().abs().max(0.001));
@@ -89,8 +117,6 @@ impl EditorSelectableTransformed<MeshComponent> for MeshComponentDef {
                     let shape_handle = ShapeHandle::new(Cuboid::new(
                         ncollide3d::math::Vector::from(vec3_glam_to_glm(half_extents))
                     ));
-                    let rotation = nalgebra::Quaternion::new(rotation.w(), rotation.x(), rotation.y(), rotation.z());
-                    let rotation = nalgebra::UnitQuaternion::from_quaternion(rotation);
                     collision_world.add(
                         ncollide3d::math::Isometry::from_parts(
                             nalgebra::Translation::from(vec3_glam_to_glm( center)),
@@ -121,12 +147,28 @@ impl SpawnFrom<MeshComponentDef> for MeshComponent {
         let mut mesh_render_nodes = resources.get_mut::<MeshRenderNodeSet>().unwrap();
         let mut dynamic_visibility_node_set =
             resources.get_mut::<DynamicVisibilityNodeSet>().unwrap();
+        let mut static_mesh_set = resources.get_mut::<StaticMeshSet>().unwrap();
 
         for (from, into, dst_entity) in izip!(
             from,
             into,
             dst_entities
         ) {
+            let mesh_handle = from.mesh.as_ref().map(|x| x.handle.clone());
+
+            if from.is_static {
+                // Picked up by the next rebuild of the static mesh GPU data
+                static_mesh_set.mark_dirty();
+
+                *into = std::mem::MaybeUninit::new(MeshComponent {
+                    mesh_handle: None,
+                    visibility_handle: None,
+                    mesh: mesh_handle,
+                    is_static: true,
+                });
+                continue;
+            }
+
             let mesh_render_node_handle = mesh_render_nodes.register_mesh(MeshRenderNode {
                 entity: *dst_entity
             });
@@ -137,12 +179,11 @@ impl SpawnFrom<MeshComponentDef> for MeshComponent {
             };
             let visibility_node_handle = dynamic_visibility_node_set.register_dynamic_aabb(aabb_info);
 
-            let mesh_handle = from.mesh.as_ref().map(|x| x.handle.clone());
-
             *into = std::mem::MaybeUninit::new(MeshComponent {
-                mesh_handle: mesh_render_node_handle,
-                visibility_handle: visibility_node_handle,
-                mesh: mesh_handle
+                mesh_handle: Some(mesh_render_node_handle),
+                visibility_handle: Some(visibility_node_handle),
+                mesh: mesh_handle,
+                is_static: false,
                 //delete_body_tx: physics.delete_body_tx().clone(),
             })
         }
@@ -30,6 +30,8 @@ use legion_transaction::iter_components_in_storage;
 
 mod editable_handle;
 pub use editable_handle::EditableHandle;
+pub use editable_handle::EditableAsset;
+pub use editable_handle::OptionalEditableHandle;
 
 mod mesh_component;
 pub use mesh_component::MeshComponent;
@@ -41,6 +43,19 @@ pub use point_light_component::PointLightComponent;
 mod spot_light_component;
 pub use spot_light_component::SpotLightComponent;
 
+mod sprite_component;
+pub use sprite_component::SpriteComponent;
+pub use sprite_component::SpriteComponentDef;
+pub use sprite_component::SpriteSpace;
+
+mod text_component;
+pub use text_component::TextComponent;
+pub use text_component::TextComponentDef;
+
+mod particle_emitter_component;
+pub use particle_emitter_component::ParticleEmitterComponent;
+pub use particle_emitter_component::ParticleEmitterComponentDef;
+
 mod directional_light_component;
 pub use directional_light_component::DirectionalLightComponent;
 
@@ -49,11 +64,3 @@ pub use directional_light_component::DirectionalLightComponent;
 //     pub position: Vec3,
 // }
 
-
-#[derive(Clone)]
-pub struct SpriteComponent {
-    pub sprite_handle: SpriteRenderNodeHandle,
-    pub visibility_handle: DynamicAabbVisibilityNodeHandle,
-    pub alpha: f32,
-    pub image: Handle<ImageAsset>,
-}
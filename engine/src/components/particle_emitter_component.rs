@@ -0,0 +1,312 @@
+use crate::features::particle::{
+    ParticleRenderNodeHandle, ParticleRenderNodeSet, ParticleRenderNode, ParticleBlendMode,
+    ParticleFloatCurve, ParticleColorCurve, ParticleEmitterSettings, ParticleEmitterState,
+};
+use renderer::visibility::DynamicAabbVisibilityNodeHandle;
+use atelier_assets::loader::handle::Handle;
+use renderer::assets::ImageAsset;
+use type_uuid::*;
+use serde::{Serialize, Deserialize};
+use serde_diff::SerdeDiff;
+use minimum::editor::EditorSelectableTransformed;
+use minimum::math::Vec3;
+use legion::storage::ComponentStorage;
+use legion::index::ComponentIndex;
+use renderer::visibility::DynamicVisibilityNodeSet;
+use renderer::visibility::DynamicAabbVisibilityNode;
+
+use imgui_inspect_derive::Inspect;
+use legion::prelude::{Entity, Resources, World, EntityStore};
+use minimum::resources::editor::OpenedPrefabState;
+use minimum::components::TransformComponentDef;
+use ncollide3d::pipeline::{CollisionGroups, GeometricQueryType};
+use ncollide3d::world::CollisionWorld;
+use ncollide3d::shape::{Ball, ShapeHandle};
+use imgui::Ui;
+use imgui_inspect::InspectArgsDefault;
+use std::ops::Range;
+use legion_prefab::SpawnFrom;
+use crate::components::{EditableHandle, OptionalEditableHandle};
+use minimum::math::na_convert::vec3_glam_to_glm;
+
+const PARTICLE_BLEND_MODES: [ParticleBlendMode; 2] =
+    [ParticleBlendMode::Alpha, ParticleBlendMode::Additive];
+
+impl imgui_inspect::InspectRenderDefault<ParticleBlendMode> for ParticleBlendMode {
+    fn render(
+        data: &[&ParticleBlendMode],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) {
+        ui.text(imgui::im_str!("{}: {:?}", label, data[0]));
+    }
+
+    fn render_mut(
+        data: &mut [&mut ParticleBlendMode],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) -> bool {
+        let mut index = PARTICLE_BLEND_MODES
+            .iter()
+            .position(|x| *x == *data[0])
+            .unwrap_or(0);
+        let names = [imgui::im_str!("Alpha"), imgui::im_str!("Additive")];
+
+        let label = imgui::ImString::new(label);
+        let changed = imgui::ComboBox::new(&label).build_simple_string(ui, &mut index, &names);
+        if changed {
+            for d in data {
+                **d = PARTICLE_BLEND_MODES[index];
+            }
+        }
+
+        changed
+    }
+}
+
+// Existing keys can be edited. Keys are added or removed by editing the prefab
+impl imgui_inspect::InspectRenderDefault<ParticleFloatCurve> for ParticleFloatCurve {
+    fn render(
+        data: &[&ParticleFloatCurve],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) {
+        ui.text(imgui::im_str!("{}: {:?}", label, data[0].keys));
+    }
+
+    fn render_mut(
+        data: &mut [&mut ParticleFloatCurve],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) -> bool {
+        let mut curve = data[0].clone();
+        let mut changed = false;
+
+        ui.text(imgui::im_str!("{}", label));
+        for (index, (time, value)) in curve.keys.iter_mut().enumerate() {
+            changed |= ui
+                .drag_float(&imgui::ImString::new(format!("age##{}{}", label, index)), time)
+                .min(0.0)
+                .max(1.0)
+                .speed(0.01)
+                .build();
+            changed |= ui
+                .drag_float(&imgui::ImString::new(format!("value##{}{}", label, index)), value)
+                .speed(0.01)
+                .build();
+        }
+
+        if changed {
+            curve.keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            for d in data {
+                **d = curve.clone();
+            }
+        }
+
+        changed
+    }
+}
+
+// Existing keys can be edited. Keys are added or removed by editing the prefab
+impl imgui_inspect::InspectRenderDefault<ParticleColorCurve> for ParticleColorCurve {
+    fn render(
+        data: &[&ParticleColorCurve],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) {
+        ui.text(imgui::im_str!("{}: {:?}", label, data[0].keys));
+    }
+
+    fn render_mut(
+        data: &mut [&mut ParticleColorCurve],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) -> bool {
+        let mut curve = data[0].clone();
+        let mut changed = false;
+
+        ui.text(imgui::im_str!("{}", label));
+        for (index, (time, value)) in curve.keys.iter_mut().enumerate() {
+            changed |= ui
+                .drag_float(&imgui::ImString::new(format!("age##{}{}", label, index)), time)
+                .min(0.0)
+                .max(1.0)
+                .speed(0.01)
+                .build();
+            changed |= imgui::ColorEdit::new(
+                &imgui::ImString::new(format!("color##{}{}", label, index)),
+                value,
+            )
+            .build(ui);
+        }
+
+        if changed {
+            curve.keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+            for d in data {
+                **d = curve.clone();
+            }
+        }
+
+        changed
+    }
+}
+
+#[derive(TypeUuid, Serialize, Deserialize, SerdeDiff, Debug, PartialEq, Clone, Inspect)]
+#[uuid = "b7e2d4f1-85a3-4c6e-9f1d-3a0c8e5b2d74"]
+pub struct ParticleEmitterComponentDef {
+    // A white square is used if there is no texture
+    #[inspect(proxy_type = "OptionalEditableHandle")]
+    pub texture: Option<EditableHandle<ImageAsset>>,
+    #[serde_diff(opaque)]
+    pub blend_mode: ParticleBlendMode,
+    // Emitters with the same seed and settings produce the same particles
+    pub seed: u32,
+    // Particles per second
+    pub spawn_rate: f32,
+    pub max_particles: u32,
+    // Seconds
+    pub lifetime_min: f32,
+    pub lifetime_max: f32,
+    pub speed_min: f32,
+    pub speed_max: f32,
+    // Center of the cone particles are emitted in, relative to the entity's rotation
+    #[serde_diff(opaque)]
+    pub direction: Vec3,
+    // Radians
+    pub cone_half_angle: f32,
+    // World-space, i.e. gravity
+    #[serde_diff(opaque)]
+    pub acceleration: Vec3,
+    #[serde_diff(opaque)]
+    pub size_over_life: ParticleFloatCurve,
+    #[serde_diff(opaque)]
+    pub color_over_life: ParticleColorCurve,
+}
+
+impl Default for ParticleEmitterComponentDef {
+    fn default() -> Self {
+        ParticleEmitterComponentDef {
+            texture: None,
+            blend_mode: ParticleBlendMode::Alpha,
+            seed: 0,
+            spawn_rate: 10.0,
+            max_particles: 100,
+            lifetime_min: 1.0,
+            lifetime_max: 2.0,
+            speed_min: 0.5,
+            speed_max: 1.0,
+            direction: glam::Vec3::new(0.0, 0.0, 1.0).into(),
+            cone_half_angle: 0.3,
+            acceleration: glam::Vec3::zero().into(),
+            size_over_life: ParticleFloatCurve::constant(0.25),
+            color_over_life: ParticleColorCurve {
+                keys: vec![(0.0, [1.0, 1.0, 1.0, 1.0]), (1.0, [1.0, 1.0, 1.0, 0.0])],
+            },
+        }
+    }
+}
+
+legion_prefab::register_component_type!(ParticleEmitterComponentDef);
+
+impl ParticleEmitterComponentDef {
+    pub fn settings(&self) -> ParticleEmitterSettings {
+        ParticleEmitterSettings {
+            spawn_rate: self.spawn_rate,
+            max_particles: self.max_particles as usize,
+            lifetime_min: self.lifetime_min,
+            lifetime_max: self.lifetime_max,
+            speed_min: self.speed_min,
+            speed_max: self.speed_max,
+            direction: *self.direction,
+            cone_half_angle: self.cone_half_angle,
+            acceleration: *self.acceleration,
+            size_over_life: self.size_over_life.clone(),
+            color_over_life: self.color_over_life.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ParticleEmitterComponent {
+    pub render_node_handle: ParticleRenderNodeHandle,
+    pub visibility_handle: DynamicAabbVisibilityNodeHandle,
+    pub texture: Option<Handle<ImageAsset>>,
+    pub blend_mode: ParticleBlendMode,
+    pub settings: ParticleEmitterSettings,
+    // Updated by the simulate_particles system
+    pub state: ParticleEmitterState,
+}
+
+impl EditorSelectableTransformed<ParticleEmitterComponent> for ParticleEmitterComponentDef {
+    fn create_editor_selection_world(
+        &self,
+        collision_world: &mut CollisionWorld<f32, Entity>,
+        _resources: &Resources,
+        _opened_prefab: &OpenedPrefabState,
+        prefab_world: &World,
+        prefab_entity: Entity,
+        _transformed_world: &World,
+        transformed_entity: Entity,
+        _transformed_component: &ParticleEmitterComponent,
+    ) {
+        // Particles move away from the emitter, so only the emitter itself is selectable
+        if let Some(transform) = prefab_world.get_component::<TransformComponentDef>(prefab_entity) {
+            let shape_handle = ShapeHandle::new(Ball::new(0.25));
+            let rotation = nalgebra::UnitQuaternion::identity();
+            collision_world.add(
+                ncollide3d::math::Isometry::from_parts(
+                    nalgebra::Translation::from(vec3_glam_to_glm(transform.position())),
+                    rotation,
+                ),
+                shape_handle,
+                CollisionGroups::new(),
+                GeometricQueryType::Proximity(0.001),
+                transformed_entity,
+            );
+        }
+    }
+}
+
+impl SpawnFrom<ParticleEmitterComponentDef> for ParticleEmitterComponent {
+    fn spawn_from(
+        _src_world: &World,
+        _src_component_storage: &ComponentStorage,
+        _src_component_storage_indexes: Range<ComponentIndex>,
+        resources: &Resources,
+        _src_entities: &[Entity],
+        dst_entities: &[Entity],
+        from: &[ParticleEmitterComponentDef],
+        into: &mut [std::mem::MaybeUninit<Self>],
+    ) {
+        let mut particle_render_nodes = resources.get_mut::<ParticleRenderNodeSet>().unwrap();
+        let mut dynamic_visibility_node_set =
+            resources.get_mut::<DynamicVisibilityNodeSet>().unwrap();
+
+        for (from, into, dst_entity) in izip!(from, into, dst_entities) {
+            let render_node_handle = particle_render_nodes.register_emitter(ParticleRenderNode {
+                entity: *dst_entity,
+            });
+
+            let aabb_info = DynamicAabbVisibilityNode {
+                handle: render_node_handle.into(),
+                // aabb bounds
+            };
+            let visibility_handle = dynamic_visibility_node_set.register_dynamic_aabb(aabb_info);
+
+            *into = std::mem::MaybeUninit::new(ParticleEmitterComponent {
+                render_node_handle,
+                visibility_handle,
+                texture: from.texture.as_ref().map(|x| x.handle.clone()),
+                blend_mode: from.blend_mode,
+                settings: from.settings(),
+                state: ParticleEmitterState::new(from.seed as u64),
+            })
+        }
+    }
+}
@@ -0,0 +1,267 @@
+use crate::features::sprite::{SpriteRenderNodeHandle, SpriteRenderNodeSet, SpriteRenderNode};
+use renderer::visibility::DynamicAabbVisibilityNodeHandle;
+use atelier_assets::loader::handle::Handle;
+use renderer::assets::ImageAsset;
+use crate::assets::sprite::SpriteSheetAsset;
+use type_uuid::*;
+use serde::{Serialize, Deserialize};
+use serde_diff::SerdeDiff;
+use minimum::editor::EditorSelectableTransformed;
+use minimum::math::{Vec2, Vec4};
+use legion::storage::ComponentStorage;
+use legion::index::ComponentIndex;
+use renderer::visibility::DynamicVisibilityNodeSet;
+use renderer::visibility::DynamicAabbVisibilityNode;
+
+use imgui_inspect_derive::Inspect;
+use legion::prelude::{Entity, Resources, World, EntityStore};
+use minimum::resources::editor::OpenedPrefabState;
+use minimum::components::TransformComponentDef;
+use ncollide3d::pipeline::{CollisionGroups, GeometricQueryType};
+use ncollide3d::world::CollisionWorld;
+use imgui::Ui;
+use imgui_inspect::InspectArgsDefault;
+use std::ops::Range;
+use legion_prefab::SpawnFrom;
+use crate::components::{EditableHandle, OptionalEditableHandle};
+use ncollide3d::shape::{Cuboid, ShapeHandle};
+use minimum::math::na_convert::vec3_glam_to_glm;
+
+/// How a sprite's quad is placed
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SpriteSpace {
+    // Lies in the XY plane of the entity's transform
+    World,
+    // Positioned by the entity's transform but always faces the camera
+    Billboard,
+    // Drawn in the screen-space view over the scene. Units are the same as the screen-space
+    // viewport (600 units tall)
+    Screen,
+}
+
+impl Default for SpriteSpace {
+    fn default() -> Self {
+        SpriteSpace::World
+    }
+}
+
+const SPRITE_SPACES: [SpriteSpace; 3] =
+    [SpriteSpace::World, SpriteSpace::Billboard, SpriteSpace::Screen];
+
+impl imgui_inspect::InspectRenderDefault<SpriteSpace> for SpriteSpace {
+    fn render(
+        data: &[&SpriteSpace],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) {
+        ui.text(imgui::im_str!("{}: {:?}", label, data[0]));
+    }
+
+    fn render_mut(
+        data: &mut [&mut SpriteSpace],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) -> bool {
+        let mut index = SPRITE_SPACES
+            .iter()
+            .position(|x| *x == *data[0])
+            .unwrap_or(0);
+        let names = [
+            imgui::im_str!("World"),
+            imgui::im_str!("Billboard"),
+            imgui::im_str!("Screen"),
+        ];
+
+        let label = imgui::ImString::new(label);
+        let changed = imgui::ComboBox::new(&label).build_simple_string(ui, &mut index, &names);
+        if changed {
+            for d in data {
+                **d = SPRITE_SPACES[index];
+            }
+        }
+
+        changed
+    }
+}
+
+#[derive(TypeUuid, Serialize, Deserialize, SerdeDiff, Debug, PartialEq, Clone, Inspect)]
+#[uuid = "f5c1b4a9-6e0d-4a8b-93f2-2d7c58e1a3b6"]
+pub struct SpriteComponentDef {
+    // Used if sprite_sheet is None, otherwise the sheet's image is used
+    #[inspect(proxy_type = "OptionalEditableHandle")]
+    pub image: Option<EditableHandle<ImageAsset>>,
+    #[serde(default)]
+    #[inspect(proxy_type = "OptionalEditableHandle")]
+    pub sprite_sheet: Option<EditableHandle<SpriteSheetAsset>>,
+    #[serde(default)]
+    pub frame: u32,
+    #[serde(default)]
+    #[serde_diff(opaque)]
+    pub space: SpriteSpace,
+    #[serde_diff(opaque)]
+    pub size: Vec2,
+    #[serde_diff(opaque)]
+    pub pivot: Vec2,
+    #[serde_diff(opaque)]
+    pub tint: Vec4,
+    #[serde(default)]
+    pub flip_x: bool,
+    #[serde(default)]
+    pub flip_y: bool,
+    // Clamped to the range of an i16 when spawned
+    #[serde(default)]
+    pub sorting_layer: i32,
+}
+
+impl Default for SpriteComponentDef {
+    fn default() -> Self {
+        SpriteComponentDef {
+            image: None,
+            sprite_sheet: None,
+            frame: 0,
+            space: SpriteSpace::World,
+            size: glam::Vec2::new(1.0, 1.0).into(),
+            pivot: glam::Vec2::new(0.5, 0.5).into(),
+            tint: glam::Vec4::new(1.0, 1.0, 1.0, 1.0).into(),
+            flip_x: false,
+            flip_y: false,
+            sorting_layer: 0,
+        }
+    }
+}
+
+legion_prefab::register_component_type!(SpriteComponentDef);
+
+#[derive(Clone)]
+pub struct SpriteComponent {
+    pub sprite_handle: SpriteRenderNodeHandle,
+    pub visibility_handle: DynamicAabbVisibilityNodeHandle,
+    // Used if sprite_sheet is None, otherwise the sheet's image is used
+    pub image: Option<Handle<ImageAsset>>,
+    pub sprite_sheet: Option<Handle<SpriteSheetAsset>>,
+    pub frame: usize,
+    pub space: SpriteSpace,
+    // Size of the quad before the entity's scale is applied
+    pub size: glam::Vec2,
+    // The point of the quad placed at the entity's position. (0, 0) is bottom-left, (1, 1) is
+    // top-right
+    pub pivot: glam::Vec2,
+    // Multiplied with the texture color. Alpha controls transparency
+    pub tint: glam::Vec4,
+    pub flip_x: bool,
+    pub flip_y: bool,
+    // Sprites on higher layers are drawn over sprites on lower layers regardless of distance
+    pub sorting_layer: i16,
+}
+
+impl EditorSelectableTransformed<SpriteComponent> for SpriteComponentDef {
+    fn create_editor_selection_world(
+        &self,
+        collision_world: &mut CollisionWorld<f32, Entity>,
+        _resources: &Resources,
+        _opened_prefab: &OpenedPrefabState,
+        prefab_world: &World,
+        prefab_entity: Entity,
+        _transformed_world: &World,
+        transformed_entity: Entity,
+        _transformed_component: &SpriteComponent,
+    ) {
+        // Screen-space sprites aren't placed in the world, so they can't be picked in the scene
+        if self.space == SpriteSpace::Screen {
+            return;
+        }
+
+        if let Some(transform) = prefab_world.get_component::<TransformComponentDef>(prefab_entity) {
+            let scale = transform.scale();
+            let rotation = transform.rotation_quat();
+
+            // The quad lies in the XY plane, give it a little depth so it can be hit edge-on.
+            // Billboards face the camera so they are approximated by the unrotated quad
+            let mut half_extents = glam::Vec3::new(self.size.x(), self.size.y(), 0.0) * scale / 2.0;
+            half_extents.set_x(half_extents.x().abs().max(0.001));
+            half_extents.set_y(half_extents.y().abs().max(0.001));
+            half_extents.set_z(0.01);
+
+            // Offset the quad so that the pivot lands on the entity's position
+            let pivot_offset = glam::Vec3::new(
+                (0.5 - self.pivot.x()) * self.size.x(),
+                (0.5 - self.pivot.y()) * self.size.y(),
+                0.0,
+            ) * scale;
+
+            let rotation = match self.space {
+                SpriteSpace::Billboard => glam::Quat::identity(),
+                _ => rotation,
+            };
+            let center = transform.position() + rotation.mul_vec3(pivot_offset);
+
+            let shape_handle = ShapeHandle::new(Cuboid::new(ncollide3d::math::Vector::from(
+                vec3_glam_to_glm(half_extents),
+            )));
+            let rotation =
+                nalgebra::Quaternion::new(rotation.w(), rotation.x(), rotation.y(), rotation.z());
+            let rotation = nalgebra::UnitQuaternion::from_quaternion(rotation);
+            collision_world.add(
+                ncollide3d::math::Isometry::from_parts(
+                    nalgebra::Translation::from(vec3_glam_to_glm(center)),
+                    rotation,
+                ),
+                shape_handle,
+                CollisionGroups::new(),
+                GeometricQueryType::Proximity(0.001),
+                transformed_entity,
+            );
+        }
+    }
+}
+
+impl SpawnFrom<SpriteComponentDef> for SpriteComponent {
+    fn spawn_from(
+        _src_world: &World,
+        _src_component_storage: &ComponentStorage,
+        _src_component_storage_indexes: Range<ComponentIndex>,
+        resources: &Resources,
+        _src_entities: &[Entity],
+        dst_entities: &[Entity],
+        from: &[SpriteComponentDef],
+        into: &mut [std::mem::MaybeUninit<Self>],
+    ) {
+        let mut sprite_render_nodes = resources.get_mut::<SpriteRenderNodeSet>().unwrap();
+        let mut dynamic_visibility_node_set =
+            resources.get_mut::<DynamicVisibilityNodeSet>().unwrap();
+
+        for (from, into, dst_entity) in izip!(from, into, dst_entities) {
+            let sprite_handle = sprite_render_nodes.register_sprite(SpriteRenderNode {
+                entity: *dst_entity,
+            });
+
+            let aabb_info = DynamicAabbVisibilityNode {
+                handle: sprite_handle.into(),
+                // aabb bounds
+            };
+            let visibility_handle = dynamic_visibility_node_set.register_dynamic_aabb(aabb_info);
+
+            let sorting_layer = from
+                .sorting_layer
+                .max(std::i16::MIN as i32)
+                .min(std::i16::MAX as i32) as i16;
+
+            *into = std::mem::MaybeUninit::new(SpriteComponent {
+                sprite_handle,
+                visibility_handle,
+                image: from.image.as_ref().map(|x| x.handle.clone()),
+                sprite_sheet: from.sprite_sheet.as_ref().map(|x| x.handle.clone()),
+                frame: from.frame as usize,
+                space: from.space,
+                size: *from.size,
+                pivot: *from.pivot,
+                tint: *from.tint,
+                flip_x: from.flip_x,
+                flip_y: from.flip_y,
+                sorting_layer,
+            })
+        }
+    }
+}
@@ -0,0 +1,147 @@
+use crate::features::text::{TextRenderNodeHandle, TextRenderNodeSet, TextRenderNode};
+use renderer::visibility::DynamicAabbVisibilityNodeHandle;
+use atelier_assets::loader::handle::Handle;
+use crate::assets::font::{FontAsset, TextAlignment};
+use type_uuid::*;
+use serde::{Serialize, Deserialize};
+use serde_diff::SerdeDiff;
+use minimum::math::Vec4;
+use legion::storage::ComponentStorage;
+use legion::index::ComponentIndex;
+use renderer::visibility::DynamicVisibilityNodeSet;
+use renderer::visibility::DynamicAabbVisibilityNode;
+
+use imgui_inspect_derive::Inspect;
+use legion::prelude::{Entity, Resources, World};
+use imgui::Ui;
+use imgui_inspect::InspectArgsDefault;
+use std::ops::Range;
+use legion_prefab::SpawnFrom;
+use crate::components::{EditableHandle, OptionalEditableHandle};
+
+const TEXT_ALIGNMENTS: [TextAlignment; 3] =
+    [TextAlignment::Left, TextAlignment::Center, TextAlignment::Right];
+
+impl imgui_inspect::InspectRenderDefault<TextAlignment> for TextAlignment {
+    fn render(
+        data: &[&TextAlignment],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) {
+        ui.text(imgui::im_str!("{}: {:?}", label, data[0]));
+    }
+
+    fn render_mut(
+        data: &mut [&mut TextAlignment],
+        label: &'static str,
+        ui: &Ui,
+        _args: &InspectArgsDefault,
+    ) -> bool {
+        let mut index = TEXT_ALIGNMENTS
+            .iter()
+            .position(|x| *x == *data[0])
+            .unwrap_or(0);
+        let names = [
+            imgui::im_str!("Left"),
+            imgui::im_str!("Center"),
+            imgui::im_str!("Right"),
+        ];
+
+        let label = imgui::ImString::new(label);
+        let changed = imgui::ComboBox::new(&label).build_simple_string(ui, &mut index, &names);
+        if changed {
+            for d in data {
+                **d = TEXT_ALIGNMENTS[index];
+            }
+        }
+
+        changed
+    }
+}
+
+#[derive(TypeUuid, Serialize, Deserialize, SerdeDiff, Debug, PartialEq, Clone, Inspect)]
+#[uuid = "9c3e5a71-d2b8-4f06-8e4a-1b7f6c2d9a35"]
+pub struct TextComponentDef {
+    #[inspect(proxy_type = "OptionalEditableHandle")]
+    pub font: Option<EditableHandle<FontAsset>>,
+    pub text: String,
+    // Height of a line of text before the entity's scale is applied
+    pub size: f32,
+    #[serde_diff(opaque)]
+    pub color: Vec4,
+    #[serde(default)]
+    #[serde_diff(opaque)]
+    pub alignment: TextAlignment,
+    // Billboarded text always faces the camera, otherwise it lies in the XY plane of the entity's
+    // transform
+    #[serde(default)]
+    pub billboard: bool,
+}
+
+impl Default for TextComponentDef {
+    fn default() -> Self {
+        TextComponentDef {
+            font: None,
+            text: String::default(),
+            size: 1.0,
+            color: glam::Vec4::new(1.0, 1.0, 1.0, 1.0).into(),
+            alignment: TextAlignment::Left,
+            billboard: false,
+        }
+    }
+}
+
+legion_prefab::register_component_type!(TextComponentDef);
+
+#[derive(Clone)]
+pub struct TextComponent {
+    pub text_handle: TextRenderNodeHandle,
+    pub visibility_handle: DynamicAabbVisibilityNodeHandle,
+    pub font: Option<Handle<FontAsset>>,
+    pub text: String,
+    pub size: f32,
+    pub color: glam::Vec4,
+    pub alignment: TextAlignment,
+    pub billboard: bool,
+}
+
+impl SpawnFrom<TextComponentDef> for TextComponent {
+    fn spawn_from(
+        _src_world: &World,
+        _src_component_storage: &ComponentStorage,
+        _src_component_storage_indexes: Range<ComponentIndex>,
+        resources: &Resources,
+        _src_entities: &[Entity],
+        dst_entities: &[Entity],
+        from: &[TextComponentDef],
+        into: &mut [std::mem::MaybeUninit<Self>],
+    ) {
+        let mut text_render_nodes = resources.get_mut::<TextRenderNodeSet>().unwrap();
+        let mut dynamic_visibility_node_set =
+            resources.get_mut::<DynamicVisibilityNodeSet>().unwrap();
+
+        for (from, into, dst_entity) in izip!(from, into, dst_entities) {
+            let text_handle = text_render_nodes.register_text(TextRenderNode {
+                entity: *dst_entity,
+            });
+
+            let aabb_info = DynamicAabbVisibilityNode {
+                handle: text_handle.into(),
+                // aabb bounds
+            };
+            let visibility_handle = dynamic_visibility_node_set.register_dynamic_aabb(aabb_info);
+
+            *into = std::mem::MaybeUninit::new(TextComponent {
+                text_handle,
+                visibility_handle,
+                font: from.font.as_ref().map(|x| x.handle.clone()),
+                text: from.text.clone(),
+                size: from.size,
+                color: *from.color,
+                alignment: from.alignment,
+                billboard: from.billboard,
+            })
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use glam::{Vec3, Vec4, Quat, Mat4};
+use minimum::resources::DebugDraw3DDepthBehavior;
+use std::time::{Duration, Instant};
+
+/// A polyline, drawn with the line strip pipelines
+#[derive(Clone, Debug)]
+pub struct DebugLineStrip3D {
+    pub points: Vec<Vec3>,
+    pub color: Vec4,
+    pub depth_behavior: DebugDraw3DDepthBehavior,
+}
+
+/// A filled triangle, blended using the alpha of its color
+#[derive(Clone, Debug)]
+pub struct DebugTriangle3D {
+    pub positions: [Vec3; 3],
+    pub color: Vec4,
+    pub depth_behavior: DebugDraw3DDepthBehavior,
+}
+
+/// Text that always faces the camera, drawn with line strips. Only A-Z, 0-9 and a few symbols are
+/// supported, lowercase letters are drawn as uppercase
+#[derive(Clone, Debug)]
+pub struct DebugLabel3D {
+    pub position: Vec3,
+    pub text: String,
+    pub height: f32,
+    pub color: Vec4,
+    pub depth_behavior: DebugDraw3DDepthBehavior,
+}
+
+/// Everything that should be drawn this frame
+#[derive(Default)]
+pub struct DebugShapes3D {
+    pub line_strips: Vec<DebugLineStrip3D>,
+    pub triangles: Vec<DebugTriangle3D>,
+    pub labels: Vec<DebugLabel3D>,
+}
+
+struct TimedDebugShape<T> {
+    shape: T,
+    // None if the shape is only drawn once
+    expires_at: Option<Instant>,
+}
+
+// Keeps shapes that have not expired yet and drains the ones that are drawn once
+fn take_timed_shapes<T: Clone>(
+    timed_shapes: &mut Vec<TimedDebugShape<T>>,
+    now: Instant,
+) -> Vec<T> {
+    timed_shapes.retain(|x| x.expires_at.map(|expires_at| expires_at > now).unwrap_or(true));
+    let shapes = timed_shapes.iter().map(|x| x.shape.clone()).collect();
+    timed_shapes.retain(|x| x.expires_at.is_some());
+    shapes
+}
+
+// Two unit vectors perpendicular to the given direction and to each other
+fn perpendicular_axes(direction: Vec3) -> (Vec3, Vec3) {
+    let reference = if direction.z().abs() < 0.9 {
+        Vec3::unit_z()
+    } else {
+        Vec3::unit_x()
+    };
+
+    let x_axis = direction.cross(reference).normalize();
+    let y_axis = direction.cross(x_axis).normalize();
+    (x_axis, y_axis)
+}
+
+/// Debug shapes beyond the lines, spheres and cones of `DebugDraw3DResource`. Every shape takes a
+/// duration in seconds. Shapes with a duration of zero are drawn for a single frame, others are
+/// drawn every frame until the duration elapses.
+pub struct DebugDraw3DShapesResource {
+    line_strips: Vec<TimedDebugShape<DebugLineStrip3D>>,
+    triangles: Vec<TimedDebugShape<DebugTriangle3D>>,
+    labels: Vec<TimedDebugShape<DebugLabel3D>>,
+}
+
+impl DebugDraw3DShapesResource {
+    pub fn new() -> Self {
+        DebugDraw3DShapesResource {
+            line_strips: Default::default(),
+            triangles: Default::default(),
+            labels: Default::default(),
+        }
+    }
+
+    fn expires_at(duration: f32) -> Option<Instant> {
+        if duration > 0.0 {
+            Some(Instant::now() + Duration::from_secs_f32(duration))
+        } else {
+            None
+        }
+    }
+
+    // Adds a single polyline
+    pub fn add_line_strip(
+        &mut self,
+        points: Vec<Vec3>,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        // Nothing will draw if we don't have at least 2 points
+        if points.len() > 1 {
+            self.line_strips.push(TimedDebugShape {
+                shape: DebugLineStrip3D {
+                    points,
+                    color,
+                    depth_behavior,
+                },
+                expires_at: Self::expires_at(duration),
+            });
+        }
+    }
+
+    // Adds a polyline that ends where it started
+    pub fn add_line_loop(
+        &mut self,
+        mut points: Vec<Vec3>,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        if points.len() > 2 {
+            points.push(points[0]);
+            self.add_line_strip(points, color, depth_behavior, duration);
+        }
+    }
+
+    pub fn add_line(
+        &mut self,
+        p0: Vec3,
+        p1: Vec3,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        self.add_line_strip(vec![p0, p1], color, depth_behavior, duration);
+    }
+
+    // Circle in the plane perpendicular to normal
+    pub fn add_circle(
+        &mut self,
+        center: Vec3,
+        normal: Vec3,
+        radius: f32,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+        segments: u32,
+    ) {
+        let (x_axis, y_axis) = perpendicular_axes(normal.normalize());
+        self.add_arc(
+            center,
+            x_axis * radius,
+            y_axis * radius,
+            std::f32::consts::PI * 2.0,
+            color,
+            depth_behavior,
+            duration,
+            segments,
+        );
+    }
+
+    pub fn add_sphere(
+        &mut self,
+        center: Vec3,
+        radius: f32,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+        segments: u32,
+    ) {
+        for normal in &[Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()] {
+            self.add_circle(
+                center,
+                *normal,
+                radius,
+                color,
+                depth_behavior,
+                duration,
+                segments,
+            );
+        }
+    }
+
+    // Sweeps from start_offset towards perpendicular_offset by angle radians around center
+    fn add_arc(
+        &mut self,
+        center: Vec3,
+        start_offset: Vec3,
+        perpendicular_offset: Vec3,
+        angle: f32,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+        segments: u32,
+    ) {
+        let segments = segments.max(2);
+        let points = (0..=segments)
+            .map(|i| {
+                let theta = angle * i as f32 / segments as f32;
+                center + start_offset * theta.cos() + perpendicular_offset * theta.sin()
+            })
+            .collect();
+
+        self.add_line_strip(points, color, depth_behavior, duration);
+    }
+
+    // Draws the 12 edges of a box from its 8 corners. The first four corners are one face and the
+    // last four are the opposite face, in the same winding
+    fn add_box_corners(
+        &mut self,
+        corners: &[Vec3; 8],
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        self.add_line_loop(corners[0..4].to_vec(), color, depth_behavior, duration);
+        self.add_line_loop(corners[4..8].to_vec(), color, depth_behavior, duration);
+        for i in 0..4 {
+            self.add_line(corners[i], corners[i + 4], color, depth_behavior, duration);
+        }
+    }
+
+    // Oriented box
+    pub fn add_obb(
+        &mut self,
+        center: Vec3,
+        half_extents: Vec3,
+        rotation: Quat,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        let corner = |x: f32, y: f32, z: f32| {
+            center
+                + rotation
+                    * Vec3::new(
+                        x * half_extents.x(),
+                        y * half_extents.y(),
+                        z * half_extents.z(),
+                    )
+        };
+
+        let corners = [
+            corner(-1.0, -1.0, -1.0),
+            corner(1.0, -1.0, -1.0),
+            corner(1.0, 1.0, -1.0),
+            corner(-1.0, 1.0, -1.0),
+            corner(-1.0, -1.0, 1.0),
+            corner(1.0, -1.0, 1.0),
+            corner(1.0, 1.0, 1.0),
+            corner(-1.0, 1.0, 1.0),
+        ];
+
+        self.add_box_corners(&corners, color, depth_behavior, duration);
+    }
+
+    // Axis-aligned box
+    pub fn add_aabb(
+        &mut self,
+        min: Vec3,
+        max: Vec3,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        self.add_obb(
+            (min + max) * 0.5,
+            (max - min) * 0.5,
+            Quat::identity(),
+            color,
+            depth_behavior,
+            duration,
+        );
+    }
+
+    // Draws the volume visible through a view/projection matrix, i.e. a camera or a shadow
+    // cascade. Uses the vulkan depth range of 0..1
+    pub fn add_frustum(
+        &mut self,
+        view_proj: Mat4,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        let inverse_view_proj = view_proj.inverse();
+        let corner = |x: f32, y: f32, z: f32| {
+            let world = inverse_view_proj * Vec4::new(x, y, z, 1.0);
+            world.truncate() / world.w()
+        };
+
+        let corners = [
+            corner(-1.0, -1.0, 0.0),
+            corner(1.0, -1.0, 0.0),
+            corner(1.0, 1.0, 0.0),
+            corner(-1.0, 1.0, 0.0),
+            corner(-1.0, -1.0, 1.0),
+            corner(1.0, -1.0, 1.0),
+            corner(1.0, 1.0, 1.0),
+            corner(-1.0, 1.0, 1.0),
+        ];
+
+        self.add_box_corners(&corners, color, depth_behavior, duration);
+    }
+
+    // Line from p0 to p1 with a head at p1. The head is a fraction of the arrow's length
+    pub fn add_arrow(
+        &mut self,
+        p0: Vec3,
+        p1: Vec3,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        self.add_line(p0, p1, color, depth_behavior, duration);
+
+        let length = (p1 - p0).length();
+        if length <= std::f32::EPSILON {
+            return;
+        }
+
+        let direction = (p1 - p0) / length;
+        let (x_axis, y_axis) = perpendicular_axes(direction);
+        let head_length = length * 0.2;
+        let head_base = p1 - direction * head_length;
+        let head_radius = head_length * 0.4;
+
+        self.add_line_strip(
+            vec![
+                head_base + x_axis * head_radius,
+                p1,
+                head_base - x_axis * head_radius,
+            ],
+            color,
+            depth_behavior,
+            duration,
+        );
+        self.add_line_strip(
+            vec![
+                head_base + y_axis * head_radius,
+                p1,
+                head_base - y_axis * head_radius,
+            ],
+            color,
+            depth_behavior,
+            duration,
+        );
+    }
+
+    // Square grid of cell_count x cell_count cells in the plane perpendicular to normal
+    pub fn add_grid(
+        &mut self,
+        center: Vec3,
+        normal: Vec3,
+        cell_size: f32,
+        cell_count: u32,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        let (x_axis, y_axis) = perpendicular_axes(normal.normalize());
+        let half_size = cell_size * cell_count as f32 * 0.5;
+
+        for i in 0..=cell_count {
+            let offset = i as f32 * cell_size - half_size;
+            self.add_line(
+                center + x_axis * offset - y_axis * half_size,
+                center + x_axis * offset + y_axis * half_size,
+                color,
+                depth_behavior,
+                duration,
+            );
+            self.add_line(
+                center + y_axis * offset - x_axis * half_size,
+                center + y_axis * offset + x_axis * half_size,
+                color,
+                depth_behavior,
+                duration,
+            );
+        }
+    }
+
+    // Cylinder from p0 to p1 with hemispheres on both ends
+    pub fn add_capsule(
+        &mut self,
+        p0: Vec3,
+        p1: Vec3,
+        radius: f32,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+        segments: u32,
+    ) {
+        let axis = p1 - p0;
+        let direction = if axis.length() > std::f32::EPSILON {
+            axis.normalize()
+        } else {
+            Vec3::unit_z()
+        };
+
+        let (x_axis, y_axis) = perpendicular_axes(direction);
+
+        // Rings where the cylinder meets the hemispheres
+        self.add_circle(p0, direction, radius, color, depth_behavior, duration, segments);
+        self.add_circle(p1, direction, radius, color, depth_behavior, duration, segments);
+
+        for side_axis in &[x_axis, -x_axis, y_axis, -y_axis] {
+            let side_offset = *side_axis * radius;
+            self.add_line(
+                p0 + side_offset,
+                p1 + side_offset,
+                color,
+                depth_behavior,
+                duration,
+            );
+        }
+
+        // Hemispheres are drawn as two half circles each
+        for side_axis in &[x_axis, y_axis] {
+            let side_offset = *side_axis * radius;
+            self.add_arc(
+                p1,
+                side_offset,
+                direction * radius,
+                std::f32::consts::PI,
+                color,
+                depth_behavior,
+                duration,
+                segments / 2,
+            );
+            self.add_arc(
+                p0,
+                side_offset,
+                -direction * radius,
+                std::f32::consts::PI,
+                color,
+                depth_behavior,
+                duration,
+                segments / 2,
+            );
+        }
+    }
+
+    // Filled triangle. Use an alpha below 1.0 to see what is behind it
+    pub fn add_triangle(
+        &mut self,
+        p0: Vec3,
+        p1: Vec3,
+        p2: Vec3,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        self.triangles.push(TimedDebugShape {
+            shape: DebugTriangle3D {
+                positions: [p0, p1, p2],
+                color,
+                depth_behavior,
+            },
+            expires_at: Self::expires_at(duration),
+        });
+    }
+
+    // Filled quad from four corners in winding order
+    pub fn add_quad(
+        &mut self,
+        corners: [Vec3; 4],
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        self.add_triangle(corners[0], corners[1], corners[2], color, depth_behavior, duration);
+        self.add_triangle(corners[2], corners[3], corners[0], color, depth_behavior, duration);
+    }
+
+    // Text centered horizontally on position, facing the camera. Height is in world units
+    pub fn add_text(
+        &mut self,
+        position: Vec3,
+        text: &str,
+        height: f32,
+        color: Vec4,
+        depth_behavior: DebugDraw3DDepthBehavior,
+        duration: f32,
+    ) {
+        self.labels.push(TimedDebugShape {
+            shape: DebugLabel3D {
+                position,
+                text: text.to_string(),
+                height,
+                color,
+                depth_behavior,
+            },
+            expires_at: Self::expires_at(duration),
+        });
+    }
+
+    // Returns everything that should be drawn this frame. Shapes with a duration remain in the
+    // resource until they expire
+    pub fn take_shapes(&mut self) -> DebugShapes3D {
+        let now = Instant::now();
+        DebugShapes3D {
+            line_strips: take_timed_shapes(&mut self.line_strips, now),
+            triangles: take_timed_shapes(&mut self.triangles, now),
+            labels: take_timed_shapes(&mut self.labels, now),
+        }
+    }
+
+    // Removes everything, including shapes that have not expired yet
+    pub fn clear(&mut self) {
+        self.line_strips.clear();
+        self.triangles.clear();
+        self.labels.clear();
+    }
+}
@@ -1,5 +1,6 @@
 use crate::features::debug3d::{
     ExtractedDebugData, Debug3dRenderFeature, DebugDraw2DResource, DebugDraw3DResource, Debug3dUniformBufferObject,
+    DebugDraw3DShapesResource,
 };
 use crate::render_contexts::{RenderJobExtractContext, RenderJobWriteContext, RenderJobPrepareContext};
 use renderer::nodes::{
@@ -43,6 +44,8 @@ pub struct Debug3dExtractJobImpl {
     extents: Extent2D,
     pipeline_info_3d: PipelineSwapchainInfo,
     pipeline_info_3d_no_depth: PipelineSwapchainInfo,
+    triangle_pipeline_info_3d: PipelineSwapchainInfo,
+    triangle_pipeline_info_3d_no_depth: PipelineSwapchainInfo,
     pipeline_info_2d: PipelineSwapchainInfo,
     debug_material_3d: Handle<MaterialAsset>,
     debug_material_3d_no_depth: Handle<MaterialAsset>,
@@ -56,6 +59,8 @@ impl Debug3dExtractJobImpl {
         extents: Extent2D,
         pipeline_info_3d: PipelineSwapchainInfo,
         pipeline_info_3d_no_depth: PipelineSwapchainInfo,
+        triangle_pipeline_info_3d: PipelineSwapchainInfo,
+        triangle_pipeline_info_3d_no_depth: PipelineSwapchainInfo,
         pipeline_info_2d: PipelineSwapchainInfo,
         debug_material_3d: Handle<MaterialAsset>,
         debug_material_3d_no_depth: Handle<MaterialAsset>,
@@ -67,6 +72,8 @@ impl Debug3dExtractJobImpl {
             extents,
             pipeline_info_3d,
             pipeline_info_3d_no_depth,
+            triangle_pipeline_info_3d,
+            triangle_pipeline_info_3d_no_depth,
             pipeline_info_2d,
             debug_material_3d,
             debug_material_3d_no_depth,
@@ -75,12 +82,12 @@ impl Debug3dExtractJobImpl {
     }
 }
 
-impl ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext>
+impl<'a> ExtractJob<RenderJobExtractContext<'a>, RenderJobPrepareContext, RenderJobWriteContext>
     for Debug3dExtractJobImpl
 {
     fn extract(
         mut self: Box<Self>,
-        extract_context: &RenderJobExtractContext,
+        extract_context: &RenderJobExtractContext<'a>,
         _frame_packet: &FramePacket,
         views: &[&RenderView],
     ) -> Box<dyn PrepareJob<RenderJobPrepareContext, RenderJobWriteContext>> {
@@ -103,7 +110,11 @@ impl ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWrite
                     .descriptor_set_allocator
                     .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
                     .unwrap();
+                extract_context.render_stats().record_descriptor_set_allocated();
                 descriptor_set.set_buffer_data(0, &debug3d_view);
+                extract_context
+                    .render_stats()
+                    .record_buffer_upload(std::mem::size_of_val(&debug3d_view) as u64);
                 descriptor_set
                     .flush(&mut self.descriptor_set_allocator)
                     .unwrap();
@@ -131,7 +142,11 @@ impl ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWrite
                 .descriptor_set_allocator
                 .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
                 .unwrap();
+            extract_context.render_stats().record_descriptor_set_allocated();
             descriptor_set.set_buffer_data(0, &debug3d_view);
+            extract_context
+                .render_stats()
+                .record_buffer_upload(std::mem::size_of_val(&debug3d_view) as u64);
             descriptor_set
                 .flush(&mut self.descriptor_set_allocator)
                 .unwrap();
@@ -150,15 +165,24 @@ impl ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWrite
             .unwrap()
             .take_line_lists();
 
+        // Shapes with a duration stay in the resource until they expire
+        let shapes_3d = extract_context
+            .resources
+            .get_mut::<DebugDraw3DShapesResource>()
+            .unwrap()
+            .take_shapes();
+
         Box::new(Debug3dPrepareJobImpl::new(
             self.device_context,
             self.pipeline_info_3d,
             self.pipeline_info_3d_no_depth,
+            self.triangle_pipeline_info_3d,
+            self.triangle_pipeline_info_3d_no_depth,
             self.pipeline_info_2d,
             dyn_resource_allocator,
             per_view_descriptor_sets_3d,
             descriptor_set_2d,
-            ExtractedDebugData { line_lists_2d, line_lists_3d },
+            ExtractedDebugData { line_lists_2d, line_lists_3d, shapes_3d },
         ))
     }
 
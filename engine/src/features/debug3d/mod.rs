@@ -1,11 +1,10 @@
-use crate::render_contexts::{RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext};
+use crate::render_jobs::RenderExtractJob;
 use atelier_assets::loader::handle::Handle;
 use std::sync::atomic::{AtomicI32, Ordering};
 use crate::features::debug3d::extract::Debug3dExtractJobImpl;
 use renderer::vulkan::VkDeviceContext;
 use renderer::assets::DescriptorSetAllocatorRef;
 use renderer::assets::PipelineSwapchainInfo;
-use renderer::nodes::ExtractJob;
 use renderer::nodes::RenderFeature;
 use renderer::nodes::RenderFeatureIndex;
 use std::convert::TryInto;
@@ -14,6 +13,14 @@ use renderer::assets::MaterialAsset;
 mod extract;
 mod prepare;
 mod write;
+mod stroke_font;
+
+mod debug_shapes;
+pub use debug_shapes::DebugDraw3DShapesResource;
+pub use debug_shapes::DebugShapes3D;
+pub use debug_shapes::DebugLineStrip3D;
+pub use debug_shapes::DebugTriangle3D;
+pub use debug_shapes::DebugLabel3D;
 
 pub use minimum::game::resources::LineList3D;
 pub use minimum::game::resources::DebugDraw3DResource;
@@ -21,23 +28,31 @@ pub use minimum::game::resources::LineList2D;
 pub use minimum::game::resources::DebugDraw2DResource;
 use ash::vk::Extent2D;
 
-pub fn create_debug3d_extract_job(
+// Passes in debug.material and debug_no_depth.material
+pub const DEBUG_LINES_PASS_INDEX: usize = 0;
+pub const DEBUG_TRIANGLES_PASS_INDEX: usize = 1;
+
+pub fn create_debug3d_extract_job<'a>(
     device_context: VkDeviceContext,
     descriptor_set_allocator: DescriptorSetAllocatorRef,
     extents: Extent2D,
     pipeline_info_3d: PipelineSwapchainInfo,
     pipeline_info_3d_no_depth: PipelineSwapchainInfo,
+    triangle_pipeline_info_3d: PipelineSwapchainInfo,
+    triangle_pipeline_info_3d_no_depth: PipelineSwapchainInfo,
     pipeline_info_2d: PipelineSwapchainInfo,
     debug_material_3d: &Handle<MaterialAsset>,
     debug_material_3d_no_depth: &Handle<MaterialAsset>,
     debug_material_2d: &Handle<MaterialAsset>,
-) -> Box<dyn ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext>> {
+) -> RenderExtractJob<'a> {
     Box::new(Debug3dExtractJobImpl::new(
         device_context,
         descriptor_set_allocator,
         extents,
         pipeline_info_3d,
         pipeline_info_3d_no_depth,
+        triangle_pipeline_info_3d,
+        triangle_pipeline_info_3d_no_depth,
         pipeline_info_2d,
         debug_material_3d.clone(),
         debug_material_3d_no_depth.clone(),
@@ -84,10 +99,14 @@ impl RenderFeature for Debug3dRenderFeature {
 pub(self) struct ExtractedDebugData {
     line_lists_3d: Vec<LineList3D>,
     line_lists_2d: Vec<LineList2D>,
+    shapes_3d: DebugShapes3D,
 }
 
 #[derive(Debug)]
 struct Debug3dDrawCall {
     first_element: u32,
     count: u32,
+    // Labels face the camera so they are built separately for each view. None if the draw call
+    // is the same for all views
+    view_index: Option<u32>,
 }
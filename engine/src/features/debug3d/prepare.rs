@@ -2,8 +2,12 @@ use renderer::nodes::{
     RenderView, ViewSubmitNodes, FeatureSubmitNodes, FeatureCommandWriter, RenderFeatureIndex,
     FramePacket, RenderFeature, PrepareJob,
 };
-use crate::features::debug3d::{Debug3dRenderFeature, ExtractedDebugData, Debug3dDrawCall, Debug3dVertex, LineList2D};
-use crate::phases::{OpaqueRenderPhase, UiRenderPhase, PreUiRenderPhase};
+use crate::features::debug3d::{
+    Debug3dRenderFeature, ExtractedDebugData, Debug3dDrawCall, Debug3dVertex, LineList2D,
+    DebugLineStrip3D, DebugTriangle3D, DebugLabel3D,
+};
+use crate::features::debug3d::stroke_font;
+use crate::phases::{OpaqueRenderPhase, UiRenderPhase, PreUiRenderPhase, TransparentRenderPhase};
 use super::write::Debug3dCommandWriter;
 use crate::render_contexts::{RenderJobWriteContext, RenderJobPrepareContext};
 use renderer::vulkan::{VkBuffer, VkDeviceContext};
@@ -17,6 +21,8 @@ pub struct Debug3dPrepareJobImpl {
     device_context: VkDeviceContext,
     pipeline_info_3d: PipelineSwapchainInfo,
     pipeline_info_3d_no_depth: PipelineSwapchainInfo,
+    triangle_pipeline_info_3d: PipelineSwapchainInfo,
+    triangle_pipeline_info_3d_no_depth: PipelineSwapchainInfo,
     pipeline_info_2d: PipelineSwapchainInfo,
     dyn_resource_allocator: renderer::assets::DynResourceAllocatorSet,
     descriptor_set_per_view_3d: Vec<DescriptorSetArc>,
@@ -29,6 +35,8 @@ impl Debug3dPrepareJobImpl {
         device_context: VkDeviceContext,
         pipeline_info_3d: PipelineSwapchainInfo,
         pipeline_info_3d_no_depth: PipelineSwapchainInfo,
+        triangle_pipeline_info_3d: PipelineSwapchainInfo,
+        triangle_pipeline_info_3d_no_depth: PipelineSwapchainInfo,
         pipeline_info_2d: PipelineSwapchainInfo,
         dyn_resource_allocator: renderer::assets::DynResourceAllocatorSet,
         descriptor_set_per_view_3d: Vec<DescriptorSetArc>,
@@ -39,6 +47,8 @@ impl Debug3dPrepareJobImpl {
             device_context,
             pipeline_info_3d,
             pipeline_info_3d_no_depth,
+            triangle_pipeline_info_3d,
+            triangle_pipeline_info_3d_no_depth,
             pipeline_info_2d,
             dyn_resource_allocator,
             descriptor_set_per_view_3d,
@@ -51,7 +61,7 @@ impl Debug3dPrepareJobImpl {
 impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for Debug3dPrepareJobImpl {
     fn prepare(
         self: Box<Self>,
-        _prepare_context: &RenderJobPrepareContext,
+        prepare_context: &RenderJobPrepareContext,
         _frame_packet: &FramePacket,
         views: &[&RenderView],
     ) -> (
@@ -74,8 +84,41 @@ impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for Debug3dPrepa
             }
         }
 
-        let vertex_buffer_3d = self.create_vertex_buffer(&mut draw_calls_3d, vertex_list_3d);
-        let vertex_buffer_3d_no_depth = self.create_vertex_buffer(&mut draw_calls_3d_no_depth, vertex_list_3d_no_depth);
+        let shapes_3d = &self.extracted_debug_data.shapes_3d;
+        for line_strip in &shapes_3d.line_strips {
+            match line_strip.depth_behavior {
+                DebugDraw3DDepthBehavior::Normal => Debug3dPrepareJobImpl::add_line_strip_3d(&mut vertex_list_3d, &mut draw_calls_3d, line_strip),
+                DebugDraw3DDepthBehavior::NoDepthTest => Debug3dPrepareJobImpl::add_line_strip_3d(&mut vertex_list_3d_no_depth, &mut draw_calls_3d_no_depth, line_strip)
+            }
+        }
+
+        // Labels face the camera, so they are built for every view
+        for view in views {
+            for label in &shapes_3d.labels {
+                match label.depth_behavior {
+                    DebugDraw3DDepthBehavior::Normal => Debug3dPrepareJobImpl::add_label_3d(&mut vertex_list_3d, &mut draw_calls_3d, label, view),
+                    DebugDraw3DDepthBehavior::NoDepthTest => Debug3dPrepareJobImpl::add_label_3d(&mut vertex_list_3d_no_depth, &mut draw_calls_3d_no_depth, label, view)
+                }
+            }
+        }
+
+        // Triangles share the vertex buffer with the lines but are drawn with the triangle list
+        // pipelines. All triangles with the same depth behavior are a single draw call
+        let mut triangle_draw_calls_3d = Vec::with_capacity(1);
+        let mut triangle_draw_calls_3d_no_depth = Vec::with_capacity(1);
+        Debug3dPrepareJobImpl::add_triangles_3d(
+            &mut vertex_list_3d,
+            &mut triangle_draw_calls_3d,
+            shapes_3d.triangles.iter().filter(|x| matches!(x.depth_behavior, DebugDraw3DDepthBehavior::Normal)),
+        );
+        Debug3dPrepareJobImpl::add_triangles_3d(
+            &mut vertex_list_3d_no_depth,
+            &mut triangle_draw_calls_3d_no_depth,
+            shapes_3d.triangles.iter().filter(|x| matches!(x.depth_behavior, DebugDraw3DDepthBehavior::NoDepthTest)),
+        );
+
+        let vertex_buffer_3d = self.create_vertex_buffer(prepare_context, vertex_list_3d);
+        let vertex_buffer_3d_no_depth = self.create_vertex_buffer(prepare_context, vertex_list_3d_no_depth);
 
         let line_lists_2d = &self.extracted_debug_data.line_lists_2d;
         let mut draw_calls_2d = Vec::with_capacity(line_lists_3d.len());
@@ -85,11 +128,11 @@ impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for Debug3dPrepa
             Debug3dPrepareJobImpl::add_line_list_2d(&mut vertex_list_2d, &mut draw_calls_2d, line_list);
         }
 
-        let vertex_buffer_2d = self.create_vertex_buffer(&mut draw_calls_2d, vertex_list_2d);
+        let vertex_buffer_2d = self.create_vertex_buffer(prepare_context, vertex_list_2d);
 
         //
-        // Submit a single node for each view
-        // TODO: Submit separate nodes for transparency
+        // Submit a single node for each view and kind of draw. Filled triangles are blended, so
+        // the ones that are depth tested are drawn with other transparent objects
         //
         let mut submit_nodes = FeatureSubmitNodes::default();
         for view in views {
@@ -98,6 +141,8 @@ impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for Debug3dPrepa
             view_submit_nodes.add_submit_node::<OpaqueRenderPhase>(0, 0, 0.0);
             view_submit_nodes.add_submit_node::<PreUiRenderPhase>(1, 0, 0.0);
             view_submit_nodes.add_submit_node::<PreUiRenderPhase>(2, 0, 0.0);
+            view_submit_nodes.add_submit_node::<TransparentRenderPhase>(3, 0, 0.0);
+            view_submit_nodes.add_submit_node::<PreUiRenderPhase>(4, 0, 0.0);
             submit_nodes.add_submit_nodes_for_view(view, view_submit_nodes);
         }
 
@@ -106,10 +151,14 @@ impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for Debug3dPrepa
             vertex_buffer_3d,
             draw_calls_3d_no_depth,
             vertex_buffer_3d_no_depth,
+            triangle_draw_calls_3d,
+            triangle_draw_calls_3d_no_depth,
             draw_calls_2d,
             vertex_buffer_2d,
             pipeline_info_3d: self.pipeline_info_3d,
             pipeline_info_3d_no_depth: self.pipeline_info_3d_no_depth,
+            triangle_pipeline_info_3d: self.triangle_pipeline_info_3d,
+            triangle_pipeline_info_3d_no_depth: self.triangle_pipeline_info_3d_no_depth,
             pipeline_info_2d: self.pipeline_info_2d,
             descriptor_set_per_view_3d: self.descriptor_set_per_view_3d,
             descriptor_set_2d: self.descriptor_set_2d,
@@ -146,11 +195,100 @@ impl Debug3dPrepareJobImpl {
         let draw_call = Debug3dDrawCall {
             first_element: vertex_buffer_first_element,
             count: line_list.points.len() as u32,
+            view_index: None,
         };
 
         draw_calls.push(draw_call);
     }
 
+    fn add_line_strip_3d(
+        vertex_list: &mut Vec<Debug3dVertex>,
+        draw_calls: &mut Vec<Debug3dDrawCall>,
+        line_strip: &DebugLineStrip3D,
+    ) {
+        let vertex_buffer_first_element = vertex_list.len() as u32;
+
+        for vertex_pos in &line_strip.points {
+            vertex_list.push(Debug3dVertex {
+                pos: (*vertex_pos).into(),
+                color: line_strip.color.into(),
+            });
+        }
+
+        let draw_call = Debug3dDrawCall {
+            first_element: vertex_buffer_first_element,
+            count: line_strip.points.len() as u32,
+            view_index: None,
+        };
+
+        draw_calls.push(draw_call);
+    }
+
+    fn add_label_3d(
+        vertex_list: &mut Vec<Debug3dVertex>,
+        draw_calls: &mut Vec<Debug3dDrawCall>,
+        label: &DebugLabel3D,
+        view: &RenderView,
+    ) {
+        let view_matrix = view.view_matrix();
+        let camera_right = glam::Vec3::new(
+            view_matrix.x_axis().x(),
+            view_matrix.y_axis().x(),
+            view_matrix.z_axis().x(),
+        );
+        let camera_up = glam::Vec3::new(
+            view_matrix.x_axis().y(),
+            view_matrix.y_axis().y(),
+            view_matrix.z_axis().y(),
+        );
+
+        // Each segment is its own line strip
+        for (p0, p1) in stroke_font::text_segments(&label.text) {
+            let vertex_buffer_first_element = vertex_list.len() as u32;
+            for p in &[p0, p1] {
+                let pos = label.position
+                    + camera_right * (p[0] * label.height)
+                    + camera_up * (p[1] * label.height);
+                vertex_list.push(Debug3dVertex {
+                    pos: pos.into(),
+                    color: label.color.into(),
+                });
+            }
+
+            draw_calls.push(Debug3dDrawCall {
+                first_element: vertex_buffer_first_element,
+                count: 2,
+                view_index: Some(view.view_index()),
+            });
+        }
+    }
+
+    fn add_triangles_3d<'a>(
+        vertex_list: &mut Vec<Debug3dVertex>,
+        draw_calls: &mut Vec<Debug3dDrawCall>,
+        triangles: impl Iterator<Item = &'a DebugTriangle3D>,
+    ) {
+        let vertex_buffer_first_element = vertex_list.len() as u32;
+
+        for triangle in triangles {
+            for vertex_pos in &triangle.positions {
+                vertex_list.push(Debug3dVertex {
+                    pos: (*vertex_pos).into(),
+                    color: triangle.color.into(),
+                });
+            }
+        }
+
+        let count = vertex_list.len() as u32 - vertex_buffer_first_element;
+        if count > 0 {
+            draw_calls.push(Debug3dDrawCall {
+                first_element: vertex_buffer_first_element,
+                count,
+                view_index: None,
+            });
+        }
+    }
+
     fn add_line_list_2d(
         vertex_list: &mut Vec<Debug3dVertex>,
         draw_calls: &mut Vec<Debug3dDrawCall>,
@@ -168,6 +306,7 @@ impl Debug3dPrepareJobImpl {
         let draw_call = Debug3dDrawCall {
             first_element: vertex_buffer_first_element,
             count: line_list.points.len() as u32,
+            view_index: None,
         };
 
         draw_calls.push(draw_call);
@@ -175,11 +314,11 @@ impl Debug3dPrepareJobImpl {
 
     fn create_vertex_buffer(
         &self,
-        draw_calls: &mut Vec<Debug3dDrawCall>,
-        mut vertex_list: Vec<Debug3dVertex>
+        prepare_context: &RenderJobPrepareContext,
+        vertex_list: Vec<Debug3dVertex>
     ) -> Option<ResourceArc<VkBufferRaw>> {
         // We would probably want to support multiple buffers at some point
-        if !draw_calls.is_empty() {
+        if !vertex_list.is_empty() {
             let vertex_buffer_size =
                 vertex_list.len() as u64 * std::mem::size_of::<Debug3dVertex>() as u64;
             let mut vertex_buffer = VkBuffer::new(
@@ -193,6 +332,9 @@ impl Debug3dPrepareJobImpl {
             vertex_buffer
                 .write_to_host_visible_buffer(vertex_list.as_slice())
                 .unwrap();
+            prepare_context
+                .render_stats()
+                .record_buffer_upload(vertex_buffer_size);
 
             Some(self.dyn_resource_allocator.insert_buffer(vertex_buffer))
         } else {
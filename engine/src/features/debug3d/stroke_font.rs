@@ -0,0 +1,117 @@
+// A 14-segment font for debug labels. It only needs line strips, so labels go through the same
+// pipelines as every other debug shape
+//
+//  ---a---
+// |\  |  /|
+// f h i j b
+// |  \|/  |
+//  -g1-g2-
+// |  /|\  |
+// e k l m c
+// |/  |  \|
+//  ---d---
+
+// Width of a character relative to its height
+const CHARACTER_WIDTH: f32 = 0.6;
+
+// Distance between the start of two characters relative to the height
+const CHARACTER_ADVANCE: f32 = 0.8;
+
+// Segment endpoints in a unit-height cell
+const SEGMENTS: [([f32; 2], [f32; 2]); 14] = [
+    ([0.0, 1.0], [CHARACTER_WIDTH, 1.0]),                         // a
+    ([CHARACTER_WIDTH, 1.0], [CHARACTER_WIDTH, 0.5]),             // b
+    ([CHARACTER_WIDTH, 0.5], [CHARACTER_WIDTH, 0.0]),             // c
+    ([0.0, 0.0], [CHARACTER_WIDTH, 0.0]),                         // d
+    ([0.0, 0.5], [0.0, 0.0]),                                     // e
+    ([0.0, 1.0], [0.0, 0.5]),                                     // f
+    ([0.0, 0.5], [CHARACTER_WIDTH * 0.5, 0.5]),                   // g1
+    ([CHARACTER_WIDTH * 0.5, 0.5], [CHARACTER_WIDTH, 0.5]),       // g2
+    ([0.0, 1.0], [CHARACTER_WIDTH * 0.5, 0.5]),                   // h
+    ([CHARACTER_WIDTH * 0.5, 1.0], [CHARACTER_WIDTH * 0.5, 0.5]), // i
+    ([CHARACTER_WIDTH, 1.0], [CHARACTER_WIDTH * 0.5, 0.5]),       // j
+    ([0.0, 0.0], [CHARACTER_WIDTH * 0.5, 0.5]),                   // k
+    ([CHARACTER_WIDTH * 0.5, 0.0], [CHARACTER_WIDTH * 0.5, 0.5]), // l
+    ([CHARACTER_WIDTH, 0.0], [CHARACTER_WIDTH * 0.5, 0.5]),       // m
+];
+
+// Bitmask of SEGMENTS for each character, 0 if the character has no shape (or is unsupported)
+fn character_segment_mask(c: char) -> u16 {
+    match c.to_ascii_uppercase() {
+        '0' => 0x0C3F,
+        '1' => 0x0406,
+        '2' => 0x00DB,
+        '3' => 0x008F,
+        '4' => 0x00E6,
+        '5' => 0x00ED,
+        '6' => 0x00FD,
+        '7' => 0x0007,
+        '8' => 0x00FF,
+        '9' => 0x00EF,
+        'A' => 0x00F7,
+        'B' => 0x128F,
+        'C' => 0x0039,
+        'D' => 0x120F,
+        'E' => 0x0079,
+        'F' => 0x0071,
+        'G' => 0x00BD,
+        'H' => 0x00F6,
+        'I' => 0x1209,
+        'J' => 0x001E,
+        'K' => 0x2470,
+        'L' => 0x0038,
+        'M' => 0x0536,
+        'N' => 0x2136,
+        'O' => 0x003F,
+        'P' => 0x00F3,
+        'Q' => 0x203F,
+        'R' => 0x20F3,
+        'S' => 0x018D,
+        'T' => 0x1201,
+        'U' => 0x003E,
+        'V' => 0x0C30,
+        'W' => 0x2836,
+        'X' => 0x2D00,
+        'Y' => 0x1500,
+        'Z' => 0x0C09,
+        '-' => 0x00C0,
+        '+' => 0x12C0,
+        '=' => 0x00C8,
+        '_' => 0x0008,
+        '/' => 0x0C00,
+        '\\' => 0x2100,
+        '(' => 0x2400,
+        ')' => 0x0900,
+        '*' => 0x3FC0,
+        '|' => 0x1200,
+        '.' | ',' => 0x0800,
+        _ => 0,
+    }
+}
+
+/// Line segments for the given text with a height of 1.0, as (right, up) offsets. The text is
+/// centered horizontally and sits on the origin
+pub(super) fn text_segments(text: &str) -> Vec<([f32; 2], [f32; 2])> {
+    let character_count = text.chars().count();
+    if character_count == 0 {
+        return vec![];
+    }
+
+    let text_width =
+        (character_count - 1) as f32 * CHARACTER_ADVANCE + CHARACTER_WIDTH;
+    let mut x = -text_width * 0.5;
+
+    let mut segments = vec![];
+    for c in text.chars() {
+        let mask = character_segment_mask(c);
+        for (segment_index, (p0, p1)) in SEGMENTS.iter().enumerate() {
+            if mask & (1 << segment_index) != 0 {
+                segments.push(([p0[0] + x, p0[1]], [p1[0] + x, p1[1]]));
+            }
+        }
+
+        x += CHARACTER_ADVANCE;
+    }
+
+    segments
+}
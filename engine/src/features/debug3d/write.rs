@@ -13,15 +13,92 @@ pub struct Debug3dCommandWriter {
     pub(super) draw_calls_3d: Vec<Debug3dDrawCall>,
     pub(super) vertex_buffer_3d_no_depth: Option<ResourceArc<VkBufferRaw>>,
     pub(super) draw_calls_3d_no_depth: Vec<Debug3dDrawCall>,
+    // These index into vertex_buffer_3d/vertex_buffer_3d_no_depth
+    pub(super) triangle_draw_calls_3d: Vec<Debug3dDrawCall>,
+    pub(super) triangle_draw_calls_3d_no_depth: Vec<Debug3dDrawCall>,
     pub(super) vertex_buffer_2d: Option<ResourceArc<VkBufferRaw>>,
     pub(super) draw_calls_2d: Vec<Debug3dDrawCall>,
     pub(super) pipeline_info_3d: PipelineSwapchainInfo,
     pub(super) pipeline_info_3d_no_depth: PipelineSwapchainInfo,
+    pub(super) triangle_pipeline_info_3d: PipelineSwapchainInfo,
+    pub(super) triangle_pipeline_info_3d_no_depth: PipelineSwapchainInfo,
     pub(super) pipeline_info_2d: PipelineSwapchainInfo,
     pub(super) descriptor_set_per_view_3d: Vec<DescriptorSetArc>,
     pub(super) descriptor_set_2d: DescriptorSetArc,
 }
 
+impl Debug3dCommandWriter {
+    fn draw(
+        write_context: &mut RenderJobWriteContext,
+        view: &RenderView,
+        pipeline_info: &PipelineSwapchainInfo,
+        descriptor_set: &DescriptorSetArc,
+        vertex_buffer: Option<&ResourceArc<VkBufferRaw>>,
+        draw_calls: &[Debug3dDrawCall],
+        is_triangle_list: bool,
+    ) {
+        let vertex_buffer = match vertex_buffer {
+            Some(vertex_buffer) => vertex_buffer,
+            None => return,
+        };
+
+        if draw_calls.is_empty() {
+            return;
+        }
+
+        let logical_device = write_context.device_context.device();
+        let command_buffer = write_context.command_buffer;
+
+        unsafe {
+            logical_device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_info.pipeline.get_raw().pipelines[0],
+            );
+
+            // Bind per-pass data (UBO with view/proj matrix, sampler)
+            logical_device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_info.pipeline_layout.get_raw().pipeline_layout,
+                0,
+                &[descriptor_set.get()],
+                &[],
+            );
+
+            logical_device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0, // first binding
+                &[vertex_buffer.get_raw().buffer],
+                &[0], // offsets
+            );
+
+            for draw_call in draw_calls {
+                // Skip draw calls that were built for a different view
+                if let Some(view_index) = draw_call.view_index {
+                    if view_index != view.view_index() {
+                        continue;
+                    }
+                }
+
+                logical_device.cmd_draw(
+                    command_buffer,
+                    draw_call.count as u32,
+                    1,
+                    draw_call.first_element as u32,
+                    0,
+                );
+                let triangle_count = if is_triangle_list {
+                    draw_call.count as u32 / 3
+                } else {
+                    0
+                };
+                write_context.record_draw_call(triangle_count);
+            }
+        }
+    }
+}
+
 impl FeatureCommandWriter<RenderJobWriteContext> for Debug3dCommandWriter {
     fn apply_setup(
         &self,
@@ -37,125 +114,61 @@ impl FeatureCommandWriter<RenderJobWriteContext> for Debug3dCommandWriter {
         &self,
         write_context: &mut RenderJobWriteContext,
         view: &RenderView,
-        _render_phase_index: RenderPhaseIndex,
+        render_phase_index: RenderPhaseIndex,
         index: SubmitNodeId,
     ) {
-        let logical_device = write_context.device_context.device();
-        let command_buffer = write_context.command_buffer;
-
-        // The prepare phase emits a single node which will draw everything. In the future it might
-        // emit a node per draw call that uses transparency
-        if index == 0 {
-            if let Some(vertex_buffer_3d) = self.vertex_buffer_3d.as_ref() {
-                unsafe {
-                    logical_device.cmd_bind_pipeline(
-                        command_buffer,
-                        vk::PipelineBindPoint::GRAPHICS,
-                        self.pipeline_info_3d.pipeline.get_raw().pipelines[0],
-                    );
-
-                    // Bind per-pass data (UBO with view/proj matrix, sampler)
-                    logical_device.cmd_bind_descriptor_sets(
-                        command_buffer,
-                        vk::PipelineBindPoint::GRAPHICS,
-                        self.pipeline_info_3d.pipeline_layout.get_raw().pipeline_layout,
-                        0,
-                        &[self.descriptor_set_per_view_3d[view.view_index() as usize].get()],
-                        &[],
-                    );
-
-                    logical_device.cmd_bind_vertex_buffers(
-                        command_buffer,
-                        0, // first binding
-                        &[vertex_buffer_3d.get_raw().buffer],
-                        &[0], // offsets
-                    );
-
-                    for draw_call in &self.draw_calls_3d {
-                        logical_device.cmd_draw(
-                            command_buffer,
-                            draw_call.count as u32,
-                            1,
-                            draw_call.first_element as u32,
-                            0,
-                        );
-                    }
-                }
-            }
-        } else if index == 1 {
-            if let Some(vertex_buffer_3d_no_depth) = self.vertex_buffer_3d_no_depth.as_ref() {
-                unsafe {
-                    logical_device.cmd_bind_pipeline(
-                        command_buffer,
-                        vk::PipelineBindPoint::GRAPHICS,
-                        self.pipeline_info_3d_no_depth.pipeline.get_raw().pipelines[0],
-                    );
-
-                    // Bind per-pass data (UBO with view/proj matrix, sampler)
-                    logical_device.cmd_bind_descriptor_sets(
-                        command_buffer,
-                        vk::PipelineBindPoint::GRAPHICS,
-                        self.pipeline_info_3d_no_depth.pipeline_layout.get_raw().pipeline_layout,
-                        0,
-                        &[self.descriptor_set_per_view_3d[view.view_index() as usize].get()],
-                        &[],
-                    );
-
-                    logical_device.cmd_bind_vertex_buffers(
-                        command_buffer,
-                        0, // first binding
-                        &[vertex_buffer_3d_no_depth.get_raw().buffer],
-                        &[0], // offsets
-                    );
-
-                    for draw_call in &self.draw_calls_3d_no_depth {
-                        logical_device.cmd_draw(
-                            command_buffer,
-                            draw_call.count as u32,
-                            1,
-                            draw_call.first_element as u32,
-                            0,
-                        );
-                    }
-                }
-            }
-        } else if index == 2 {
-            if let Some(vertex_buffer_2d) = self.vertex_buffer_2d.as_ref() {
-                unsafe {
-                    logical_device.cmd_bind_pipeline(
-                        command_buffer,
-                        vk::PipelineBindPoint::GRAPHICS,
-                        self.pipeline_info_2d.pipeline.get_raw().pipelines[0],
-                    );
-
-                    // Bind per-pass data (UBO with view/proj matrix, sampler)
-                    logical_device.cmd_bind_descriptor_sets(
-                        command_buffer,
-                        vk::PipelineBindPoint::GRAPHICS,
-                        self.pipeline_info_2d.pipeline_layout.get_raw().pipeline_layout,
-                        0,
-                        &[self.descriptor_set_2d.get()],
-                        &[],
-                    );
-
-                    logical_device.cmd_bind_vertex_buffers(
-                        command_buffer,
-                        0, // first binding
-                        &[vertex_buffer_2d.get_raw().buffer],
-                        &[0], // offsets
-                    );
-
-                    for draw_call in &self.draw_calls_2d {
-                        logical_device.cmd_draw(
-                            command_buffer,
-                            draw_call.count as u32,
-                            1,
-                            draw_call.first_element as u32,
-                            0,
-                        );
-                    }
-                }
-            }
+        write_context.record_submit_node(render_phase_index);
+
+        let descriptor_set_3d = &self.descriptor_set_per_view_3d[view.view_index() as usize];
+
+        // The prepare phase emits a node per pipeline which will draw everything using it
+        match index {
+            0 => Self::draw(
+                write_context,
+                view,
+                &self.pipeline_info_3d,
+                descriptor_set_3d,
+                self.vertex_buffer_3d.as_ref(),
+                &self.draw_calls_3d,
+                false,
+            ),
+            1 => Self::draw(
+                write_context,
+                view,
+                &self.pipeline_info_3d_no_depth,
+                descriptor_set_3d,
+                self.vertex_buffer_3d_no_depth.as_ref(),
+                &self.draw_calls_3d_no_depth,
+                false,
+            ),
+            2 => Self::draw(
+                write_context,
+                view,
+                &self.pipeline_info_2d,
+                &self.descriptor_set_2d,
+                self.vertex_buffer_2d.as_ref(),
+                &self.draw_calls_2d,
+                false,
+            ),
+            3 => Self::draw(
+                write_context,
+                view,
+                &self.triangle_pipeline_info_3d,
+                descriptor_set_3d,
+                self.vertex_buffer_3d.as_ref(),
+                &self.triangle_draw_calls_3d,
+                true,
+            ),
+            4 => Self::draw(
+                write_context,
+                view,
+                &self.triangle_pipeline_info_3d_no_depth,
+                descriptor_set_3d,
+                self.vertex_buffer_3d_no_depth.as_ref(),
+                &self.triangle_draw_calls_3d_no_depth,
+                true,
+            ),
+            _ => unreachable!(),
         }
     }
 
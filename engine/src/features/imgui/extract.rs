@@ -1,4 +1,6 @@
-use crate::features::imgui::{ExtractedImGuiData, ImGuiRenderFeature, ImGuiUniformBufferObject};
+use crate::features::imgui::{
+    ExtractedImGuiData, ImGuiRenderFeature, ImGuiUniformBufferObject, ImguiTextureResource,
+};
 use crate::render_contexts::{RenderJobExtractContext, RenderJobWriteContext, RenderJobPrepareContext};
 use renderer::nodes::{
     FramePacket, RenderView, PrepareJob, RenderFeatureIndex, RenderFeature, ExtractJob,
@@ -66,12 +68,12 @@ impl ImGuiExtractJobImpl {
     }
 }
 
-impl ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext>
+impl<'a> ExtractJob<RenderJobExtractContext<'a>, RenderJobPrepareContext, RenderJobWriteContext>
     for ImGuiExtractJobImpl
 {
     fn extract(
         mut self: Box<Self>,
-        extract_context: &RenderJobExtractContext,
+        extract_context: &RenderJobExtractContext<'a>,
         _frame_packet: &FramePacket,
         _views: &[&RenderView],
     ) -> Box<dyn PrepareJob<RenderJobPrepareContext, RenderJobWriteContext>> {
@@ -109,7 +111,11 @@ impl ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWrite
             .descriptor_set_allocator
             .create_dyn_descriptor_set_uninitialized(&per_pass_layout.descriptor_set_layout)
             .unwrap();
+        extract_context.render_stats().record_descriptor_set_allocated();
         per_pass_descriptor_set.set_buffer_data(0, &ubo);
+        extract_context
+            .render_stats()
+            .record_buffer_upload(std::mem::size_of_val(&ubo) as u64);
         per_pass_descriptor_set
             .flush(&mut self.descriptor_set_allocator)
             .unwrap();
@@ -118,17 +124,40 @@ impl ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWrite
             extract_context
                 .resource_manager
                 .get_descriptor_set_info(&self.imgui_material, 0, 1);
-        let mut per_image_descriptor_set = self
-            .descriptor_set_allocator
-            .create_dyn_descriptor_set_uninitialized(&per_image_layout.descriptor_set_layout)
-            .unwrap();
-        per_image_descriptor_set.set_image(0, self.font_atlas);
-        per_image_descriptor_set
-            .flush(&mut self.descriptor_set_allocator)
-            .unwrap();
+
+        // One descriptor set per texture ID, the font atlas first
+        let images = extract_context
+            .resources
+            .get_mut::<ImguiTextureResource>()
+            .unwrap()
+            .take_images();
+        let mut image_views = Vec::with_capacity(images.len() + 1);
+        image_views.push(self.font_atlas.clone());
+        for image in &images {
+            // Images that aren't loaded yet are drawn with the font atlas
+            let image_view = extract_context
+                .resource_manager
+                .get_image_info(image)
+                .map(|image_info| image_info.image_view)
+                .unwrap_or_else(|| self.font_atlas.clone());
+            image_views.push(image_view);
+        }
+
+        let mut per_image_descriptor_sets = Vec::with_capacity(image_views.len());
+        for image_view in image_views {
+            let mut per_image_descriptor_set = self
+                .descriptor_set_allocator
+                .create_dyn_descriptor_set_uninitialized(&per_image_layout.descriptor_set_layout)
+                .unwrap();
+            extract_context.render_stats().record_descriptor_set_allocated();
+            per_image_descriptor_set.set_image(0, image_view);
+            per_image_descriptor_set
+                .flush(&mut self.descriptor_set_allocator)
+                .unwrap();
+            per_image_descriptor_sets.push(per_image_descriptor_set.descriptor_set().clone());
+        }
 
         let per_pass_descriptor_set = per_pass_descriptor_set.descriptor_set().clone();
-        let per_image_descriptor_sets = vec![per_image_descriptor_set.descriptor_set().clone()];
 
         Box::new(ImguiPrepareJobImpl::new(
             self.device_context,
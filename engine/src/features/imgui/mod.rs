@@ -1,11 +1,10 @@
-use crate::render_contexts::{RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext};
+use crate::render_jobs::RenderExtractJob;
 use atelier_assets::loader::handle::Handle;
 use std::sync::atomic::{AtomicI32, Ordering};
 use crate::features::imgui::extract::ImGuiExtractJobImpl;
 use renderer::vulkan::VkDeviceContext;
 use renderer::assets::DescriptorSetAllocatorRef;
 use renderer::assets::PipelineSwapchainInfo;
-use renderer::nodes::ExtractJob;
 use renderer::nodes::RenderFeature;
 use renderer::nodes::RenderFeatureIndex;
 use std::convert::TryInto;
@@ -13,19 +12,20 @@ use minimum::game::imgui::ImguiDrawData;
 use ash::vk::Extent2D;
 use renderer::assets::{ImageViewResource, ResourceArc};
 use renderer::assets::MaterialAsset;
+use renderer::assets::ImageAsset;
 
 mod extract;
 mod prepare;
 mod write;
 
-pub fn create_imgui_extract_job(
+pub fn create_imgui_extract_job<'a>(
     device_context: VkDeviceContext,
     descriptor_set_allocator: DescriptorSetAllocatorRef,
     pipeline_info: PipelineSwapchainInfo,
     extents: Extent2D,
     imgui_material: &Handle<MaterialAsset>,
     font_atlas: ResourceArc<ImageViewResource>,
-) -> Box<dyn ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext>> {
+) -> RenderExtractJob<'a> {
     Box::new(ImGuiExtractJobImpl::new(
         device_context,
         descriptor_set_allocator,
@@ -72,6 +72,27 @@ impl RenderFeature for ImGuiRenderFeature {
     }
 }
 
+// Images that windows want to draw with imgui::Image this frame. Texture 0 is the font atlas, so
+// the returned IDs start at 1. The list is consumed when imgui is extracted.
+#[derive(Default)]
+pub struct ImguiTextureResource {
+    images: Vec<Handle<ImageAsset>>,
+}
+
+impl ImguiTextureResource {
+    pub fn add_image(
+        &mut self,
+        image: &Handle<ImageAsset>,
+    ) -> imgui::TextureId {
+        self.images.push(image.clone());
+        imgui::TextureId::from(self.images.len())
+    }
+
+    fn take_images(&mut self) -> Vec<Handle<ImageAsset>> {
+        std::mem::replace(&mut self.images, vec![])
+    }
+}
+
 pub(self) struct ExtractedImGuiData {
     imgui_draw_data: Option<ImguiDrawData>,
 }
@@ -42,7 +42,7 @@ impl ImguiPrepareJobImpl {
 impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for ImguiPrepareJobImpl {
     fn prepare(
         self: Box<Self>,
-        _prepare_context: &RenderJobPrepareContext,
+        prepare_context: &RenderJobPrepareContext,
         _frame_packet: &FramePacket,
         views: &[&RenderView],
     ) -> (
@@ -73,6 +73,9 @@ impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for ImguiPrepare
                 vertex_buffer
                     .write_to_host_visible_buffer(draw_list.vertex_buffer())
                     .unwrap();
+                prepare_context
+                    .render_stats()
+                    .record_buffer_upload(vertex_buffer_size);
                 let vertex_buffer = self.dyn_resource_allocator.insert_buffer(vertex_buffer);
 
                 let index_buffer_size = draw_list.index_buffer().len() as u64
@@ -89,6 +92,9 @@ impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for ImguiPrepare
                 index_buffer
                     .write_to_host_visible_buffer(draw_list.index_buffer())
                     .unwrap();
+                prepare_context
+                    .render_stats()
+                    .record_buffer_upload(index_buffer_size);
                 let index_buffer = self.dyn_resource_allocator.insert_buffer(index_buffer);
 
                 vertex_buffers.push(vertex_buffer);
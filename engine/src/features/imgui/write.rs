@@ -55,9 +55,11 @@ impl FeatureCommandWriter<RenderJobWriteContext> for ImGuiCommandWriter {
         &self,
         write_context: &mut RenderJobWriteContext,
         _view: &RenderView,
-        _render_phase_index: RenderPhaseIndex,
+        render_phase_index: RenderPhaseIndex,
         index: SubmitNodeId,
     ) {
+        write_context.record_submit_node(render_phase_index);
+
         // The prepare phase emits a single node which will draw everything. In the future it might
         // emit a node per draw call that uses transparency
         if index == 0 {
@@ -67,6 +69,8 @@ impl FeatureCommandWriter<RenderJobWriteContext> for ImGuiCommandWriter {
 
             unsafe {
                 let mut draw_list_index = 0;
+                // apply_setup binds the font atlas's descriptor set
+                let mut bound_texture_index = 0;
                 if let Some(draw_data) = &self.imgui_draw_data {
                     for draw_list in draw_data.draw_lists() {
                         logical_device.cmd_bind_vertex_buffers(
@@ -91,12 +95,32 @@ impl FeatureCommandWriter<RenderJobWriteContext> for ImGuiCommandWriter {
                                     cmd_params:
                                         imgui::DrawCmdParams {
                                             clip_rect,
-                                            //texture_id,
+                                            texture_id,
                                             ..
                                         },
                                 } => {
                                     let element_end_index = element_begin_index + *count as u32;
 
+                                    let mut texture_index = texture_id.id();
+                                    if texture_index >= self.per_image_descriptor_sets.len() {
+                                        texture_index = 0;
+                                    }
+
+                                    if texture_index != bound_texture_index {
+                                        logical_device.cmd_bind_descriptor_sets(
+                                            command_buffer,
+                                            vk::PipelineBindPoint::GRAPHICS,
+                                            self.pipeline_info
+                                                .pipeline_layout
+                                                .get_raw()
+                                                .pipeline_layout,
+                                            1,
+                                            &[self.per_image_descriptor_sets[texture_index].get()],
+                                            &[],
+                                        );
+                                        bound_texture_index = texture_index;
+                                    }
+
                                     let scissors = vk::Rect2D {
                                         offset: vk::Offset2D {
                                             x: ((clip_rect[0] - draw_data.display_pos[0])
@@ -130,6 +154,9 @@ impl FeatureCommandWriter<RenderJobWriteContext> for ImGuiCommandWriter {
                                         0,
                                         0,
                                     );
+                                    write_context.record_draw_call(
+                                        (element_end_index - element_begin_index) / 3,
+                                    );
 
                                     element_begin_index = element_end_index;
                                 }
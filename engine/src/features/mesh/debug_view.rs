@@ -0,0 +1,63 @@
+/// What the mesh shader outputs. Everything except `None` and `Wireframe` replaces the shaded
+/// color with a single input to lighting. The values are written to `MeshPerViewShaderParam` and
+/// must match the constants in mesh.frag
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MeshDebugViewMode {
+    None = 0,
+    BaseColor = 1,
+    VertexNormals = 2,
+    // Normals after the normal map is applied
+    MappedNormals = 3,
+    Roughness = 4,
+    Metallic = 5,
+    Occlusion = 6,
+    Emissive = 7,
+    Uvs = 8,
+    // Heatmap of how many lights reach each pixel
+    LightCount = 9,
+    // Shaded normally with triangle edges drawn on top
+    Wireframe = 10,
+}
+
+impl Default for MeshDebugViewMode {
+    fn default() -> Self {
+        MeshDebugViewMode::None
+    }
+}
+
+impl MeshDebugViewMode {
+    pub const ALL: [MeshDebugViewMode; 11] = [
+        MeshDebugViewMode::None,
+        MeshDebugViewMode::BaseColor,
+        MeshDebugViewMode::VertexNormals,
+        MeshDebugViewMode::MappedNormals,
+        MeshDebugViewMode::Roughness,
+        MeshDebugViewMode::Metallic,
+        MeshDebugViewMode::Occlusion,
+        MeshDebugViewMode::Emissive,
+        MeshDebugViewMode::Uvs,
+        MeshDebugViewMode::LightCount,
+        MeshDebugViewMode::Wireframe,
+    ];
+
+    pub fn display_name(self) -> &'static str {
+        match self {
+            MeshDebugViewMode::None => "Lit",
+            MeshDebugViewMode::BaseColor => "Base Color",
+            MeshDebugViewMode::VertexNormals => "Vertex Normals",
+            MeshDebugViewMode::MappedNormals => "Mapped Normals",
+            MeshDebugViewMode::Roughness => "Roughness",
+            MeshDebugViewMode::Metallic => "Metallic",
+            MeshDebugViewMode::Occlusion => "Occlusion",
+            MeshDebugViewMode::Emissive => "Emissive",
+            MeshDebugViewMode::Uvs => "UVs",
+            MeshDebugViewMode::LightCount => "Light Count",
+            MeshDebugViewMode::Wireframe => "Wireframe",
+        }
+    }
+
+    // Value of MeshPerViewShaderParam::debug_view_mode
+    pub fn shader_value(self) -> u32 {
+        self as u32
+    }
+}
@@ -1,6 +1,9 @@
 use crate::features::mesh::{
     ExtractedFrameNodeMeshData, MeshRenderNodeSet, MeshRenderFeature, MeshRenderNode, MeshDrawCall,
     MeshPerObjectShaderParam, ExtractedViewNodeMeshData, MeshPerViewShaderParam,
+    MESH_OPAQUE_PASS_INDEX, MESH_OPAQUE_DEPTH_EQUAL_PASS_INDEX, StaticMeshFrameData,
+    ExtractedStaticMeshData, MeshLodSelectionState, MESH_LOD_HYSTERESIS, MAX_MESH_LOD_COUNT,
+    mesh_lod_debug_color, projected_screen_size, select_mesh_lod, MeshDebugViewMode,
 };
 use crate::components::{
     PointLightComponent, SpotLightComponent, DirectionalLightComponent,
@@ -12,7 +15,9 @@ use renderer::nodes::{
 };
 use renderer::base::slab::RawSlabKey;
 use crate::features::mesh::prepare::MeshPrepareJobImpl;
+use crate::render_jobs::SharedDescriptorSetAllocator;
 use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetAllocatorRef};
+use renderer::vulkan::VkDeviceContext;
 use atelier_assets::loader::handle::Handle;
 use renderer::assets::resources::DescriptorSetArc;
 use legion::prelude::*;
@@ -22,8 +27,16 @@ use renderer::assets::MaterialAsset;
 use minimum::components::{TransformComponent};
 
 pub struct MeshExtractJobImpl {
+    device_context: VkDeviceContext,
     descriptor_set_allocator: DescriptorSetAllocatorRef,
     pipeline_info: PipelineSwapchainInfo,
+    depth_prepass_pipeline_info: Option<PipelineSwapchainInfo>,
+    wireframe_pipeline_info: Option<PipelineSwapchainInfo>,
+    // One per view that static meshes were culled for
+    static_mesh_frame_data: Vec<StaticMeshFrameData>,
+    lod_debug_view_enabled: bool,
+    debug_view_mode: MeshDebugViewMode,
+    opaque_pass_index: usize,
     mesh_material: Handle<MaterialAsset>,
     descriptor_sets_per_view: Vec<DescriptorSetArc>,
     extracted_frame_node_mesh_data: Vec<Option<ExtractedFrameNodeMeshData>>,
@@ -32,13 +45,33 @@ pub struct MeshExtractJobImpl {
 
 impl MeshExtractJobImpl {
     pub fn new(
+        device_context: VkDeviceContext,
         descriptor_set_allocator: DescriptorSetAllocatorRef,
         pipeline_info: PipelineSwapchainInfo,
+        depth_prepass_pipeline_info: Option<PipelineSwapchainInfo>,
+        wireframe_pipeline_info: Option<PipelineSwapchainInfo>,
+        static_mesh_frame_data: Vec<StaticMeshFrameData>,
+        lod_debug_view_enabled: bool,
+        debug_view_mode: MeshDebugViewMode,
         mesh_material: &Handle<MaterialAsset>,
     ) -> Self {
+        // The per-material descriptor sets must come from the same pass as the pipeline
+        let opaque_pass_index = if depth_prepass_pipeline_info.is_some() {
+            MESH_OPAQUE_DEPTH_EQUAL_PASS_INDEX
+        } else {
+            MESH_OPAQUE_PASS_INDEX
+        };
+
         MeshExtractJobImpl {
+            device_context,
             descriptor_set_allocator,
             pipeline_info,
+            depth_prepass_pipeline_info,
+            wireframe_pipeline_info,
+            static_mesh_frame_data,
+            lod_debug_view_enabled,
+            debug_view_mode,
+            opaque_pass_index,
             mesh_material: mesh_material.clone(),
             descriptor_sets_per_view: Default::default(),
             extracted_frame_node_mesh_data: Default::default(),
@@ -47,15 +80,25 @@ impl MeshExtractJobImpl {
     }
 }
 
-impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext>
-    for MeshExtractJobImpl
+impl<'a>
+    DefaultExtractJobImpl<
+        RenderJobExtractContext<'a>,
+        RenderJobPrepareContext,
+        RenderJobWriteContext,
+    > for MeshExtractJobImpl
 {
     fn extract_begin(
         &mut self,
-        _extract_context: &RenderJobExtractContext,
+        extract_context: &RenderJobExtractContext<'a>,
         frame_packet: &FramePacket,
         views: &[&RenderView],
     ) {
+        extract_context
+            .resources
+            .get_mut::<MeshLodSelectionState>()
+            .unwrap()
+            .begin_frame();
+
         self.extracted_frame_node_mesh_data
             .reserve(frame_packet.frame_node_count(self.feature_index()) as usize);
 
@@ -69,7 +112,7 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
 
     fn extract_frame_node(
         &mut self,
-        extract_context: &RenderJobExtractContext,
+        extract_context: &RenderJobExtractContext<'a>,
         frame_node: PerFrameNode,
         _frame_node_index: u32,
     ) {
@@ -108,7 +151,8 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
         }
         let mesh_info = mesh_info.unwrap();
 
-        let draw_calls: Vec<_> = mesh_info
+        // All LODs share the materials of LOD 0
+        let per_material_descriptors: Vec<_> = mesh_info
             .mesh_asset
             .mesh_parts
             .iter()
@@ -116,31 +160,64 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
                 let material_instance_info = extract_context
                     .resource_manager
                     .get_material_instance_info(&mesh_part.material_instance);
-                let per_material_descriptor = material_instance_info.descriptor_sets[0][1].clone();
-                MeshDrawCall {
-                    vertex_buffer_offset_in_bytes: mesh_part.vertex_buffer_offset_in_bytes,
-                    vertex_buffer_size_in_bytes: mesh_part.vertex_buffer_size_in_bytes,
-                    index_buffer_offset_in_bytes: mesh_part.index_buffer_offset_in_bytes,
-                    index_buffer_size_in_bytes: mesh_part.index_buffer_size_in_bytes,
-                    per_material_descriptor,
-                }
+                material_instance_info.descriptor_sets[self.opaque_pass_index][1].clone()
             })
             .collect();
 
+        let lod_count = mesh_info.mesh_asset.lod_count().min(MAX_MESH_LOD_COUNT);
+        let draw_calls_per_lod: Vec<Vec<_>> = (0..lod_count)
+            .map(|lod| {
+                mesh_info
+                    .mesh_asset
+                    .lod_part_ranges(lod)
+                    .iter()
+                    .zip(&per_material_descriptors)
+                    .map(|(part_range, per_material_descriptor)| MeshDrawCall {
+                        vertex_buffer_offset_in_bytes: part_range.vertex_buffer_offset_in_bytes,
+                        vertex_buffer_size_in_bytes: part_range.vertex_buffer_size_in_bytes,
+                        index_buffer_offset_in_bytes: part_range.index_buffer_offset_in_bytes,
+                        index_buffer_size_in_bytes: part_range.index_buffer_size_in_bytes,
+                        per_material_descriptor: per_material_descriptor.clone(),
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let lod_screen_sizes = mesh_info
+            .mesh_asset
+            .lods
+            .iter()
+            .take(lod_count - 1)
+            .map(|lod| lod.screen_size)
+            .collect();
+
         let world_transform = transform_component.transform();
 
+        let bounding_sphere = &mesh_info.mesh_asset.bounding_sphere;
+        let max_scale = world_transform
+            .x_axis()
+            .truncate()
+            .length()
+            .max(world_transform.y_axis().truncate().length())
+            .max(world_transform.z_axis().truncate().length());
+
         self.extracted_frame_node_mesh_data
             .push(Some(ExtractedFrameNodeMeshData {
+                render_node_index,
                 world_transform,
+                bounding_sphere_center_ws: world_transform
+                    .transform_point3(bounding_sphere.center),
+                bounding_sphere_radius_ws: bounding_sphere.radius * max_scale,
+                lod_screen_sizes,
                 vertex_buffer: mesh_info.vertex_buffer.clone(),
                 index_buffer: mesh_info.index_buffer.clone(),
-                draw_calls,
+                draw_calls_per_lod,
             }));
     }
 
     fn extract_view_node(
         &mut self,
-        extract_context: &RenderJobExtractContext,
+        extract_context: &RenderJobExtractContext<'a>,
         view: &RenderView,
         view_node: PerViewNode,
         _view_node_index: u32,
@@ -156,37 +233,50 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
         let model_view = view.view_matrix() * frame_node_data.world_transform;
         let model_view_proj = view.projection_matrix() * model_view;
 
+        let screen_size = projected_screen_size(
+            view,
+            frame_node_data.bounding_sphere_center_ws,
+            frame_node_data.bounding_sphere_radius_ws,
+        );
+
+        let mut lod_selection_state = extract_context
+            .resources
+            .get_mut::<MeshLodSelectionState>()
+            .unwrap();
+        let previous_lod =
+            lod_selection_state.previous_lod(view.view_index(), frame_node_data.render_node_index);
+        let lod = select_mesh_lod(
+            &frame_node_data.lod_screen_sizes,
+            screen_size,
+            previous_lod,
+            MESH_LOD_HYSTERESIS,
+        );
+        lod_selection_state.set_lod(view.view_index(), frame_node_data.render_node_index, lod);
+
+        // This is written into a per-view storage buffer during prepare once we know how the
+        // instances get batched
         let per_object_param = MeshPerObjectShaderParam {
             model_view,
             model_view_proj,
+            debug_color: mesh_lod_debug_color(lod, self.lod_debug_view_enabled),
         };
 
-        let layout =
-            extract_context
-                .resource_manager
-                .get_descriptor_set_info(&self.mesh_material, 0, 2);
-        let mut descriptor_set = self
-            .descriptor_set_allocator
-            .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
-            .unwrap();
-        descriptor_set.set_buffer_data(0, &per_object_param);
-        descriptor_set
-            .flush(&mut self.descriptor_set_allocator)
-            .unwrap();
-
         self.extracted_view_node_mesh_data[view.view_index() as usize].push(Some(
             ExtractedViewNodeMeshData {
-                per_instance_descriptor: descriptor_set.descriptor_set().clone(),
+                frame_node_index: view_node.frame_node_index(),
+                per_object_param,
+                lod: lod as u32,
             },
         ))
     }
 
     fn extract_view_finalize(
         &mut self,
-        extract_context: &RenderJobExtractContext,
+        extract_context: &RenderJobExtractContext<'a>,
         view: &RenderView,
     ) {
         let mut per_view_data = MeshPerViewShaderParam::default();
+        per_view_data.debug_view_mode = self.debug_view_mode.shader_value();
 
         let query = <Read<DirectionalLightComponent>>::query();
         for light in query.iter(extract_context.world) {
@@ -267,7 +357,11 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
             .descriptor_set_allocator
             .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
             .unwrap();
+        extract_context.render_stats().record_descriptor_set_allocated();
         descriptor_set.set_buffer_data(0, &per_view_data);
+        extract_context
+            .render_stats()
+            .record_buffer_upload(std::mem::size_of_val(&per_view_data) as u64);
         descriptor_set
             .flush(&mut self.descriptor_set_allocator)
             .unwrap();
@@ -278,13 +372,43 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
 
     fn extract_frame_finalize(
         self,
-        _extract_context: &RenderJobExtractContext,
+        extract_context: &RenderJobExtractContext<'a>,
     ) -> Box<dyn PrepareJob<RenderJobPrepareContext, RenderJobWriteContext>> {
+        let per_instance_layout =
+            extract_context
+                .resource_manager
+                .get_descriptor_set_info(&self.mesh_material, 0, 2);
+
+        let opaque_pass_index = self.opaque_pass_index;
+        let extracted_static_mesh_data = self
+            .static_mesh_frame_data
+            .into_iter()
+            .map(|frame_data| {
+                let per_material_descriptors = frame_data
+                    .gpu_data
+                    .draws
+                    .iter()
+                    .map(|draw| draw.material_instance[opaque_pass_index][1].clone())
+                    .collect();
+
+                ExtractedStaticMeshData {
+                    frame_data,
+                    per_material_descriptors,
+                }
+            })
+            .collect();
+
         let prepare_impl = MeshPrepareJobImpl::new(
+            self.device_context,
+            SharedDescriptorSetAllocator::new(self.descriptor_set_allocator),
+            per_instance_layout.descriptor_set_layout.clone(),
             self.pipeline_info,
+            self.depth_prepass_pipeline_info,
+            self.wireframe_pipeline_info,
             self.descriptor_sets_per_view,
             self.extracted_frame_node_mesh_data,
             self.extracted_view_node_mesh_data,
+            extracted_static_mesh_data,
         );
 
         Box::new(DefaultPrepareJob::new(prepare_impl))
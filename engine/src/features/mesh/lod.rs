@@ -0,0 +1,110 @@
+use renderer::nodes::{RenderView, RenderViewIndex};
+use fnv::FnvHashMap;
+
+// A mesh's projected size has to be this far (as a fraction) past a LOD threshold before the LOD
+// changes, so that meshes sitting right at a threshold don't flicker between LODs
+pub const MESH_LOD_HYSTERESIS: f32 = 0.1;
+
+// Most LODs supported per mesh, including the full detail mesh. The static mesh culling shader
+// stores LOD thresholds in a vec4
+pub const MAX_MESH_LOD_COUNT: usize = 5;
+
+// Colors used by the LOD debug view, indexed by LOD
+pub const MESH_LOD_DEBUG_COLORS: [[f32; 3]; MAX_MESH_LOD_COUNT] = [
+    [0.1, 0.9, 0.1],
+    [0.9, 0.9, 0.1],
+    [0.9, 0.5, 0.1],
+    [0.9, 0.1, 0.1],
+    [0.7, 0.1, 0.9],
+];
+
+/// Color that meshes at the given LOD are tinted with. Alpha is 0 if the debug view is disabled,
+/// in which case the shaders ignore it
+pub fn mesh_lod_debug_color(
+    lod: usize,
+    lod_debug_view_enabled: bool,
+) -> glam::Vec4 {
+    let color = MESH_LOD_DEBUG_COLORS[lod.min(MAX_MESH_LOD_COUNT - 1)];
+    let alpha = if lod_debug_view_enabled { 1.0 } else { 0.0 };
+    glam::Vec4::new(color[0], color[1], color[2], alpha)
+}
+
+/// Projected diameter of a bounding sphere as a fraction of the view's height
+pub fn projected_screen_size(
+    view: &RenderView,
+    center_ws: glam::Vec3,
+    radius_ws: f32,
+) -> f32 {
+    let distance = (center_ws - view.eye_position()).length();
+    if distance <= radius_ws {
+        return std::f32::MAX;
+    }
+
+    // [1][1] of a perspective projection is cot(fov_y / 2). It's negated by the vulkan correction
+    let projection_scale = view.projection_matrix().y_axis().y().abs();
+    radius_ws * projection_scale / distance
+}
+
+/// Picks a LOD for the given projected size, given the screen size thresholds of LOD 1 and beyond
+/// (see MeshLodData). To avoid popping back and forth, a mesh has to be `hysteresis` (i.e. 0.1 =
+/// 10%) past a threshold before switching away from `previous_lod`.
+pub fn select_mesh_lod(
+    lod_screen_sizes: &[f32],
+    screen_size: f32,
+    previous_lod: Option<usize>,
+    hysteresis: f32,
+) -> usize {
+    let lod_for_scale = |scale: f32| {
+        lod_screen_sizes
+            .iter()
+            .take_while(|lod_screen_size| screen_size < **lod_screen_size * scale)
+            .count()
+    };
+
+    match previous_lod {
+        Some(previous_lod) => {
+            let coarsest = lod_for_scale(1.0 + hysteresis);
+            let finest = lod_for_scale(1.0 - hysteresis);
+            previous_lod.max(finest).min(coarsest)
+        }
+        None => lod_for_scale(1.0),
+    }
+}
+
+/// Remembers which LOD each mesh render node used in each view last frame, so that LOD selection
+/// can apply hysteresis. Only the LODs set during the previous frame are kept, so entries for
+/// removed render nodes and views don't accumulate
+#[derive(Default)]
+pub struct MeshLodSelectionState {
+    previous_lods: FnvHashMap<(RenderViewIndex, u32), usize>,
+    current_lods: FnvHashMap<(RenderViewIndex, u32), usize>,
+}
+
+impl MeshLodSelectionState {
+    /// Must be called once per frame before any LODs are selected. Drops the LODs of the frame
+    /// before last
+    pub fn begin_frame(&mut self) {
+        std::mem::swap(&mut self.previous_lods, &mut self.current_lods);
+        self.current_lods.clear();
+    }
+
+    pub fn previous_lod(
+        &self,
+        view_index: RenderViewIndex,
+        render_node_index: u32,
+    ) -> Option<usize> {
+        self.previous_lods
+            .get(&(view_index, render_node_index))
+            .copied()
+    }
+
+    pub fn set_lod(
+        &mut self,
+        view_index: RenderViewIndex,
+        render_node_index: u32,
+        lod: usize,
+    ) {
+        self.current_lods
+            .insert((view_index, render_node_index), lod);
+    }
+}
@@ -1,9 +1,9 @@
 use renderer::nodes::{
-    RenderFeature, RenderFeatureIndex, DefaultExtractJob, ExtractJob, GenericRenderNodeHandle,
+    RenderFeature, RenderFeatureIndex, DefaultExtractJob, GenericRenderNodeHandle,
     RenderNodeSet, RenderNodeCount, FrameNodeIndex,
 };
 use std::sync::atomic::{Ordering, AtomicI32};
-use crate::render_contexts::{RenderJobExtractContext, RenderJobWriteContext, RenderJobPrepareContext};
+use crate::render_jobs::RenderExtractJob;
 use legion::prelude::Entity;
 use renderer::base::slab::{RawSlabKey, RawSlab};
 use std::convert::TryInto;
@@ -16,7 +16,22 @@ mod prepare;
 
 mod write;
 use write::MeshCommandWriter;
-use renderer::vulkan::VkBufferRaw;
+
+mod lod;
+pub use lod::{
+    MeshLodSelectionState, MESH_LOD_HYSTERESIS, MAX_MESH_LOD_COUNT, MESH_LOD_DEBUG_COLORS,
+    mesh_lod_debug_color, projected_screen_size, select_mesh_lod,
+};
+
+mod debug_view;
+pub use debug_view::MeshDebugViewMode;
+
+mod static_meshes;
+pub use static_meshes::{
+    StaticMeshSet, StaticMeshGpuData, StaticMeshFrameData, StaticMeshDraw,
+    StaticMeshInstanceShaderParam, MeshCullShaderParam,
+};
+use renderer::vulkan::{VkBufferRaw, VkDeviceContext};
 use renderer::assets::resources::{
     PipelineSwapchainInfo, DescriptorSetArc, DescriptorSetAllocatorRef, ResourceArc,
 };
@@ -66,26 +81,52 @@ pub struct MeshPerViewShaderParam {
     pub point_light_count: u32,                     // +16
     pub directional_light_count: u32,               // 20
     pub spot_light_count: u32,                      // +24
+    pub debug_view_mode: u32,                       // +28 (see MeshDebugViewMode)
     pub point_lights: [PointLight; 16],             // +32 (64*16 = 1024),
     pub directional_lights: [DirectionalLight; 16], // +1056 (64*16 = 1024),
     pub spot_lights: [SpotLight; 16],               // +2080 (96*16 = 1536)
 } // 3616 bytes
 
-#[derive(Default, Copy, Clone)]
+#[derive(Default, Copy, Clone, Debug)]
 #[repr(C)]
 pub struct MeshPerObjectShaderParam {
     pub model_view: glam::Mat4,      // +0
     pub model_view_proj: glam::Mat4, // +64
-} // 128 bytes
+    pub debug_color: glam::Vec4,     // +128 (mixed into the shaded color by alpha)
+} // 144 bytes, tightly packed in a storage buffer indexed by gl_InstanceIndex
+
+// Pass indices in mesh.material
+pub const MESH_OPAQUE_PASS_INDEX: usize = 0;
+pub const MESH_DEPTH_PREPASS_PASS_INDEX: usize = 1;
+pub const MESH_OPAQUE_DEPTH_EQUAL_PASS_INDEX: usize = 2;
+pub const MESH_WIREFRAME_PASS_INDEX: usize = 3;
 
-pub fn create_mesh_extract_job(
+// If depth_prepass_pipeline_info is provided, meshes are drawn into DepthPrepassRenderPhase with
+// it, and pipeline_info is expected to be the depth-equal opaque pass. Static meshes culled by
+// VkStaticMeshCullPass are drawn indirectly in the view of each static_mesh_frame_data. If
+// lod_debug_view_enabled is set, meshes are tinted by the LOD they were drawn with. If
+// wireframe_pipeline_info is provided, triangle edges are drawn over meshes in
+// TransparentRenderPhase with it.
+pub fn create_mesh_extract_job<'a>(
+    device_context: VkDeviceContext,
     descriptor_set_allocator: DescriptorSetAllocatorRef,
     pipeline_info: PipelineSwapchainInfo,
+    depth_prepass_pipeline_info: Option<PipelineSwapchainInfo>,
+    wireframe_pipeline_info: Option<PipelineSwapchainInfo>,
+    static_mesh_frame_data: Vec<StaticMeshFrameData>,
+    lod_debug_view_enabled: bool,
+    debug_view_mode: MeshDebugViewMode,
     mesh_material: &Handle<MaterialAsset>,
-) -> Box<dyn ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext>> {
+) -> RenderExtractJob<'a> {
     Box::new(DefaultExtractJob::new(MeshExtractJobImpl::new(
+        device_context,
         descriptor_set_allocator,
         pipeline_info,
+        depth_prepass_pipeline_info,
+        wireframe_pipeline_info,
+        static_mesh_frame_data,
+        lod_debug_view_enabled,
+        debug_view_mode,
         mesh_material,
     )))
 }
@@ -173,8 +214,14 @@ impl RenderFeature for MeshRenderFeature {
 
 #[derive(Debug)]
 pub struct ExtractedFrameNodeMeshData {
+    render_node_index: u32,
     world_transform: glam::Mat4,
-    draw_calls: Vec<MeshDrawCall>,
+    bounding_sphere_center_ws: glam::Vec3,
+    bounding_sphere_radius_ws: f32,
+    // Screen size thresholds of LOD 1 and beyond, see MeshLodData
+    lod_screen_sizes: Vec<f32>,
+    // Indexed by LOD, then by mesh part
+    draw_calls_per_lod: Vec<Vec<MeshDrawCall>>,
     vertex_buffer: ResourceArc<VkBufferRaw>,
     index_buffer: ResourceArc<VkBufferRaw>,
 }
@@ -190,12 +237,39 @@ pub struct MeshDrawCall {
 
 #[derive(Debug)]
 pub struct ExtractedViewNodeMeshData {
-    pub per_instance_descriptor: DescriptorSetArc, // set 2
+    pub frame_node_index: FrameNodeIndex,
+    pub per_object_param: MeshPerObjectShaderParam,
+    pub lod: u32,
+}
+
+// Static meshes to draw indirectly in the view they were culled for
+pub struct ExtractedStaticMeshData {
+    pub frame_data: StaticMeshFrameData,
+    // Per-material descriptor set (set 1) for each StaticMeshDraw
+    pub per_material_descriptors: Vec<DescriptorSetArc>,
+}
+
+pub struct PreparedStaticMeshData {
+    pub extracted: ExtractedStaticMeshData,
+    // Points at the per-instance buffer written by the culling shader (set 2)
+    pub per_instance_descriptor_set: DescriptorSetArc,
+}
+
+pub enum PreparedMeshDraw {
+    Batch(PreparedMeshBatch),
+    // Index into StaticMeshGpuData::draws, drawn with the static mesh data of the view being
+    // written. The instance count is written by the GPU
+    StaticIndirect(u32),
 }
 
+// A single instanced draw of one mesh part. Instances of the same mesh within a view are laid out
+// contiguously in that view's per-instance storage buffer (set 2)
 #[derive(Debug)]
-pub struct PreparedViewNodeMeshData {
-    pub per_instance_descriptor: DescriptorSetArc, // set 2
+pub struct PreparedMeshBatch {
+    // Any of the frame nodes in the batch, used to look up buffers and the draw call
     pub frame_node_index: FrameNodeIndex,
-    pub per_view_descriptor: DescriptorSetArc, // set 0
+    pub lod: u32,
+    pub draw_call_index: u32,
+    pub first_instance: u32,
+    pub instance_count: u32,
 }
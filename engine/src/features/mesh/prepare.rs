@@ -1,51 +1,359 @@
 use renderer::nodes::{
     RenderView, ViewSubmitNodes, FeatureSubmitNodes, FeatureCommandWriter, RenderFeatureIndex,
-    FramePacket, DefaultPrepareJobImpl, PerFrameNode, PerViewNode, RenderFeature,
+    FramePacket, DefaultPrepareJobImpl, PerFrameNode, PerViewNode, RenderFeature, FrameNodeIndex,
 };
 use crate::features::mesh::{
-    MeshRenderFeature, ExtractedFrameNodeMeshData, ExtractedViewNodeMeshData,
-    PreparedViewNodeMeshData,
+    MeshRenderFeature, ExtractedFrameNodeMeshData, ExtractedViewNodeMeshData, PreparedMeshBatch,
+    MeshPerObjectShaderParam, PreparedMeshDraw, ExtractedStaticMeshData, PreparedStaticMeshData,
 };
-use crate::phases::OpaqueRenderPhase;
+use crate::phases::{OpaqueRenderPhase, DepthPrepassRenderPhase, TransparentRenderPhase};
 use glam::Vec3;
 use super::MeshCommandWriter;
 use crate::render_contexts::{RenderJobWriteContext, RenderJobPrepareContext};
-use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetArc};
+use crate::render_jobs::SharedDescriptorSetAllocator;
+use renderer::assets::resources::{
+    PipelineSwapchainInfo, DescriptorSetArc, ResourceArc, DescriptorSetLayoutResource,
+};
+use renderer::vulkan::{VkBuffer, VkBufferRaw, VkDeviceContext};
+use ash::vk;
+use fnv::FnvHashMap;
+use rayon::prelude::*;
+
+// A visible mesh in a view, gathered and batched in prepare_view_batches
+struct MeshViewInstance {
+    frame_node_index: FrameNodeIndex,
+    lod: u32,
+    per_object_param: MeshPerObjectShaderParam,
+    distance_from_camera: f32,
+}
+
+// All visible instances of a single mesh at a single LOD within a view
+struct MeshViewInstances {
+    frame_node_index: FrameNodeIndex,
+    lod: u32,
+    per_object_params: Vec<MeshPerObjectShaderParam>,
+    nearest_distance_from_camera: f32,
+}
+
+// A batch along with what's needed to add its submit nodes
+struct MeshViewBatch {
+    batch: PreparedMeshBatch,
+    sort_key: u32,
+    distance_from_camera: f32,
+}
+
+// The batches of a single view. These are built for all views at once in prepare_begin and turned
+// into draws and submit nodes in prepare_view_finalize
+#[derive(Default)]
+struct MeshViewBatches {
+    batches: Vec<MeshViewBatch>,
+    material_ordinals: FnvHashMap<vk::DescriptorSet, u32>,
+    per_instance_descriptor_set: Option<DescriptorSetArc>,
+}
 
 pub struct MeshPrepareJobImpl {
+    device_context: VkDeviceContext,
+    // Shared because views are batched in parallel
+    descriptor_set_allocator: SharedDescriptorSetAllocator,
+    per_instance_descriptor_set_layout: ResourceArc<DescriptorSetLayoutResource>,
     pipeline_info: PipelineSwapchainInfo,
+    depth_prepass_pipeline_info: Option<PipelineSwapchainInfo>,
+    wireframe_pipeline_info: Option<PipelineSwapchainInfo>,
     descriptor_sets_per_view: Vec<DescriptorSetArc>,
     extracted_frame_node_mesh_data: Vec<Option<ExtractedFrameNodeMeshData>>,
     extracted_view_node_mesh_data: Vec<Vec<Option<ExtractedViewNodeMeshData>>>,
-    prepared_view_node_mesh_data: Vec<PreparedViewNodeMeshData>,
+    view_batches: Vec<MeshViewBatches>,
+    per_instance_descriptor_sets_per_view: Vec<Option<DescriptorSetArc>>,
+    // One per view that static meshes were culled for, taken as each view is prepared
+    extracted_static_mesh_data: Vec<ExtractedStaticMeshData>,
+    prepared_static_mesh_data_per_view: Vec<Option<PreparedStaticMeshData>>,
+    draws: Vec<PreparedMeshDraw>,
 }
 
 impl MeshPrepareJobImpl {
     pub(super) fn new(
+        device_context: VkDeviceContext,
+        descriptor_set_allocator: SharedDescriptorSetAllocator,
+        per_instance_descriptor_set_layout: ResourceArc<DescriptorSetLayoutResource>,
         pipeline_info: PipelineSwapchainInfo,
+        depth_prepass_pipeline_info: Option<PipelineSwapchainInfo>,
+        wireframe_pipeline_info: Option<PipelineSwapchainInfo>,
         descriptor_sets_per_view: Vec<DescriptorSetArc>,
         extracted_frame_node_mesh_data: Vec<Option<ExtractedFrameNodeMeshData>>,
         extracted_view_node_mesh_data: Vec<Vec<Option<ExtractedViewNodeMeshData>>>,
+        extracted_static_mesh_data: Vec<ExtractedStaticMeshData>,
     ) -> Self {
-        let prepared_view_node_mesh_data = Vec::with_capacity(extracted_view_node_mesh_data.len());
         MeshPrepareJobImpl {
+            device_context,
+            descriptor_set_allocator,
+            per_instance_descriptor_set_layout,
             pipeline_info,
+            depth_prepass_pipeline_info,
+            wireframe_pipeline_info,
             descriptor_sets_per_view,
             extracted_frame_node_mesh_data,
             extracted_view_node_mesh_data,
-            prepared_view_node_mesh_data,
+            view_batches: Default::default(),
+            per_instance_descriptor_sets_per_view: Default::default(),
+            extracted_static_mesh_data,
+            prepared_static_mesh_data_per_view: Default::default(),
+            draws: Default::default(),
+        }
+    }
+
+    fn create_per_instance_descriptor_set(
+        &self,
+        prepare_context: &RenderJobPrepareContext,
+        per_object_params: &[MeshPerObjectShaderParam],
+    ) -> DescriptorSetArc {
+        let storage_buffer_size = per_object_params.len() as u64
+            * std::mem::size_of::<MeshPerObjectShaderParam>() as u64;
+        let mut storage_buffer = VkBuffer::new(
+            &self.device_context,
+            vk_mem::MemoryUsage::CpuToGpu,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            storage_buffer_size,
+        )
+        .unwrap();
+
+        storage_buffer
+            .write_to_host_visible_buffer(per_object_params)
+            .unwrap();
+        prepare_context
+            .render_stats()
+            .record_buffer_upload(storage_buffer_size);
+
+        let storage_buffer = prepare_context
+            .dyn_resource_lookups
+            .insert_buffer(storage_buffer);
+
+        self.create_per_instance_descriptor_set_for_buffer(prepare_context, &storage_buffer)
+    }
+
+    fn create_per_instance_descriptor_set_for_buffer(
+        &self,
+        prepare_context: &RenderJobPrepareContext,
+        storage_buffer: &ResourceArc<VkBufferRaw>,
+    ) -> DescriptorSetArc {
+        let descriptor_set = self.descriptor_set_allocator.with_allocator(|allocator| {
+            let mut descriptor_set = allocator
+                .create_dyn_descriptor_set_uninitialized(&self.per_instance_descriptor_set_layout)
+                .unwrap();
+            descriptor_set.set_buffer(0, storage_buffer);
+            descriptor_set.flush(allocator).unwrap();
+            descriptor_set
+        });
+        prepare_context
+            .render_stats()
+            .record_descriptor_set_allocated();
+
+        descriptor_set.descriptor_set().clone()
+    }
+
+    // Only reads extracted data, so this can run for several views at once
+    fn prepare_view_batches(
+        &self,
+        prepare_context: &RenderJobPrepareContext,
+        view: &RenderView,
+    ) -> MeshViewBatches {
+        let mut view_batches = MeshViewBatches::default();
+
+        let extracted_view_data = match self
+            .extracted_view_node_mesh_data
+            .get(view.view_index() as usize)
+        {
+            Some(extracted_view_data) => extracted_view_data,
+            None => return view_batches,
+        };
+
+        // Gathering instances is the only per-node work, so it is split further across the pool
+        let view_instances: Vec<_> = extracted_view_data
+            .par_iter()
+            .filter_map(|extracted_view_data| {
+                let extracted_view_data = extracted_view_data.as_ref()?;
+                let frame_node_index = extracted_view_data.frame_node_index;
+                let extracted_frame_data =
+                    self.extracted_frame_node_mesh_data[frame_node_index as usize].as_ref()?;
+
+                let distance_from_camera = Vec3::length(
+                    extracted_frame_data.world_transform.w_axis().truncate() - view.eye_position(),
+                );
+
+                Some(MeshViewInstance {
+                    frame_node_index,
+                    lod: extracted_view_data.lod,
+                    per_object_param: extracted_view_data.per_object_param,
+                    distance_from_camera,
+                })
+            })
+            .collect();
+
+        if view_instances.is_empty() {
+            return view_batches;
+        }
+
+        // Group the visible instances by mesh and LOD. Each mesh part becomes a single instanced
+        // draw
+        let mut instances_by_mesh = FnvHashMap::<(vk::Buffer, u32), MeshViewInstances>::default();
+        for instance in view_instances {
+            let frame_node_data = self.extracted_frame_node_mesh_data
+                [instance.frame_node_index as usize]
+                .as_ref()
+                .unwrap();
+
+            let mesh_instances = instances_by_mesh
+                .entry((frame_node_data.vertex_buffer.get_raw().buffer, instance.lod))
+                .or_insert_with(|| MeshViewInstances {
+                    frame_node_index: instance.frame_node_index,
+                    lod: instance.lod,
+                    per_object_params: vec![],
+                    nearest_distance_from_camera: std::f32::MAX,
+                });
+
+            mesh_instances
+                .per_object_params
+                .push(instance.per_object_param);
+            mesh_instances.nearest_distance_from_camera = mesh_instances
+                .nearest_distance_from_camera
+                .min(instance.distance_from_camera);
+        }
+
+        let mut per_object_params = vec![];
+
+        let instances_by_mesh = instances_by_mesh.into_iter().map(|(_, x)| x);
+        for (mesh_ordinal, mesh_instances) in instances_by_mesh.enumerate() {
+            let first_instance = per_object_params.len() as u32;
+            let instance_count = mesh_instances.per_object_params.len() as u32;
+            per_object_params.extend(mesh_instances.per_object_params);
+
+            let frame_node_data = self.extracted_frame_node_mesh_data
+                [mesh_instances.frame_node_index as usize]
+                .as_ref()
+                .unwrap();
+
+            let draw_calls = &frame_node_data.draw_calls_per_lod[mesh_instances.lod as usize];
+            for (draw_call_index, draw_call) in draw_calls.iter().enumerate() {
+                let material_ordinals = &mut view_batches.material_ordinals;
+                let next_material_ordinal = material_ordinals.len() as u32;
+                let material_ordinal = *material_ordinals
+                    .entry(draw_call.per_material_descriptor.get())
+                    .or_insert(next_material_ordinal);
+
+                let sort_key = (material_ordinal << 16) | (mesh_ordinal as u32 & 0x7FFF);
+
+                view_batches.batches.push(MeshViewBatch {
+                    batch: PreparedMeshBatch {
+                        frame_node_index: mesh_instances.frame_node_index,
+                        lod: mesh_instances.lod,
+                        draw_call_index: draw_call_index as u32,
+                        first_instance,
+                        instance_count,
+                    },
+                    sort_key,
+                    distance_from_camera: mesh_instances.nearest_distance_from_camera,
+                });
+            }
+        }
+
+        view_batches.per_instance_descriptor_set =
+            Some(self.create_per_instance_descriptor_set(prepare_context, &per_object_params));
+
+        view_batches
+    }
+
+    // Static meshes are culled on the GPU, so every mesh part gets a submit node regardless of
+    // whether any of its instances are visible. Culled parts are drawn with an instance count of 0
+    fn prepare_view_static_meshes(
+        &mut self,
+        prepare_context: &RenderJobPrepareContext,
+        view_index: usize,
+        extracted: ExtractedStaticMeshData,
+        material_ordinals: &mut FnvHashMap<vk::DescriptorSet, u32>,
+        submit_nodes: &mut ViewSubmitNodes,
+    ) {
+        let per_instance_descriptor_set = self.create_per_instance_descriptor_set_for_buffer(
+            prepare_context,
+            &extracted.frame_data.per_instance_buffer,
+        );
+
+        for (draw_index, per_material_descriptor) in
+            extracted.per_material_descriptors.iter().enumerate()
+        {
+            let next_material_ordinal = material_ordinals.len() as u32;
+            let material_ordinal = *material_ordinals
+                .entry(per_material_descriptor.get())
+                .or_insert(next_material_ordinal);
+
+            // Static draws sort after batches using the same material
+            let sort_key = (material_ordinal << 16) | 0x8000 | (draw_index as u32 & 0x7FFF);
+
+            let submit_node_id = self.draws.len() as u32;
+            self.draws
+                .push(PreparedMeshDraw::StaticIndirect(draw_index as u32));
+
+            // The per-instance distance is not known on the CPU
+            submit_nodes.add_submit_node::<OpaqueRenderPhase>(submit_node_id, sort_key, 0.0);
+
+            if self.depth_prepass_pipeline_info.is_some() {
+                submit_nodes.add_submit_node::<DepthPrepassRenderPhase>(
+                    submit_node_id,
+                    sort_key,
+                    0.0,
+                );
+            }
+
+            if self.wireframe_pipeline_info.is_some() {
+                submit_nodes.add_submit_node::<TransparentRenderPhase>(
+                    submit_node_id,
+                    sort_key,
+                    0.0,
+                );
+            }
         }
+
+        self.prepared_static_mesh_data_per_view[view_index] = Some(PreparedStaticMeshData {
+            extracted,
+            per_instance_descriptor_set,
+        });
     }
 }
 
 impl DefaultPrepareJobImpl<RenderJobPrepareContext, RenderJobWriteContext> for MeshPrepareJobImpl {
     fn prepare_begin(
         &mut self,
-        _prepare_context: &RenderJobPrepareContext,
+        prepare_context: &RenderJobPrepareContext,
         _frame_packet: &FramePacket,
-        _views: &[&RenderView],
+        views: &[&RenderView],
         _submit_nodes: &mut FeatureSubmitNodes,
     ) {
+        let view_count = views
+            .iter()
+            .map(|view| view.view_index() as usize + 1)
+            .max()
+            .unwrap_or(0);
+
+        self.view_batches.resize_with(view_count, Default::default);
+        self.per_instance_descriptor_sets_per_view
+            .resize_with(view_count, Default::default);
+        self.prepared_static_mesh_data_per_view
+            .resize_with(view_count, Default::default);
+
+        // Runs on the render job thread pool when called within it
+        let this = &*self;
+        let view_batches: Vec<_> = views
+            .par_iter()
+            .map(|view| {
+                (
+                    view.view_index() as usize,
+                    this.prepare_view_batches(prepare_context, view),
+                )
+            })
+            .collect();
+
+        for (view_index, view_batches) in view_batches {
+            self.view_batches[view_index] = view_batches;
+        }
     }
 
     fn prepare_frame_node(
@@ -57,63 +365,99 @@ impl DefaultPrepareJobImpl<RenderJobPrepareContext, RenderJobWriteContext> for M
     ) {
     }
 
+    // View nodes are batched in prepare_begin
     fn prepare_view_node(
         &mut self,
         _prepare_context: &RenderJobPrepareContext,
+        _view: &RenderView,
+        _view_node: PerViewNode,
+        _view_node_index: u32,
+        _submit_nodes: &mut ViewSubmitNodes,
+    ) {
+    }
+
+    fn prepare_view_finalize(
+        &mut self,
+        prepare_context: &RenderJobPrepareContext,
         view: &RenderView,
-        view_node: PerViewNode,
-        view_node_index: u32,
         submit_nodes: &mut ViewSubmitNodes,
     ) {
-        let frame_node_index = view_node.frame_node_index();
-        let extracted_frame_data = &self.extracted_frame_node_mesh_data[frame_node_index as usize];
-
-        if let Some(extracted_frame_data) = extracted_frame_data {
-            if let Some(extracted_view_data) = &self.extracted_view_node_mesh_data
-                [view.view_index() as usize][view_node_index as usize]
-            {
-                let submit_node_id = self.prepared_view_node_mesh_data.len() as u32;
-                self.prepared_view_node_mesh_data
-                    .push(PreparedViewNodeMeshData {
-                        per_view_descriptor: self.descriptor_sets_per_view
-                            [view.view_index() as usize]
-                            .clone(),
-                        frame_node_index,
-                        per_instance_descriptor: extracted_view_data
-                            .per_instance_descriptor
-                            .clone(),
-                    });
+        let view_index = view.view_index() as usize;
+        let view_batches =
+            std::mem::replace(&mut self.view_batches[view_index], Default::default());
 
-                let distance_from_camera = Vec3::length(
-                    extracted_frame_data.world_transform.w_axis().truncate() - view.eye_position(),
+        // Materials and meshes are assigned small ordinals so that they can be packed into the
+        // sort key. The opaque phase sorts by it, grouping draws by material and then by mesh so
+        // that the writer can skip redundant binds
+        let mut material_ordinals = view_batches.material_ordinals;
+
+        for view_batch in view_batches.batches {
+            let submit_node_id = self.draws.len() as u32;
+            self.draws.push(PreparedMeshDraw::Batch(view_batch.batch));
+
+            submit_nodes.add_submit_node::<OpaqueRenderPhase>(
+                submit_node_id,
+                view_batch.sort_key,
+                view_batch.distance_from_camera,
+            );
+
+            if self.depth_prepass_pipeline_info.is_some() {
+                submit_nodes.add_submit_node::<DepthPrepassRenderPhase>(
+                    submit_node_id,
+                    view_batch.sort_key,
+                    view_batch.distance_from_camera,
                 );
-                submit_nodes.add_submit_node::<OpaqueRenderPhase>(
+            }
+
+            if self.wireframe_pipeline_info.is_some() {
+                submit_nodes.add_submit_node::<TransparentRenderPhase>(
                     submit_node_id,
-                    0,
-                    distance_from_camera,
+                    view_batch.sort_key,
+                    view_batch.distance_from_camera,
                 );
             }
         }
-    }
 
-    fn prepare_view_finalize(
-        &mut self,
-        _prepare_context: &RenderJobPrepareContext,
-        _view: &RenderView,
-        _submit_nodes: &mut ViewSubmitNodes,
-    ) {
+        self.per_instance_descriptor_sets_per_view[view_index] =
+            view_batches.per_instance_descriptor_set;
+
+        let static_mesh_data_index = self
+            .extracted_static_mesh_data
+            .iter()
+            .position(|x| x.frame_data.view_index as usize == view_index);
+        if let Some(static_mesh_data_index) = static_mesh_data_index {
+            let extracted = self
+                .extracted_static_mesh_data
+                .swap_remove(static_mesh_data_index);
+            self.prepare_view_static_meshes(
+                prepare_context,
+                view_index,
+                extracted,
+                &mut material_ordinals,
+                submit_nodes,
+            );
+        }
     }
 
     fn prepare_frame_finalize(
-        self,
+        mut self,
         _prepare_context: &RenderJobPrepareContext,
         _submit_nodes: &mut FeatureSubmitNodes,
     ) -> Box<dyn FeatureCommandWriter<RenderJobWriteContext>> {
+        self.descriptor_set_allocator
+            .with_allocator(|allocator| allocator.flush_changes())
+            .unwrap();
+
         Box::new(MeshCommandWriter {
             pipeline_info: self.pipeline_info,
+            depth_prepass_pipeline_info: self.depth_prepass_pipeline_info,
+            wireframe_pipeline_info: self.wireframe_pipeline_info,
             descriptor_sets_per_view: self.descriptor_sets_per_view,
+            per_instance_descriptor_sets_per_view: self.per_instance_descriptor_sets_per_view,
             extracted_frame_node_mesh_data: self.extracted_frame_node_mesh_data,
-            prepared_view_node_mesh_data: self.prepared_view_node_mesh_data,
+            static_mesh_data_per_view: self.prepared_static_mesh_data_per_view,
+            draws: self.draws,
+            bound_state: Default::default(),
         })
     }
 
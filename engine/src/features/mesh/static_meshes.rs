@@ -0,0 +1,406 @@
+use crate::components::MeshComponent;
+use crate::game_resource_manager::{GameResourceManager, MeshInfo};
+use crate::assets::gltf::MeshVertex;
+use super::{MAX_MESH_LOD_COUNT, mesh_lod_debug_color};
+use renderer::assets::resources::{ResourceArc, DescriptorSetArc, DynResourceAllocatorSet};
+use renderer::vulkan::{VkBuffer, VkBufferRaw, VkDeviceContext};
+use renderer::nodes::{RenderView, RenderViewIndex};
+use minimum::components::TransformComponent;
+use atelier_assets::loader::handle::AssetHandle;
+use atelier_assets::loader::LoadHandle;
+use legion::prelude::*;
+use ash::prelude::VkResult;
+use ash::vk;
+use fnv::{FnvHashMap, FnvHasher};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+// Per-instance data read by mesh_cull.comp
+#[derive(Default, Copy, Clone)]
+#[repr(C)]
+pub struct StaticMeshInstanceShaderParam {
+    pub model: glam::Mat4,            // +0
+    pub bounding_sphere: glam::Vec4,  // +64 (world space center in xyz, radius in w)
+    pub lod_screen_sizes: glam::Vec4, // +80 (thresholds of LOD 1 and beyond, see MeshLodData)
+    pub first_draw_command: u32,      // +96
+    pub parts_per_lod: u32,           // +100
+    pub lod_count: u32,               // +104
+    pub _padding: u32,                // +108
+} // 112 bytes
+
+// Uniform data read by mesh_cull.comp
+#[derive(Default, Copy, Clone)]
+#[repr(C)]
+pub struct MeshCullShaderParam {
+    pub view: glam::Mat4,                // +0
+    pub proj: glam::Mat4,                // +64
+    pub frustum_planes: [glam::Vec4; 6], // +128 (xyz = normal pointing inward, w = distance)
+    pub eye_position: glam::Vec4,        // +224
+    pub lod_debug_colors: [glam::Vec4; MAX_MESH_LOD_COUNT], // +240 (alpha 0 if disabled)
+    pub instance_count: u32,             // +320
+    pub _padding: [u32; 3],              // +324
+} // 336 bytes
+
+impl MeshCullShaderParam {
+    // The frustum is taken from cull_projection, which may differ from the view's projection when
+    // the view only covers a region of the window (see SceneView). Drawing and LOD selection still
+    // use the view's projection.
+    pub fn new(
+        view: &RenderView,
+        cull_projection: glam::Mat4,
+        instance_count: u32,
+        lod_debug_view_enabled: bool,
+    ) -> Self {
+        let view_matrix = view.view_matrix();
+        let proj_matrix = view.projection_matrix();
+
+        // Gribb/Hartmann plane extraction. The projection matrix includes the vulkan correction,
+        // so clip space z is 0..w
+        let view_proj = (cull_projection * view_matrix).transpose();
+        let row0 = view_proj.x_axis();
+        let row1 = view_proj.y_axis();
+        let row2 = view_proj.z_axis();
+        let row3 = view_proj.w_axis();
+
+        let mut frustum_planes = [
+            row3 + row0, // left
+            row3 - row0, // right
+            row3 + row1, // bottom
+            row3 - row1, // top
+            row2,        // near
+            row3 - row2, // far
+        ];
+
+        for plane in &mut frustum_planes {
+            *plane /= plane.truncate().length();
+        }
+
+        let mut lod_debug_colors = [glam::Vec4::zero(); MAX_MESH_LOD_COUNT];
+        for (lod, color) in lod_debug_colors.iter_mut().enumerate() {
+            *color = mesh_lod_debug_color(lod, lod_debug_view_enabled);
+        }
+
+        MeshCullShaderParam {
+            view: view_matrix,
+            proj: proj_matrix,
+            frustum_planes,
+            eye_position: view.eye_position().extend(1.0),
+            lod_debug_colors,
+            instance_count,
+            _padding: Default::default(),
+        }
+    }
+}
+
+// A single mesh part at a single LOD, shared by all static instances of its mesh. Its indirect draw
+// command sits at the same index in the draw command buffer.
+pub struct StaticMeshDraw {
+    pub vertex_buffer: ResourceArc<VkBufferRaw>,
+    pub index_buffer: ResourceArc<VkBufferRaw>,
+    pub material_instance: Arc<Vec<Vec<DescriptorSetArc>>>,
+}
+
+/// All static mesh instances and mesh part ranges, uploaded once and reused every frame until the
+/// set of static meshes changes.
+pub struct StaticMeshGpuData {
+    pub instance_count: u32,
+    pub instance_buffer: ResourceArc<VkBufferRaw>,
+
+    // One vk::DrawIndexedIndirectCommand per StaticMeshDraw with instance_count = 0. Each frame
+    // this is copied into the frame's draw command buffer and the culling shader increments
+    // instance_count for every visible instance.
+    pub draw_command_template_buffer: ResourceArc<VkBufferRaw>,
+    pub draws: Vec<StaticMeshDraw>,
+
+    // Every draw reserves space for all instances of its mesh in the per-instance output buffer
+    pub max_visible_instance_count: u32,
+}
+
+impl StaticMeshGpuData {
+    /// Allocates the buffers that the culling pass writes for one frame. See
+    /// `MeshCullShaderParam::new` for `cull_projection`
+    pub fn create_frame_data(
+        self: &Arc<Self>,
+        device_context: &VkDeviceContext,
+        dyn_resource_allocator: &DynResourceAllocatorSet,
+        view: &RenderView,
+        cull_projection: glam::Mat4,
+        lod_debug_view_enabled: bool,
+    ) -> VkResult<StaticMeshFrameData> {
+        let cull_params = MeshCullShaderParam::new(
+            view,
+            cull_projection,
+            self.instance_count,
+            lod_debug_view_enabled,
+        );
+        let mut cull_params_buffer = VkBuffer::new(
+            device_context,
+            vk_mem::MemoryUsage::CpuToGpu,
+            vk::BufferUsageFlags::UNIFORM_BUFFER,
+            vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+            std::mem::size_of::<MeshCullShaderParam>() as u64,
+        )?;
+        cull_params_buffer.write_to_host_visible_buffer(&[cull_params])?;
+
+        let draw_command_buffer = VkBuffer::new(
+            device_context,
+            vk_mem::MemoryUsage::GpuOnly,
+            vk::BufferUsageFlags::STORAGE_BUFFER
+                | vk::BufferUsageFlags::INDIRECT_BUFFER
+                | vk::BufferUsageFlags::TRANSFER_DST,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            (self.draws.len() * std::mem::size_of::<vk::DrawIndexedIndirectCommand>()) as u64,
+        )?;
+
+        let per_instance_buffer = VkBuffer::new(
+            device_context,
+            vk_mem::MemoryUsage::GpuOnly,
+            vk::BufferUsageFlags::STORAGE_BUFFER,
+            vk::MemoryPropertyFlags::DEVICE_LOCAL,
+            self.max_visible_instance_count as u64
+                * std::mem::size_of::<super::MeshPerObjectShaderParam>() as u64,
+        )?;
+
+        Ok(StaticMeshFrameData {
+            gpu_data: self.clone(),
+            view_index: view.view_index(),
+            cull_params_buffer: dyn_resource_allocator.insert_buffer(cull_params_buffer),
+            draw_command_buffer: dyn_resource_allocator.insert_buffer(draw_command_buffer),
+            per_instance_buffer: dyn_resource_allocator.insert_buffer(per_instance_buffer),
+        })
+    }
+}
+
+/// Per-frame buffers for culling and drawing static meshes in a single view
+#[derive(Clone)]
+pub struct StaticMeshFrameData {
+    pub gpu_data: Arc<StaticMeshGpuData>,
+    pub view_index: RenderViewIndex,
+    pub cull_params_buffer: ResourceArc<VkBufferRaw>,
+    pub draw_command_buffer: ResourceArc<VkBufferRaw>,
+    // MeshPerObjectShaderParam per visible instance, bound as set 2 of the mesh material
+    pub per_instance_buffer: ResourceArc<VkBufferRaw>,
+}
+
+// All static instances of a single mesh, gathered while rebuilding
+struct StaticMeshInstances {
+    mesh_info: MeshInfo,
+    transforms: Vec<glam::Mat4>,
+}
+
+/// Tracks meshes spawned with `is_static`. These are not registered as render nodes. Instead,
+/// their data is uploaded once and they are culled on the GPU and drawn indirectly in every view,
+/// so the only per-frame CPU cost is checking whether they changed.
+#[derive(Default)]
+pub struct StaticMeshSet {
+    dirty: bool,
+    // Hash of the static meshes the GPU data was built from, see static_meshes_hash()
+    gpu_data_hash: u64,
+    gpu_data: Option<Arc<StaticMeshGpuData>>,
+}
+
+impl StaticMeshSet {
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn gpu_data(&self) -> Option<&Arc<StaticMeshGpuData>> {
+        self.gpu_data.as_ref()
+    }
+
+    // Static meshes aren't expected to move, but the editor can still move, delete or respawn
+    // them. Rather than tracking every way that can happen, the entity, mesh and transform of every
+    // static mesh are hashed and compared with the hash the GPU data was built from
+    fn static_meshes_hash(world: &World) -> u64 {
+        let mut hasher = FnvHasher::default();
+        let query = <(Read<TransformComponent>, Read<MeshComponent>)>::query();
+        for (entity, (transform, mesh_component)) in query.iter_entities(world) {
+            if !mesh_component.is_static {
+                continue;
+            }
+
+            entity.hash(&mut hasher);
+            mesh_component
+                .mesh
+                .as_ref()
+                .map(|mesh_handle| mesh_handle.load_handle())
+                .hash(&mut hasher);
+            for value in &transform.transform().to_cols_array() {
+                value.to_bits().hash(&mut hasher);
+            }
+        }
+
+        hasher.finish()
+    }
+
+    /// Rebuilds the GPU data if static meshes were spawned, removed or moved since the last
+    /// update. If some meshes have not finished loading, the data is rebuilt again on the next
+    /// update.
+    pub fn update_gpu_data(
+        &mut self,
+        world: &World,
+        device_context: &VkDeviceContext,
+        dyn_resource_allocator: &DynResourceAllocatorSet,
+        game_resource_manager: &GameResourceManager,
+    ) -> VkResult<()> {
+        let static_meshes_hash = Self::static_meshes_hash(world);
+        if !self.dirty && static_meshes_hash == self.gpu_data_hash {
+            return Ok(());
+        }
+
+        let mut has_pending_meshes = false;
+        let mut mesh_indices = FnvHashMap::<LoadHandle, usize>::default();
+        let mut meshes = Vec::<StaticMeshInstances>::default();
+
+        let query = <(Read<TransformComponent>, Read<MeshComponent>)>::query();
+        for (transform, mesh_component) in query.iter(world) {
+            if !mesh_component.is_static {
+                continue;
+            }
+
+            let mesh_handle = match &mesh_component.mesh {
+                Some(mesh_handle) => mesh_handle,
+                None => continue,
+            };
+
+            let mesh_index = match mesh_indices.get(&mesh_handle.load_handle()) {
+                Some(mesh_index) => *mesh_index,
+                None => match game_resource_manager.get_mesh_info(mesh_handle) {
+                    Some(mesh_info) => {
+                        let mesh_index = meshes.len();
+                        meshes.push(StaticMeshInstances {
+                            mesh_info,
+                            transforms: vec![],
+                        });
+                        mesh_indices.insert(mesh_handle.load_handle(), mesh_index);
+                        mesh_index
+                    }
+                    None => {
+                        has_pending_meshes = true;
+                        continue;
+                    }
+                },
+            };
+
+            meshes[mesh_index].transforms.push(transform.transform());
+        }
+
+        let mut instances = vec![];
+        let mut draw_commands = vec![];
+        let mut draws = vec![];
+        let mut max_visible_instance_count = 0;
+
+        for mesh in meshes {
+            let first_draw_command = draw_commands.len() as u32;
+            let instance_count = mesh.transforms.len() as u32;
+            let mesh_asset = &mesh.mesh_info.mesh_asset;
+            let lod_count = mesh_asset.lod_count().min(MAX_MESH_LOD_COUNT);
+
+            // Draw commands are laid out by LOD, then by part. Part offsets are baked into the
+            // draw commands so that all parts of a mesh can share the same vertex/index buffer
+            // binding
+            for lod in 0..lod_count {
+                for (part_range, mesh_part_info) in mesh_asset
+                    .lod_part_ranges(lod)
+                    .iter()
+                    .zip(&mesh.mesh_info.mesh_parts)
+                {
+                    draw_commands.push(vk::DrawIndexedIndirectCommand {
+                        index_count: part_range.index_buffer_size_in_bytes / 2, //sizeof(u16)
+                        instance_count: 0,
+                        first_index: part_range.index_buffer_offset_in_bytes / 2,
+                        vertex_offset: (part_range.vertex_buffer_offset_in_bytes as usize
+                            / std::mem::size_of::<MeshVertex>())
+                            as i32,
+                        first_instance: max_visible_instance_count,
+                    });
+
+                    draws.push(StaticMeshDraw {
+                        vertex_buffer: mesh.mesh_info.vertex_buffer.clone(),
+                        index_buffer: mesh.mesh_info.index_buffer.clone(),
+                        material_instance: mesh_part_info.material_instance.clone(),
+                    });
+
+                    max_visible_instance_count += instance_count;
+                }
+            }
+
+            let parts_per_lod = mesh.mesh_info.mesh_parts.len() as u32;
+
+            // Unused thresholds are 0 so that they are never selected
+            let mut lod_screen_sizes = [0.0; 4];
+            for (screen_size, lod) in lod_screen_sizes.iter_mut().zip(&mesh_asset.lods) {
+                *screen_size = lod.screen_size;
+            }
+            let lod_screen_sizes = glam::Vec4::new(
+                lod_screen_sizes[0],
+                lod_screen_sizes[1],
+                lod_screen_sizes[2],
+                lod_screen_sizes[3],
+            );
+
+            let bounding_sphere = &mesh.mesh_info.mesh_asset.bounding_sphere;
+            for transform in mesh.transforms {
+                let center = transform.transform_point3(bounding_sphere.center);
+                let max_scale = transform
+                    .x_axis()
+                    .truncate()
+                    .length()
+                    .max(transform.y_axis().truncate().length())
+                    .max(transform.z_axis().truncate().length());
+
+                instances.push(StaticMeshInstanceShaderParam {
+                    model: transform,
+                    bounding_sphere: center.extend(bounding_sphere.radius * max_scale),
+                    lod_screen_sizes,
+                    first_draw_command,
+                    parts_per_lod,
+                    lod_count: lod_count as u32,
+                    _padding: 0,
+                });
+            }
+        }
+
+        log::debug!(
+            "Rebuilt static mesh GPU data: {} instances, {} draws",
+            instances.len(),
+            draws.len()
+        );
+
+        self.gpu_data = if instances.is_empty() || draws.is_empty() {
+            None
+        } else {
+            let mut instance_buffer = VkBuffer::new(
+                device_context,
+                vk_mem::MemoryUsage::CpuToGpu,
+                vk::BufferUsageFlags::STORAGE_BUFFER,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                (instances.len() * std::mem::size_of::<StaticMeshInstanceShaderParam>()) as u64,
+            )?;
+            instance_buffer.write_to_host_visible_buffer(&instances)?;
+
+            let mut draw_command_template_buffer = VkBuffer::new(
+                device_context,
+                vk_mem::MemoryUsage::CpuToGpu,
+                vk::BufferUsageFlags::TRANSFER_SRC,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                (draw_commands.len() * std::mem::size_of::<vk::DrawIndexedIndirectCommand>())
+                    as u64,
+            )?;
+            draw_command_template_buffer.write_to_host_visible_buffer(&draw_commands)?;
+
+            Some(Arc::new(StaticMeshGpuData {
+                instance_count: instances.len() as u32,
+                instance_buffer: dyn_resource_allocator.insert_buffer(instance_buffer),
+                draw_command_template_buffer: dyn_resource_allocator
+                    .insert_buffer(draw_command_template_buffer),
+                draws,
+                max_visible_instance_count,
+            }))
+        };
+
+        self.dirty = has_pending_meshes;
+        self.gpu_data_hash = static_meshes_hash;
+        Ok(())
+    }
+}
@@ -1,111 +1,325 @@
-use crate::features::mesh::{MeshRenderFeature, ExtractedFrameNodeMeshData, PreparedViewNodeMeshData};
+use crate::features::mesh::{
+    MeshRenderFeature, ExtractedFrameNodeMeshData, PreparedMeshBatch, PreparedMeshDraw,
+    PreparedStaticMeshData,
+};
 use renderer::nodes::{
     RenderFeatureIndex, RenderPhaseIndex, RenderFeature, SubmitNodeId, FeatureCommandWriter, RenderView,
+    RenderPhase,
 };
+use crate::phases::{DepthPrepassRenderPhase, TransparentRenderPhase};
 use crate::render_contexts::RenderJobWriteContext;
 use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetArc};
 use ash::vk;
 use ash::version::DeviceV1_0;
+use std::sync::Mutex;
+
+// What is currently bound on the command buffer. Batches are sorted by material and then mesh, so
+// consecutive batches frequently share state.
+#[derive(Default)]
+pub struct MeshBoundState {
+    per_material_descriptor: Option<vk::DescriptorSet>,
+    per_instance_descriptor: Option<vk::DescriptorSet>,
+    vertex_buffer: Option<(vk::Buffer, u32)>,
+    index_buffer: Option<(vk::Buffer, u32)>,
+}
 
 pub struct MeshCommandWriter {
     pub pipeline_info: PipelineSwapchainInfo,
+    pub depth_prepass_pipeline_info: Option<PipelineSwapchainInfo>,
+    pub wireframe_pipeline_info: Option<PipelineSwapchainInfo>,
     pub descriptor_sets_per_view: Vec<DescriptorSetArc>,
+    pub per_instance_descriptor_sets_per_view: Vec<Option<DescriptorSetArc>>,
     pub extracted_frame_node_mesh_data: Vec<Option<ExtractedFrameNodeMeshData>>,
-    pub prepared_view_node_mesh_data: Vec<PreparedViewNodeMeshData>,
+    pub static_mesh_data_per_view: Vec<Option<PreparedStaticMeshData>>,
+    pub draws: Vec<PreparedMeshDraw>,
+    pub bound_state: Mutex<MeshBoundState>,
 }
 
-impl FeatureCommandWriter<RenderJobWriteContext> for MeshCommandWriter {
-    fn apply_setup(
+impl MeshCommandWriter {
+    // Submit nodes are only added to the depth prepass and transparent phases if we have a
+    // pipeline for them
+    fn pipeline_info_for_phase(
         &self,
-        write_context: &mut RenderJobWriteContext,
-        _view: &RenderView,
-        _render_phase_index: RenderPhaseIndex
-    ) {
-        // println!("render");
-        let logical_device = write_context.device_context.device();
-        let command_buffer = write_context.command_buffer;
-        unsafe {
-            logical_device.cmd_bind_pipeline(
-                command_buffer,
-                vk::PipelineBindPoint::GRAPHICS,
-                self.pipeline_info.pipeline.get_raw().pipelines[0],
-            );
+        render_phase_index: RenderPhaseIndex,
+    ) -> &PipelineSwapchainInfo {
+        if render_phase_index == DepthPrepassRenderPhase::render_phase_index() {
+            self.depth_prepass_pipeline_info.as_ref().unwrap()
+        } else if render_phase_index == TransparentRenderPhase::render_phase_index() {
+            self.wireframe_pipeline_info.as_ref().unwrap()
+        } else {
+            &self.pipeline_info
         }
     }
 
-    fn render_element(
+    // Binds per-material data (i.e. texture) unless it is already bound. Only needed when shading
+    fn bind_per_material_descriptor(
         &self,
-        write_context: &mut RenderJobWriteContext,
-        _view: &RenderView,
-        _render_phase_index: RenderPhaseIndex,
-        index: SubmitNodeId,
+        write_context: &RenderJobWriteContext,
+        render_phase_index: RenderPhaseIndex,
+        bound_state: &mut MeshBoundState,
+        per_material_descriptor: vk::DescriptorSet,
     ) {
-        let logical_device = write_context.device_context.device();
-        let command_buffer = write_context.command_buffer;
-
-        let view_node_data = &self.prepared_view_node_mesh_data[index as usize];
-        let frame_node_data = self.extracted_frame_node_mesh_data
-            [view_node_data.frame_node_index as usize]
-            .as_ref()
-            .unwrap();
+        if render_phase_index == DepthPrepassRenderPhase::render_phase_index()
+            || bound_state.per_material_descriptor == Some(per_material_descriptor)
+        {
+            return;
+        }
 
         unsafe {
-            // Bind per-pass data (UBO with view/proj matrix, sampler)
-            logical_device.cmd_bind_descriptor_sets(
-                command_buffer,
+            write_context.device_context.device().cmd_bind_descriptor_sets(
+                write_context.command_buffer,
                 vk::PipelineBindPoint::GRAPHICS,
-                self.pipeline_info.pipeline_layout.get_raw().pipeline_layout,
-                0,
-                &[view_node_data.per_view_descriptor.get()],
+                self.pipeline_info_for_phase(render_phase_index)
+                    .pipeline_layout
+                    .get_raw()
+                    .pipeline_layout,
+                1,
+                &[per_material_descriptor],
                 &[],
             );
+        }
+        bound_state.per_material_descriptor = Some(per_material_descriptor);
+    }
 
-            logical_device.cmd_bind_descriptor_sets(
-                command_buffer,
+    // Binds the per-instance transforms (set 2) unless they are already bound. Batches and static
+    // meshes read from different buffers
+    fn bind_per_instance_descriptor(
+        &self,
+        write_context: &RenderJobWriteContext,
+        render_phase_index: RenderPhaseIndex,
+        bound_state: &mut MeshBoundState,
+        per_instance_descriptor: vk::DescriptorSet,
+    ) {
+        if bound_state.per_instance_descriptor == Some(per_instance_descriptor) {
+            return;
+        }
+
+        unsafe {
+            write_context.device_context.device().cmd_bind_descriptor_sets(
+                write_context.command_buffer,
                 vk::PipelineBindPoint::GRAPHICS,
-                self.pipeline_info.pipeline_layout.get_raw().pipeline_layout,
+                self.pipeline_info_for_phase(render_phase_index)
+                    .pipeline_layout
+                    .get_raw()
+                    .pipeline_layout,
                 2,
-                &[view_node_data.per_instance_descriptor.get()],
+                &[per_instance_descriptor],
                 &[],
             );
+        }
+        bound_state.per_instance_descriptor = Some(per_instance_descriptor);
+    }
 
-            for draw_call in &frame_node_data.draw_calls {
-                // Bind per-draw-call data (i.e. texture)
-                logical_device.cmd_bind_descriptor_sets(
-                    command_buffer,
-                    vk::PipelineBindPoint::GRAPHICS,
-                    self.pipeline_info.pipeline_layout.get_raw().pipeline_layout,
-                    1,
-                    &[draw_call.per_material_descriptor.get()],
-                    &[],
-                );
+    fn bind_vertex_and_index_buffers(
+        &self,
+        write_context: &RenderJobWriteContext,
+        bound_state: &mut MeshBoundState,
+        vertex_buffer: (vk::Buffer, u32),
+        index_buffer: (vk::Buffer, u32),
+    ) {
+        let logical_device = write_context.device_context.device();
+        let command_buffer = write_context.command_buffer;
 
+        unsafe {
+            if bound_state.vertex_buffer != Some(vertex_buffer) {
                 logical_device.cmd_bind_vertex_buffers(
                     command_buffer,
                     0, // first binding
-                    &[frame_node_data.vertex_buffer.get_raw().buffer],
-                    &[draw_call.vertex_buffer_offset_in_bytes as u64], // offsets
+                    &[vertex_buffer.0],
+                    &[vertex_buffer.1 as u64], // offsets
                 );
+                bound_state.vertex_buffer = Some(vertex_buffer);
+            }
 
+            if bound_state.index_buffer != Some(index_buffer) {
                 logical_device.cmd_bind_index_buffer(
                     command_buffer,
-                    frame_node_data.index_buffer.get_raw().buffer,
-                    draw_call.index_buffer_offset_in_bytes as u64, // offset
+                    index_buffer.0,
+                    index_buffer.1 as u64, // offset
                     vk::IndexType::UINT16,
                 );
+                bound_state.index_buffer = Some(index_buffer);
+            }
+        }
+    }
+
+    fn render_batch(
+        &self,
+        write_context: &mut RenderJobWriteContext,
+        view_index: usize,
+        render_phase_index: RenderPhaseIndex,
+        batch: &PreparedMeshBatch,
+    ) {
+        let frame_node_data = self.extracted_frame_node_mesh_data
+            [batch.frame_node_index as usize]
+            .as_ref()
+            .unwrap();
+        let draw_call =
+            &frame_node_data.draw_calls_per_lod[batch.lod as usize][batch.draw_call_index as usize];
+
+        let mut bound_state = self.bound_state.lock().unwrap();
+
+        self.bind_per_material_descriptor(
+            write_context,
+            render_phase_index,
+            &mut bound_state,
+            draw_call.per_material_descriptor.get(),
+        );
+
+        self.bind_per_instance_descriptor(
+            write_context,
+            render_phase_index,
+            &mut bound_state,
+            self.per_instance_descriptor_sets_per_view[view_index]
+                .as_ref()
+                .unwrap()
+                .get(),
+        );
+
+        self.bind_vertex_and_index_buffers(
+            write_context,
+            &mut bound_state,
+            (
+                frame_node_data.vertex_buffer.get_raw().buffer,
+                draw_call.vertex_buffer_offset_in_bytes,
+            ),
+            (
+                frame_node_data.index_buffer.get_raw().buffer,
+                draw_call.index_buffer_offset_in_bytes,
+            ),
+        );
+
+        unsafe {
+            // The shader indexes the per-instance storage buffer with gl_InstanceIndex, which
+            // includes first_instance
+            write_context.device_context.device().cmd_draw_indexed(
+                write_context.command_buffer,
+                draw_call.index_buffer_size_in_bytes / 2, //sizeof(u16)
+                batch.instance_count,
+                0,
+                0,
+                batch.first_instance,
+            );
+        }
+
+        write_context.record_draw_call(
+            draw_call.index_buffer_size_in_bytes / 2 / 3 * batch.instance_count,
+        );
+    }
+
+    fn render_static_indirect(
+        &self,
+        write_context: &mut RenderJobWriteContext,
+        view_index: usize,
+        render_phase_index: RenderPhaseIndex,
+        draw_index: u32,
+    ) {
+        let static_mesh_data = self.static_mesh_data_per_view[view_index].as_ref().unwrap();
+        let frame_data = &static_mesh_data.extracted.frame_data;
+        let draw = &frame_data.gpu_data.draws[draw_index as usize];
+
+        let mut bound_state = self.bound_state.lock().unwrap();
+
+        self.bind_per_material_descriptor(
+            write_context,
+            render_phase_index,
+            &mut bound_state,
+            static_mesh_data.extracted.per_material_descriptors[draw_index as usize].get(),
+        );
+
+        self.bind_per_instance_descriptor(
+            write_context,
+            render_phase_index,
+            &mut bound_state,
+            static_mesh_data.per_instance_descriptor_set.get(),
+        );
+
+        // Mesh part offsets are baked into the draw command, so buffers are bound at offset 0
+        self.bind_vertex_and_index_buffers(
+            write_context,
+            &mut bound_state,
+            (draw.vertex_buffer.get_raw().buffer, 0),
+            (draw.index_buffer.get_raw().buffer, 0),
+        );
+
+        let stride = std::mem::size_of::<vk::DrawIndexedIndirectCommand>();
+        unsafe {
+            // The culling shader wrote the instance count and per-instance data for this draw
+            write_context.device_context.device().cmd_draw_indexed_indirect(
+                write_context.command_buffer,
+                frame_data.draw_command_buffer.get_raw().buffer,
+                (draw_index as usize * stride) as u64,
+                1,
+                stride as u32,
+            );
+        }
+
+        // Instances are culled on the GPU so the number of triangles drawn isn't known here
+        write_context.record_draw_call(0);
+    }
+}
 
-                logical_device.cmd_draw_indexed(
+impl FeatureCommandWriter<RenderJobWriteContext> for MeshCommandWriter {
+    fn apply_setup(
+        &self,
+        write_context: &mut RenderJobWriteContext,
+        view: &RenderView,
+        render_phase_index: RenderPhaseIndex
+    ) {
+        let logical_device = write_context.device_context.device();
+        let command_buffer = write_context.command_buffer;
+        let pipeline_info = self.pipeline_info_for_phase(render_phase_index);
+        let pipeline_layout = pipeline_info.pipeline_layout.get_raw().pipeline_layout;
+        let view_index = view.view_index() as usize;
+
+        *self.bound_state.lock().unwrap() = MeshBoundState::default();
+
+        unsafe {
+            logical_device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline_info.pipeline.get_raw().pipelines[0],
+            );
+
+            // Bind per-pass data (UBO with lights, sampler). Not used when only writing depth
+            if render_phase_index != DepthPrepassRenderPhase::render_phase_index() {
+                logical_device.cmd_bind_descriptor_sets(
                     command_buffer,
-                    draw_call.index_buffer_size_in_bytes / 2, //sizeof(u16)
-                    1,
-                    0,
-                    0,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline_layout,
                     0,
+                    &[self.descriptor_sets_per_view[view_index].get()],
+                    &[],
                 );
             }
         }
     }
 
+    fn render_element(
+        &self,
+        write_context: &mut RenderJobWriteContext,
+        view: &RenderView,
+        render_phase_index: RenderPhaseIndex,
+        index: SubmitNodeId,
+    ) {
+        write_context.record_submit_node(render_phase_index);
+
+        match &self.draws[index as usize] {
+            PreparedMeshDraw::Batch(batch) => self.render_batch(
+                write_context,
+                view.view_index() as usize,
+                render_phase_index,
+                batch,
+            ),
+            PreparedMeshDraw::StaticIndirect(draw_index) => self.render_static_indirect(
+                write_context,
+                view.view_index() as usize,
+                render_phase_index,
+                *draw_index,
+            ),
+        }
+    }
+
     fn revert_setup(
         &self,
         _write_context: &mut RenderJobWriteContext,
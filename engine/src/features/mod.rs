@@ -1,4 +1,8 @@
 pub mod mesh;
 pub mod sprite;
+pub mod text;
+pub mod particle;
 pub mod debug3d;
 pub mod imgui;
+pub mod selection_outline;
+pub mod picking;
@@ -0,0 +1,212 @@
+use crate::features::particle::{
+    ExtractedParticleEmitterData, ExtractedParticle, ParticleRenderNodeSet, ParticleRenderFeature,
+    ParticleRenderNode,
+};
+use crate::components::ParticleEmitterComponent;
+use crate::render_contexts::{RenderJobExtractContext, RenderJobWriteContext, RenderJobPrepareContext};
+use renderer::nodes::{
+    DefaultExtractJobImpl, FramePacket, RenderView, PerViewNode, PrepareJob, DefaultPrepareJob,
+    RenderFeatureIndex, RenderFeature, PerFrameNode,
+};
+use renderer::base::slab::RawSlabKey;
+use crate::features::particle::prepare::ParticlePrepareJobImpl;
+use renderer::vulkan::VkDeviceContext;
+use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetAllocatorRef};
+use atelier_assets::loader::handle::Handle;
+use renderer::assets::resources::DescriptorSetArc;
+use legion::prelude::EntityStore;
+use renderer::assets::{MaterialAsset, ImageAsset};
+use minimum::components::TransformComponent;
+
+pub struct ParticleExtractJobImpl {
+    device_context: VkDeviceContext,
+    descriptor_set_allocator: DescriptorSetAllocatorRef,
+    alpha_blend_pipeline_info: PipelineSwapchainInfo,
+    additive_blend_pipeline_info: PipelineSwapchainInfo,
+    particle_material: Handle<MaterialAsset>,
+    default_texture: Handle<ImageAsset>,
+    extracted_frame_node_emitter_data: Vec<Option<ExtractedParticleEmitterData>>,
+    per_view_descriptors: Vec<DescriptorSetArc>,
+}
+
+impl ParticleExtractJobImpl {
+    pub fn new(
+        device_context: VkDeviceContext,
+        descriptor_set_allocator: DescriptorSetAllocatorRef,
+        alpha_blend_pipeline_info: PipelineSwapchainInfo,
+        additive_blend_pipeline_info: PipelineSwapchainInfo,
+        particle_material: &Handle<MaterialAsset>,
+        default_texture: &Handle<ImageAsset>,
+    ) -> Self {
+        ParticleExtractJobImpl {
+            device_context,
+            descriptor_set_allocator,
+            alpha_blend_pipeline_info,
+            additive_blend_pipeline_info,
+            particle_material: particle_material.clone(),
+            default_texture: default_texture.clone(),
+            extracted_frame_node_emitter_data: Default::default(),
+            per_view_descriptors: Default::default(),
+        }
+    }
+}
+
+impl<'a>
+    DefaultExtractJobImpl<
+        RenderJobExtractContext<'a>,
+        RenderJobPrepareContext,
+        RenderJobWriteContext,
+    > for ParticleExtractJobImpl
+{
+    fn extract_begin(
+        &mut self,
+        _extract_context: &RenderJobExtractContext<'a>,
+        frame_packet: &FramePacket,
+        _views: &[&RenderView],
+    ) {
+        self.extracted_frame_node_emitter_data
+            .reserve(frame_packet.frame_node_count(self.feature_index()) as usize);
+    }
+
+    fn extract_frame_node(
+        &mut self,
+        extract_context: &RenderJobExtractContext<'a>,
+        frame_node: PerFrameNode,
+        _frame_node_index: u32,
+    ) {
+        let render_node_index = frame_node.render_node_index();
+        let render_node_handle = RawSlabKey::<ParticleRenderNode>::new(render_node_index);
+
+        let particle_nodes = extract_context
+            .resources
+            .get::<ParticleRenderNodeSet>()
+            .unwrap();
+        let particle_render_node = particle_nodes.emitters.get(render_node_handle).unwrap();
+
+        let transform_component = extract_context
+            .world
+            .get_component::<TransformComponent>(particle_render_node.entity)
+            .unwrap();
+        let emitter_component = extract_context
+            .world
+            .get_component::<ParticleEmitterComponent>(particle_render_node.entity)
+            .unwrap();
+
+        let particles = emitter_component.state.particles();
+        if particles.is_empty() {
+            self.extracted_frame_node_emitter_data.push(None);
+            return;
+        }
+
+        let texture = emitter_component
+            .texture
+            .as_ref()
+            .unwrap_or(&self.default_texture);
+        let image_info = match extract_context.resource_manager.get_image_info(texture) {
+            Some(image_info) => image_info,
+            None => {
+                self.extracted_frame_node_emitter_data.push(None);
+                return;
+            }
+        };
+
+        let descriptor_set_info =
+            extract_context
+                .resource_manager
+                .get_descriptor_set_info(&self.particle_material, 0, 1);
+        let mut texture_descriptor = self
+            .descriptor_set_allocator
+            .create_dyn_descriptor_set_uninitialized(&descriptor_set_info.descriptor_set_layout)
+            .unwrap();
+        extract_context.render_stats().record_descriptor_set_allocated();
+
+        texture_descriptor.set_image(0, image_info.image_view);
+        texture_descriptor
+            .flush(&mut self.descriptor_set_allocator)
+            .unwrap();
+        let texture_descriptor_set = texture_descriptor.descriptor_set().clone();
+
+        // Evaluate the curves now so that prepare only needs to build quads
+        let settings = &emitter_component.settings;
+        let particles = particles
+            .iter()
+            .map(|particle| {
+                let normalized_age = particle.normalized_age();
+                ExtractedParticle {
+                    position: particle.position,
+                    size: settings.size_over_life.evaluate(normalized_age),
+                    color: settings.color_over_life.evaluate(normalized_age),
+                }
+            })
+            .collect();
+
+        self.extracted_frame_node_emitter_data
+            .push(Some(ExtractedParticleEmitterData {
+                emitter_position: transform_component.position(),
+                blend_mode: emitter_component.blend_mode,
+                particles,
+                texture_descriptor_set,
+            }));
+    }
+
+    fn extract_view_node(
+        &mut self,
+        _extract_context: &RenderJobExtractContext<'a>,
+        _view: &RenderView,
+        _view_node: PerViewNode,
+        _view_node_index: u32,
+    ) {
+    }
+
+    fn extract_view_finalize(
+        &mut self,
+        extract_context: &RenderJobExtractContext<'a>,
+        view: &RenderView,
+    ) {
+        let layout =
+            extract_context
+                .resource_manager
+                .get_descriptor_set_info(&self.particle_material, 0, 0);
+        let mut descriptor_set = self
+            .descriptor_set_allocator
+            .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
+            .unwrap();
+        extract_context.render_stats().record_descriptor_set_allocated();
+
+        let view_proj = view.projection_matrix() * view.view_matrix();
+
+        descriptor_set.set_buffer_data(0, &view_proj);
+        extract_context
+            .render_stats()
+            .record_buffer_upload(std::mem::size_of_val(&view_proj) as u64);
+        descriptor_set
+            .flush(&mut self.descriptor_set_allocator)
+            .unwrap();
+
+        self.per_view_descriptors
+            .push(descriptor_set.descriptor_set().clone());
+    }
+
+    fn extract_frame_finalize(
+        self,
+        _extract_context: &RenderJobExtractContext<'a>,
+    ) -> Box<dyn PrepareJob<RenderJobPrepareContext, RenderJobWriteContext>> {
+        let prepare_impl = ParticlePrepareJobImpl::new(
+            self.device_context,
+            self.alpha_blend_pipeline_info,
+            self.additive_blend_pipeline_info,
+            self.per_view_descriptors,
+            self.extracted_frame_node_emitter_data,
+        );
+
+        Box::new(DefaultPrepareJob::new(prepare_impl))
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        ParticleRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        ParticleRenderFeature::feature_index()
+    }
+}
@@ -0,0 +1,188 @@
+use renderer::nodes::{
+    RenderFeature, RenderFeatureIndex, DefaultExtractJob, GenericRenderNodeHandle,
+    RenderNodeSet, RenderNodeCount,
+};
+use std::sync::atomic::{Ordering, AtomicI32};
+use crate::render_jobs::RenderExtractJob;
+use legion::prelude::Entity;
+use renderer::base::slab::{RawSlabKey, RawSlab};
+use std::convert::TryInto;
+use atelier_assets::loader::handle::Handle;
+use renderer::assets::{MaterialAsset, ImageAsset};
+use serde::{Deserialize, Serialize};
+use crate::features::sprite::sprite_sort_key;
+
+mod extract;
+use extract::ParticleExtractJobImpl;
+
+mod prepare;
+
+mod write;
+use write::ParticleCommandWriter;
+
+mod simulation;
+pub use simulation::ParticleFloatCurve;
+pub use simulation::ParticleColorCurve;
+pub use simulation::ParticleEmitterSettings;
+pub use simulation::ParticleEmitterState;
+pub use simulation::Particle;
+
+use renderer::vulkan::VkDeviceContext;
+use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetArc, DescriptorSetAllocatorRef};
+
+// Passes in particle.material
+pub const PARTICLE_ALPHA_BLEND_PASS_INDEX: usize = 0;
+pub const PARTICLE_ADDITIVE_BLEND_PASS_INDEX: usize = 1;
+
+/// How an emitter's particles are combined with what is behind them
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ParticleBlendMode {
+    // Drawn back to front, i.e. smoke and dust
+    Alpha,
+    // Brightens what is behind, order independent, i.e. sparks and fire
+    Additive,
+}
+
+impl Default for ParticleBlendMode {
+    fn default() -> Self {
+        ParticleBlendMode::Alpha
+    }
+}
+
+/// Vertex format for vertices sent to the GPU. Same layout as sprites
+#[derive(Clone, Debug, Copy)]
+#[repr(C)]
+pub struct ParticleVertex {
+    pub pos: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// Corners of a camera-facing particle quad as (right, up) offsets and texture coordinates
+const PARTICLE_QUAD_CORNERS: [([f32; 2], [f32; 2]); 4] = [
+    ([-0.5, -0.5], [0.0, 1.0]),
+    ([0.5, -0.5], [1.0, 1.0]),
+    ([0.5, 0.5], [1.0, 0.0]),
+    ([-0.5, 0.5], [0.0, 0.0]),
+];
+
+/// Draw order of PARTICLE_QUAD_CORNERS
+const PARTICLE_QUAD_INDEX_LIST: [u32; 6] = [0, 1, 2, 2, 3, 0];
+
+// Emitters without a texture use default_texture
+pub fn create_particle_extract_job<'a>(
+    device_context: VkDeviceContext,
+    descriptor_set_allocator: DescriptorSetAllocatorRef,
+    alpha_blend_pipeline_info: PipelineSwapchainInfo,
+    additive_blend_pipeline_info: PipelineSwapchainInfo,
+    particle_material: &Handle<MaterialAsset>,
+    default_texture: &Handle<ImageAsset>,
+) -> RenderExtractJob<'a> {
+    Box::new(DefaultExtractJob::new(ParticleExtractJobImpl::new(
+        device_context,
+        descriptor_set_allocator,
+        alpha_blend_pipeline_info,
+        additive_blend_pipeline_info,
+        particle_material,
+        default_texture,
+    )))
+}
+
+//
+// This is boiler-platish
+//
+pub struct ParticleRenderNode {
+    pub entity: Entity,
+}
+
+#[derive(Copy, Clone)]
+pub struct ParticleRenderNodeHandle(pub RawSlabKey<ParticleRenderNode>);
+
+impl Into<GenericRenderNodeHandle> for ParticleRenderNodeHandle {
+    fn into(self) -> GenericRenderNodeHandle {
+        GenericRenderNodeHandle::new(
+            <ParticleRenderFeature as RenderFeature>::feature_index(),
+            self.0.index(),
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct ParticleRenderNodeSet {
+    emitters: RawSlab<ParticleRenderNode>,
+}
+
+impl ParticleRenderNodeSet {
+    pub fn register_emitter(
+        &mut self,
+        node: ParticleRenderNode,
+    ) -> ParticleRenderNodeHandle {
+        ParticleRenderNodeHandle(self.emitters.allocate(node))
+    }
+
+    pub fn unregister_emitter(
+        &mut self,
+        handle: ParticleRenderNodeHandle,
+    ) {
+        self.emitters.free(handle.0);
+    }
+}
+
+impl RenderNodeSet for ParticleRenderNodeSet {
+    fn feature_index(&self) -> RenderFeatureIndex {
+        ParticleRenderFeature::feature_index()
+    }
+
+    fn max_render_node_count(&self) -> RenderNodeCount {
+        self.emitters.storage_size() as RenderNodeCount
+    }
+}
+
+//
+// This is boilerplate that could be macro'd
+//
+static PARTICLE_FEATURE_INDEX: AtomicI32 = AtomicI32::new(-1);
+
+pub struct ParticleRenderFeature;
+
+impl RenderFeature for ParticleRenderFeature {
+    fn set_feature_index(index: RenderFeatureIndex) {
+        PARTICLE_FEATURE_INDEX.store(index.try_into().unwrap(), Ordering::Release);
+    }
+
+    fn feature_index() -> RenderFeatureIndex {
+        PARTICLE_FEATURE_INDEX.load(Ordering::Acquire) as RenderFeatureIndex
+    }
+
+    fn feature_debug_name() -> &'static str {
+        "ParticleRenderFeature"
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub(self) struct ExtractedParticle {
+    position: glam::Vec3,
+    size: f32,
+    color: glam::Vec4,
+}
+
+#[derive(Debug)]
+pub(self) struct ExtractedParticleEmitterData {
+    emitter_position: glam::Vec3,
+    blend_mode: ParticleBlendMode,
+    particles: Vec<ExtractedParticle>,
+    texture_descriptor_set: DescriptorSetArc,
+}
+
+#[derive(Debug)]
+pub struct ParticleDrawCall {
+    blend_mode: ParticleBlendMode,
+    index_buffer_first_element: u32,
+    index_buffer_count: u32,
+    texture_descriptor_set: DescriptorSetArc,
+}
+
+// Particles sort with sprites on the default sorting layer
+fn particle_sort_key() -> u32 {
+    sprite_sort_key(0)
+}
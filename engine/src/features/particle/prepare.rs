@@ -0,0 +1,239 @@
+use crate::phases::TransparentRenderPhase;
+use renderer::nodes::{
+    RenderView, ViewSubmitNodes, FeatureSubmitNodes, FeatureCommandWriter, RenderFeatureIndex,
+    FramePacket, DefaultPrepareJobImpl, PerFrameNode, PerViewNode, RenderFeature,
+};
+use crate::features::particle::{
+    ParticleRenderFeature, ExtractedParticleEmitterData, ParticleDrawCall, ParticleVertex,
+    ParticleBlendMode, PARTICLE_QUAD_CORNERS, PARTICLE_QUAD_INDEX_LIST, particle_sort_key,
+};
+use glam::Vec3;
+use super::ParticleCommandWriter;
+use crate::render_contexts::{RenderJobWriteContext, RenderJobPrepareContext};
+use renderer::vulkan::{VkBuffer, VkDeviceContext};
+use ash::vk;
+use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetArc};
+
+pub struct ParticlePrepareJobImpl {
+    device_context: VkDeviceContext,
+    alpha_blend_pipeline_info: PipelineSwapchainInfo,
+    additive_blend_pipeline_info: PipelineSwapchainInfo,
+    descriptor_set_per_view: Vec<DescriptorSetArc>,
+    extracted_frame_node_emitter_data: Vec<Option<ExtractedParticleEmitterData>>,
+
+    draw_calls: Vec<ParticleDrawCall>,
+    vertex_list: Vec<ParticleVertex>,
+    index_list: Vec<u32>,
+}
+
+impl ParticlePrepareJobImpl {
+    pub(super) fn new(
+        device_context: VkDeviceContext,
+        alpha_blend_pipeline_info: PipelineSwapchainInfo,
+        additive_blend_pipeline_info: PipelineSwapchainInfo,
+        descriptor_set_per_view: Vec<DescriptorSetArc>,
+        extracted_frame_node_emitter_data: Vec<Option<ExtractedParticleEmitterData>>,
+    ) -> Self {
+        let emitter_count = extracted_frame_node_emitter_data.len();
+        ParticlePrepareJobImpl {
+            device_context,
+            alpha_blend_pipeline_info,
+            additive_blend_pipeline_info,
+            descriptor_set_per_view,
+            extracted_frame_node_emitter_data,
+            draw_calls: Vec::with_capacity(emitter_count),
+            vertex_list: Default::default(),
+            index_list: Default::default(),
+        }
+    }
+}
+
+impl DefaultPrepareJobImpl<RenderJobPrepareContext, RenderJobWriteContext>
+    for ParticlePrepareJobImpl
+{
+    fn prepare_begin(
+        &mut self,
+        _prepare_context: &RenderJobPrepareContext,
+        _frame_packet: &FramePacket,
+        _views: &[&RenderView],
+        _submit_nodes: &mut FeatureSubmitNodes,
+    ) {
+    }
+
+    fn prepare_frame_node(
+        &mut self,
+        _prepare_context: &RenderJobPrepareContext,
+        _frame_node: PerFrameNode,
+        _frame_node_index: u32,
+        _submit_nodes: &mut FeatureSubmitNodes,
+    ) {
+    }
+
+    fn prepare_view_node(
+        &mut self,
+        _prepare_context: &RenderJobPrepareContext,
+        view: &RenderView,
+        view_node: PerViewNode,
+        _view_node_index: u32,
+        submit_nodes: &mut ViewSubmitNodes,
+    ) {
+        let frame_node_index = view_node.frame_node_index();
+        let emitter = match &self.extracted_frame_node_emitter_data[frame_node_index as usize] {
+            Some(emitter) => emitter,
+            None => return,
+        };
+
+        // Quads face the camera
+        let view_matrix = view.view_matrix();
+        let camera_right = Vec3::new(
+            view_matrix.x_axis().x(),
+            view_matrix.y_axis().x(),
+            view_matrix.z_axis().x(),
+        );
+        let camera_up = Vec3::new(
+            view_matrix.x_axis().y(),
+            view_matrix.y_axis().y(),
+            view_matrix.z_axis().y(),
+        );
+
+        // Alpha blended particles must be drawn back to front. Additive blending doesn't depend
+        // on order
+        let eye_position = view.eye_position();
+        let mut particle_order: Vec<usize> = (0..emitter.particles.len()).collect();
+        if emitter.blend_mode == ParticleBlendMode::Alpha {
+            let distances: Vec<f32> = emitter
+                .particles
+                .iter()
+                .map(|particle| (particle.position - eye_position).length_squared())
+                .collect();
+            particle_order.sort_unstable_by(|a, b| {
+                distances[*b].partial_cmp(&distances[*a]).unwrap()
+            });
+        }
+
+        let index_buffer_first_element = self.index_list.len() as u32;
+        for particle_index in particle_order {
+            let particle = &emitter.particles[particle_index];
+            let color: [f32; 4] = particle.color.into();
+
+            let vertex_buffer_first_element = self.vertex_list.len() as u32;
+            for (offset, tex_coord) in &PARTICLE_QUAD_CORNERS {
+                let pos = particle.position
+                    + camera_right * (offset[0] * particle.size)
+                    + camera_up * (offset[1] * particle.size);
+                self.vertex_list.push(ParticleVertex {
+                    pos: pos.into(),
+                    tex_coord: *tex_coord,
+                    color,
+                });
+            }
+
+            for index in &PARTICLE_QUAD_INDEX_LIST {
+                self.index_list.push(*index + vertex_buffer_first_element);
+            }
+        }
+
+        let submit_node_id = self.draw_calls.len() as u32;
+        self.draw_calls.push(ParticleDrawCall {
+            blend_mode: emitter.blend_mode,
+            index_buffer_first_element,
+            index_buffer_count: self.index_list.len() as u32 - index_buffer_first_element,
+            texture_descriptor_set: emitter.texture_descriptor_set.clone(),
+        });
+
+        let distance_from_camera = Vec3::length(emitter.emitter_position - eye_position);
+        submit_nodes.add_submit_node::<TransparentRenderPhase>(
+            submit_node_id,
+            particle_sort_key(),
+            distance_from_camera,
+        );
+    }
+
+    fn prepare_view_finalize(
+        &mut self,
+        _prepare_context: &RenderJobPrepareContext,
+        _view: &RenderView,
+        _submit_nodes: &mut ViewSubmitNodes,
+    ) {
+    }
+
+    fn prepare_frame_finalize(
+        self,
+        prepare_context: &RenderJobPrepareContext,
+        _submit_nodes: &mut FeatureSubmitNodes,
+    ) -> Box<dyn FeatureCommandWriter<RenderJobWriteContext>> {
+        let mut vertex_buffer = None;
+        let mut index_buffer = None;
+
+        if self.draw_calls.len() > 0 {
+            vertex_buffer = {
+                let vertex_buffer_size =
+                    self.vertex_list.len() as u64 * std::mem::size_of::<ParticleVertex>() as u64;
+                let mut vertex_buffer = VkBuffer::new(
+                    &self.device_context,
+                    vk_mem::MemoryUsage::CpuToGpu,
+                    vk::BufferUsageFlags::VERTEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    vertex_buffer_size,
+                )
+                .unwrap();
+
+                vertex_buffer
+                    .write_to_host_visible_buffer(self.vertex_list.as_slice())
+                    .unwrap();
+                prepare_context
+                    .render_stats()
+                    .record_buffer_upload(vertex_buffer_size);
+
+                Some(
+                    prepare_context
+                        .dyn_resource_lookups
+                        .insert_buffer(vertex_buffer),
+                )
+            };
+
+            index_buffer = {
+                let index_buffer_size =
+                    self.index_list.len() as u64 * std::mem::size_of::<u32>() as u64;
+                let mut index_buffer = VkBuffer::new(
+                    &self.device_context,
+                    vk_mem::MemoryUsage::CpuToGpu,
+                    vk::BufferUsageFlags::INDEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    index_buffer_size,
+                )
+                .unwrap();
+
+                index_buffer
+                    .write_to_host_visible_buffer(self.index_list.as_slice())
+                    .unwrap();
+                prepare_context
+                    .render_stats()
+                    .record_buffer_upload(index_buffer_size);
+
+                Some(
+                    prepare_context
+                        .dyn_resource_lookups
+                        .insert_buffer(index_buffer),
+                )
+            };
+        }
+
+        Box::new(ParticleCommandWriter {
+            draw_calls: self.draw_calls,
+            vertex_buffer,
+            index_buffer,
+            alpha_blend_pipeline_info: self.alpha_blend_pipeline_info,
+            additive_blend_pipeline_info: self.additive_blend_pipeline_info,
+            descriptor_set_per_view: self.descriptor_set_per_view,
+        })
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        ParticleRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        ParticleRenderFeature::feature_index()
+    }
+}
@@ -0,0 +1,288 @@
+use serde::{Deserialize, Serialize};
+use rand::{Rng, SeedableRng};
+use pcg_rand::Pcg32;
+
+// Evaluates piecewise-linear keys sorted by time. Times outside the keys are clamped to the first
+// or last key
+fn evaluate_keys<T: Copy>(
+    keys: &[(f32, T)],
+    t: f32,
+    default: T,
+    lerp: fn(T, T, f32) -> T,
+) -> T {
+    let first = match keys.first() {
+        Some(first) => first,
+        None => return default,
+    };
+
+    if t <= first.0 {
+        return first.1;
+    }
+
+    for pair in keys.windows(2) {
+        let (t0, v0) = pair[0];
+        let (t1, v1) = pair[1];
+        if t <= t1 {
+            let duration = t1 - t0;
+            let fraction = if duration > 0.0 { (t - t0) / duration } else { 1.0 };
+            return lerp(v0, v1, fraction);
+        }
+    }
+
+    keys.last().unwrap().1
+}
+
+/// A value that changes over a particle's life. Keys are (normalized age, value) pairs sorted by
+/// age, where 0 is when the particle spawns and 1 is when it dies
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ParticleFloatCurve {
+    pub keys: Vec<(f32, f32)>,
+}
+
+impl ParticleFloatCurve {
+    pub fn constant(value: f32) -> Self {
+        ParticleFloatCurve {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    pub fn evaluate(
+        &self,
+        normalized_age: f32,
+    ) -> f32 {
+        evaluate_keys(&self.keys, normalized_age, 1.0, |a, b, t| a + (b - a) * t)
+    }
+}
+
+/// An RGBA color that changes over a particle's life, see ParticleFloatCurve
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ParticleColorCurve {
+    pub keys: Vec<(f32, [f32; 4])>,
+}
+
+impl ParticleColorCurve {
+    pub fn constant(value: [f32; 4]) -> Self {
+        ParticleColorCurve {
+            keys: vec![(0.0, value)],
+        }
+    }
+
+    pub fn evaluate(
+        &self,
+        normalized_age: f32,
+    ) -> glam::Vec4 {
+        evaluate_keys(
+            &self.keys,
+            normalized_age,
+            glam::Vec4::one(),
+            |a, b, t| a.lerp(b, t),
+        )
+    }
+}
+
+/// Everything needed to simulate an emitter's particles
+#[derive(Clone, Debug)]
+pub struct ParticleEmitterSettings {
+    // Particles per second
+    pub spawn_rate: f32,
+    // No particles are spawned while this many are alive
+    pub max_particles: usize,
+    // Seconds, each particle picks a random lifetime in this range
+    pub lifetime_min: f32,
+    pub lifetime_max: f32,
+    // Each particle picks a random speed in this range
+    pub speed_min: f32,
+    pub speed_max: f32,
+    // Center of the cone particles are emitted in, in the emitter's local space
+    pub direction: glam::Vec3,
+    // Radians. 0 emits in a straight line, PI emits in every direction
+    pub cone_half_angle: f32,
+    // World-space acceleration applied to every particle, i.e. gravity
+    pub acceleration: glam::Vec3,
+    pub size_over_life: ParticleFloatCurve,
+    pub color_over_life: ParticleColorCurve,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Particle {
+    // Particles are simulated in world space so that they stay behind when the emitter moves
+    pub position: glam::Vec3,
+    pub velocity: glam::Vec3,
+    pub age: f32,
+    pub lifetime: f32,
+}
+
+impl Particle {
+    pub fn normalized_age(&self) -> f32 {
+        (self.age / self.lifetime).min(1.0)
+    }
+}
+
+/// Live particles of an emitter. Simulation only depends on the seed, settings, emitter transforms
+/// and timesteps it's given, so the same inputs always produce the same particles.
+#[derive(Clone)]
+pub struct ParticleEmitterState {
+    particles: Vec<Particle>,
+    rng: Pcg32,
+    // Fractional particles carried over to the next step so that low spawn rates still spawn
+    spawn_accumulator: f32,
+}
+
+impl ParticleEmitterState {
+    pub fn new(seed: u64) -> Self {
+        ParticleEmitterState {
+            particles: Default::default(),
+            rng: Pcg32::seed_from_u64(seed),
+            spawn_accumulator: 0.0,
+        }
+    }
+
+    pub fn particles(&self) -> &[Particle] {
+        &self.particles
+    }
+
+    pub fn simulate(
+        &mut self,
+        settings: &ParticleEmitterSettings,
+        emitter_transform: &glam::Mat4,
+        dt: f32,
+    ) {
+        //
+        // Age and move existing particles
+        //
+        for particle in &mut self.particles {
+            particle.age += dt;
+            particle.velocity += settings.acceleration * dt;
+            particle.position += particle.velocity * dt;
+        }
+
+        self.particles.retain(|particle| particle.age < particle.lifetime);
+
+        //
+        // Spawn new particles
+        //
+        self.spawn_accumulator += settings.spawn_rate.max(0.0) * dt;
+
+        let emitter_position = emitter_transform.w_axis().truncate();
+        let direction = emitter_transform
+            .transform_vector3(settings.direction)
+            .normalize();
+
+        while self.spawn_accumulator >= 1.0 {
+            self.spawn_accumulator -= 1.0;
+            if self.particles.len() >= settings.max_particles {
+                continue;
+            }
+
+            let lifetime = Self::random_range(&mut self.rng, settings.lifetime_min, settings.lifetime_max);
+            let speed = Self::random_range(&mut self.rng, settings.speed_min, settings.speed_max);
+            let velocity = Self::random_cone_direction(&mut self.rng, direction, settings.cone_half_angle) * speed;
+
+            if lifetime > 0.0 {
+                self.particles.push(Particle {
+                    position: emitter_position,
+                    velocity,
+                    age: 0.0,
+                    lifetime,
+                });
+            }
+        }
+    }
+
+    fn random_range(
+        rng: &mut Pcg32,
+        min: f32,
+        max: f32,
+    ) -> f32 {
+        min + (max - min) * rng.gen::<f32>()
+    }
+
+    // Uniformly distributed over the spherical cap around the direction
+    fn random_cone_direction(
+        rng: &mut Pcg32,
+        direction: glam::Vec3,
+        cone_half_angle: f32,
+    ) -> glam::Vec3 {
+        let cos_theta = 1.0 - rng.gen::<f32>() * (1.0 - cone_half_angle.cos());
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = rng.gen::<f32>() * 2.0 * std::f32::consts::PI;
+
+        // Build a basis around the direction
+        let helper = if direction.x().abs() < 0.9 {
+            glam::Vec3::unit_x()
+        } else {
+            glam::Vec3::unit_y()
+        };
+        let tangent = direction.cross(helper).normalize();
+        let bitangent = direction.cross(tangent);
+
+        (tangent * (sin_theta * phi.cos()) + bitangent * (sin_theta * phi.sin()) + direction * cos_theta)
+            .normalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME_COUNT: usize = 120;
+    const DT: f32 = 1.0 / 60.0;
+
+    fn test_settings() -> ParticleEmitterSettings {
+        ParticleEmitterSettings {
+            spawn_rate: 50.0,
+            max_particles: 100,
+            lifetime_min: 0.5,
+            lifetime_max: 2.0,
+            speed_min: 1.0,
+            speed_max: 3.0,
+            direction: glam::Vec3::unit_y(),
+            cone_half_angle: 0.5,
+            acceleration: glam::Vec3::new(0.0, -9.8, 0.0),
+            size_over_life: ParticleFloatCurve::constant(1.0),
+            color_over_life: ParticleColorCurve {
+                keys: vec![(0.0, [1.0, 0.5, 0.0, 1.0]), (1.0, [0.2, 0.2, 0.2, 0.0])],
+            },
+        }
+    }
+
+    // Positions and colors of the particles alive after stepping an emitter with the given seed
+    fn simulate_frames(seed: u64) -> Vec<(glam::Vec3, glam::Vec4)> {
+        let settings = test_settings();
+        let emitter_transform = glam::Mat4::from_translation(glam::Vec3::new(1.0, 2.0, 3.0));
+
+        let mut state = ParticleEmitterState::new(seed);
+        for _ in 0..FRAME_COUNT {
+            state.simulate(&settings, &emitter_transform, DT);
+        }
+
+        state
+            .particles()
+            .iter()
+            .map(|particle| {
+                let color = settings
+                    .color_over_life
+                    .evaluate(particle.normalized_age());
+                (particle.position, color)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_is_deterministic() {
+        let a = simulate_frames(7);
+        let b = simulate_frames(7);
+
+        assert!(!a.is_empty());
+        assert_eq!(a, b);
+    }
+
+    #[test]
+    fn different_seed_differs() {
+        let a = simulate_frames(7);
+        let b = simulate_frames(8);
+
+        assert!(!a.is_empty());
+        assert_ne!(a, b);
+    }
+}
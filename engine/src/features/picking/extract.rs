@@ -0,0 +1,143 @@
+use crate::features::picking::{PickingRenderFeature, ExtractedPickingObject, PickingShaderParam};
+use crate::features::picking::prepare::PickingPrepareJobImpl;
+use crate::render_contexts::{RenderJobExtractContext, RenderJobWriteContext, RenderJobPrepareContext};
+use renderer::nodes::{
+    FramePacket, RenderView, PrepareJob, RenderFeatureIndex, RenderFeature, ExtractJob,
+    RenderViewIndex,
+};
+use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetAllocatorRef};
+use atelier_assets::loader::handle::Handle;
+use renderer::assets::MaterialAsset;
+use legion::prelude::*;
+use crate::components::MeshComponent;
+use crate::game_resource_manager::GameResourceManager;
+use minimum::components::TransformComponent;
+
+pub struct PickingExtractJobImpl {
+    descriptor_set_allocator: DescriptorSetAllocatorRef,
+    picking_pipeline_info: PipelineSwapchainInfo,
+    picking_view_index: RenderViewIndex,
+    picking_material: Handle<MaterialAsset>,
+    entities: Vec<Entity>,
+}
+
+impl PickingExtractJobImpl {
+    pub fn new(
+        descriptor_set_allocator: DescriptorSetAllocatorRef,
+        picking_pipeline_info: PipelineSwapchainInfo,
+        picking_view_index: RenderViewIndex,
+        picking_material: &Handle<MaterialAsset>,
+        entities: Vec<Entity>,
+    ) -> Self {
+        PickingExtractJobImpl {
+            descriptor_set_allocator,
+            picking_pipeline_info,
+            picking_view_index,
+            picking_material: picking_material.clone(),
+            entities,
+        }
+    }
+
+    // Returns None if the entity doesn't have a loaded mesh
+    fn extract_object(
+        &mut self,
+        extract_context: &RenderJobExtractContext,
+        game_resource_manager: &GameResourceManager,
+        view: &RenderView,
+        entity: Entity,
+        picking_id: u32,
+    ) -> Option<ExtractedPickingObject> {
+        let transform_component = extract_context
+            .world
+            .get_component::<TransformComponent>(entity)?;
+        let mesh_component = extract_context
+            .world
+            .get_component::<MeshComponent>(entity)?;
+        let mesh_info = mesh_component
+            .mesh
+            .as_ref()
+            .and_then(|mesh_asset_handle| game_resource_manager.get_mesh_info(mesh_asset_handle))?;
+
+        let per_object_param = PickingShaderParam {
+            model_view_proj: view.projection_matrix()
+                * view.view_matrix()
+                * transform_component.transform(),
+            picking_id,
+        };
+
+        let layout =
+            extract_context
+                .resource_manager
+                .get_descriptor_set_info(&self.picking_material, 0, 0);
+        let mut descriptor_set = self
+            .descriptor_set_allocator
+            .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
+            .unwrap();
+        extract_context
+            .render_stats()
+            .record_descriptor_set_allocated();
+        descriptor_set.set_buffer_data(0, &per_object_param);
+        extract_context
+            .render_stats()
+            .record_buffer_upload(std::mem::size_of_val(&per_object_param) as u64);
+        descriptor_set
+            .flush(&mut self.descriptor_set_allocator)
+            .unwrap();
+
+        // Picking uses the full detail mesh regardless of the LOD drawn in the scene
+        Some(ExtractedPickingObject {
+            vertex_buffer: mesh_info.vertex_buffer.clone(),
+            index_buffer: mesh_info.index_buffer.clone(),
+            part_ranges: mesh_info.mesh_asset.lod_part_ranges(0),
+            per_object_descriptor_set: descriptor_set.descriptor_set().clone(),
+        })
+    }
+}
+
+impl<'a> ExtractJob<RenderJobExtractContext<'a>, RenderJobPrepareContext, RenderJobWriteContext>
+    for PickingExtractJobImpl
+{
+    fn extract(
+        mut self: Box<Self>,
+        extract_context: &RenderJobExtractContext<'a>,
+        _frame_packet: &FramePacket,
+        views: &[&RenderView],
+    ) -> Box<dyn PrepareJob<RenderJobPrepareContext, RenderJobWriteContext>> {
+        let mut objects = Vec::with_capacity(self.entities.len());
+        let picking_view = views
+            .iter()
+            .find(|view| view.view_index() == self.picking_view_index);
+        let game_resource_manager = extract_context.resources.get::<GameResourceManager>();
+
+        if let (Some(picking_view), Some(game_resource_manager)) =
+            (picking_view, game_resource_manager)
+        {
+            let entities = std::mem::replace(&mut self.entities, vec![]);
+            for (index, entity) in entities.into_iter().enumerate() {
+                if let Some(object) = self.extract_object(
+                    extract_context,
+                    &*game_resource_manager,
+                    picking_view,
+                    entity,
+                    index as u32 + 1,
+                ) {
+                    objects.push(object);
+                }
+            }
+        }
+
+        Box::new(PickingPrepareJobImpl::new(
+            self.picking_pipeline_info,
+            self.picking_view_index,
+            objects,
+        ))
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        PickingRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        PickingRenderFeature::feature_index()
+    }
+}
@@ -0,0 +1,209 @@
+use crate::render_jobs::RenderExtractJob;
+use crate::components::MeshComponent;
+use atelier_assets::loader::handle::Handle;
+use std::sync::atomic::{AtomicI32, Ordering};
+use renderer::vulkan::VkBufferRaw;
+use renderer::assets::resources::{
+    PipelineSwapchainInfo, DescriptorSetArc, DescriptorSetAllocatorRef, ResourceArc,
+};
+use renderer::nodes::{RenderFeature, RenderFeatureIndex, RenderView, RenderViewIndex};
+use std::convert::TryInto;
+use renderer::assets::MaterialAsset;
+use crate::assets::gltf::MeshPartRangeData;
+use legion::prelude::*;
+use minimum::resources::ViewportResource;
+use minimum::resources::editor::EditorSelectionResource;
+use minimum::math::na_convert::vec3_glam_to_glm;
+use ncollide3d::pipeline::CollisionGroups;
+use ncollide3d::query::Ray;
+use ash::vk;
+
+mod extract;
+use extract::PickingExtractJobImpl;
+
+mod prepare;
+
+mod write;
+use write::PickingCommandWriter;
+
+// Draws the meshes in the request into PickingRenderPhase in the view under the cursor only. Each
+// mesh is drawn with its index in the request's entity list plus one, see VkPickingRenderPass
+pub fn create_picking_extract_job<'a>(
+    descriptor_set_allocator: DescriptorSetAllocatorRef,
+    picking_pipeline_info: PipelineSwapchainInfo,
+    picking_view_index: RenderViewIndex,
+    picking_material: &Handle<MaterialAsset>,
+    entities: Vec<Entity>,
+) -> RenderExtractJob<'a> {
+    Box::new(PickingExtractJobImpl::new(
+        descriptor_set_allocator,
+        picking_pipeline_info,
+        picking_view_index,
+        picking_material,
+        entities,
+    ))
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[repr(C)]
+pub struct PickingShaderParam {
+    pub model_view_proj: glam::Mat4, // +0
+    pub picking_id: u32,             // +64
+} // 80 bytes
+
+/// Meshes that might be under the cursor this frame. Picking IDs index into `entities`, offset by
+/// one so that a cleared pixel reads back as nothing
+#[derive(Debug, Clone)]
+pub struct PickingRequest {
+    pub request_id: u64,
+    pub camera: PickingCamera,
+    pub cursor_position: glam::Vec2,
+    pub pixel: (u32, u32),
+    pub entities: Vec<Entity>,
+}
+
+/// The mesh drawn under the cursor for a frame that has finished rendering. This lags a few
+/// frames behind the cursor, so match `request_id` against the
+/// `GameRenderer::next_picking_request_id` of the frame the cursor position is needed for. `depth`
+/// is the picked mesh's depth buffer value, 1.0 if nothing was picked
+#[derive(Debug, Copy, Clone)]
+pub struct PickingResult {
+    pub request_id: u64,
+    pub camera: PickingCamera,
+    pub cursor_position: glam::Vec2,
+    pub entity: Option<Entity>,
+    pub depth: f32,
+}
+
+impl PickingResult {
+    // Ray through the cursor from the camera the request was rendered with
+    pub fn ray(&self) -> Ray<f32> {
+        viewport_space_ray(&self.camera.viewport(), self.cursor_position)
+    }
+
+    // Where the picked mesh is along ray(), so it can be compared with the time of impact of other
+    // ray casts. None if nothing was picked
+    pub fn toi(&self) -> Option<f32> {
+        self.entity?;
+
+        let viewport = self.camera.viewport();
+        let ray_start = viewport.viewport_space_to_world_space(self.cursor_position, 0.0);
+        let ray_end = viewport.viewport_space_to_world_space(self.cursor_position, 1.0);
+        let picked = viewport.viewport_space_to_world_space(self.cursor_position, self.depth);
+
+        Some((picked - ray_start).length() / (ray_end - ray_start).length())
+    }
+}
+
+/// The camera a picking request was made with. The result is read back frames later, after the
+/// camera may have moved, so its depth must be unprojected with this rather than the current
+/// ViewportResource
+#[derive(Debug, Copy, Clone)]
+pub struct PickingCamera {
+    pub view: glam::Mat4,
+    pub projection: glam::Mat4,
+    pub eye_position: glam::Vec3,
+    pub viewport_size: glam::Vec2,
+}
+
+impl PickingCamera {
+    pub fn viewport(&self) -> ViewportResource {
+        let mut viewport = ViewportResource::empty();
+        viewport.set_viewport_size_in_pixels(self.viewport_size);
+        viewport.set_world_space_view(self.projection, self.view, self.eye_position);
+        viewport
+    }
+}
+
+// Ray from the near plane to the far plane through a point in the viewport
+pub fn viewport_space_ray(
+    viewport: &ViewportResource,
+    viewport_position: glam::Vec2,
+) -> Ray<f32> {
+    let ray_start = viewport.viewport_space_to_world_space(viewport_position, 0.0);
+    let ray_end = viewport.viewport_space_to_world_space(viewport_position, 1.0);
+
+    Ray::new(
+        nalgebra::Point3::from(vec3_glam_to_glm(ray_start)),
+        vec3_glam_to_glm(ray_end - ray_start),
+    )
+}
+
+// Only meshes whose editor selection shape is under the cursor are drawn, so the picking pass
+// costs little even in large scenes. The shapes enclose the mesh, so no visible mesh is missed.
+// Returns None if the cursor is outside the viewport. The viewport must be set up with the view
+// picking_view
+pub fn create_picking_request(
+    world: &World,
+    viewport: &ViewportResource,
+    picking_view: &RenderView,
+    editor_selection: &EditorSelectionResource,
+    request_id: u64,
+    cursor_position: glam::Vec2,
+    extents: vk::Extent2D,
+) -> Option<PickingRequest> {
+    if cursor_position.x() < 0.0
+        || cursor_position.y() < 0.0
+        || cursor_position.x() >= extents.width as f32
+        || cursor_position.y() >= extents.height as f32
+    {
+        return None;
+    }
+
+    let ray = viewport_space_ray(viewport, cursor_position);
+
+    let mut entities = vec![];
+    for (_, object, _) in editor_selection
+        .editor_selection_world()
+        .interferences_with_ray(&ray, &CollisionGroups::new())
+    {
+        let entity = *object.data();
+        if !entities.contains(&entity) && world.get_component::<MeshComponent>(entity).is_some() {
+            entities.push(entity);
+        }
+    }
+
+    let camera = PickingCamera {
+        view: picking_view.view_matrix(),
+        projection: picking_view.projection_matrix(),
+        eye_position: picking_view.eye_position(),
+        viewport_size: glam::Vec2::new(extents.width as f32, extents.height as f32),
+    };
+
+    Some(PickingRequest {
+        request_id,
+        camera,
+        cursor_position,
+        pixel: (cursor_position.x() as u32, cursor_position.y() as u32),
+        entities,
+    })
+}
+
+//
+// This is boilerplate that could be macro'd
+//
+static PICKING_FEATURE_INDEX: AtomicI32 = AtomicI32::new(-1);
+
+pub struct PickingRenderFeature;
+
+impl RenderFeature for PickingRenderFeature {
+    fn set_feature_index(index: RenderFeatureIndex) {
+        PICKING_FEATURE_INDEX.store(index.try_into().unwrap(), Ordering::Release);
+    }
+
+    fn feature_index() -> RenderFeatureIndex {
+        PICKING_FEATURE_INDEX.load(Ordering::Acquire) as RenderFeatureIndex
+    }
+
+    fn feature_debug_name() -> &'static str {
+        "PickingRenderFeature"
+    }
+}
+
+// A mesh under the cursor. Every part of LOD 0 is drawn with the same per-object data
+pub(self) struct ExtractedPickingObject {
+    vertex_buffer: ResourceArc<VkBufferRaw>,
+    index_buffer: ResourceArc<VkBufferRaw>,
+    part_ranges: Vec<MeshPartRangeData>,
+    per_object_descriptor_set: DescriptorSetArc,
+}
@@ -0,0 +1,67 @@
+use renderer::nodes::{
+    RenderView, ViewSubmitNodes, FeatureSubmitNodes, FeatureCommandWriter, RenderFeatureIndex,
+    FramePacket, RenderFeature, PrepareJob, RenderViewIndex,
+};
+use crate::features::picking::{PickingRenderFeature, ExtractedPickingObject};
+use crate::phases::PickingRenderPhase;
+use super::PickingCommandWriter;
+use crate::render_contexts::{RenderJobWriteContext, RenderJobPrepareContext};
+use renderer::assets::resources::PipelineSwapchainInfo;
+
+pub struct PickingPrepareJobImpl {
+    picking_pipeline_info: PipelineSwapchainInfo,
+    picking_view_index: RenderViewIndex,
+    objects: Vec<ExtractedPickingObject>,
+}
+
+impl PickingPrepareJobImpl {
+    pub(super) fn new(
+        picking_pipeline_info: PipelineSwapchainInfo,
+        picking_view_index: RenderViewIndex,
+        objects: Vec<ExtractedPickingObject>,
+    ) -> Self {
+        PickingPrepareJobImpl {
+            picking_pipeline_info,
+            picking_view_index,
+            objects,
+        }
+    }
+}
+
+impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for PickingPrepareJobImpl {
+    fn prepare(
+        self: Box<Self>,
+        _prepare_context: &RenderJobPrepareContext,
+        _frame_packet: &FramePacket,
+        views: &[&RenderView],
+    ) -> (
+        Box<dyn FeatureCommandWriter<RenderJobWriteContext>>,
+        FeatureSubmitNodes,
+    ) {
+        // A single node draws every object, the depth test keeps the nearest one's ID
+        let mut submit_nodes = FeatureSubmitNodes::default();
+        for view in views {
+            let mut view_submit_nodes =
+                ViewSubmitNodes::new(self.feature_index(), view.render_phase_mask());
+            if view.view_index() == self.picking_view_index && !self.objects.is_empty() {
+                view_submit_nodes.add_submit_node::<PickingRenderPhase>(0, 0, 0.0);
+            }
+            submit_nodes.add_submit_nodes_for_view(view, view_submit_nodes);
+        }
+
+        let writer = Box::new(PickingCommandWriter {
+            picking_pipeline_info: self.picking_pipeline_info,
+            objects: self.objects,
+        });
+
+        (writer, submit_nodes)
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        PickingRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        PickingRenderFeature::feature_index()
+    }
+}
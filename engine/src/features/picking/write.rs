@@ -0,0 +1,100 @@
+use crate::features::picking::{PickingRenderFeature, ExtractedPickingObject};
+use renderer::nodes::{
+    RenderFeatureIndex, RenderPhaseIndex, RenderFeature, SubmitNodeId, FeatureCommandWriter,
+    RenderView,
+};
+use crate::render_contexts::RenderJobWriteContext;
+use renderer::assets::resources::PipelineSwapchainInfo;
+use ash::vk;
+use ash::version::DeviceV1_0;
+
+pub struct PickingCommandWriter {
+    pub(super) picking_pipeline_info: PipelineSwapchainInfo,
+    pub(super) objects: Vec<ExtractedPickingObject>,
+}
+
+impl FeatureCommandWriter<RenderJobWriteContext> for PickingCommandWriter {
+    fn apply_setup(
+        &self,
+        write_context: &mut RenderJobWriteContext,
+        _view: &RenderView,
+        _render_phase_index: RenderPhaseIndex,
+    ) {
+        unsafe {
+            write_context.device_context.device().cmd_bind_pipeline(
+                write_context.command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.picking_pipeline_info.pipeline.get_raw().pipelines[0],
+            );
+        }
+    }
+
+    fn render_element(
+        &self,
+        write_context: &mut RenderJobWriteContext,
+        _view: &RenderView,
+        render_phase_index: RenderPhaseIndex,
+        _index: SubmitNodeId,
+    ) {
+        write_context.record_submit_node(render_phase_index);
+
+        let logical_device = write_context.device_context.device().clone();
+        let command_buffer = write_context.command_buffer;
+        let pipeline_layout = self
+            .picking_pipeline_info
+            .pipeline_layout
+            .get_raw()
+            .pipeline_layout;
+
+        for object in &self.objects {
+            unsafe {
+                logical_device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::GRAPHICS,
+                    pipeline_layout,
+                    0,
+                    &[object.per_object_descriptor_set.get()],
+                    &[],
+                );
+            }
+
+            for part_range in &object.part_ranges {
+                let index_count = part_range.index_buffer_size_in_bytes / 2; //sizeof(u16)
+                unsafe {
+                    logical_device.cmd_bind_vertex_buffers(
+                        command_buffer,
+                        0, // first binding
+                        &[object.vertex_buffer.get_raw().buffer],
+                        &[part_range.vertex_buffer_offset_in_bytes as u64], // offsets
+                    );
+
+                    logical_device.cmd_bind_index_buffer(
+                        command_buffer,
+                        object.index_buffer.get_raw().buffer,
+                        part_range.index_buffer_offset_in_bytes as u64, // offset
+                        vk::IndexType::UINT16,
+                    );
+
+                    logical_device.cmd_draw_indexed(command_buffer, index_count, 1, 0, 0, 0);
+                }
+                write_context.record_draw_call(index_count / 3);
+            }
+        }
+    }
+
+    fn revert_setup(
+        &self,
+        _write_context: &mut RenderJobWriteContext,
+        _view: &RenderView,
+        _render_phase_index: RenderPhaseIndex,
+    ) {
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        PickingRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        PickingRenderFeature::feature_index()
+    }
+}
@@ -0,0 +1,178 @@
+use crate::features::selection_outline::{
+    SelectionOutlineRenderFeature, ExtractedSelectionOutlineObject,
+    SelectionOutlineMaskShaderParam, EditorHoveredEntityResource,
+    SELECTION_OUTLINE_MASK_PASS_INDEX, SELECTION_OUTLINE_SELECTED_MASK,
+    SELECTION_OUTLINE_HOVERED_MASK,
+};
+use crate::features::selection_outline::prepare::SelectionOutlinePrepareJobImpl;
+use crate::render_contexts::{RenderJobExtractContext, RenderJobWriteContext, RenderJobPrepareContext};
+use renderer::nodes::{
+    FramePacket, RenderView, PrepareJob, RenderFeatureIndex, RenderFeature, ExtractJob,
+    RenderViewIndex,
+};
+use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetAllocatorRef};
+use atelier_assets::loader::handle::Handle;
+use renderer::assets::MaterialAsset;
+use legion::prelude::*;
+use crate::components::MeshComponent;
+use crate::game_resource_manager::GameResourceManager;
+use minimum::components::TransformComponent;
+use minimum::resources::editor::EditorSelectionResource;
+
+pub struct SelectionOutlineExtractJobImpl {
+    descriptor_set_allocator: DescriptorSetAllocatorRef,
+    mask_pipeline_info: PipelineSwapchainInfo,
+    view_indices: Vec<RenderViewIndex>,
+    outline_material: Handle<MaterialAsset>,
+}
+
+impl SelectionOutlineExtractJobImpl {
+    pub fn new(
+        descriptor_set_allocator: DescriptorSetAllocatorRef,
+        mask_pipeline_info: PipelineSwapchainInfo,
+        view_indices: Vec<RenderViewIndex>,
+        outline_material: &Handle<MaterialAsset>,
+    ) -> Self {
+        SelectionOutlineExtractJobImpl {
+            descriptor_set_allocator,
+            mask_pipeline_info,
+            view_indices,
+            outline_material: outline_material.clone(),
+        }
+    }
+
+    // Returns None if the entity doesn't have a loaded mesh
+    fn extract_object(
+        &mut self,
+        extract_context: &RenderJobExtractContext,
+        game_resource_manager: &GameResourceManager,
+        view: &RenderView,
+        entity: Entity,
+        mask_color: [f32; 4],
+    ) -> Option<ExtractedSelectionOutlineObject> {
+        let transform_component = extract_context
+            .world
+            .get_component::<TransformComponent>(entity)?;
+        let mesh_component = extract_context
+            .world
+            .get_component::<MeshComponent>(entity)?;
+        let mesh_info = mesh_component
+            .mesh
+            .as_ref()
+            .and_then(|mesh_asset_handle| game_resource_manager.get_mesh_info(mesh_asset_handle))?;
+
+        let per_object_param = SelectionOutlineMaskShaderParam {
+            model_view_proj: view.projection_matrix()
+                * view.view_matrix()
+                * transform_component.transform(),
+            mask_color: mask_color.into(),
+        };
+
+        let layout = extract_context.resource_manager.get_descriptor_set_info(
+            &self.outline_material,
+            SELECTION_OUTLINE_MASK_PASS_INDEX,
+            0,
+        );
+        let mut descriptor_set = self
+            .descriptor_set_allocator
+            .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
+            .unwrap();
+        extract_context
+            .render_stats()
+            .record_descriptor_set_allocated();
+        descriptor_set.set_buffer_data(0, &per_object_param);
+        extract_context
+            .render_stats()
+            .record_buffer_upload(std::mem::size_of_val(&per_object_param) as u64);
+        descriptor_set
+            .flush(&mut self.descriptor_set_allocator)
+            .unwrap();
+
+        // The outline follows the full detail mesh regardless of the LOD drawn in the scene
+        Some(ExtractedSelectionOutlineObject {
+            vertex_buffer: mesh_info.vertex_buffer.clone(),
+            index_buffer: mesh_info.index_buffer.clone(),
+            part_ranges: mesh_info.mesh_asset.lod_part_ranges(0),
+            per_object_descriptor_set: descriptor_set.descriptor_set().clone(),
+        })
+    }
+}
+
+impl<'a> ExtractJob<RenderJobExtractContext<'a>, RenderJobPrepareContext, RenderJobWriteContext>
+    for SelectionOutlineExtractJobImpl
+{
+    fn extract(
+        mut self: Box<Self>,
+        extract_context: &RenderJobExtractContext<'a>,
+        _frame_packet: &FramePacket,
+        views: &[&RenderView],
+    ) -> Box<dyn PrepareJob<RenderJobPrepareContext, RenderJobWriteContext>> {
+        let selected_entities: Vec<Entity> = extract_context
+            .resources
+            .get::<EditorSelectionResource>()
+            .map(|editor_selection| {
+                editor_selection
+                    .selected_entities()
+                    .iter()
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // An entity that is both hovered and selected is only outlined as selected
+        let hovered_entity = extract_context
+            .resources
+            .get::<EditorHoveredEntityResource>()
+            .and_then(|hovered| hovered.hovered_entity())
+            .filter(|entity| !selected_entities.contains(entity));
+
+        let entities: Vec<_> = hovered_entity
+            .iter()
+            .map(|entity| (*entity, SELECTION_OUTLINE_HOVERED_MASK))
+            .chain(
+                selected_entities
+                    .iter()
+                    .map(|entity| (*entity, SELECTION_OUTLINE_SELECTED_MASK)),
+            )
+            .collect();
+
+        // Each view has its own projection, so the objects are extracted once per view
+        let mut view_objects = Vec::with_capacity(self.view_indices.len());
+        let game_resource_manager = extract_context.resources.get::<GameResourceManager>();
+        if let Some(game_resource_manager) = game_resource_manager {
+            for view in views {
+                if !self.view_indices.contains(&view.view_index()) {
+                    continue;
+                }
+
+                let mut objects = Vec::with_capacity(entities.len());
+                for (entity, mask_color) in &entities {
+                    if let Some(object) = self.extract_object(
+                        extract_context,
+                        &*game_resource_manager,
+                        view,
+                        *entity,
+                        *mask_color,
+                    ) {
+                        objects.push(object);
+                    }
+                }
+
+                view_objects.push((view.view_index(), objects));
+            }
+        }
+
+        Box::new(SelectionOutlinePrepareJobImpl::new(
+            self.mask_pipeline_info,
+            view_objects,
+        ))
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        SelectionOutlineRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        SelectionOutlineRenderFeature::feature_index()
+    }
+}
@@ -0,0 +1,25 @@
+use legion::prelude::Entity;
+
+/// The selectable entity under the mouse cursor in the editor, if any. Updated every frame by
+/// `editor_update_hovered_entity` and drawn with `SELECTION_OUTLINE_HOVERED_COLOR`.
+#[derive(Default)]
+pub struct EditorHoveredEntityResource {
+    hovered_entity: Option<Entity>,
+}
+
+impl EditorHoveredEntityResource {
+    pub fn new() -> Self {
+        EditorHoveredEntityResource::default()
+    }
+
+    pub fn hovered_entity(&self) -> Option<Entity> {
+        self.hovered_entity
+    }
+
+    pub fn set_hovered_entity(
+        &mut self,
+        hovered_entity: Option<Entity>,
+    ) {
+        self.hovered_entity = hovered_entity;
+    }
+}
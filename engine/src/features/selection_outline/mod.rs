@@ -0,0 +1,107 @@
+use crate::render_jobs::RenderExtractJob;
+use atelier_assets::loader::handle::Handle;
+use std::sync::atomic::{AtomicI32, Ordering};
+use renderer::vulkan::VkBufferRaw;
+use renderer::assets::resources::{
+    PipelineSwapchainInfo, DescriptorSetArc, DescriptorSetAllocatorRef, ResourceArc,
+};
+use renderer::nodes::{RenderFeature, RenderFeatureIndex, RenderViewIndex};
+use std::convert::TryInto;
+use renderer::assets::MaterialAsset;
+use crate::assets::gltf::MeshPartRangeData;
+
+mod extract;
+use extract::SelectionOutlineExtractJobImpl;
+
+mod prepare;
+
+mod write;
+use write::SelectionOutlineCommandWriter;
+
+mod hovered_entity;
+pub use hovered_entity::EditorHoveredEntityResource;
+
+// Passes in outline.material
+pub const SELECTION_OUTLINE_MASK_PASS_INDEX: usize = 0;
+pub const SELECTION_OUTLINE_COMPOSITE_PASS_INDEX: usize = 1;
+
+// Written into the mask for each kind of object. The composite pass reads red as selected and
+// green as hovered
+pub const SELECTION_OUTLINE_SELECTED_MASK: [f32; 4] = [1.0, 0.0, 0.0, 1.0];
+pub const SELECTION_OUTLINE_HOVERED_MASK: [f32; 4] = [0.0, 1.0, 0.0, 1.0];
+
+// Colors of the outline drawn around selected and hovered objects
+pub const SELECTION_OUTLINE_SELECTED_COLOR: [f32; 4] = [1.0, 0.6, 0.1, 1.0];
+pub const SELECTION_OUTLINE_HOVERED_COLOR: [f32; 4] = [0.4, 0.8, 1.0, 0.8];
+pub const SELECTION_OUTLINE_THICKNESS_IN_PIXELS: i32 = 2;
+
+// Draws meshes of entities selected in EditorSelectionResource, and the entity in
+// EditorHoveredEntityResource, into SelectionOutlineRenderPhase in each of the given views. This
+// is independent of visibility so that static meshes are outlined too.
+pub fn create_selection_outline_extract_job<'a>(
+    descriptor_set_allocator: DescriptorSetAllocatorRef,
+    mask_pipeline_info: PipelineSwapchainInfo,
+    view_indices: Vec<RenderViewIndex>,
+    outline_material: &Handle<MaterialAsset>,
+) -> RenderExtractJob<'a> {
+    Box::new(SelectionOutlineExtractJobImpl::new(
+        descriptor_set_allocator,
+        mask_pipeline_info,
+        view_indices,
+        outline_material,
+    ))
+}
+
+#[derive(Default, Copy, Clone, Debug)]
+#[repr(C)]
+pub struct SelectionOutlineMaskShaderParam {
+    pub model_view_proj: glam::Mat4, // +0
+    pub mask_color: glam::Vec4,      // +64
+} // 80 bytes
+
+#[derive(Default, Copy, Clone, Debug)]
+#[repr(C)]
+pub struct SelectionOutlineCompositeShaderParam {
+    pub selected_color: glam::Vec4, // +0
+    pub hovered_color: glam::Vec4,  // +16
+    pub thickness_in_pixels: i32,   // +32
+} // 48 bytes
+
+impl SelectionOutlineCompositeShaderParam {
+    pub fn new() -> Self {
+        SelectionOutlineCompositeShaderParam {
+            selected_color: SELECTION_OUTLINE_SELECTED_COLOR.into(),
+            hovered_color: SELECTION_OUTLINE_HOVERED_COLOR.into(),
+            thickness_in_pixels: SELECTION_OUTLINE_THICKNESS_IN_PIXELS,
+        }
+    }
+}
+
+//
+// This is boilerplate that could be macro'd
+//
+static SELECTION_OUTLINE_FEATURE_INDEX: AtomicI32 = AtomicI32::new(-1);
+
+pub struct SelectionOutlineRenderFeature;
+
+impl RenderFeature for SelectionOutlineRenderFeature {
+    fn set_feature_index(index: RenderFeatureIndex) {
+        SELECTION_OUTLINE_FEATURE_INDEX.store(index.try_into().unwrap(), Ordering::Release);
+    }
+
+    fn feature_index() -> RenderFeatureIndex {
+        SELECTION_OUTLINE_FEATURE_INDEX.load(Ordering::Acquire) as RenderFeatureIndex
+    }
+
+    fn feature_debug_name() -> &'static str {
+        "SelectionOutlineRenderFeature"
+    }
+}
+
+// A selected or hovered mesh. Every part of LOD 0 is drawn with the same per-object data
+pub(self) struct ExtractedSelectionOutlineObject {
+    vertex_buffer: ResourceArc<VkBufferRaw>,
+    index_buffer: ResourceArc<VkBufferRaw>,
+    part_ranges: Vec<MeshPartRangeData>,
+    per_object_descriptor_set: DescriptorSetArc,
+}
@@ -0,0 +1,75 @@
+use renderer::nodes::{
+    RenderView, ViewSubmitNodes, FeatureSubmitNodes, FeatureCommandWriter, RenderFeatureIndex,
+    FramePacket, RenderFeature, PrepareJob, RenderViewIndex, SubmitNodeId,
+};
+use crate::features::selection_outline::{
+    SelectionOutlineRenderFeature, ExtractedSelectionOutlineObject,
+};
+use crate::phases::SelectionOutlineRenderPhase;
+use super::SelectionOutlineCommandWriter;
+use crate::render_contexts::{RenderJobWriteContext, RenderJobPrepareContext};
+use renderer::assets::resources::PipelineSwapchainInfo;
+
+pub struct SelectionOutlinePrepareJobImpl {
+    mask_pipeline_info: PipelineSwapchainInfo,
+    // The objects to outline in each view, drawn with that view's projection
+    view_objects: Vec<(RenderViewIndex, Vec<ExtractedSelectionOutlineObject>)>,
+}
+
+impl SelectionOutlinePrepareJobImpl {
+    pub(super) fn new(
+        mask_pipeline_info: PipelineSwapchainInfo,
+        view_objects: Vec<(RenderViewIndex, Vec<ExtractedSelectionOutlineObject>)>,
+    ) -> Self {
+        SelectionOutlinePrepareJobImpl {
+            mask_pipeline_info,
+            view_objects,
+        }
+    }
+}
+
+impl PrepareJob<RenderJobPrepareContext, RenderJobWriteContext> for SelectionOutlinePrepareJobImpl {
+    fn prepare(
+        self: Box<Self>,
+        _prepare_context: &RenderJobPrepareContext,
+        _frame_packet: &FramePacket,
+        views: &[&RenderView],
+    ) -> (
+        Box<dyn FeatureCommandWriter<RenderJobWriteContext>>,
+        FeatureSubmitNodes,
+    ) {
+        // A single node per view draws every object into the mask. Its ID is the index into
+        // view_objects. Hovered objects come first so that selected objects are drawn over them
+        let mut submit_nodes = FeatureSubmitNodes::default();
+        for view in views {
+            let mut view_submit_nodes =
+                ViewSubmitNodes::new(self.feature_index(), view.render_phase_mask());
+            let view_objects_index = self.view_objects.iter().position(|(view_index, objects)| {
+                *view_index == view.view_index() && !objects.is_empty()
+            });
+            if let Some(view_objects_index) = view_objects_index {
+                view_submit_nodes.add_submit_node::<SelectionOutlineRenderPhase>(
+                    view_objects_index as SubmitNodeId,
+                    0,
+                    0.0,
+                );
+            }
+            submit_nodes.add_submit_nodes_for_view(view, view_submit_nodes);
+        }
+
+        let writer = Box::new(SelectionOutlineCommandWriter {
+            mask_pipeline_info: self.mask_pipeline_info,
+            view_objects: self.view_objects,
+        });
+
+        (writer, submit_nodes)
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        SelectionOutlineRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        SelectionOutlineRenderFeature::feature_index()
+    }
+}
@@ -2,50 +2,28 @@ use crate::features::sprite::{
     ExtractedSpriteData, SpriteRenderNodeSet, SpriteRenderFeature, SpriteRenderNode,
 };
 use crate::components::{SpriteComponent};
+use crate::assets::sprite::SpriteSheetAsset;
 use crate::render_contexts::{RenderJobExtractContext, RenderJobWriteContext, RenderJobPrepareContext};
 use renderer::nodes::{
     DefaultExtractJobImpl, FramePacket, RenderView, PerViewNode, PrepareJob, DefaultPrepareJob,
-    RenderFeatureIndex, RenderFeature, PerFrameNode,
+    RenderFeatureIndex, RenderFeature, PerFrameNode, RenderViewIndex,
 };
 use renderer::base::slab::RawSlabKey;
 use crate::features::sprite::prepare::SpritePrepareJobImpl;
 use renderer::vulkan::VkDeviceContext;
 use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetAllocatorRef};
-use atelier_assets::loader::handle::Handle;
+use atelier_assets::loader::handle::{AssetHandle, Handle};
 use renderer::assets::resources::DescriptorSetArc;
 use legion::prelude::EntityStore;
 use renderer::assets::MaterialAsset;
 use minimum::components::{TransformComponentDef, TransformComponent};
-
-// This is almost copy-pasted from glam. I wanted to avoid pulling in the entire library for a
-// single function
-pub fn orthographic_rh_gl(
-    left: f32,
-    right: f32,
-    bottom: f32,
-    top: f32,
-    near: f32,
-    far: f32,
-) -> [[f32; 4]; 4] {
-    let a = 2.0 / (right - left);
-    let b = 2.0 / (top - bottom);
-    let c = -2.0 / (far - near);
-    let tx = -(right + left) / (right - left);
-    let ty = -(top + bottom) / (top - bottom);
-    let tz = -(far + near) / (far - near);
-
-    [
-        [a, 0.0, 0.0, 0.0],
-        [0.0, b, 0.0, 0.0],
-        [0.0, 0.0, c, 0.0],
-        [tx, ty, tz, 1.0],
-    ]
-}
+use minimum::resources::AssetResource;
 
 pub struct SpriteExtractJobImpl {
     device_context: VkDeviceContext,
     descriptor_set_allocator: DescriptorSetAllocatorRef,
     pipeline_info: PipelineSwapchainInfo,
+    screen_space_view_index: RenderViewIndex,
     sprite_material: Handle<MaterialAsset>,
     extracted_frame_node_sprite_data: Vec<Option<ExtractedSpriteData>>,
     per_view_descriptors: Vec<DescriptorSetArc>,
@@ -56,12 +34,14 @@ impl SpriteExtractJobImpl {
         device_context: VkDeviceContext,
         descriptor_set_allocator: DescriptorSetAllocatorRef,
         pipeline_info: PipelineSwapchainInfo,
+        screen_space_view_index: RenderViewIndex,
         sprite_material: &Handle<MaterialAsset>,
     ) -> Self {
         SpriteExtractJobImpl {
             device_context,
             descriptor_set_allocator,
             pipeline_info,
+            screen_space_view_index,
             sprite_material: sprite_material.clone(),
             //descriptor_set_per_pass,
             extracted_frame_node_sprite_data: Default::default(),
@@ -70,68 +50,26 @@ impl SpriteExtractJobImpl {
     }
 }
 
-impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext>
-    for SpriteExtractJobImpl
+impl<'a>
+    DefaultExtractJobImpl<
+        RenderJobExtractContext<'a>,
+        RenderJobPrepareContext,
+        RenderJobWriteContext,
+    > for SpriteExtractJobImpl
 {
     fn extract_begin(
         &mut self,
-        extract_context: &RenderJobExtractContext,
+        _extract_context: &RenderJobExtractContext<'a>,
         frame_packet: &FramePacket,
         _views: &[&RenderView],
     ) {
         self.extracted_frame_node_sprite_data
             .reserve(frame_packet.frame_node_count(self.feature_index()) as usize);
-
-        // for view in views {
-        //     let layout = extract_context.resource_manager.get_descriptor_set_info(&self.sprite_material, 0, 0);
-        //     let mut descriptor_set = self.descriptor_set_allocator.create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout).unwrap();
-        //
-        //     let view_proj = view.projection_matrix() * view.view_matrix();
-        //
-        //     descriptor_set.set_buffer_data(0, &view_proj);
-        //     descriptor_set.flush(&mut self.descriptor_set_allocator);
-        //
-        //     self.per_view_descriptors.push(descriptor_set.descriptor_set().clone());
-        // }
-
-        //TODO: Multi-view support for sprites. Not clear on if we want to do a screen-space view specifically
-        // for sprites
-        //TODO: Extents is hard-coded
-        let extents_width = 900;
-        let extents_height = 600;
-        let aspect_ration = extents_width as f32 / extents_height as f32;
-        let half_width = 400.0;
-        let half_height = 400.0 / aspect_ration;
-        let view_proj = orthographic_rh_gl(
-            -half_width,
-            half_width,
-            -half_height,
-            half_height,
-            -100.0,
-            100.0,
-        );
-
-        let layout =
-            extract_context
-                .resource_manager
-                .get_descriptor_set_info(&self.sprite_material, 0, 0);
-        let mut descriptor_set = self
-            .descriptor_set_allocator
-            .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
-            .unwrap();
-
-        descriptor_set.set_buffer_data(0, &view_proj);
-        descriptor_set
-            .flush(&mut self.descriptor_set_allocator)
-            .unwrap();
-
-        self.per_view_descriptors
-            .push(descriptor_set.descriptor_set().clone());
     }
 
     fn extract_frame_node(
         &mut self,
-        extract_context: &RenderJobExtractContext,
+        extract_context: &RenderJobExtractContext<'a>,
         frame_node: PerFrameNode,
         _frame_node_index: u32,
     ) {
@@ -153,9 +91,34 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
             .get_component::<SpriteComponent>(sprite_render_node.entity)
             .unwrap();
 
-        let image_info = extract_context
-            .resource_manager
-            .get_image_info(&sprite_component.image);
+        // A sprite sheet overrides the image and selects a rect of it
+        let asset_resource = extract_context.resources.get::<AssetResource>().unwrap();
+        let (image, (mut uv_min, mut uv_max)) = match &sprite_component.sprite_sheet {
+            Some(sprite_sheet) => {
+                let sprite_sheet: Option<&SpriteSheetAsset> =
+                    sprite_sheet.asset(asset_resource.storage());
+                let uv_rect = sprite_sheet.and_then(|sprite_sheet| {
+                    sprite_sheet.frame_uv_rect(sprite_component.frame)
+                });
+
+                match (sprite_sheet, uv_rect) {
+                    (Some(sprite_sheet), Some(uv_rect)) => (&sprite_sheet.image, uv_rect),
+                    _ => {
+                        self.extracted_frame_node_sprite_data.push(None);
+                        return;
+                    }
+                }
+            }
+            None => match &sprite_component.image {
+                Some(image) => (image, (glam::Vec2::new(0.0, 0.0), glam::Vec2::new(1.0, 1.0))),
+                None => {
+                    self.extracted_frame_node_sprite_data.push(None);
+                    return;
+                }
+            },
+        };
+
+        let image_info = extract_context.resource_manager.get_image_info(image);
         if image_info.is_none() {
             self.extracted_frame_node_sprite_data.push(None);
             return;
@@ -170,6 +133,7 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
             .descriptor_set_allocator
             .create_dyn_descriptor_set_uninitialized(&descriptor_set_info.descriptor_set_layout)
             .unwrap();
+        extract_context.render_stats().record_descriptor_set_allocated();
 
         sprite_texture_descriptor.set_image(0, image_info.image_view);
         sprite_texture_descriptor
@@ -177,20 +141,30 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
             .unwrap();
         let texture_descriptor_set = sprite_texture_descriptor.descriptor_set().clone();
 
+        if sprite_component.flip_x {
+            std::mem::swap(uv_min.x_mut(), uv_max.x_mut());
+        }
+        if sprite_component.flip_y {
+            std::mem::swap(uv_min.y_mut(), uv_max.y_mut());
+        }
+
         self.extracted_frame_node_sprite_data
             .push(Some(ExtractedSpriteData {
-                position: position_component.position(),
-                texture_size: glam::Vec2::new(50.0, 50.0),
-                scale: 1.0,
-                rotation: 0.0,
-                alpha: sprite_component.alpha,
+                world_transform: position_component.transform(),
+                space: sprite_component.space,
+                size: sprite_component.size,
+                pivot: sprite_component.pivot,
+                uv_min,
+                uv_max,
+                tint: sprite_component.tint,
+                sorting_layer: sprite_component.sorting_layer,
                 texture_descriptor_set,
             }));
     }
 
     fn extract_view_node(
         &mut self,
-        _extract_context: &RenderJobExtractContext,
+        _extract_context: &RenderJobExtractContext<'a>,
         _view: &RenderView,
         _view_node: PerViewNode,
         _view_node_index: u32,
@@ -199,18 +173,41 @@ impl DefaultExtractJobImpl<RenderJobExtractContext, RenderJobPrepareContext, Ren
 
     fn extract_view_finalize(
         &mut self,
-        _extract_context: &RenderJobExtractContext,
-        _view: &RenderView,
+        extract_context: &RenderJobExtractContext<'a>,
+        view: &RenderView,
     ) {
+        let layout =
+            extract_context
+                .resource_manager
+                .get_descriptor_set_info(&self.sprite_material, 0, 0);
+        let mut descriptor_set = self
+            .descriptor_set_allocator
+            .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
+            .unwrap();
+        extract_context.render_stats().record_descriptor_set_allocated();
+
+        let view_proj = view.projection_matrix() * view.view_matrix();
+
+        descriptor_set.set_buffer_data(0, &view_proj);
+        extract_context
+            .render_stats()
+            .record_buffer_upload(std::mem::size_of_val(&view_proj) as u64);
+        descriptor_set
+            .flush(&mut self.descriptor_set_allocator)
+            .unwrap();
+
+        self.per_view_descriptors
+            .push(descriptor_set.descriptor_set().clone());
     }
 
     fn extract_frame_finalize(
         self,
-        _extract_context: &RenderJobExtractContext,
+        _extract_context: &RenderJobExtractContext<'a>,
     ) -> Box<dyn PrepareJob<RenderJobPrepareContext, RenderJobWriteContext>> {
         let prepare_impl = SpritePrepareJobImpl::new(
             self.device_context,
             self.pipeline_info,
+            self.screen_space_view_index,
             self.per_view_descriptors.clone(),
             self.extracted_frame_node_sprite_data,
         );
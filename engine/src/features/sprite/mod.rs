@@ -1,9 +1,10 @@
 use renderer::nodes::{
-    RenderFeature, RenderFeatureIndex, DefaultExtractJob, ExtractJob, GenericRenderNodeHandle,
-    RenderNodeSet, RenderNodeCount,
+    RenderFeature, RenderFeatureIndex, DefaultExtractJob, GenericRenderNodeHandle,
+    RenderNodeSet, RenderNodeCount, RenderViewIndex,
 };
+use crate::components::SpriteSpace;
 use std::sync::atomic::{Ordering, AtomicI32};
-use crate::render_contexts::{RenderJobExtractContext, RenderJobWriteContext, RenderJobPrepareContext};
+use crate::render_jobs::RenderExtractJob;
 use legion::prelude::Entity;
 use renderer::base::slab::{RawSlabKey, RawSlab};
 use std::convert::TryInto;
@@ -31,9 +32,9 @@ struct SpriteUniformBufferObject {
 #[derive(Clone, Debug, Copy)]
 #[repr(C)]
 pub struct SpriteVertex {
-    pub pos: [f32; 2],
+    pub pos: [f32; 3],
     pub tex_coord: [f32; 2],
-    //color: [u8; 4],
+    pub color: [f32; 4],
 }
 
 /// Used as static data to represent a quad
@@ -43,39 +44,44 @@ struct QuadVertex {
     tex_coord: [f32; 2],
 }
 
-/// Static data the represents a "unit" quad
+/// Static data the represents a "unit" quad. Texture coordinates are relative to the sprite's frame,
+/// with (0, 0) at the top-left
 const QUAD_VERTEX_LIST: [QuadVertex; 4] = [
     QuadVertex {
         pos: [-0.5, -0.5, 0.0],
-        tex_coord: [1.0, 0.0],
+        tex_coord: [0.0, 1.0],
     },
     QuadVertex {
         pos: [0.5, -0.5, 0.0],
-        tex_coord: [0.0, 0.0],
+        tex_coord: [1.0, 1.0],
     },
     QuadVertex {
         pos: [0.5, 0.5, 0.0],
-        tex_coord: [0.0, 1.0],
+        tex_coord: [1.0, 0.0],
     },
     QuadVertex {
         pos: [-0.5, 0.5, 0.0],
-        tex_coord: [1.0, 1.0],
+        tex_coord: [0.0, 0.0],
     },
 ];
 
 /// Draw order of QUAD_VERTEX_LIST
 const QUAD_INDEX_LIST: [u16; 6] = [0, 1, 2, 2, 3, 0];
 
-pub fn create_sprite_extract_job(
+// Sprites with SpriteSpace::Screen are only drawn in the view with screen_space_view_index, all
+// other sprites are drawn in every other view
+pub fn create_sprite_extract_job<'a>(
     device_context: VkDeviceContext,
     descriptor_set_allocator: DescriptorSetAllocatorRef,
     pipeline_info: PipelineSwapchainInfo,
+    screen_space_view_index: RenderViewIndex,
     sprite_material: &Handle<MaterialAsset>,
-) -> Box<dyn ExtractJob<RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext>> {
+) -> RenderExtractJob<'a> {
     Box::new(DefaultExtractJob::new(SpriteExtractJobImpl::new(
         device_context,
         descriptor_set_allocator,
         pipeline_info,
+        screen_space_view_index,
         sprite_material,
     )))
 }
@@ -163,11 +169,15 @@ impl RenderFeature for SpriteRenderFeature {
 
 #[derive(Debug)]
 pub(self) struct ExtractedSpriteData {
-    position: glam::Vec3,
-    texture_size: glam::Vec2,
-    scale: f32,
-    rotation: f32,
-    alpha: f32,
+    world_transform: glam::Mat4,
+    space: SpriteSpace,
+    size: glam::Vec2,
+    pivot: glam::Vec2,
+    // Top-left and bottom-right texture coordinates, swapped on an axis if the sprite is flipped
+    uv_min: glam::Vec2,
+    uv_max: glam::Vec2,
+    tint: glam::Vec4,
+    sorting_layer: i16,
     texture_descriptor_set: DescriptorSetArc, //TODO: I'd prefer to use something ref-counted
 }
 
@@ -177,3 +187,9 @@ pub struct SpriteDrawCall {
     index_buffer_count: u16,
     texture_descriptor_set: DescriptorSetArc,
 }
+
+// Sorting layers are stored in the upper bits of the transparent phase's sort key. The i16 is
+// biased so that lower layers have lower keys
+pub fn sprite_sort_key(sorting_layer: i16) -> u32 {
+    ((sorting_layer as i32 - std::i16::MIN as i32) as u32) << 16
+}
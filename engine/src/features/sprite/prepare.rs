@@ -1,13 +1,13 @@
 use crate::phases::TransparentRenderPhase;
 use renderer::nodes::{
     RenderView, ViewSubmitNodes, FeatureSubmitNodes, FeatureCommandWriter, RenderFeatureIndex,
-    FramePacket, DefaultPrepareJobImpl, PerFrameNode, PerViewNode, RenderFeature,
+    FramePacket, DefaultPrepareJobImpl, PerFrameNode, PerViewNode, RenderFeature, RenderViewIndex,
 };
 use crate::features::sprite::{
     SpriteRenderFeature, ExtractedSpriteData, QUAD_VERTEX_LIST, QUAD_INDEX_LIST, SpriteDrawCall,
-    SpriteVertex,
+    SpriteVertex, sprite_sort_key,
 };
-use crate::phases::OpaqueRenderPhase;
+use crate::components::SpriteSpace;
 use glam::Vec3;
 use super::SpriteCommandWriter;
 use crate::render_contexts::{RenderJobWriteContext, RenderJobPrepareContext};
@@ -18,6 +18,7 @@ use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetArc};
 pub struct SpritePrepareJobImpl {
     device_context: VkDeviceContext,
     pipeline_info: PipelineSwapchainInfo,
+    screen_space_view_index: RenderViewIndex,
     descriptor_set_per_view: Vec<DescriptorSetArc>,
     extracted_frame_node_sprite_data: Vec<Option<ExtractedSpriteData>>,
 
@@ -30,6 +31,7 @@ impl SpritePrepareJobImpl {
     pub(super) fn new(
         device_context: VkDeviceContext,
         pipeline_info: PipelineSwapchainInfo,
+        screen_space_view_index: RenderViewIndex,
         descriptor_set_per_view: Vec<DescriptorSetArc>,
         extracted_sprite_data: Vec<Option<ExtractedSpriteData>>,
     ) -> Self {
@@ -38,6 +40,7 @@ impl SpritePrepareJobImpl {
             device_context,
             extracted_frame_node_sprite_data: extracted_sprite_data,
             pipeline_info,
+            screen_space_view_index,
             descriptor_set_per_view,
             draw_calls: Vec::with_capacity(sprite_count),
             vertex_list: Vec::with_capacity(sprite_count * QUAD_VERTEX_LIST.len()),
@@ -56,45 +59,6 @@ impl DefaultPrepareJobImpl<RenderJobPrepareContext, RenderJobWriteContext>
         _views: &[&RenderView],
         _submit_nodes: &mut FeatureSubmitNodes,
     ) {
-        for sprite in &self.extracted_frame_node_sprite_data {
-            if let Some(sprite) = sprite {
-                const DEG_TO_RAD: f32 = std::f32::consts::PI / 180.0;
-
-                let matrix = glam::Mat4::from_translation(sprite.position)
-                    * glam::Mat4::from_rotation_z(sprite.rotation * DEG_TO_RAD)
-                    * glam::Mat4::from_scale(glam::Vec3::new(
-                        sprite.texture_size.x() * sprite.scale,
-                        sprite.texture_size.y() * sprite.scale,
-                        1.0,
-                    ));
-
-                let vertex_buffer_first_element = self.vertex_list.len() as u16;
-
-                for vertex in &QUAD_VERTEX_LIST {
-                    //let pos = vertex.pos;
-                    let transformed_pos = matrix.transform_point3(vertex.pos.into());
-
-                    self.vertex_list.push(SpriteVertex {
-                        pos: transformed_pos.truncate().into(),
-                        tex_coord: vertex.tex_coord,
-                        //color: [255, 255, 255, 255]
-                    });
-                }
-
-                let index_buffer_first_element = self.index_list.len() as u16;
-                for index in &QUAD_INDEX_LIST {
-                    self.index_list.push(*index + vertex_buffer_first_element);
-                }
-
-                let draw_call = SpriteDrawCall {
-                    index_buffer_first_element,
-                    index_buffer_count: QUAD_INDEX_LIST.len() as u16,
-                    texture_descriptor_set: sprite.texture_descriptor_set.clone(),
-                };
-
-                self.draw_calls.push(draw_call);
-            }
-        }
     }
 
     fn prepare_frame_node(
@@ -114,26 +78,83 @@ impl DefaultPrepareJobImpl<RenderJobPrepareContext, RenderJobWriteContext>
         _view_node_index: u32,
         submit_nodes: &mut ViewSubmitNodes,
     ) {
-        // Use the frame node index as the submit ID since we don't have any view-specific data
-        // to cache
         let frame_node_index = view_node.frame_node_index();
+        let sprite = match &self.extracted_frame_node_sprite_data[frame_node_index as usize] {
+            Some(sprite) => sprite,
+            None => return,
+        };
+
+        // Screen-space sprites only go in the screen-space view, and only screen-space sprites go
+        // in it
+        let is_screen_space_view = view.view_index() == self.screen_space_view_index;
+        if (sprite.space == SpriteSpace::Screen) != is_screen_space_view {
+            return;
+        }
 
-        // This can read per-frame and per-view data
-        if let Some(extracted_data) =
-            &self.extracted_frame_node_sprite_data[frame_node_index as usize]
-        {
-            if extracted_data.alpha >= 1.0 {
-                submit_nodes.add_submit_node::<OpaqueRenderPhase>(frame_node_index, 0, 0.0);
-            } else {
-                let distance_from_camera =
-                    Vec3::length(extracted_data.position - view.eye_position());
-                submit_nodes.add_submit_node::<TransparentRenderPhase>(
-                    frame_node_index,
-                    0,
-                    distance_from_camera,
+        // Billboards are oriented by the camera, other sprites lie in the XY plane of their
+        // transform
+        let position = sprite.world_transform.w_axis().truncate();
+        let (axis_x, axis_y) = match sprite.space {
+            SpriteSpace::Billboard => {
+                let view_matrix = view.view_matrix();
+                let camera_right = Vec3::new(
+                    view_matrix.x_axis().x(),
+                    view_matrix.y_axis().x(),
+                    view_matrix.z_axis().x(),
                 );
+                let camera_up = Vec3::new(
+                    view_matrix.x_axis().y(),
+                    view_matrix.y_axis().y(),
+                    view_matrix.z_axis().y(),
+                );
+
+                let scale_x = sprite.world_transform.x_axis().truncate().length();
+                let scale_y = sprite.world_transform.y_axis().truncate().length();
+                (camera_right * scale_x, camera_up * scale_y)
             }
+            SpriteSpace::World | SpriteSpace::Screen => (
+                sprite.world_transform.x_axis().truncate(),
+                sprite.world_transform.y_axis().truncate(),
+            ),
+        };
+
+        //TODO: indexes are u16 so we may need to produce more than one set of buffers
+        let vertex_buffer_first_element = self.vertex_list.len() as u16;
+        for vertex in &QUAD_VERTEX_LIST {
+            // Offset the unit quad so that the pivot lands on the sprite's position
+            let local_x = (vertex.pos[0] + 0.5 - sprite.pivot.x()) * sprite.size.x();
+            let local_y = (vertex.pos[1] + 0.5 - sprite.pivot.y()) * sprite.size.y();
+            let pos = position + axis_x * local_x + axis_y * local_y;
+
+            let tex_coord = sprite.uv_min
+                + (sprite.uv_max - sprite.uv_min) * glam::Vec2::from(vertex.tex_coord);
+
+            self.vertex_list.push(SpriteVertex {
+                pos: pos.into(),
+                tex_coord: tex_coord.into(),
+                color: sprite.tint.into(),
+            });
         }
+
+        let index_buffer_first_element = self.index_list.len() as u16;
+        for index in &QUAD_INDEX_LIST {
+            self.index_list.push(*index + vertex_buffer_first_element);
+        }
+
+        let submit_node_id = self.draw_calls.len() as u32;
+        self.draw_calls.push(SpriteDrawCall {
+            index_buffer_first_element,
+            index_buffer_count: QUAD_INDEX_LIST.len() as u16,
+            texture_descriptor_set: sprite.texture_descriptor_set.clone(),
+        });
+
+        // Sprites are always alpha blended. Within a sorting layer they are drawn back to front
+        let distance_from_camera = Vec3::length(position - view.eye_position());
+        submit_nodes.add_submit_node::<TransparentRenderPhase>(
+            submit_node_id,
+            sprite_sort_key(sprite.sorting_layer),
+            distance_from_camera,
+        );
     }
 
     fn prepare_view_finalize(
@@ -171,6 +192,9 @@ impl DefaultPrepareJobImpl<RenderJobPrepareContext, RenderJobWriteContext>
                 vertex_buffer
                     .write_to_host_visible_buffer(self.vertex_list.as_slice())
                     .unwrap();
+                prepare_context
+                    .render_stats()
+                    .record_buffer_upload(vertex_buffer_size);
 
                 let vertex_buffer = prepare_context
                     .dyn_resource_lookups
@@ -193,6 +217,9 @@ impl DefaultPrepareJobImpl<RenderJobPrepareContext, RenderJobWriteContext>
                 index_buffer
                     .write_to_host_visible_buffer(self.index_list.as_slice())
                     .unwrap();
+                prepare_context
+                    .render_stats()
+                    .record_buffer_upload(index_buffer_size);
 
                 let index_buffer = prepare_context
                     .dyn_resource_lookups
@@ -63,9 +63,11 @@ impl FeatureCommandWriter<RenderJobWriteContext> for SpriteCommandWriter {
         &self,
         write_context: &mut RenderJobWriteContext,
         _view: &RenderView,
-        _render_phase_index: RenderPhaseIndex,
+        render_phase_index: RenderPhaseIndex,
         index: SubmitNodeId,
     ) {
+        write_context.record_submit_node(render_phase_index);
+
         // //println!("render");
         let logical_device = write_context.device_context.device();
         let command_buffer = write_context.command_buffer;
@@ -90,6 +92,7 @@ impl FeatureCommandWriter<RenderJobWriteContext> for SpriteCommandWriter {
                 0,
                 0,
             );
+            write_context.record_draw_call(draw_call.index_buffer_count as u32 / 3);
 
             // for draw_call in &self.draw_calls {
             //     // Bind per-draw-call data (i.e. texture)
@@ -0,0 +1,250 @@
+use crate::features::text::{
+    ExtractedTextData, TextRenderNodeSet, TextRenderFeature, TextRenderNode, ScreenTextResource,
+};
+use crate::components::TextComponent;
+use crate::assets::font::FontAsset;
+use crate::render_contexts::{RenderJobExtractContext, RenderJobWriteContext, RenderJobPrepareContext};
+use renderer::nodes::{
+    DefaultExtractJobImpl, FramePacket, RenderView, PerViewNode, PrepareJob, DefaultPrepareJob,
+    RenderFeatureIndex, RenderFeature, PerFrameNode, RenderViewIndex,
+};
+use renderer::base::slab::RawSlabKey;
+use crate::features::text::prepare::TextPrepareJobImpl;
+use renderer::vulkan::VkDeviceContext;
+use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetAllocatorRef};
+use atelier_assets::loader::handle::{AssetHandle, Handle};
+use renderer::assets::resources::DescriptorSetArc;
+use legion::prelude::EntityStore;
+use renderer::assets::{MaterialAsset, ImageAsset};
+use minimum::components::TransformComponent;
+use minimum::resources::AssetResource;
+
+pub struct TextExtractJobImpl {
+    device_context: VkDeviceContext,
+    descriptor_set_allocator: DescriptorSetAllocatorRef,
+    pipeline_info: PipelineSwapchainInfo,
+    screen_space_view_index: RenderViewIndex,
+    text_material: Handle<MaterialAsset>,
+    extracted_frame_node_text_data: Vec<Option<ExtractedTextData>>,
+    extracted_screen_text_data: Vec<ExtractedTextData>,
+    per_view_descriptors: Vec<DescriptorSetArc>,
+}
+
+impl TextExtractJobImpl {
+    pub fn new(
+        device_context: VkDeviceContext,
+        descriptor_set_allocator: DescriptorSetAllocatorRef,
+        pipeline_info: PipelineSwapchainInfo,
+        screen_space_view_index: RenderViewIndex,
+        text_material: &Handle<MaterialAsset>,
+    ) -> Self {
+        TextExtractJobImpl {
+            device_context,
+            descriptor_set_allocator,
+            pipeline_info,
+            screen_space_view_index,
+            text_material: text_material.clone(),
+            extracted_frame_node_text_data: Default::default(),
+            extracted_screen_text_data: Default::default(),
+            per_view_descriptors: Default::default(),
+        }
+    }
+
+    // Returns None if the atlas is not loaded yet
+    fn create_atlas_descriptor_set(
+        &mut self,
+        extract_context: &RenderJobExtractContext,
+        atlas: &Handle<ImageAsset>,
+    ) -> Option<DescriptorSetArc> {
+        let image_info = extract_context.resource_manager.get_image_info(atlas)?;
+
+        let descriptor_set_info =
+            extract_context
+                .resource_manager
+                .get_descriptor_set_info(&self.text_material, 0, 1);
+        let mut atlas_descriptor = self
+            .descriptor_set_allocator
+            .create_dyn_descriptor_set_uninitialized(&descriptor_set_info.descriptor_set_layout)
+            .unwrap();
+        extract_context.render_stats().record_descriptor_set_allocated();
+
+        atlas_descriptor.set_image(0, image_info.image_view);
+        atlas_descriptor
+            .flush(&mut self.descriptor_set_allocator)
+            .unwrap();
+        Some(atlas_descriptor.descriptor_set().clone())
+    }
+}
+
+impl<'a>
+    DefaultExtractJobImpl<
+        RenderJobExtractContext<'a>,
+        RenderJobPrepareContext,
+        RenderJobWriteContext,
+    > for TextExtractJobImpl
+{
+    fn extract_begin(
+        &mut self,
+        extract_context: &RenderJobExtractContext<'a>,
+        frame_packet: &FramePacket,
+        _views: &[&RenderView],
+    ) {
+        self.extracted_frame_node_text_data
+            .reserve(frame_packet.frame_node_count(self.feature_index()) as usize);
+
+        // Screen-space text isn't associated with render nodes, so it is all extracted here
+        let screen_texts = extract_context
+            .resources
+            .get_mut::<ScreenTextResource>()
+            .unwrap()
+            .take_texts();
+
+        let asset_resource = extract_context.resources.get::<AssetResource>().unwrap();
+        for screen_text in screen_texts {
+            let font: Option<&FontAsset> = screen_text.font.asset(asset_resource.storage());
+            let font = match font {
+                Some(font) => font,
+                None => continue,
+            };
+
+            let atlas_descriptor_set =
+                match self.create_atlas_descriptor_set(extract_context, &font.atlas) {
+                    Some(atlas_descriptor_set) => atlas_descriptor_set,
+                    None => continue,
+                };
+
+            self.extracted_screen_text_data.push(ExtractedTextData {
+                world_transform: glam::Mat4::from_translation(screen_text.position.extend(0.0)),
+                billboard: false,
+                glyphs: font.layout_text(
+                    &screen_text.text,
+                    screen_text.size,
+                    screen_text.alignment,
+                ),
+                color: screen_text.color,
+                atlas_descriptor_set,
+            });
+        }
+    }
+
+    fn extract_frame_node(
+        &mut self,
+        extract_context: &RenderJobExtractContext<'a>,
+        frame_node: PerFrameNode,
+        _frame_node_index: u32,
+    ) {
+        let render_node_index = frame_node.render_node_index();
+        let render_node_handle = RawSlabKey::<TextRenderNode>::new(render_node_index);
+
+        let text_nodes = extract_context
+            .resources
+            .get::<TextRenderNodeSet>()
+            .unwrap();
+        let text_render_node = text_nodes.texts.get(render_node_handle).unwrap();
+
+        let transform_component = extract_context
+            .world
+            .get_component::<TransformComponent>(text_render_node.entity)
+            .unwrap();
+        let text_component = extract_context
+            .world
+            .get_component::<TextComponent>(text_render_node.entity)
+            .unwrap();
+
+        let asset_resource = extract_context.resources.get::<AssetResource>().unwrap();
+        let font: Option<&FontAsset> = text_component
+            .font
+            .as_ref()
+            .and_then(|font| font.asset(asset_resource.storage()));
+        let font = match font {
+            Some(font) => font,
+            None => {
+                self.extracted_frame_node_text_data.push(None);
+                return;
+            }
+        };
+
+        let atlas_descriptor_set = match self.create_atlas_descriptor_set(extract_context, &font.atlas) {
+            Some(atlas_descriptor_set) => atlas_descriptor_set,
+            None => {
+                self.extracted_frame_node_text_data.push(None);
+                return;
+            }
+        };
+
+        self.extracted_frame_node_text_data
+            .push(Some(ExtractedTextData {
+                world_transform: transform_component.transform(),
+                billboard: text_component.billboard,
+                glyphs: font.layout_text(
+                    &text_component.text,
+                    text_component.size,
+                    text_component.alignment,
+                ),
+                color: text_component.color,
+                atlas_descriptor_set,
+            }));
+    }
+
+    fn extract_view_node(
+        &mut self,
+        _extract_context: &RenderJobExtractContext<'a>,
+        _view: &RenderView,
+        _view_node: PerViewNode,
+        _view_node_index: u32,
+    ) {
+    }
+
+    fn extract_view_finalize(
+        &mut self,
+        extract_context: &RenderJobExtractContext<'a>,
+        view: &RenderView,
+    ) {
+        let layout =
+            extract_context
+                .resource_manager
+                .get_descriptor_set_info(&self.text_material, 0, 0);
+        let mut descriptor_set = self
+            .descriptor_set_allocator
+            .create_dyn_descriptor_set_uninitialized(&layout.descriptor_set_layout)
+            .unwrap();
+        extract_context.render_stats().record_descriptor_set_allocated();
+
+        let view_proj = view.projection_matrix() * view.view_matrix();
+
+        descriptor_set.set_buffer_data(0, &view_proj);
+        extract_context
+            .render_stats()
+            .record_buffer_upload(std::mem::size_of_val(&view_proj) as u64);
+        descriptor_set
+            .flush(&mut self.descriptor_set_allocator)
+            .unwrap();
+
+        self.per_view_descriptors
+            .push(descriptor_set.descriptor_set().clone());
+    }
+
+    fn extract_frame_finalize(
+        self,
+        _extract_context: &RenderJobExtractContext<'a>,
+    ) -> Box<dyn PrepareJob<RenderJobPrepareContext, RenderJobWriteContext>> {
+        let prepare_impl = TextPrepareJobImpl::new(
+            self.device_context,
+            self.pipeline_info,
+            self.screen_space_view_index,
+            self.per_view_descriptors,
+            self.extracted_frame_node_text_data,
+            self.extracted_screen_text_data,
+        );
+
+        Box::new(DefaultPrepareJob::new(prepare_impl))
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        TextRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        TextRenderFeature::feature_index()
+    }
+}
@@ -0,0 +1,157 @@
+use renderer::nodes::{
+    RenderFeature, RenderFeatureIndex, DefaultExtractJob, GenericRenderNodeHandle,
+    RenderNodeSet, RenderNodeCount, RenderViewIndex,
+};
+use std::sync::atomic::{Ordering, AtomicI32};
+use crate::render_jobs::RenderExtractJob;
+use legion::prelude::Entity;
+use renderer::base::slab::{RawSlabKey, RawSlab};
+use std::convert::TryInto;
+use atelier_assets::loader::handle::Handle;
+use renderer::assets::MaterialAsset;
+use crate::assets::font::TextLayoutGlyph;
+use crate::features::sprite::sprite_sort_key;
+
+mod extract;
+use extract::TextExtractJobImpl;
+
+mod prepare;
+
+mod write;
+use write::TextCommandWriter;
+
+mod screen_text;
+pub use screen_text::ScreenText;
+pub use screen_text::ScreenTextResource;
+
+use renderer::vulkan::VkDeviceContext;
+use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetArc, DescriptorSetAllocatorRef};
+
+/// Vertex format for vertices sent to the GPU. Same layout as sprites, the texture is a signed
+/// distance field
+#[derive(Clone, Debug, Copy)]
+#[repr(C)]
+pub struct TextVertex {
+    pub pos: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// Draw order of a glyph quad's corners: bottom-left, bottom-right, top-right, top-left
+const GLYPH_INDEX_LIST: [u32; 6] = [0, 1, 2, 2, 3, 0];
+
+// TextComponents are drawn in every view except the one with screen_space_view_index. Text added
+// to the ScreenTextResource is only drawn in that view.
+pub fn create_text_extract_job<'a>(
+    device_context: VkDeviceContext,
+    descriptor_set_allocator: DescriptorSetAllocatorRef,
+    pipeline_info: PipelineSwapchainInfo,
+    screen_space_view_index: RenderViewIndex,
+    text_material: &Handle<MaterialAsset>,
+) -> RenderExtractJob<'a> {
+    Box::new(DefaultExtractJob::new(TextExtractJobImpl::new(
+        device_context,
+        descriptor_set_allocator,
+        pipeline_info,
+        screen_space_view_index,
+        text_material,
+    )))
+}
+
+//
+// This is boiler-platish
+//
+pub struct TextRenderNode {
+    pub entity: Entity,
+}
+
+#[derive(Copy, Clone)]
+pub struct TextRenderNodeHandle(pub RawSlabKey<TextRenderNode>);
+
+impl Into<GenericRenderNodeHandle> for TextRenderNodeHandle {
+    fn into(self) -> GenericRenderNodeHandle {
+        GenericRenderNodeHandle::new(
+            <TextRenderFeature as RenderFeature>::feature_index(),
+            self.0.index(),
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct TextRenderNodeSet {
+    texts: RawSlab<TextRenderNode>,
+}
+
+impl TextRenderNodeSet {
+    pub fn register_text(
+        &mut self,
+        node: TextRenderNode,
+    ) -> TextRenderNodeHandle {
+        TextRenderNodeHandle(self.texts.allocate(node))
+    }
+
+    pub fn unregister_text(
+        &mut self,
+        handle: TextRenderNodeHandle,
+    ) {
+        self.texts.free(handle.0);
+    }
+}
+
+impl RenderNodeSet for TextRenderNodeSet {
+    fn feature_index(&self) -> RenderFeatureIndex {
+        TextRenderFeature::feature_index()
+    }
+
+    fn max_render_node_count(&self) -> RenderNodeCount {
+        self.texts.storage_size() as RenderNodeCount
+    }
+}
+
+//
+// This is boilerplate that could be macro'd
+//
+static TEXT_FEATURE_INDEX: AtomicI32 = AtomicI32::new(-1);
+
+pub struct TextRenderFeature;
+
+impl RenderFeature for TextRenderFeature {
+    fn set_feature_index(index: RenderFeatureIndex) {
+        TEXT_FEATURE_INDEX.store(index.try_into().unwrap(), Ordering::Release);
+    }
+
+    fn feature_index() -> RenderFeatureIndex {
+        TEXT_FEATURE_INDEX.load(Ordering::Acquire) as RenderFeatureIndex
+    }
+
+    fn feature_debug_name() -> &'static str {
+        "TextRenderFeature"
+    }
+}
+
+#[derive(Debug)]
+pub(self) struct ExtractedTextData {
+    world_transform: glam::Mat4,
+    billboard: bool,
+    // Laid out in the XY plane of world_transform
+    glyphs: Vec<TextLayoutGlyph>,
+    color: glam::Vec4,
+    atlas_descriptor_set: DescriptorSetArc,
+}
+
+#[derive(Debug)]
+pub struct TextDrawCall {
+    index_buffer_first_element: u32,
+    index_buffer_count: u32,
+    atlas_descriptor_set: DescriptorSetArc,
+}
+
+// World-space text sorts with sprites on the default sorting layer. Screen-space text is drawn
+// over everything else in the screen-space view
+fn world_text_sort_key() -> u32 {
+    sprite_sort_key(0)
+}
+
+fn screen_text_sort_key() -> u32 {
+    std::u32::MAX
+}
@@ -0,0 +1,297 @@
+use crate::phases::TransparentRenderPhase;
+use renderer::nodes::{
+    RenderView, ViewSubmitNodes, FeatureSubmitNodes, FeatureCommandWriter, RenderFeatureIndex,
+    FramePacket, DefaultPrepareJobImpl, PerFrameNode, PerViewNode, RenderFeature, RenderViewIndex,
+};
+use crate::features::text::{
+    TextRenderFeature, ExtractedTextData, TextDrawCall, TextVertex, GLYPH_INDEX_LIST,
+    world_text_sort_key, screen_text_sort_key,
+};
+use glam::Vec3;
+use super::TextCommandWriter;
+use crate::render_contexts::{RenderJobWriteContext, RenderJobPrepareContext};
+use renderer::vulkan::{VkBuffer, VkDeviceContext};
+use ash::vk;
+use renderer::assets::resources::{PipelineSwapchainInfo, DescriptorSetArc};
+
+pub struct TextPrepareJobImpl {
+    device_context: VkDeviceContext,
+    pipeline_info: PipelineSwapchainInfo,
+    screen_space_view_index: RenderViewIndex,
+    descriptor_set_per_view: Vec<DescriptorSetArc>,
+    extracted_frame_node_text_data: Vec<Option<ExtractedTextData>>,
+    extracted_screen_text_data: Vec<ExtractedTextData>,
+
+    draw_calls: Vec<TextDrawCall>,
+    vertex_list: Vec<TextVertex>,
+    index_list: Vec<u32>,
+}
+
+impl TextPrepareJobImpl {
+    pub(super) fn new(
+        device_context: VkDeviceContext,
+        pipeline_info: PipelineSwapchainInfo,
+        screen_space_view_index: RenderViewIndex,
+        descriptor_set_per_view: Vec<DescriptorSetArc>,
+        extracted_frame_node_text_data: Vec<Option<ExtractedTextData>>,
+        extracted_screen_text_data: Vec<ExtractedTextData>,
+    ) -> Self {
+        let text_count = extracted_frame_node_text_data.len() + extracted_screen_text_data.len();
+        TextPrepareJobImpl {
+            device_context,
+            pipeline_info,
+            screen_space_view_index,
+            descriptor_set_per_view,
+            extracted_frame_node_text_data,
+            extracted_screen_text_data,
+            draw_calls: Vec::with_capacity(text_count),
+            vertex_list: Default::default(),
+            index_list: Default::default(),
+        }
+    }
+
+    // Appends the glyph quads of the text oriented for the given view and returns the index of
+    // the draw call
+    fn add_text_draw_call(
+        draw_calls: &mut Vec<TextDrawCall>,
+        vertex_list: &mut Vec<TextVertex>,
+        index_list: &mut Vec<u32>,
+        text: &ExtractedTextData,
+        view: &RenderView,
+    ) -> u32 {
+        // Billboards are oriented by the camera, other text lies in the XY plane of its transform
+        let position = text.world_transform.w_axis().truncate();
+        let (axis_x, axis_y) = if text.billboard {
+            let view_matrix = view.view_matrix();
+            let camera_right = Vec3::new(
+                view_matrix.x_axis().x(),
+                view_matrix.y_axis().x(),
+                view_matrix.z_axis().x(),
+            );
+            let camera_up = Vec3::new(
+                view_matrix.x_axis().y(),
+                view_matrix.y_axis().y(),
+                view_matrix.z_axis().y(),
+            );
+
+            let scale_x = text.world_transform.x_axis().truncate().length();
+            let scale_y = text.world_transform.y_axis().truncate().length();
+            (camera_right * scale_x, camera_up * scale_y)
+        } else {
+            (
+                text.world_transform.x_axis().truncate(),
+                text.world_transform.y_axis().truncate(),
+            )
+        };
+
+        let index_buffer_first_element = index_list.len() as u32;
+        let color: [f32; 4] = text.color.into();
+        for glyph in &text.glyphs {
+            let vertex_buffer_first_element = vertex_list.len() as u32;
+            let corners = [
+                (glyph.min.x(), glyph.min.y(), glyph.uv_min.x(), glyph.uv_max.y()),
+                (glyph.max.x(), glyph.min.y(), glyph.uv_max.x(), glyph.uv_max.y()),
+                (glyph.max.x(), glyph.max.y(), glyph.uv_max.x(), glyph.uv_min.y()),
+                (glyph.min.x(), glyph.max.y(), glyph.uv_min.x(), glyph.uv_min.y()),
+            ];
+
+            for (x, y, u, v) in &corners {
+                let pos = position + axis_x * *x + axis_y * *y;
+                vertex_list.push(TextVertex {
+                    pos: pos.into(),
+                    tex_coord: [*u, *v],
+                    color,
+                });
+            }
+
+            for index in &GLYPH_INDEX_LIST {
+                index_list.push(*index + vertex_buffer_first_element);
+            }
+        }
+
+        let submit_node_id = draw_calls.len() as u32;
+        draw_calls.push(TextDrawCall {
+            index_buffer_first_element,
+            index_buffer_count: index_list.len() as u32 - index_buffer_first_element,
+            atlas_descriptor_set: text.atlas_descriptor_set.clone(),
+        });
+
+        submit_node_id
+    }
+}
+
+impl DefaultPrepareJobImpl<RenderJobPrepareContext, RenderJobWriteContext>
+    for TextPrepareJobImpl
+{
+    fn prepare_begin(
+        &mut self,
+        _prepare_context: &RenderJobPrepareContext,
+        _frame_packet: &FramePacket,
+        _views: &[&RenderView],
+        _submit_nodes: &mut FeatureSubmitNodes,
+    ) {
+    }
+
+    fn prepare_frame_node(
+        &mut self,
+        _prepare_context: &RenderJobPrepareContext,
+        _frame_node: PerFrameNode,
+        _frame_node_index: u32,
+        _submit_nodes: &mut FeatureSubmitNodes,
+    ) {
+    }
+
+    fn prepare_view_node(
+        &mut self,
+        _prepare_context: &RenderJobPrepareContext,
+        view: &RenderView,
+        view_node: PerViewNode,
+        _view_node_index: u32,
+        submit_nodes: &mut ViewSubmitNodes,
+    ) {
+        // TextComponents are never drawn in the screen-space view
+        if view.view_index() == self.screen_space_view_index {
+            return;
+        }
+
+        let frame_node_index = view_node.frame_node_index();
+        let text = match &self.extracted_frame_node_text_data[frame_node_index as usize] {
+            Some(text) => text,
+            None => return,
+        };
+
+        if text.glyphs.is_empty() {
+            return;
+        }
+
+        let submit_node_id = Self::add_text_draw_call(
+            &mut self.draw_calls,
+            &mut self.vertex_list,
+            &mut self.index_list,
+            text,
+            view,
+        );
+
+        // Text is always alpha blended and drawn back to front
+        let position = text.world_transform.w_axis().truncate();
+        let distance_from_camera = Vec3::length(position - view.eye_position());
+        submit_nodes.add_submit_node::<TransparentRenderPhase>(
+            submit_node_id,
+            world_text_sort_key(),
+            distance_from_camera,
+        );
+    }
+
+    fn prepare_view_finalize(
+        &mut self,
+        _prepare_context: &RenderJobPrepareContext,
+        view: &RenderView,
+        submit_nodes: &mut ViewSubmitNodes,
+    ) {
+        if view.view_index() != self.screen_space_view_index {
+            return;
+        }
+
+        // Screen-space text is drawn in the order it was added
+        for (index, text) in self.extracted_screen_text_data.iter().enumerate() {
+            if text.glyphs.is_empty() {
+                continue;
+            }
+
+            let submit_node_id = Self::add_text_draw_call(
+                &mut self.draw_calls,
+                &mut self.vertex_list,
+                &mut self.index_list,
+                text,
+                view,
+            );
+
+            // The transparent phase draws larger distances first
+            let distance_from_camera = (self.extracted_screen_text_data.len() - index) as f32;
+            submit_nodes.add_submit_node::<TransparentRenderPhase>(
+                submit_node_id,
+                screen_text_sort_key(),
+                distance_from_camera,
+            );
+        }
+    }
+
+    fn prepare_frame_finalize(
+        self,
+        prepare_context: &RenderJobPrepareContext,
+        _submit_nodes: &mut FeatureSubmitNodes,
+    ) -> Box<dyn FeatureCommandWriter<RenderJobWriteContext>> {
+        let mut vertex_buffer = None;
+        let mut index_buffer = None;
+
+        if self.draw_calls.len() > 0 {
+            vertex_buffer = {
+                let vertex_buffer_size =
+                    self.vertex_list.len() as u64 * std::mem::size_of::<TextVertex>() as u64;
+                let mut vertex_buffer = VkBuffer::new(
+                    &self.device_context,
+                    vk_mem::MemoryUsage::CpuToGpu,
+                    vk::BufferUsageFlags::VERTEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    vertex_buffer_size,
+                )
+                .unwrap();
+
+                vertex_buffer
+                    .write_to_host_visible_buffer(self.vertex_list.as_slice())
+                    .unwrap();
+                prepare_context
+                    .render_stats()
+                    .record_buffer_upload(vertex_buffer_size);
+
+                Some(
+                    prepare_context
+                        .dyn_resource_lookups
+                        .insert_buffer(vertex_buffer),
+                )
+            };
+
+            index_buffer = {
+                let index_buffer_size =
+                    self.index_list.len() as u64 * std::mem::size_of::<u32>() as u64;
+                let mut index_buffer = VkBuffer::new(
+                    &self.device_context,
+                    vk_mem::MemoryUsage::CpuToGpu,
+                    vk::BufferUsageFlags::INDEX_BUFFER,
+                    vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                    index_buffer_size,
+                )
+                .unwrap();
+
+                index_buffer
+                    .write_to_host_visible_buffer(self.index_list.as_slice())
+                    .unwrap();
+                prepare_context
+                    .render_stats()
+                    .record_buffer_upload(index_buffer_size);
+
+                Some(
+                    prepare_context
+                        .dyn_resource_lookups
+                        .insert_buffer(index_buffer),
+                )
+            };
+        }
+
+        Box::new(TextCommandWriter {
+            draw_calls: self.draw_calls,
+            vertex_buffer,
+            index_buffer,
+            pipeline_info: self.pipeline_info,
+            descriptor_set_per_view: self.descriptor_set_per_view,
+        })
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        TextRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        TextRenderFeature::feature_index()
+    }
+}
@@ -0,0 +1,52 @@
+use atelier_assets::loader::handle::Handle;
+use crate::assets::font::{FontAsset, TextAlignment};
+
+/// Text drawn in the screen-space view for a single frame
+pub struct ScreenText {
+    pub font: Handle<FontAsset>,
+    pub text: String,
+    // In screen-space view units, (0, 0) is the center of the screen and +Y is up. The screen is
+    // 600 units tall
+    pub position: glam::Vec2,
+    // Height of a line of text
+    pub size: f32,
+    pub color: glam::Vec4,
+    pub alignment: TextAlignment,
+}
+
+/// Immediate-mode API for HUD text. Text added here is drawn on the next rendered frame and then
+/// cleared, so it must be added every frame it should be visible.
+#[derive(Default)]
+pub struct ScreenTextResource {
+    texts: Vec<ScreenText>,
+}
+
+impl ScreenTextResource {
+    pub fn new() -> Self {
+        ScreenTextResource::default()
+    }
+
+    pub fn add_text(
+        &mut self,
+        font: &Handle<FontAsset>,
+        text: &str,
+        position: glam::Vec2,
+        size: f32,
+        color: glam::Vec4,
+        alignment: TextAlignment,
+    ) {
+        self.texts.push(ScreenText {
+            font: font.clone(),
+            text: text.to_string(),
+            position,
+            size,
+            color,
+            alignment,
+        });
+    }
+
+    // Returns the text added since the last call
+    pub fn take_texts(&mut self) -> Vec<ScreenText> {
+        std::mem::replace(&mut self.texts, vec![])
+    }
+}
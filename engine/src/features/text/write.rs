@@ -0,0 +1,118 @@
+use crate::features::text::{TextRenderFeature, TextDrawCall};
+use renderer::nodes::{
+    RenderFeatureIndex, RenderPhaseIndex, RenderFeature, SubmitNodeId, FeatureCommandWriter, RenderView,
+};
+use crate::render_contexts::RenderJobWriteContext;
+use renderer::vulkan::VkBufferRaw;
+use renderer::assets::resources::{ResourceArc, PipelineSwapchainInfo, DescriptorSetArc};
+use ash::vk;
+use ash::version::DeviceV1_0;
+
+pub struct TextCommandWriter {
+    // None if there is no text this frame, in which case nothing is submitted
+    pub vertex_buffer: Option<ResourceArc<VkBufferRaw>>,
+    pub index_buffer: Option<ResourceArc<VkBufferRaw>>,
+    pub draw_calls: Vec<TextDrawCall>,
+    pub pipeline_info: PipelineSwapchainInfo,
+    pub descriptor_set_per_view: Vec<DescriptorSetArc>,
+}
+
+impl FeatureCommandWriter<RenderJobWriteContext> for TextCommandWriter {
+    fn apply_setup(
+        &self,
+        write_context: &mut RenderJobWriteContext,
+        view: &RenderView,
+        _render_phase_index: RenderPhaseIndex
+    ) {
+        let (vertex_buffer, index_buffer) = match (&self.vertex_buffer, &self.index_buffer) {
+            (Some(vertex_buffer), Some(index_buffer)) => (vertex_buffer, index_buffer),
+            _ => return,
+        };
+
+        let logical_device = write_context.device_context.device();
+        let command_buffer = write_context.command_buffer;
+        unsafe {
+            logical_device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_info.pipeline.get_raw().pipelines[0],
+            );
+
+            // Bind per-pass data (UBO with view/proj matrix, sampler)
+            logical_device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_info.pipeline_layout.get_raw().pipeline_layout,
+                0,
+                &[self.descriptor_set_per_view[view.view_index() as usize].get()],
+                &[],
+            );
+
+            logical_device.cmd_bind_vertex_buffers(
+                command_buffer,
+                0, // first binding
+                &[vertex_buffer.get_raw().buffer],
+                &[0], // offsets
+            );
+
+            logical_device.cmd_bind_index_buffer(
+                command_buffer,
+                index_buffer.get_raw().buffer,
+                0, // offset
+                vk::IndexType::UINT32,
+            );
+        }
+    }
+
+    fn render_element(
+        &self,
+        write_context: &mut RenderJobWriteContext,
+        _view: &RenderView,
+        render_phase_index: RenderPhaseIndex,
+        index: SubmitNodeId,
+    ) {
+        write_context.record_submit_node(render_phase_index);
+
+        let logical_device = write_context.device_context.device();
+        let command_buffer = write_context.command_buffer;
+        let draw_call = &self.draw_calls[index as usize];
+
+        unsafe {
+            // Bind per-draw-call data (the font's atlas)
+            logical_device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                self.pipeline_info.pipeline_layout.get_raw().pipeline_layout,
+                1,
+                &[draw_call.atlas_descriptor_set.get()],
+                &[],
+            );
+
+            logical_device.cmd_draw_indexed(
+                command_buffer,
+                draw_call.index_buffer_count,
+                1,
+                draw_call.index_buffer_first_element,
+                0,
+                0,
+            );
+            write_context.record_draw_call(draw_call.index_buffer_count / 3);
+        }
+    }
+
+    fn revert_setup(
+        &self,
+        _write_context: &mut RenderJobWriteContext,
+        _view: &RenderView,
+        _render_phase_index: RenderPhaseIndex
+    ) {
+    }
+
+    fn feature_debug_name(&self) -> &'static str {
+        TextRenderFeature::feature_debug_name()
+    }
+
+    fn feature_index(&self) -> RenderFeatureIndex {
+        TextRenderFeature::feature_index()
+    }
+}
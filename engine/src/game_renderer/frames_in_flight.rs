@@ -0,0 +1,179 @@
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use renderer::vulkan::MAX_FRAMES_IN_FLIGHT;
+
+/// Frames in flight when nothing else is configured. The main thread simulates and extracts one
+/// frame while the render thread prepares and writes the one before it
+pub const DEFAULT_MAX_FRAMES_IN_FLIGHT: usize = 2;
+
+struct FramesInFlightState {
+    // Limit requested at startup, and the limit actually in use, which can't exceed the number of
+    // swapchain images
+    requested_max_frames_in_flight: usize,
+    max_frames_in_flight: usize,
+    frame_slot_count: usize,
+    frames_in_flight: usize,
+    next_frame_index: u64,
+}
+
+struct FramesInFlightInner {
+    state: Mutex<FramesInFlightState>,
+    frame_finished: Condvar,
+}
+
+/// Limits how many frames can be between extraction on the main thread and presentation on the
+/// render thread. Each frame holds a `FrameInFlightToken` from the time the main thread begins
+/// extracting it until the render thread has submitted it, so the main thread only blocks once
+/// the render thread has fallen the maximum number of frames behind.
+///
+/// Submitted frames can still be running on the GPU. Acquiring a swapchain image waits until the
+/// GPU is at most MAX_FRAMES_IN_FLIGHT frames behind, so there are enough frame slots that a
+/// slot is never written by the main thread (after acquiring) while the GPU may still read it.
+#[derive(Clone)]
+pub struct FramesInFlight {
+    inner: Arc<FramesInFlightInner>,
+}
+
+impl FramesInFlight {
+    pub fn new(max_frames_in_flight: usize) -> Self {
+        let max_frames_in_flight = max_frames_in_flight.max(1);
+        let state = FramesInFlightState {
+            requested_max_frames_in_flight: max_frames_in_flight,
+            max_frames_in_flight,
+            frame_slot_count: max_frames_in_flight.max(MAX_FRAMES_IN_FLIGHT),
+            frames_in_flight: 0,
+            next_frame_index: 0,
+        };
+
+        FramesInFlight {
+            inner: Arc::new(FramesInFlightInner {
+                state: Mutex::new(state),
+                frame_finished: Condvar::new(),
+            }),
+        }
+    }
+
+    pub fn max_frames_in_flight(&self) -> usize {
+        self.inner.state.lock().unwrap().max_frames_in_flight
+    }
+
+    /// Number of per-frame resources that need to be allocated, see
+    /// `FrameInFlightToken::frame_slot`
+    pub fn frame_slot_count(&self) -> usize {
+        self.inner.state.lock().unwrap().frame_slot_count
+    }
+
+    /// Called when the swapchain is created. Returns the limit that will be used
+    pub fn on_swapchain_created(
+        &self,
+        swapchain_image_count: usize,
+    ) -> usize {
+        let mut state = self.inner.state.lock().unwrap();
+        state.max_frames_in_flight = state
+            .requested_max_frames_in_flight
+            .min(swapchain_image_count)
+            .max(1);
+        state.frame_slot_count = state.max_frames_in_flight.max(MAX_FRAMES_IN_FLIGHT);
+        state.max_frames_in_flight
+    }
+
+    /// Blocks until a frame can be started. The returned token must be kept alive until the frame
+    /// has been submitted or abandoned
+    pub fn begin_frame(&self) -> FrameInFlightToken {
+        let wait_start = Instant::now();
+        let mut state = self.inner.state.lock().unwrap();
+        while state.frames_in_flight >= state.max_frames_in_flight {
+            state = self.inner.frame_finished.wait(state).unwrap();
+        }
+
+        // Anything still in flight is being worked on by the render thread while this frame is
+        // extracted
+        let overlapped_frames = state.frames_in_flight as u32;
+        let frame_index = state.next_frame_index;
+        let frame_slot = (frame_index % state.frame_slot_count as u64) as usize;
+
+        state.frames_in_flight += 1;
+        state.next_frame_index += 1;
+
+        FrameInFlightToken {
+            frames_in_flight: self.clone(),
+            frame_index,
+            frame_slot,
+            max_frames_in_flight: state.max_frames_in_flight as u32,
+            overlapped_frames,
+            wait_duration: wait_start.elapsed(),
+            swapchain_image_wait_duration: Duration::default(),
+        }
+    }
+
+    /// Blocks until the render thread has finished with every frame. Must be called before
+    /// anything the render thread uses, such as the swapchain, is destroyed
+    pub fn wait_until_idle(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        while state.frames_in_flight > 0 {
+            state = self.inner.frame_finished.wait(state).unwrap();
+        }
+    }
+
+    fn end_frame(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.frames_in_flight -= 1;
+        self.inner.frame_finished.notify_all();
+    }
+}
+
+/// Marks a frame as in flight until dropped
+pub struct FrameInFlightToken {
+    frames_in_flight: FramesInFlight,
+    frame_index: u64,
+    frame_slot: usize,
+    max_frames_in_flight: u32,
+    overlapped_frames: u32,
+    wait_duration: Duration,
+    swapchain_image_wait_duration: Duration,
+}
+
+impl FrameInFlightToken {
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// Index into per-frame resources. Neither frames in flight nor frames the GPU may still be
+    /// rendering share the slot, once a swapchain image has been acquired for this frame
+    pub fn frame_slot(&self) -> usize {
+        self.frame_slot
+    }
+
+    pub fn max_frames_in_flight(&self) -> u32 {
+        self.max_frames_in_flight
+    }
+
+    /// Earlier frames that the render thread was still working on when this frame began
+    pub fn overlapped_frames(&self) -> u32 {
+        self.overlapped_frames
+    }
+
+    /// How long the main thread was blocked waiting for the render thread to free up a frame
+    pub fn wait_duration(&self) -> Duration {
+        self.wait_duration
+    }
+
+    /// How long the main thread was blocked acquiring a swapchain image. The renderer waits there
+    /// for the GPU to finish older frames, which serializes the main thread with the GPU
+    pub fn swapchain_image_wait_duration(&self) -> Duration {
+        self.swapchain_image_wait_duration
+    }
+
+    pub fn set_swapchain_image_wait_duration(
+        &mut self,
+        swapchain_image_wait_duration: Duration,
+    ) {
+        self.swapchain_image_wait_duration = swapchain_image_wait_duration;
+    }
+}
+
+impl Drop for FrameInFlightToken {
+    fn drop(&mut self) {
+        self.frames_in_flight.end_frame();
+    }
+}
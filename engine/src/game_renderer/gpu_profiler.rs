@@ -0,0 +1,343 @@
+use ash::vk;
+use ash::prelude::VkResult;
+use ash::version::{DeviceV1_0, InstanceV1_0};
+
+use renderer::vulkan::{VkDeviceContext, VkSwapchain, SwapchainInfo, VkQueueFamilyIndices};
+use crate::profiler::GpuScope;
+use std::time::{Duration, Instant};
+
+// Upper bound on timestamps written in a single frame, including the one that starts the frame
+const MAX_GPU_TIMESTAMPS: u32 = 32;
+
+/// Measures how long each renderpass takes on the GPU. Timestamps are written by small command
+/// buffers that are submitted between the renderpasses' command buffers, so no renderpass needs
+/// to know it is being timed. Each interval runs from the previous timestamp until all work
+/// submitted before the next one completes. If the graphics queue doesn't support timestamps,
+/// no scopes are recorded.
+///
+/// GPU timestamps use their own clock. To place GPU frames on the CPU timeline, each frame's first
+/// timestamp is paired with the CPU time its command buffers were submitted, which the GPU can't
+/// have started before. The pair with the latest CPU time relative to its timestamp is the
+/// tightest bound, and it's exact whenever the GPU was idle when a frame was submitted.
+pub struct VkGpuProfiler {
+    pub device_context: VkDeviceContext,
+
+    // False if the graphics queue family has no valid timestamp bits
+    timestamps_supported: bool,
+    // Nanoseconds per timestamp tick, VkPhysicalDeviceLimits::timestampPeriod
+    timestamp_period_ns: f64,
+    // Timestamps only have timestampValidBits valid bits and wrap around past them
+    timestamp_mask: u64,
+
+    // One query pool per present index
+    query_pools: Vec<vk::QueryPool>,
+
+    // Names of the intervals recorded for each present index. The first timestamp starts the
+    // frame and has no name, so timestamp i + 1 ends the interval named scope_names[i]
+    scope_names: Vec<Vec<&'static str>>,
+
+    // True if the timestamps for the present index were submitted and haven't been read back
+    has_pending_results: Vec<bool>,
+
+    // CPU time just before the present index's command buffers were submitted
+    submit_times: Vec<Option<Instant>>,
+    // A CPU time and a GPU timestamp that was written no earlier than it, see above
+    clock_reference: Option<(Instant, u64)>,
+
+    // Command pool and MAX_GPU_TIMESTAMPS command buffers per present index
+    pub command_pool: vk::CommandPool,
+    command_buffers: Vec<Vec<vk::CommandBuffer>>,
+}
+
+impl VkGpuProfiler {
+    pub fn new(
+        device_context: &VkDeviceContext,
+        swapchain: &VkSwapchain,
+    ) -> VkResult<Self> {
+        let logical_device = device_context.device();
+        let image_count = swapchain.swapchain_info.image_count;
+
+        let (timestamp_period, timestamp_valid_bits) = unsafe {
+            let instance = device_context.instance();
+            let physical_device = device_context.physical_device();
+            let properties = instance.get_physical_device_properties(physical_device);
+            let queue_families =
+                instance.get_physical_device_queue_family_properties(physical_device);
+            let graphics_queue_family_index =
+                device_context.queue_family_indices().graphics_queue_family_index as usize;
+
+            (
+                properties.limits.timestamp_period,
+                queue_families[graphics_queue_family_index].timestamp_valid_bits,
+            )
+        };
+
+        let timestamps_supported = timestamp_valid_bits > 0;
+        if !timestamps_supported {
+            log::warn!("The graphics queue doesn't support timestamps, GPU scopes are disabled");
+        }
+
+        let timestamp_mask = if timestamp_valid_bits >= 64 {
+            std::u64::MAX
+        } else {
+            (1u64 << timestamp_valid_bits) - 1
+        };
+
+        let command_pool =
+            Self::create_command_pool(logical_device, &device_context.queue_family_indices())?;
+
+        let command_buffers =
+            Self::create_command_buffers(logical_device, &swapchain.swapchain_info, &command_pool)?;
+
+        let mut query_pools = Vec::with_capacity(image_count);
+        for _ in 0..image_count {
+            let query_pool_create_info = vk::QueryPoolCreateInfo::builder()
+                .query_type(vk::QueryType::TIMESTAMP)
+                .query_count(MAX_GPU_TIMESTAMPS);
+
+            query_pools
+                .push(unsafe { logical_device.create_query_pool(&query_pool_create_info, None)? });
+        }
+
+        Ok(VkGpuProfiler {
+            device_context: device_context.clone(),
+            timestamps_supported,
+            timestamp_period_ns: timestamp_period as f64,
+            timestamp_mask,
+            query_pools,
+            scope_names: vec![vec![]; image_count],
+            has_pending_results: vec![false; image_count],
+            submit_times: vec![None; image_count],
+            clock_reference: None,
+            command_pool,
+            command_buffers,
+        })
+    }
+
+    fn create_command_pool(
+        logical_device: &ash::Device,
+        queue_family_indices: &VkQueueFamilyIndices,
+    ) -> VkResult<vk::CommandPool> {
+        log::trace!(
+            "Creating command pool with queue family index {}",
+            queue_family_indices.graphics_queue_family_index
+        );
+        let pool_create_info = vk::CommandPoolCreateInfo::builder()
+            .flags(
+                vk::CommandPoolCreateFlags::TRANSIENT
+                    | vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+            )
+            .queue_family_index(queue_family_indices.graphics_queue_family_index);
+
+        unsafe { logical_device.create_command_pool(&pool_create_info, None) }
+    }
+
+    fn create_command_buffers(
+        logical_device: &ash::Device,
+        swapchain_info: &SwapchainInfo,
+        command_pool: &vk::CommandPool,
+    ) -> VkResult<Vec<Vec<vk::CommandBuffer>>> {
+        let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_buffer_count(MAX_GPU_TIMESTAMPS)
+            .command_pool(*command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY);
+
+        (0..swapchain_info.image_count)
+            .map(|_| unsafe { logical_device.allocate_command_buffers(&command_buffer_allocate_info) })
+            .collect()
+    }
+
+    fn update_command_buffer(
+        logical_device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        query_pool: vk::QueryPool,
+        query_index: u32,
+    ) -> VkResult<()> {
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder();
+
+        unsafe {
+            logical_device.begin_command_buffer(command_buffer, &command_buffer_begin_info)?;
+
+            // Queries must be reset before they are written again
+            if query_index == 0 {
+                logical_device.cmd_reset_query_pool(
+                    command_buffer,
+                    query_pool,
+                    0,
+                    MAX_GPU_TIMESTAMPS,
+                );
+            }
+
+            logical_device.cmd_write_timestamp(
+                command_buffer,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                query_pool,
+                query_index,
+            );
+
+            logical_device.end_command_buffer(command_buffer)
+        }
+    }
+
+    /// Returns the timings written the last time this present index was rendered. The GPU must
+    /// be finished with the present index, which is the case once its swapchain image has been
+    /// acquired again.
+    /// GPU scopes must not be recorded if this is false
+    pub fn timestamps_supported(&self) -> bool {
+        self.timestamps_supported
+    }
+
+    /// Returns the estimated CPU time the GPU started the frame and the intervals recorded in it
+    pub fn read_results(
+        &mut self,
+        present_index: usize,
+    ) -> VkResult<Option<(Instant, Vec<GpuScope>)>> {
+        if !self.has_pending_results[present_index] {
+            return Ok(None);
+        }
+
+        self.has_pending_results[present_index] = false;
+
+        let scope_names = &self.scope_names[present_index];
+        let timestamp_count = scope_names.len() + 1;
+        let mut timestamps = vec![0u64; timestamp_count];
+        let result = unsafe {
+            self.device_context.device().get_query_pool_results(
+                self.query_pools[present_index],
+                0,
+                timestamp_count as u32,
+                &mut timestamps,
+                vk::QueryResultFlags::TYPE_64,
+            )
+        };
+
+        match result {
+            Ok(_) => {}
+            // Drop the frame rather than stalling on it
+            Err(vk::Result::NOT_READY) => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let timestamp_period_ns = self.timestamp_period_ns;
+        let timestamp_mask = self.timestamp_mask;
+        let to_duration = |from: u64, to: u64| {
+            let ticks = to.wrapping_sub(from) & timestamp_mask;
+            Duration::from_nanos((ticks as f64 * timestamp_period_ns) as u64)
+        };
+
+        let frame_start = timestamps[0];
+
+        let submit_time = match self.submit_times[present_index].take() {
+            Some(submit_time) => submit_time,
+            None => return Ok(None),
+        };
+        let clock_reference = match self.clock_reference {
+            Some((reference_time, reference_timestamp))
+                if reference_time + to_duration(reference_timestamp, frame_start)
+                    >= submit_time =>
+            {
+                (reference_time, reference_timestamp)
+            }
+            // The GPU started this frame no earlier than it was submitted, so the old reference
+            // underestimated when GPU work happens
+            _ => (submit_time, frame_start),
+        };
+        self.clock_reference = Some(clock_reference);
+        let gpu_start = clock_reference.0 + to_duration(clock_reference.1, frame_start);
+
+        let gpu_scopes = scope_names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| GpuScope {
+                name: *name,
+                start: to_duration(frame_start, timestamps[i]),
+                duration: to_duration(timestamps[i], timestamps[i + 1]),
+            })
+            .collect();
+
+        Ok(Some((gpu_start, gpu_scopes)))
+    }
+
+    /// Returns a command buffer that resets the present index's queries and writes the starting
+    /// timestamp. Must be submitted before any command buffer returned by `end_scope`
+    pub fn begin_frame(
+        &mut self,
+        present_index: usize,
+    ) -> VkResult<vk::CommandBuffer> {
+        self.scope_names[present_index].clear();
+        self.has_pending_results[present_index] = true;
+        self.submit_times[present_index] = None;
+
+        let command_buffer = self.command_buffers[present_index][0];
+        Self::update_command_buffer(
+            self.device_context.device(),
+            command_buffer,
+            self.query_pools[present_index],
+            0,
+        )?;
+
+        Ok(command_buffer)
+    }
+
+    /// Returns a command buffer that writes a timestamp ending the named interval, or None if
+    /// this frame has run out of queries
+    pub fn end_scope(
+        &mut self,
+        present_index: usize,
+        name: &'static str,
+    ) -> VkResult<Option<vk::CommandBuffer>> {
+        let query_index = self.scope_names[present_index].len() as u32 + 1;
+        if query_index >= MAX_GPU_TIMESTAMPS {
+            return Ok(None);
+        }
+
+        self.scope_names[present_index].push(name);
+
+        let command_buffer = self.command_buffers[present_index][query_index as usize];
+        Self::update_command_buffer(
+            self.device_context.device(),
+            command_buffer,
+            self.query_pools[present_index],
+            query_index,
+        )?;
+
+        Ok(Some(command_buffer))
+    }
+
+    /// Call once every command buffer for the present index has been recorded, just before they are
+    /// submitted
+    pub fn end_frame(
+        &mut self,
+        present_index: usize,
+    ) {
+        if self.has_pending_results[present_index] {
+            self.submit_times[present_index] = Some(Instant::now());
+        }
+    }
+
+    /// Call if the command buffers for the present index were not submitted, so that stale
+    /// queries aren't read back
+    pub fn cancel_frame(
+        &mut self,
+        present_index: usize,
+    ) {
+        self.has_pending_results[present_index] = false;
+        self.submit_times[present_index] = None;
+    }
+}
+
+impl Drop for VkGpuProfiler {
+    fn drop(&mut self) {
+        log::trace!("destroying VkGpuProfiler");
+
+        unsafe {
+            let device = self.device_context.device();
+            for query_pool in &self.query_pools {
+                device.destroy_query_pool(*query_pool, None);
+            }
+            device.destroy_command_pool(self.command_pool, None);
+        }
+
+        log::trace!("destroyed VkGpuProfiler");
+    }
+}
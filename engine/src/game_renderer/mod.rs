@@ -3,21 +3,43 @@ use renderer::vulkan::{VkSurface, Window, VkDeviceContext, VkContext, FrameInFli
 use ash::prelude::VkResult;
 use std::mem::ManuallyDrop;
 use ash::vk;
-use minimum::resources::{AssetResource, TimeResource, ViewportResource};
+use minimum::resources::{AssetResource, TimeResource, ViewportResource, InputResource};
+use minimum::resources::editor::EditorSelectionResource;
 use renderer::assets::resources::{ResourceManager, ResourceArc, ImageViewResource};
-use crate::features::debug3d::create_debug3d_extract_job;
-use crate::features::sprite::{SpriteRenderNodeSet, create_sprite_extract_job};
+use crate::features::debug3d::{
+    create_debug3d_extract_job, DEBUG_LINES_PASS_INDEX, DEBUG_TRIANGLES_PASS_INDEX,
+};
+use crate::features::sprite::{SpriteRenderNodeSet, SpriteRenderFeature, create_sprite_extract_job};
+use crate::features::text::{TextRenderNodeSet, create_text_extract_job};
+use crate::features::particle::{
+    ParticleRenderNodeSet, create_particle_extract_job, PARTICLE_ALPHA_BLEND_PASS_INDEX,
+    PARTICLE_ADDITIVE_BLEND_PASS_INDEX,
+};
 use renderer::visibility::{StaticVisibilityNodeSet, DynamicVisibilityNodeSet};
 use renderer::nodes::{
     RenderPhaseMaskBuilder, RenderPhaseMask, RenderRegistry, RenderViewSet, AllRenderNodes,
-    FramePacketBuilder, ExtractJobSet,
+    FramePacketBuilder, RenderFeature,
 };
-use crate::phases::{OpaqueRenderPhase, UiRenderPhase, PreUiRenderPhase};
-use crate::phases::TransparentRenderPhase;
+use crate::phases::{OpaqueRenderPhase, UiRenderPhase, PreUiRenderPhase, DepthPrepassRenderPhase};
+use crate::phases::{TransparentRenderPhase, SelectionOutlineRenderPhase, PickingRenderPhase};
 use legion::prelude::*;
 use crate::render_contexts::{RenderJobExtractContext};
-use crate::features::mesh::{create_mesh_extract_job, MeshRenderNodeSet};
+use crate::render_jobs::ParallelExtractJobSet;
+use crate::features::mesh::{
+    create_mesh_extract_job, MeshRenderNodeSet, StaticMeshSet, MESH_OPAQUE_PASS_INDEX,
+    MESH_DEPTH_PREPASS_PASS_INDEX, MESH_OPAQUE_DEPTH_EQUAL_PASS_INDEX, MESH_WIREFRAME_PASS_INDEX,
+    MeshDebugViewMode, MeshCullShaderParam,
+};
+use crate::game_resource_manager::GameResourceManager;
+use crate::systems::{EditorCameraResource, EditorViewportResource};
+use crate::renderpass::{SceneView, MAX_STATIC_MESH_CULL_VIEWS};
+use crate::profiler::Profiler;
+use crate::render_stats::{
+    RenderStats, RenderStatsCounters, RenderStatsResource, ViewRenderStats,
+    render_node_feature_names,
+};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 mod static_resources;
 use static_resources::GameRendererStaticResources;
@@ -26,40 +48,95 @@ mod render_thread;
 use render_thread::RenderThread;
 
 mod swapchain_resources;
-use swapchain_resources::SwapchainResources;
+use swapchain_resources::{SwapchainResources, SwapchainExtractResources};
+
+mod frames_in_flight;
+pub use frames_in_flight::{FramesInFlight, FrameInFlightToken, DEFAULT_MAX_FRAMES_IN_FLIGHT};
+
+mod render_scale;
+pub use render_scale::{RenderScaleMode, MIN_RENDER_SCALE, MAX_RENDER_SCALE};
+use render_scale::RenderScaleController;
 
 mod render_frame_job;
 use render_frame_job::RenderFrameJob;
 
+mod screenshot;
+pub use screenshot::{ScreenshotRequest, FrameSequenceCapture};
+
+mod gpu_profiler;
+
 //TODO: Find a way to not expose this
 mod swapchain_handling;
 pub use swapchain_handling::SwapchainLifetimeListener;
 use ash::version::DeviceV1_0;
 use crate::features::imgui::create_imgui_extract_job;
+use crate::features::selection_outline::{
+    create_selection_outline_extract_job, SELECTION_OUTLINE_MASK_PASS_INDEX,
+};
+use crate::features::picking::{create_picking_extract_job, create_picking_request, PickingResult};
 
 pub struct GameRendererInner {
     #[cfg(feature = "use_imgui")]
     imgui_font_atlas_image_view: ResourceArc<ImageViewResource>,
 
     static_resources: GameRendererStaticResources,
-    swapchain_resources: Option<SwapchainResources>,
+    swapchain_extract_resources: Option<SwapchainExtractResources>,
 
     main_camera_render_phase_mask: RenderPhaseMask,
+    screen_space_render_phase_mask: RenderPhaseMask,
+
+    // Lay down depth for opaque meshes first so that the opaque pass only shades visible fragments
+    depth_prepass_enabled: bool,
+
+    // Tint meshes by the LOD they are drawn with
+    mesh_lod_debug_view_enabled: bool,
+
+    // Replaces shaded mesh colors with a single lighting input, or draws a wireframe over them
+    mesh_debug_view_mode: MeshDebugViewMode,
+
+    // Outline meshes of selected and hovered entities in every scene view
+    selection_outline_enabled: bool,
+
+    // Fraction of the output resolution that the 3D scene is drawn at. The scene targets are
+    // recreated at the start of the next frame if the mode is changed
+    render_scale: RenderScaleController,
+    render_scale_mode_changed: bool,
 
     previous_frame_result: Option<VkResult<()>>,
 
+    // Screenshot to take on the next rendered frame, and an optional ongoing image sequence capture
+    pending_screenshot: Option<ScreenshotRequest>,
+    frame_sequence_capture: Option<FrameSequenceCapture>,
+
     render_thread: RenderThread,
 }
 
+// The main thread locks inner while it extracts a frame and the render thread locks
+// swapchain_resources while it writes one, so extracting frame N + 1 doesn't wait for frame N to
+// be written
 #[derive(Clone)]
 pub struct GameRenderer {
     inner: Arc<Mutex<GameRendererInner>>,
+    swapchain_resources: Arc<Mutex<Option<SwapchainResources>>>,
+
+    // Counters for the most recently rendered frame
+    previous_frame_render_stats: Arc<Mutex<RenderStats>>,
+
+    // The mesh under the cursor in the most recently read back frame
+    picking_result: Arc<Mutex<Option<PickingResult>>>,
+
+    // Used by the next extracted frame's picking request, incremented every frame even if there
+    // is no request so that an ID is never used for a later cursor position
+    next_picking_request_id: Arc<AtomicU64>,
+
+    frames_in_flight: FramesInFlight,
 }
 
 impl GameRenderer {
     pub fn new(
         _window: &dyn Window,
         resources: &Resources,
+        max_frames_in_flight: usize,
     ) -> VkResult<Self> {
         let game_renderer_resources =
             GameRendererStaticResources::new(resources)?;
@@ -83,29 +160,53 @@ impl GameRenderer {
         )?;
 
         let main_camera_render_phase_mask = RenderPhaseMaskBuilder::default()
+            .add_render_phase::<DepthPrepassRenderPhase>()
             .add_render_phase::<OpaqueRenderPhase>()
             .add_render_phase::<TransparentRenderPhase>()
             .add_render_phase::<PreUiRenderPhase>()
+            .add_render_phase::<SelectionOutlineRenderPhase>()
+            .add_render_phase::<PickingRenderPhase>()
             .add_render_phase::<UiRenderPhase>()
             .build();
 
-        let render_thread = RenderThread::start();
+        // Screen-space sprites are blended over the scene after the main view's transparent phase
+        let screen_space_render_phase_mask = RenderPhaseMaskBuilder::default()
+            .add_render_phase::<TransparentRenderPhase>()
+            .build();
+
+        let render_thread = RenderThread::start(max_frames_in_flight);
 
         let renderer = GameRendererInner {
             #[cfg(feature = "use_imgui")]
             imgui_font_atlas_image_view,
             static_resources: game_renderer_resources,
-            swapchain_resources: None,
+            swapchain_extract_resources: None,
 
             main_camera_render_phase_mask,
+            screen_space_render_phase_mask,
+
+            depth_prepass_enabled: true,
+            mesh_lod_debug_view_enabled: false,
+            mesh_debug_view_mode: MeshDebugViewMode::None,
+            selection_outline_enabled: true,
+            render_scale: RenderScaleController::new(RenderScaleMode::default()),
+            render_scale_mode_changed: false,
 
             render_thread,
 
             previous_frame_result: Some(Ok(())),
+
+            pending_screenshot: None,
+            frame_sequence_capture: None,
         };
 
         Ok(GameRenderer {
             inner: Arc::new(Mutex::new(renderer)),
+            swapchain_resources: Arc::new(Mutex::new(None)),
+            previous_frame_render_stats: Default::default(),
+            picking_result: Default::default(),
+            next_picking_request_id: Default::default(),
+            frames_in_flight: FramesInFlight::new(max_frames_in_flight),
         })
     }
 
@@ -173,6 +274,151 @@ impl GameRenderer {
 
         Ok(imgui_font_atlas_image_view)
     }
+
+    pub fn previous_frame_draw_call_count(&self) -> u32 {
+        self.previous_frame_render_stats.lock().unwrap().draw_calls
+    }
+
+    pub fn previous_frame_render_stats(&self) -> RenderStats {
+        self.previous_frame_render_stats.lock().unwrap().clone()
+    }
+
+    /// The mesh that was under the cursor in the most recently completed frame, see
+    /// `VkPickingRenderPass`
+    pub fn picking_result(&self) -> Option<PickingResult> {
+        *self.picking_result.lock().unwrap()
+    }
+
+    /// The ID the next rendered frame gives its picking request, compare with
+    /// `PickingResult::request_id` to find the result for the current cursor position
+    pub fn next_picking_request_id(&self) -> u64 {
+        self.next_picking_request_id.load(Ordering::Acquire)
+    }
+
+    pub fn max_frames_in_flight(&self) -> usize {
+        self.frames_in_flight.max_frames_in_flight()
+    }
+
+    /// Blocks until the render thread has finished every frame it was given
+    pub fn wait_for_frames_in_flight(&self) {
+        self.frames_in_flight.wait_until_idle();
+    }
+
+    pub fn depth_prepass_enabled(&self) -> bool {
+        self.inner.lock().unwrap().depth_prepass_enabled
+    }
+
+    pub fn set_depth_prepass_enabled(
+        &self,
+        depth_prepass_enabled: bool,
+    ) {
+        self.inner.lock().unwrap().depth_prepass_enabled = depth_prepass_enabled;
+    }
+
+    pub fn mesh_lod_debug_view_enabled(&self) -> bool {
+        self.inner.lock().unwrap().mesh_lod_debug_view_enabled
+    }
+
+    pub fn set_mesh_lod_debug_view_enabled(
+        &self,
+        mesh_lod_debug_view_enabled: bool,
+    ) {
+        self.inner.lock().unwrap().mesh_lod_debug_view_enabled = mesh_lod_debug_view_enabled;
+    }
+
+    pub fn mesh_debug_view_mode(&self) -> MeshDebugViewMode {
+        self.inner.lock().unwrap().mesh_debug_view_mode
+    }
+
+    pub fn set_mesh_debug_view_mode(
+        &self,
+        mesh_debug_view_mode: MeshDebugViewMode,
+    ) {
+        self.inner.lock().unwrap().mesh_debug_view_mode = mesh_debug_view_mode;
+    }
+
+    pub fn selection_outline_enabled(&self) -> bool {
+        self.inner.lock().unwrap().selection_outline_enabled
+    }
+
+    pub fn set_selection_outline_enabled(
+        &self,
+        selection_outline_enabled: bool,
+    ) {
+        self.inner.lock().unwrap().selection_outline_enabled = selection_outline_enabled;
+    }
+
+    pub fn render_scale_mode(&self) -> RenderScaleMode {
+        self.inner.lock().unwrap().render_scale.mode()
+    }
+
+    pub fn set_render_scale_mode(
+        &self,
+        render_scale_mode: RenderScaleMode,
+    ) {
+        let mut guard = self.inner.lock().unwrap();
+        if guard.render_scale.mode() != render_scale_mode {
+            log::info!("Render scale mode: {:?}", render_scale_mode);
+            guard.render_scale.set_mode(render_scale_mode);
+            guard.render_scale_mode_changed = true;
+        }
+    }
+
+    /// The fraction of the output resolution that the scene is currently drawn at
+    pub fn render_scale(&self) -> f32 {
+        self.inner.lock().unwrap().render_scale.render_scale()
+    }
+
+    /// Writes the next presented frame to the given path as a PNG once it completes. If
+    /// `include_ui` is false, the image is captured after bloom but before the UI is drawn.
+    pub fn request_screenshot<P: Into<std::path::PathBuf>>(
+        &self,
+        path: P,
+        include_ui: bool,
+    ) {
+        let path = path.into();
+        log::info!("Screenshot requested: {:?}", path);
+        self.inner.lock().unwrap().pending_screenshot = Some(ScreenshotRequest { path, include_ui });
+    }
+
+    /// Writes every rendered frame to `directory` as frame_000000.png, frame_000001.png, etc. The
+    /// simulation will advance by `fixed_timestep` per frame until the capture is stopped.
+    pub fn begin_frame_sequence_capture<P: Into<std::path::PathBuf>>(
+        &self,
+        directory: P,
+        fixed_timestep: std::time::Duration,
+        include_ui: bool,
+    ) {
+        let directory = directory.into();
+        log::info!("Beginning frame sequence capture to {:?}", directory);
+        self.inner.lock().unwrap().frame_sequence_capture = Some(FrameSequenceCapture {
+            directory,
+            fixed_timestep,
+            include_ui,
+            next_frame_index: 0,
+        });
+    }
+
+    pub fn end_frame_sequence_capture(&self) {
+        let capture = self.inner.lock().unwrap().frame_sequence_capture.take();
+        if let Some(capture) = capture {
+            log::info!(
+                "Ended frame sequence capture to {:?} after {} frames",
+                capture.directory,
+                capture.next_frame_index
+            );
+        }
+    }
+
+    /// If a frame sequence capture is in progress, returns the fixed timestep it runs at
+    pub fn frame_sequence_capture_timestep(&self) -> Option<std::time::Duration> {
+        self.inner
+            .lock()
+            .unwrap()
+            .frame_sequence_capture
+            .as_ref()
+            .map(|x| x.fixed_timestep)
+    }
 }
 
 impl GameRenderer {
@@ -184,17 +430,32 @@ impl GameRenderer {
         world: &World,
         window: &dyn Window,
     ) -> VkResult<()> {
-        let t0 = std::time::Instant::now();
-        // This lock will delay until the previous frame completes being submitted to GPU
-        resources
-            .get_mut::<VkSurface>()
-            .unwrap()
-            .wait_until_frame_not_in_flight()?;
-        let t1 = std::time::Instant::now();
-        log::trace!(
-            "[main] wait for previous frame present {} ms",
-            (t1 - t0).as_secs_f32() * 1000.0
-        );
+        let profiler = resources.get::<Profiler>().unwrap().clone();
+        let _scope = profiler.scope("begin_render");
+
+        // Stats from the most recently submitted frame, which may be a few frames behind this one
+        if let Some(mut render_stats_resource) = resources.get_mut::<RenderStatsResource>() {
+            render_stats_resource.previous_frame = self.previous_frame_render_stats();
+        }
+
+        // Automatic render scale follows the GPU time of the most recent frame with timings. A
+        // new scale takes effect by reallocating the scene targets, which waits for any frames in
+        // flight
+        let gpu_frame_time = profiler.with_history(|history, _| {
+            history
+                .back()
+                .map(|frame| frame.gpu_duration())
+                .filter(|gpu_duration| *gpu_duration > std::time::Duration::default())
+        });
+        let render_scale_changed = {
+            let mut guard = self.inner.lock().unwrap();
+            let mode_changed = std::mem::replace(&mut guard.render_scale_mode_changed, false);
+            let scale_changed = guard.render_scale.update(gpu_frame_time);
+            mode_changed || scale_changed
+        };
+        if render_scale_changed {
+            SwapchainLifetimeListener::resize_scene_targets(resources, self)?;
+        }
 
         // Here, we error check from the previous frame. This includes checking for errors that happened
         // during setup (i.e. before we finished building the frame job). So
@@ -218,9 +479,16 @@ impl GameRenderer {
             }
         }
 
+        // Only blocks if the render thread is max_frames_in_flight frames behind. This must happen
+        // after the swapchain is rebuilt above, as rebuilding waits for all frames in flight
+        let frame_in_flight_token = {
+            let _scope = profiler.wait_scope("wait for frame in flight");
+            self.frames_in_flight.begin_frame()
+        };
+
         // If we get an error before kicking off rendering, stash it for the next frame. We could
         // consider acting on it instead, but for now lets just have a single consistent codepath
-        if let Err(e) = self.do_begin_render(resources, world, window) {
+        if let Err(e) = self.do_begin_render(resources, world, window, frame_in_flight_token) {
             log::warn!("Received error immediately from do_begin_render: {:?}", e);
             self.inner.lock().unwrap().previous_frame_result = Some(Err(e));
         }
@@ -235,22 +503,21 @@ impl GameRenderer {
         resources: &Resources,
         world: &World,
         window: &dyn Window,
+        mut frame_in_flight_token: FrameInFlightToken,
     ) -> VkResult<()> {
-        // Fetch the next swapchain image
+        // Fetch the next swapchain image. This also waits for the GPU to finish older frames
         let frame_in_flight = {
+            let profiler = resources.get::<Profiler>().unwrap().clone();
+            let _scope = profiler.wait_scope("wait for swapchain image");
+            let wait_start = std::time::Instant::now();
             let mut surface = resources.get_mut::<VkSurface>().unwrap();
-            let t0 = std::time::Instant::now();
-            let result = surface.acquire_next_swapchain_image(window);
-            let t1 = std::time::Instant::now();
-            log::trace!(
-                "[main] wait for swapchain image took {} ms",
-                (t1 - t0).as_secs_f32() * 1000.0
-            );
-            result?
+            let frame_in_flight = surface.acquire_next_swapchain_image(window)?;
+            frame_in_flight_token.set_swapchain_image_wait_duration(wait_start.elapsed());
+            frame_in_flight
         };
 
         // Get command buffers to submit
-        Self::render(self, world, resources, window, frame_in_flight)
+        Self::render(self, world, resources, window, frame_in_flight, frame_in_flight_token)
     }
 
     pub fn render(
@@ -259,8 +526,13 @@ impl GameRenderer {
         resources: &Resources,
         _window: &dyn Window,
         frame_in_flight: FrameInFlight,
+        frame_in_flight_token: FrameInFlightToken,
     ) -> VkResult<()> {
-        let t0 = std::time::Instant::now();
+        let profiler = resources.get::<Profiler>().unwrap().clone();
+        let _scope = profiler.scope("render extract");
+
+        // Shared by every extract, prepare and write context created for this frame
+        let render_stats = Arc::new(RenderStatsCounters::default());
 
         //
         // Fetch resources
@@ -289,8 +561,22 @@ impl GameRenderer {
 
         let mut guard = game_renderer.inner.lock().unwrap();
         let main_camera_render_phase_mask = guard.main_camera_render_phase_mask.clone();
-        let swapchain_resources = guard.swapchain_resources.as_mut().unwrap();
-        let swapchain_surface_info = swapchain_resources.swapchain_surface_info.clone();
+        let screen_space_render_phase_mask = guard.screen_space_render_phase_mask.clone();
+
+        // A one-off screenshot takes priority over the next frame of a sequence capture
+        let screenshot_request = match guard.pending_screenshot.take() {
+            Some(request) => Some(request),
+            None => guard
+                .frame_sequence_capture
+                .as_mut()
+                .map(|capture| capture.next_request()),
+        };
+
+        let swapchain_extract_resources = guard.swapchain_extract_resources.as_mut().unwrap();
+        let swapchain_surface_info = swapchain_extract_resources.swapchain_surface_info.clone();
+
+        // The 3D scene may be drawn smaller than the swapchain and upscaled, see RenderScaleMode
+        let scene_surface_info = swapchain_extract_resources.scene_surface_info.clone();
 
         // https://matthewwellings.com/blog/the-new-vulkan-coordinate-system/
         let vulkan_projection_correction = glam::Mat4::from_scale(glam::Vec3::new(1.0, -1.0, 0.5)) *
@@ -300,75 +586,102 @@ impl GameRenderer {
         // View Management
         //
         let render_view_set = RenderViewSet::default();
-        let (main_view, view_proj) = {
-            // let camera_rotate_speed = 1.0;
-            // let camera_distance_multiplier = 1.0;
-            // let loop_time = time_resource.simulation_time.total_time().as_secs_f32() + 0.0;
-            // let eye = glam::Vec3::new(
-            //     camera_distance_multiplier * 8.0 * f32::cos(camera_rotate_speed * loop_time / 2.0),
-            //     camera_distance_multiplier * 8.0 * f32::sin(camera_rotate_speed * loop_time / 2.0),
-            //     camera_distance_multiplier * 5.0,
-            // );
-            // let eye = glam::Vec3::new(
-            //     -1.0,
-            //     -8.0,
-            //     3.0
-            // );
 
-            let eye = glam::Vec3::new(
-                -8.0,
-                -1.0,
-                3.0
+        // Each editor viewport is drawn into its own region of the window, see SceneView. The
+        // first is the main view
+        let (scene_views, picking_view, view_proj) = {
+            let window_size = glam::Vec2::new(
+                swapchain_surface_info.extents.width as f32,
+                swapchain_surface_info.extents.height as f32,
             );
-
-            let extents_width = swapchain_surface_info.extents.width;
-            let extents_height = swapchain_surface_info.extents.height;
-            let aspect_ratio = extents_width as f32 / extents_height as f32;
-
-            let near_clip = 0.1;
-            let far_clip = 25.0;
-            let fov = std::f32::consts::FRAC_PI_4;
-            let up = glam::Vec3::new(0.0, 0.0, 1.0);
-            let dir = (glam::Vec3::new(0.0, 0.0, 0.0) - eye).normalize();
-
-            let view = glam::Mat4::look_at_rh(
-                eye,
-                glam::Vec3::new(0.0, 0.0, 0.0),
-                glam::Vec3::new(0.0, 0.0, 1.0),
-            );
-            let proj = vulkan_projection_correction * glam::Mat4::perspective_rh_gl(
-                fov,
-                aspect_ratio,
-                near_clip,
-                far_clip,
+            let scene_scale = glam::Vec2::new(
+                scene_surface_info.extents.width as f32 / window_size.x(),
+                scene_surface_info.extents.height as f32 / window_size.y(),
             );
 
-            let view_proj = proj * view;
+            let editor_camera = resources.get::<EditorCameraResource>().unwrap();
+            let editor_viewports = resources.get::<EditorViewportResource>().unwrap();
+            let viewport_views = editor_viewports.views(editor_camera.state(), window_size);
+
+            let mut scene_views = Vec::with_capacity(viewport_views.len());
+            let mut picking_view = None;
+            let mut view_proj = glam::Mat4::identity();
+            for viewport_view in viewport_views {
+                let region = viewport_view.region;
+
+                // Moves the region's clip space into its part of the window's, so that the view
+                // can be drawn into the window-sized targets and the editor can cast rays into it
+                // with window coordinates
+                let region_correction = glam::Mat4::from_translation(glam::Vec3::new(
+                    (region.position.x() * 2.0 + region.size.x()) / window_size.x() - 1.0,
+                    (region.position.y() * 2.0 + region.size.y()) / window_size.y() - 1.0,
+                    0.0,
+                )) * glam::Mat4::from_scale(glam::Vec3::new(
+                    region.size.x() / window_size.x(),
+                    region.size.y() / window_size.y(),
+                    1.0,
+                ));
+
+                let eye = viewport_view.eye_position;
+                let view = viewport_view.view;
+                let cull_projection = vulkan_projection_correction * viewport_view.projection;
+                let proj = region_correction * cull_projection;
+
+                let view_name = if scene_views.is_empty() {
+                    view_proj = proj * view;
+                    "main".to_string()
+                } else {
+                    viewport_view.kind.name().to_lowercase()
+                };
+
+                let render_view = render_view_set.create_view(
+                    eye,
+                    view,
+                    proj,
+                    main_camera_render_phase_mask.clone(),
+                    view_name,
+                );
+
+                // Selection and gizmos use the viewport under the mouse
+                if viewport_view.kind == editor_viewports.active_viewport() {
+                    viewport.set_world_space_view(proj, view, eye);
+                    picking_view = Some(render_view.clone());
+                }
 
-            let main_view = render_view_set.create_view(
-                eye,
-                view,
-                proj,
-                main_camera_render_phase_mask,
-                "main".to_string(),
-            );
+                scene_views.push(SceneView {
+                    view: render_view,
+                    region: region_rect(
+                        region.position,
+                        region.size,
+                        swapchain_surface_info.extents,
+                    ),
+                    scene_region: region_rect(
+                        region.position * scene_scale,
+                        region.size * scene_scale,
+                        scene_surface_info.extents,
+                    ),
+                    cull_projection,
+                });
+            }
 
-            viewport.set_world_space_view(
-                proj,
-                view,
-                eye,
-                // dir,
-                // up,
-                // fov,
-                // near_clip,
-                // far_clip
-            );
+            let picking_view = match picking_view {
+                Some(picking_view) => picking_view,
+                None => {
+                    let main_view = &scene_views[0].view;
+                    viewport.set_world_space_view(
+                        main_view.projection_matrix(),
+                        main_view.view_matrix(),
+                        main_view.eye_position(),
+                    );
+                    main_view.clone()
+                }
+            };
 
-            (main_view, view_proj)
+            (scene_views, picking_view, view_proj)
         };
 
         // Set up the screen-space viewport matrices
-        {
+        let screen_space_view = {
             let multiplier = 600.0 as f32 / swapchain_surface_info.extents.height as f32;
 
             let half_extents_width = (swapchain_surface_info.extents.width as f32 * multiplier) / 2.0;
@@ -389,7 +702,17 @@ impl GameRenderer {
             );
 
             viewport.set_screen_space_view(proj /* * view*/);
-        }
+
+            // Nothing is placed behind the eye, it only gives sprites a distance to sort by so
+            // that higher z is drawn on top
+            render_view_set.create_view(
+                glam::Vec3::new(0.0, 0.0, 100.0),
+                glam::Mat4::identity(),
+                proj,
+                screen_space_render_phase_mask,
+                "screen_space".to_string(),
+            )
+        };
 
         viewport.set_viewport_size_in_pixels(glam::Vec2::new(
             swapchain_surface_info.extents.width as f32,
@@ -397,86 +720,276 @@ impl GameRenderer {
         ));
 
         //
-        // Visibility
+        // Picking - find the meshes that may be under the cursor using this frame's view
         //
-        let main_view_static_visibility_result =
-            static_visibility_node_set.calculate_static_visibility(&main_view);
-        let main_view_dynamic_visibility_result =
-            dynamic_visibility_node_set.calculate_dynamic_visibility(&main_view);
-
-        log::trace!(
-            "main view static node count: {}",
-            main_view_static_visibility_result.handles.len()
-        );
+        let picking_request_id = game_renderer
+            .next_picking_request_id
+            .fetch_add(1, Ordering::AcqRel);
+        let picking_request = match (
+            resources.get::<InputResource>(),
+            resources.get::<EditorSelectionResource>(),
+        ) {
+            (Some(input), Some(editor_selection)) => create_picking_request(
+                world,
+                &*viewport,
+                &picking_view,
+                &*editor_selection,
+                picking_request_id,
+                input.mouse_position(),
+                swapchain_surface_info.extents,
+            ),
+            _ => None,
+        };
 
-        log::trace!(
-            "main view dynamic node count: {}",
-            main_view_dynamic_visibility_result.handles.len()
-        );
+        //
+        // Visibility
+        //
+        let visibility_scope = profiler.scope("visibility");
+        let scene_view_visibility_results: Vec<_> = scene_views
+            .iter()
+            .map(|scene_view| {
+                let static_visibility_result =
+                    static_visibility_node_set.calculate_static_visibility(&scene_view.view);
+                let dynamic_visibility_result =
+                    dynamic_visibility_node_set.calculate_dynamic_visibility(&scene_view.view);
+
+                log::trace!(
+                    "{} view static node count: {}",
+                    scene_view.view.debug_name(),
+                    static_visibility_result.handles.len()
+                );
+
+                log::trace!(
+                    "{} view dynamic node count: {}",
+                    scene_view.view.debug_name(),
+                    dynamic_visibility_result.handles.len()
+                );
+
+                (static_visibility_result, dynamic_visibility_result)
+            })
+            .collect();
+
+        // Only sprites are drawn in the screen-space view. Screen-space text is not associated with
+        // render nodes, see ScreenTextResource
+        let mut screen_space_view_dynamic_visibility_result =
+            dynamic_visibility_node_set.calculate_dynamic_visibility(&screen_space_view);
+        screen_space_view_dynamic_visibility_result
+            .handles
+            .retain(|handle| handle.render_feature_index() == SpriteRenderFeature::feature_index());
+        std::mem::drop(visibility_scope);
+
+        let mut view_render_stats: Vec<_> = scene_views
+            .iter()
+            .zip(scene_view_visibility_results.iter())
+            .map(|(scene_view, (static_visibility_result, dynamic_visibility_result))| {
+                ViewRenderStats {
+                    view_name: scene_view.view.debug_name().to_string(),
+                    static_visible_nodes: static_visibility_result.handles.len() as u32,
+                    dynamic_visible_nodes: dynamic_visibility_result.handles.len() as u32,
+                    view_nodes_per_feature: vec![],
+                }
+            })
+            .collect();
+        view_render_stats.push(ViewRenderStats {
+            view_name: "screen_space".to_string(),
+            static_visible_nodes: 0,
+            dynamic_visible_nodes: screen_space_view_dynamic_visibility_result.handles.len() as u32,
+            view_nodes_per_feature: vec![],
+        });
 
         let sprite_render_nodes = resources.get::<SpriteRenderNodeSet>().unwrap();
+        let text_render_nodes = resources.get::<TextRenderNodeSet>().unwrap();
+        let particle_render_nodes = resources.get::<ParticleRenderNodeSet>().unwrap();
         let mesh_render_nodes = resources.get::<MeshRenderNodeSet>().unwrap();
         let mut all_render_nodes = AllRenderNodes::new();
         all_render_nodes.add_render_nodes(&*sprite_render_nodes);
+        all_render_nodes.add_render_nodes(&*text_render_nodes);
+        all_render_nodes.add_render_nodes(&*particle_render_nodes);
         all_render_nodes.add_render_nodes(&*mesh_render_nodes);
 
         let frame_packet_builder = FramePacketBuilder::new(&all_render_nodes);
 
         // After these jobs end, user calls functions to start jobs that extract data
+        for (scene_view, (static_visibility_result, dynamic_visibility_result)) in
+            scene_views.iter().zip(scene_view_visibility_results)
+        {
+            frame_packet_builder.add_view(
+                &scene_view.view,
+                &[static_visibility_result, dynamic_visibility_result],
+            );
+        }
+
         frame_packet_builder.add_view(
-            &main_view,
-            &[
-                main_view_static_visibility_result,
-                main_view_dynamic_visibility_result,
-            ],
+            &screen_space_view,
+            &[screen_space_view_dynamic_visibility_result],
         );
 
+        // Each frame slot has its own copy so that the render thread and GPU can still be using the
+        // previous frames'
         let mut descriptor_set_allocator = resource_manager.create_descriptor_set_allocator();
-        swapchain_resources
-            .debug_material_per_frame_data
-            .set_buffer_data(0, &view_proj);
-        swapchain_resources
-            .debug_material_per_frame_data
-            .flush(&mut descriptor_set_allocator)?;
+        let debug_material_per_frame_data = &mut swapchain_extract_resources
+            .debug_material_per_frame_data[frame_in_flight_token.frame_slot()];
+        debug_material_per_frame_data.set_buffer_data(0, &view_proj);
+        render_stats.record_buffer_upload(std::mem::size_of_val(&view_proj) as u64);
+        debug_material_per_frame_data.flush(&mut descriptor_set_allocator)?;
         descriptor_set_allocator.flush_changes()?;
+        let debug_descriptor_set_per_pass = debug_material_per_frame_data.descriptor_set().get();
 
         //
         // Update Resources and flush descriptor set changes
         //
         resource_manager.on_begin_frame()?;
 
+        //
+        // Static meshes - GPU data is only rebuilt when static meshes are spawned, removed or
+        // moved. Each scene view gets its own per-frame buffers, written by the culling pass on the
+        // render thread
+        //
+        let static_mesh_frame_data = {
+            let mut static_mesh_set = resources.get_mut::<StaticMeshSet>().unwrap();
+            let game_resource_manager = resources.get::<GameResourceManager>().unwrap();
+            let dyn_resource_allocator = resource_manager.create_dyn_resource_allocator_set();
+            static_mesh_set.update_gpu_data(
+                world,
+                &device_context,
+                &dyn_resource_allocator,
+                &*game_resource_manager,
+            )?;
+
+            let mut static_mesh_frame_data = Vec::with_capacity(scene_views.len());
+            if let Some(gpu_data) = static_mesh_set.gpu_data() {
+                for scene_view in scene_views.iter().take(MAX_STATIC_MESH_CULL_VIEWS) {
+                    static_mesh_frame_data.push(gpu_data.create_frame_data(
+                        &device_context,
+                        &dyn_resource_allocator,
+                        &scene_view.view,
+                        scene_view.cull_projection,
+                        guard.mesh_lod_debug_view_enabled,
+                    )?);
+                    render_stats
+                        .record_buffer_upload(std::mem::size_of::<MeshCullShaderParam>() as u64);
+                }
+            }
+
+            static_mesh_frame_data
+        };
+
         //
         // Extract Jobs
         //
         let frame_packet = frame_packet_builder.build();
+
+        let render_node_features = render_node_feature_names();
+        let frame_nodes_per_feature = render_node_features
+            .iter()
+            .map(|(feature_index, name)| {
+                (*name, frame_packet.frame_node_count(*feature_index) as u32)
+            })
+            .collect();
+        let views: Vec<_> = scene_views
+            .iter()
+            .map(|scene_view| &scene_view.view)
+            .chain(std::iter::once(&screen_space_view))
+            .collect();
+        for (view, view_stats) in views.iter().zip(view_render_stats.iter_mut()) {
+            view_stats.view_nodes_per_feature = render_node_features
+                .iter()
+                .map(|(feature_index, name)| {
+                    (*name, frame_packet.view_node_count(view, *feature_index) as u32)
+                })
+                .collect();
+        }
+
         let extract_job_set = {
             let sprite_pipeline_info = resource_manager.get_pipeline_info(
                 &guard.static_resources.sprite_material,
-                &swapchain_surface_info,
+                &scene_surface_info,
                 0,
             );
 
-            let mesh_pipeline_info = resource_manager.get_pipeline_info(
-                &guard.static_resources.mesh_material,
-                &swapchain_surface_info,
+            let text_pipeline_info = resource_manager.get_pipeline_info(
+                &guard.static_resources.text_material,
+                &scene_surface_info,
                 0,
             );
 
+            let particle_alpha_blend_pipeline_info = resource_manager.get_pipeline_info(
+                &guard.static_resources.particle_material,
+                &scene_surface_info,
+                PARTICLE_ALPHA_BLEND_PASS_INDEX,
+            );
+
+            let particle_additive_blend_pipeline_info = resource_manager.get_pipeline_info(
+                &guard.static_resources.particle_material,
+                &scene_surface_info,
+                PARTICLE_ADDITIVE_BLEND_PASS_INDEX,
+            );
+
+            // With the depth prepass enabled, the opaque pass uses a depth-equal variant that does
+            // not write depth
+            let (mesh_pipeline_info, mesh_depth_prepass_pipeline_info) =
+                if guard.depth_prepass_enabled {
+                    let mesh_pipeline_info = resource_manager.get_pipeline_info(
+                        &guard.static_resources.mesh_material,
+                        &scene_surface_info,
+                        MESH_OPAQUE_DEPTH_EQUAL_PASS_INDEX,
+                    );
+
+                    let mesh_depth_prepass_pipeline_info = resource_manager.get_pipeline_info(
+                        &guard.static_resources.mesh_material,
+                        &scene_surface_info,
+                        MESH_DEPTH_PREPASS_PASS_INDEX,
+                    );
+
+                    (mesh_pipeline_info, Some(mesh_depth_prepass_pipeline_info))
+                } else {
+                    let mesh_pipeline_info = resource_manager.get_pipeline_info(
+                        &guard.static_resources.mesh_material,
+                        &scene_surface_info,
+                        MESH_OPAQUE_PASS_INDEX,
+                    );
+
+                    (mesh_pipeline_info, None)
+                };
+
+            let mesh_wireframe_pipeline_info =
+                if guard.mesh_debug_view_mode == MeshDebugViewMode::Wireframe {
+                    Some(resource_manager.get_pipeline_info(
+                        &guard.static_resources.mesh_material,
+                        &scene_surface_info,
+                        MESH_WIREFRAME_PASS_INDEX,
+                    ))
+                } else {
+                    None
+                };
+
             let debug_pipeline_info_3d = resource_manager.get_pipeline_info(
                 &guard.static_resources.debug_material_3d,
-                &swapchain_surface_info,
-                0,
+                &scene_surface_info,
+                DEBUG_LINES_PASS_INDEX,
             );
 
             let debug_pipeline_info_3d_no_depth = resource_manager.get_pipeline_info(
                 &guard.static_resources.debug_material_3d_no_depth,
-                &swapchain_surface_info,
-                0,
+                &scene_surface_info,
+                DEBUG_LINES_PASS_INDEX,
+            );
+
+            let debug_triangle_pipeline_info_3d = resource_manager.get_pipeline_info(
+                &guard.static_resources.debug_material_3d,
+                &scene_surface_info,
+                DEBUG_TRIANGLES_PASS_INDEX,
+            );
+
+            let debug_triangle_pipeline_info_3d_no_depth = resource_manager.get_pipeline_info(
+                &guard.static_resources.debug_material_3d_no_depth,
+                &scene_surface_info,
+                DEBUG_TRIANGLES_PASS_INDEX,
             );
 
             let debug_pipeline_info_2d = resource_manager.get_pipeline_info(
                 &guard.static_resources.debug_material_2d,
-                &swapchain_surface_info,
+                &scene_surface_info,
                 0,
             );
 
@@ -486,20 +999,46 @@ impl GameRenderer {
                 0,
             );
 
-            let mut extract_job_set = ExtractJobSet::new();
+            let mut extract_job_set = ParallelExtractJobSet::new();
 
             // Sprites
             extract_job_set.add_job(create_sprite_extract_job(
                 device_context.clone(),
                 resource_manager.create_descriptor_set_allocator(),
                 sprite_pipeline_info,
+                screen_space_view.view_index(),
                 &guard.static_resources.sprite_material,
             ));
 
+            // Text
+            extract_job_set.add_job(create_text_extract_job(
+                device_context.clone(),
+                resource_manager.create_descriptor_set_allocator(),
+                text_pipeline_info,
+                screen_space_view.view_index(),
+                &guard.static_resources.text_material,
+            ));
+
+            // Particles
+            extract_job_set.add_job(create_particle_extract_job(
+                device_context.clone(),
+                resource_manager.create_descriptor_set_allocator(),
+                particle_alpha_blend_pipeline_info,
+                particle_additive_blend_pipeline_info,
+                &guard.static_resources.particle_material,
+                &guard.static_resources.white_image,
+            ));
+
             // Meshes
             extract_job_set.add_job(create_mesh_extract_job(
+                device_context.clone(),
                 resource_manager.create_descriptor_set_allocator(),
                 mesh_pipeline_info,
+                mesh_depth_prepass_pipeline_info,
+                mesh_wireframe_pipeline_info,
+                static_mesh_frame_data.clone(),
+                guard.mesh_lod_debug_view_enabled,
+                guard.mesh_debug_view_mode,
                 &guard.static_resources.mesh_material,
             ));
 
@@ -510,6 +1049,8 @@ impl GameRenderer {
                 swapchain_surface_info.extents,
                 debug_pipeline_info_3d,
                 debug_pipeline_info_3d_no_depth,
+                debug_triangle_pipeline_info_3d,
+                debug_triangle_pipeline_info_3d_no_depth,
                 debug_pipeline_info_2d,
                 &guard.static_resources.debug_material_3d,
                 &guard.static_resources.debug_material_3d_no_depth,
@@ -526,17 +1067,55 @@ impl GameRenderer {
                 guard.imgui_font_atlas_image_view.clone(),
             ));
 
+            // Picking
+            if let Some(picking_request) = &picking_request {
+                let picking_pipeline_info = resource_manager.get_pipeline_info(
+                    &guard.static_resources.picking_material,
+                    &swapchain_surface_info,
+                    0,
+                );
+
+                extract_job_set.add_job(create_picking_extract_job(
+                    resource_manager.create_descriptor_set_allocator(),
+                    picking_pipeline_info,
+                    picking_view.view_index(),
+                    &guard.static_resources.picking_material,
+                    picking_request.entities.clone(),
+                ));
+            }
+
+            // Selection outline
+            if guard.selection_outline_enabled {
+                let selection_outline_mask_pipeline_info = resource_manager.get_pipeline_info(
+                    &guard.static_resources.selection_outline_material,
+                    &swapchain_surface_info,
+                    SELECTION_OUTLINE_MASK_PASS_INDEX,
+                );
+
+                extract_job_set.add_job(create_selection_outline_extract_job(
+                    resource_manager.create_descriptor_set_allocator(),
+                    selection_outline_mask_pipeline_info,
+                    scene_views
+                        .iter()
+                        .map(|scene_view| scene_view.view.view_index())
+                        .collect(),
+                    &guard.static_resources.selection_outline_material,
+                ));
+            }
+
             extract_job_set
         };
 
-        let mut extract_context =
-            RenderJobExtractContext::new(&world, &resources, resource_manager);
-        let prepare_job_set =
-            extract_job_set.extract(&mut extract_context, &frame_packet, &[&main_view]);
+        let prepare_job_set = {
+            let _scope = profiler.scope("extract jobs");
+            let extract_context =
+                RenderJobExtractContext::new(&world, &resources, resource_manager, render_stats.clone());
+            extract_job_set.extract(&profiler, &extract_context, &frame_packet, &views)
+        };
 
         let opaque_pipeline_info = resource_manager.get_pipeline_info(
             &guard.static_resources.sprite_material,
-            &swapchain_surface_info,
+            &scene_surface_info,
             0,
         );
 
@@ -546,14 +1125,20 @@ impl GameRenderer {
             0,
         );
 
-        let dyn_resource_allocator_set = resource_manager.create_dyn_resource_allocator_set();
+        let selection_outline_mask_pipeline_info = resource_manager.get_pipeline_info(
+            &guard.static_resources.selection_outline_material,
+            &swapchain_surface_info,
+            SELECTION_OUTLINE_MASK_PASS_INDEX,
+        );
 
-        let t1 = std::time::Instant::now();
-        log::trace!(
-            "[main] render extract took {} ms",
-            (t1 - t0).as_secs_f32() * 1000.0
+        let picking_pipeline_info = resource_manager.get_pipeline_info(
+            &guard.static_resources.picking_material,
+            &swapchain_surface_info,
+            0,
         );
 
+        let dyn_resource_allocator_set = resource_manager.create_dyn_resource_allocator_set();
+
         let game_renderer = game_renderer.clone();
 
         let prepared_frame = RenderFrameJob {
@@ -561,12 +1146,25 @@ impl GameRenderer {
             prepare_job_set,
             dyn_resource_allocator_set,
             frame_packet,
-            main_view,
+            scene_views,
+            screen_space_view,
+            picking_view,
             render_registry: render_registry.clone(),
             device_context: device_context.clone(),
             opaque_pipeline_info,
             imgui_pipeline_info,
+            selection_outline_mask_pipeline_info,
+            picking_pipeline_info,
+            picking_request,
+            screenshot_request,
+            static_mesh_frame_data,
+            debug_descriptor_set_per_pass,
             frame_in_flight,
+            frame_in_flight_token,
+            profiler: profiler.clone(),
+            render_stats,
+            view_render_stats,
+            frame_nodes_per_feature,
         };
 
         guard.render_thread.render(prepared_frame);
@@ -574,3 +1172,22 @@ impl GameRenderer {
         Ok(())
     }
 }
+
+// Converts a region in pixels to a rect covering every pixel it touches, clamped to the target
+fn region_rect(position: glam::Vec2, size: glam::Vec2, extents: vk::Extent2D) -> vk::Rect2D {
+    let min_x = (position.x().floor().max(0.0) as u32).min(extents.width);
+    let min_y = (position.y().floor().max(0.0) as u32).min(extents.height);
+    let max_x = ((position.x() + size.x()).ceil().max(0.0) as u32).min(extents.width);
+    let max_y = ((position.y() + size.y()).ceil().max(0.0) as u32).min(extents.height);
+
+    vk::Rect2D {
+        offset: vk::Offset2D {
+            x: min_x as i32,
+            y: min_y as i32,
+        },
+        extent: vk::Extent2D {
+            width: max_x - min_x,
+            height: max_y - min_y,
+        },
+    }
+}
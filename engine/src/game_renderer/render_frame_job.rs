@@ -1,5 +1,13 @@
-use crate::game_renderer::{GameRenderer, GameRendererInner};
-use renderer::nodes::{PrepareJobSet, FramePacket, RenderView, RenderRegistry};
+use crate::game_renderer::{GameRenderer, ScreenshotRequest, FrameInFlightToken};
+use crate::game_renderer::gpu_profiler::VkGpuProfiler;
+use crate::game_renderer::swapchain_resources::SwapchainResources;
+use crate::features::mesh::StaticMeshFrameData;
+use crate::profiler::Profiler;
+use crate::render_stats::{RenderStats, RenderStatsCounters, ViewRenderStats};
+use crate::features::picking::{PickingRequest, PickingResult};
+use crate::renderpass::SceneView;
+use std::sync::{Arc, Mutex};
+use renderer::nodes::{PrepareJobSet, FramePacket, RenderView, RenderRegistry, PreparedRenderData};
 use crate::render_contexts::{
     RenderJobPrepareContext, RenderJobWriteContext, RenderJobWriteContextFactory,
 };
@@ -14,87 +22,176 @@ pub struct RenderFrameJob {
     pub prepare_job_set: PrepareJobSet<RenderJobPrepareContext, RenderJobWriteContext>,
     pub dyn_resource_allocator_set: DynResourceAllocatorSet,
     pub frame_packet: FramePacket,
-    pub main_view: RenderView,
+    // The first is the main view
+    pub scene_views: Vec<SceneView>,
+    pub screen_space_view: RenderView,
+    // The view under the cursor
+    pub picking_view: RenderView,
     pub render_registry: RenderRegistry,
     pub device_context: VkDeviceContext,
     pub opaque_pipeline_info: PipelineSwapchainInfo,
     pub imgui_pipeline_info: PipelineSwapchainInfo,
+    pub selection_outline_mask_pipeline_info: PipelineSwapchainInfo,
+    pub picking_pipeline_info: PipelineSwapchainInfo,
+    // None if the cursor is outside the window
+    pub picking_request: Option<PickingRequest>,
+    pub screenshot_request: Option<ScreenshotRequest>,
+    // One per scene view that static meshes were culled for
+    pub static_mesh_frame_data: Vec<StaticMeshFrameData>,
+    pub debug_descriptor_set_per_pass: vk::DescriptorSet,
     pub frame_in_flight: FrameInFlight,
+    // Released when this job is dropped, after the frame is presented
+    pub frame_in_flight_token: FrameInFlightToken,
+    pub profiler: Profiler,
+    pub render_stats: Arc<RenderStatsCounters>,
+    // Counts gathered on the main thread during extract
+    pub view_render_stats: Vec<ViewRenderStats>,
+    pub frame_nodes_per_feature: Vec<(&'static str, u32)>,
 }
 
 impl RenderFrameJob {
     pub fn render_async(self) {
-        // let t0 = std::time::Instant::now();
-        let guard = self.game_renderer.inner.lock().unwrap();
+        let profiler = self.profiler.clone();
+        let _scope = profiler.scope("render frame");
+
+        let present_index = self.frame_in_flight.present_index() as usize;
+
+        //
+        // Prepare Jobs - these don't need the swapchain resources, so they run before taking
+        // their lock. Features may spread their work across the render job thread pool
+        //
+        let prepare_context = RenderJobPrepareContext::new(
+            self.dyn_resource_allocator_set,
+            self.render_stats.clone(),
+        );
+        let prepared_render_data = {
+            let _scope = profiler.scope("prepare jobs");
+            let mut views: Vec<&RenderView> =
+                self.scene_views.iter().map(|scene_view| &scene_view.view).collect();
+            views.push(&self.screen_space_view);
+            self.prepare_job_set.prepare(
+                &prepare_context,
+                &self.frame_packet,
+                &views,
+                &self.render_registry,
+            )
+        };
+
+        let guard = self.game_renderer.swapchain_resources.lock().unwrap();
 
         let result = Self::do_render_async(
             guard,
-            self.prepare_job_set,
-            self.dyn_resource_allocator_set,
-            self.frame_packet,
-            self.main_view,
-            self.render_registry,
-            self.device_context,
+            prepared_render_data,
+            prepare_context,
+            self.scene_views,
+            self.screen_space_view,
+            self.picking_view,
+            self.device_context.clone(),
             self.opaque_pipeline_info,
             self.imgui_pipeline_info,
-            self.frame_in_flight.present_index() as usize,
+            self.selection_outline_mask_pipeline_info,
+            self.picking_pipeline_info,
+            self.picking_request,
+            self.screenshot_request.as_ref(),
+            &self.static_mesh_frame_data,
+            self.debug_descriptor_set_per_pass,
+            &profiler,
+            self.render_stats,
+            self.view_render_stats,
+            self.frame_nodes_per_feature,
+            &self.frame_in_flight_token,
+            &self.game_renderer.previous_frame_render_stats,
+            &self.game_renderer.picking_result,
+            present_index,
         );
 
-        let t1 = std::time::Instant::now();
-        //log::info!("[async] render took {} ms", (t1 - t0).as_secs_f32() * 1000.0);
-
         match result {
             Ok(command_buffers) => {
+                let _scope = profiler.scope("present");
+
                 // ignore the error, we will receive it when we try to acquire the next image
                 let _ = self.frame_in_flight.present(command_buffers.as_slice());
             }
             Err(err) => {
                 log::error!("Render thread failed with error {:?}", err);
+
+                // Any timestamps recorded for this frame were never submitted
+                let mut guard = self.game_renderer.swapchain_resources.lock().unwrap();
+                if let Some(swapchain_resources) = guard.as_mut() {
+                    swapchain_resources.gpu_profiler.cancel_frame(present_index);
+                    swapchain_resources.picking_renderpass.cancel_frame(present_index);
+                    swapchain_resources.screenshot_copy.cancel_frame(present_index);
+                }
+                std::mem::drop(guard);
+
                 // Pass error on to the next swapchain image acquire call
                 self.frame_in_flight.cancel_present(Err(err));
             }
         }
-
-        let t2 = std::time::Instant::now();
-        log::trace!(
-            "[async] present took {} ms",
-            (t2 - t1).as_secs_f32() * 1000.0
-        );
     }
 
     fn do_render_async(
-        mut guard: MutexGuard<GameRendererInner>,
-        prepare_job_set: PrepareJobSet<RenderJobPrepareContext, RenderJobWriteContext>,
-        dyn_resource_allocator_set: DynResourceAllocatorSet,
-        frame_packet: FramePacket,
-        main_view: RenderView,
-        render_registry: RenderRegistry,
+        mut guard: MutexGuard<Option<SwapchainResources>>,
+        prepared_render_data: Box<PreparedRenderData<RenderJobWriteContext>>,
+        prepare_context: RenderJobPrepareContext,
+        scene_views: Vec<SceneView>,
+        screen_space_view: RenderView,
+        picking_view: RenderView,
         device_context: VkDeviceContext,
         opaque_pipeline_info: PipelineSwapchainInfo,
         imgui_pipeline_info: PipelineSwapchainInfo,
+        selection_outline_mask_pipeline_info: PipelineSwapchainInfo,
+        picking_pipeline_info: PipelineSwapchainInfo,
+        picking_request: Option<PickingRequest>,
+        screenshot_request: Option<&ScreenshotRequest>,
+        static_mesh_frame_data: &[StaticMeshFrameData],
+        debug_descriptor_set_per_pass: vk::DescriptorSet,
+        profiler: &Profiler,
+        render_stats: Arc<RenderStatsCounters>,
+        view_render_stats: Vec<ViewRenderStats>,
+        frame_nodes_per_feature: Vec<(&'static str, u32)>,
+        frame_in_flight_token: &FrameInFlightToken,
+        previous_frame_render_stats: &Mutex<RenderStats>,
+        picking_result: &Mutex<Option<PickingResult>>,
         present_index: usize,
     ) -> VkResult<Vec<vk::CommandBuffer>> {
-        let t0 = std::time::Instant::now();
-        //let mut guard = self.inner.lock().unwrap();
-        let swapchain_resources = guard.swapchain_resources.as_mut().unwrap();
+        let swapchain_resources = guard.as_mut().unwrap();
 
         let mut command_buffers = vec![];
 
         //
-        // Prepare Jobs - everything beyond this point could be done in parallel with the main thread
+        // GPU timestamps - the GPU is done with this present index, so the timings from the last
+        // time it was rendered can be read back before the queries are reused
         //
-        let prepare_context = RenderJobPrepareContext::new(dyn_resource_allocator_set);
-        let prepared_render_data = prepare_job_set.prepare(
-            &prepare_context,
-            &frame_packet,
-            &[&main_view],
-            &render_registry,
-        );
-        let t1 = std::time::Instant::now();
-        log::trace!(
-            "[async] render prepare took {} ms",
-            (t1 - t0).as_secs_f32() * 1000.0
-        );
+        if let Some((gpu_start, gpu_scopes)) =
+            swapchain_resources.gpu_profiler.read_results(present_index)?
+        {
+            profiler.record_gpu_scopes(gpu_start, gpu_scopes);
+        }
+
+        //
+        // Picking - likewise, the ID under the cursor from the last time this present index was
+        // rendered is ready
+        //
+        if let Some(result) = swapchain_resources.picking_renderpass.read_result(present_index)? {
+            *picking_result.lock().unwrap() = Some(result);
+        }
+
+        //
+        // Screenshot - likewise, the image copied the last time this present index was rendered
+        // can be read back and written out
+        //
+        if let Some(screenshot) = swapchain_resources.screenshot_copy.read_result(present_index)? {
+            screenshot.save_async();
+        }
+
+        let gpu_profiling_enabled =
+            profiler.is_enabled() && swapchain_resources.gpu_profiler.timestamps_supported();
+        if gpu_profiling_enabled {
+            command_buffers.push(swapchain_resources.gpu_profiler.begin_frame(present_index)?);
+        }
+
+        let write_scope = profiler.scope("write jobs");
 
         //
         // Write Jobs - called from within renderpasses for now
@@ -102,8 +199,29 @@ impl RenderFrameJob {
         let write_context_factory = RenderJobWriteContextFactory::new(
             device_context.clone(),
             prepare_context.dyn_resource_lookups,
+            render_stats.clone(),
         );
 
+        //
+        // Static mesh culling - produces the indirect draws consumed by the opaque renderpass
+        //
+        if !static_mesh_frame_data.is_empty() {
+            log::trace!("static_mesh_cull_pass update");
+            swapchain_resources
+                .static_mesh_cull_pass
+                .update(present_index, static_mesh_frame_data)?;
+            command_buffers.push(
+                swapchain_resources.static_mesh_cull_pass.command_buffers[present_index].clone(),
+            );
+            Self::end_gpu_scope(
+                &mut swapchain_resources.gpu_profiler,
+                &mut command_buffers,
+                gpu_profiling_enabled,
+                present_index,
+                "static mesh cull",
+            )?;
+        }
+
         //
         // Opaque renderpass
         //
@@ -112,28 +230,39 @@ impl RenderFrameJob {
             &opaque_pipeline_info,
             present_index,
             &*prepared_render_data,
-            &main_view,
+            &scene_views,
+            &screen_space_view,
             &write_context_factory,
         )?;
         command_buffers
             .push(swapchain_resources.opaque_renderpass.command_buffers[present_index].clone());
+        Self::end_gpu_scope(
+            &mut swapchain_resources.gpu_profiler,
+            &mut command_buffers,
+            gpu_profiling_enabled,
+            present_index,
+            "opaque",
+        )?;
 
         //
         // Debug Renderpass
         //
-        let descriptor_set_per_pass = swapchain_resources
-            .debug_material_per_frame_data
-            .descriptor_set()
-            .get();
         log::trace!("msaa_renderpass update");
 
         swapchain_resources.msaa_renderpass.update(
             present_index,
-            descriptor_set_per_pass,
+            debug_descriptor_set_per_pass,
             //debug_draw_3d_line_lists,
         )?;
         command_buffers
             .push(swapchain_resources.msaa_renderpass.command_buffers[present_index].clone());
+        Self::end_gpu_scope(
+            &mut swapchain_resources.gpu_profiler,
+            &mut command_buffers,
+            gpu_profiling_enabled,
+            present_index,
+            "msaa resolve",
+        )?;
 
         //
         // bloom extract
@@ -150,6 +279,13 @@ impl RenderFrameJob {
         command_buffers.push(
             swapchain_resources.bloom_extract_renderpass.command_buffers[present_index].clone(),
         );
+        Self::end_gpu_scope(
+            &mut swapchain_resources.gpu_profiler,
+            &mut command_buffers,
+            gpu_profiling_enabled,
+            present_index,
+            "bloom extract",
+        )?;
 
         //
         // bloom blur
@@ -165,6 +301,13 @@ impl RenderFrameJob {
         command_buffers.push(swapchain_resources.bloom_blur_renderpass.command_buffers[1].clone());
         command_buffers.push(swapchain_resources.bloom_blur_renderpass.command_buffers[0].clone());
         command_buffers.push(swapchain_resources.bloom_blur_renderpass.command_buffers[1].clone());
+        Self::end_gpu_scope(
+            &mut swapchain_resources.gpu_profiler,
+            &mut command_buffers,
+            gpu_profiling_enabled,
+            present_index,
+            "bloom blur",
+        )?;
 
         //
         // bloom combine
@@ -181,6 +324,91 @@ impl RenderFrameJob {
         command_buffers.push(
             swapchain_resources.bloom_combine_renderpass.command_buffers[present_index].clone(),
         );
+        Self::end_gpu_scope(
+            &mut swapchain_resources.gpu_profiler,
+            &mut command_buffers,
+            gpu_profiling_enabled,
+            present_index,
+            "bloom combine",
+        )?;
+
+        //
+        // screenshot (without UI)
+        //
+        if let Some(screenshot_request) = screenshot_request {
+            if !screenshot_request.include_ui {
+                swapchain_resources
+                    .screenshot_copy
+                    .update(present_index, screenshot_request.clone())?;
+                command_buffers.push(
+                    swapchain_resources.screenshot_copy.command_buffers[present_index].clone(),
+                );
+            }
+        }
+
+        //
+        // selection outline
+        //
+        log::trace!("selection_outline_mask_renderpass update");
+        swapchain_resources.selection_outline_mask_renderpass.update(
+            &selection_outline_mask_pipeline_info,
+            present_index,
+            &*prepared_render_data,
+            &scene_views,
+            &write_context_factory,
+        )?;
+        command_buffers.push(
+            swapchain_resources.selection_outline_mask_renderpass.command_buffers[present_index]
+                .clone(),
+        );
+
+        let descriptor_set_per_pass = swapchain_resources
+            .selection_outline_composite_material_dyn_set
+            .descriptor_set()
+            .get();
+        log::trace!("selection_outline_composite_renderpass update");
+        swapchain_resources
+            .selection_outline_composite_renderpass
+            .update(present_index, descriptor_set_per_pass)?;
+        command_buffers.push(
+            swapchain_resources.selection_outline_composite_renderpass.command_buffers
+                [present_index]
+                .clone(),
+        );
+        Self::end_gpu_scope(
+            &mut swapchain_resources.gpu_profiler,
+            &mut command_buffers,
+            gpu_profiling_enabled,
+            present_index,
+            "selection outline",
+        )?;
+
+        //
+        // picking
+        //
+        if let Some(picking_request) = picking_request {
+            log::trace!("picking_renderpass update");
+            let has_commands = swapchain_resources.picking_renderpass.update(
+                &picking_pipeline_info,
+                present_index,
+                picking_request,
+                &*prepared_render_data,
+                &picking_view,
+                &write_context_factory,
+            )?;
+            if has_commands {
+                command_buffers.push(
+                    swapchain_resources.picking_renderpass.command_buffers[present_index].clone(),
+                );
+                Self::end_gpu_scope(
+                    &mut swapchain_resources.gpu_profiler,
+                    &mut command_buffers,
+                    gpu_profiling_enabled,
+                    present_index,
+                    "picking",
+                )?;
+            }
+        }
 
         //
         // imgui
@@ -189,18 +417,62 @@ impl RenderFrameJob {
             &imgui_pipeline_info,
             present_index,
             &*prepared_render_data,
-            &main_view,
+            &scene_views,
             &write_context_factory,
         )?;
         command_buffers
             .push(swapchain_resources.ui_renderpass.command_buffers[present_index].clone());
+        Self::end_gpu_scope(
+            &mut swapchain_resources.gpu_profiler,
+            &mut command_buffers,
+            gpu_profiling_enabled,
+            present_index,
+            "ui",
+        )?;
+
+        //
+        // screenshot (with UI)
+        //
+        if let Some(screenshot_request) = screenshot_request {
+            if screenshot_request.include_ui {
+                swapchain_resources
+                    .screenshot_copy
+                    .update(present_index, screenshot_request.clone())?;
+                command_buffers.push(
+                    swapchain_resources.screenshot_copy.command_buffers[present_index].clone(),
+                );
+            }
+        }
 
-        let t2 = std::time::Instant::now();
-        log::trace!(
-            "[async] render write took {} ms",
-            (t2 - t1).as_secs_f32() * 1000.0
+        std::mem::drop(write_scope);
+
+        // The command buffers are submitted right after this returns
+        swapchain_resources.gpu_profiler.end_frame(present_index);
+
+        log::trace!("[async] draw calls: {}", write_context_factory.draw_call_count());
+        *previous_frame_render_stats.lock().unwrap() = render_stats.finish(
+            view_render_stats,
+            frame_nodes_per_feature,
+            frame_in_flight_token,
         );
 
         Ok(command_buffers)
     }
+
+    // Appends a timestamp ending the named GPU interval after the command buffers pushed so far
+    fn end_gpu_scope(
+        gpu_profiler: &mut VkGpuProfiler,
+        command_buffers: &mut Vec<vk::CommandBuffer>,
+        gpu_profiling_enabled: bool,
+        present_index: usize,
+        name: &'static str,
+    ) -> VkResult<()> {
+        if gpu_profiling_enabled {
+            if let Some(command_buffer) = gpu_profiler.end_scope(present_index, name)? {
+                command_buffers.push(command_buffer);
+            }
+        }
+
+        Ok(())
+    }
 }
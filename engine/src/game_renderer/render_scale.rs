@@ -0,0 +1,152 @@
+use ash::vk;
+use std::time::Duration;
+
+pub const MIN_RENDER_SCALE: f32 = 0.5;
+pub const MAX_RENDER_SCALE: f32 = 1.0;
+
+// Changing the scale recreates the scene render targets, so automatic mode moves in coarse steps
+// and waits for the GPU timings to settle before changing again
+const AUTOMATIC_SCALE_STEP: f32 = 0.05;
+const AUTOMATIC_SETTLE_FRAMES: u32 = 30;
+
+// Automatic mode scales down when over the target and only scales back up with this much headroom,
+// so that it doesn't oscillate between two steps
+const AUTOMATIC_HEADROOM: f32 = 0.85;
+
+// Weight of the newest GPU frame time in the running average
+const GPU_TIME_SMOOTHING: f32 = 0.1;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RenderScaleMode {
+    /// Always render the scene at this fraction of the output resolution
+    Fixed(f32),
+
+    /// Adjust the scale to keep the GPU frame time under the target. Requires GPU profiling to be
+    /// enabled, otherwise the scale stays where it is
+    Automatic { target_frame_time: Duration },
+}
+
+impl Default for RenderScaleMode {
+    fn default() -> Self {
+        RenderScaleMode::Fixed(MAX_RENDER_SCALE)
+    }
+}
+
+/// Decides the fraction of the output resolution that the 3D scene is rendered at. The scene,
+/// MSAA resolve and bloom passes run at the scaled size and bloom combine upscales the result to
+/// the swapchain before the UI is drawn.
+pub struct RenderScaleController {
+    mode: RenderScaleMode,
+    render_scale: f32,
+    average_gpu_frame_time: Option<f32>,
+    frames_since_change: u32,
+}
+
+impl RenderScaleController {
+    pub fn new(mode: RenderScaleMode) -> Self {
+        let render_scale = match mode {
+            RenderScaleMode::Fixed(scale) => Self::clamp_scale(scale),
+            RenderScaleMode::Automatic { .. } => MAX_RENDER_SCALE,
+        };
+
+        RenderScaleController {
+            mode,
+            render_scale,
+            average_gpu_frame_time: None,
+            frames_since_change: 0,
+        }
+    }
+
+    pub fn mode(&self) -> RenderScaleMode {
+        self.mode
+    }
+
+    pub fn set_mode(
+        &mut self,
+        mode: RenderScaleMode,
+    ) {
+        self.mode = mode;
+        if let RenderScaleMode::Fixed(scale) = mode {
+            self.render_scale = Self::clamp_scale(scale);
+        }
+
+        self.average_gpu_frame_time = None;
+        self.frames_since_change = 0;
+    }
+
+    /// The scale that the current scene render targets should be created at
+    pub fn render_scale(&self) -> f32 {
+        self.render_scale
+    }
+
+    /// Feeds in the GPU time of the most recently completed frame, if known. Returns true if the
+    /// scale changed and the scene render targets need to be recreated
+    pub fn update(
+        &mut self,
+        gpu_frame_time: Option<Duration>,
+    ) -> bool {
+        let target_frame_time = match self.mode {
+            RenderScaleMode::Fixed(_) => return false,
+            RenderScaleMode::Automatic { target_frame_time } => target_frame_time,
+        };
+
+        let gpu_frame_time = match gpu_frame_time {
+            Some(gpu_frame_time) => gpu_frame_time.as_secs_f32(),
+            None => return false,
+        };
+
+        let average_gpu_frame_time = match self.average_gpu_frame_time {
+            Some(average) => average + (gpu_frame_time - average) * GPU_TIME_SMOOTHING,
+            None => gpu_frame_time,
+        };
+        self.average_gpu_frame_time = Some(average_gpu_frame_time);
+
+        self.frames_since_change += 1;
+        if self.frames_since_change < AUTOMATIC_SETTLE_FRAMES {
+            return false;
+        }
+
+        let target_frame_time = target_frame_time.as_secs_f32();
+        let new_scale = if average_gpu_frame_time > target_frame_time {
+            self.render_scale - AUTOMATIC_SCALE_STEP
+        } else if average_gpu_frame_time < target_frame_time * AUTOMATIC_HEADROOM {
+            self.render_scale + AUTOMATIC_SCALE_STEP
+        } else {
+            self.render_scale
+        };
+
+        let new_scale = Self::clamp_scale(new_scale);
+        if (new_scale - self.render_scale).abs() < std::f32::EPSILON {
+            return false;
+        }
+
+        log::debug!(
+            "Render scale {:.2} -> {:.2} (GPU {:.2} ms, target {:.2} ms)",
+            self.render_scale,
+            new_scale,
+            average_gpu_frame_time * 1000.0,
+            target_frame_time * 1000.0
+        );
+
+        // Timings from before the change no longer apply
+        self.render_scale = new_scale;
+        self.average_gpu_frame_time = None;
+        self.frames_since_change = 0;
+        true
+    }
+
+    /// Size of the scene render targets for the given output size
+    pub fn scaled_extents(
+        &self,
+        extents: vk::Extent2D,
+    ) -> vk::Extent2D {
+        vk::Extent2D {
+            width: ((extents.width as f32 * self.render_scale).round() as u32).max(1),
+            height: ((extents.height as f32 * self.render_scale).round() as u32).max(1),
+        }
+    }
+
+    fn clamp_scale(scale: f32) -> f32 {
+        scale.max(MIN_RENDER_SCALE).min(MAX_RENDER_SCALE)
+    }
+}
@@ -13,12 +13,17 @@ pub struct RenderThread {
 }
 
 impl RenderThread {
-    pub fn start() -> Self {
-        let (job_tx, job_rx) = crossbeam_channel::bounded(1);
-        let join_handle = std::thread::spawn(|| match Self::render_thread(job_rx) {
-            Ok(_) => log::info!("Render thread ended without error"),
-            Err(err) => log::info!("Render thread ended with error: {:?}", err),
-        });
+    pub fn start(max_frames_in_flight: usize) -> Self {
+        // Frames are limited by FramesInFlight before they are sent, so this never blocks
+        let (job_tx, job_rx) = crossbeam_channel::bounded(max_frames_in_flight.max(1));
+        // Named so that its scopes are labeled in the profiler
+        let join_handle = std::thread::Builder::new()
+            .name("render".to_string())
+            .spawn(|| match Self::render_thread(job_rx) {
+                Ok(_) => log::info!("Render thread ended without error"),
+                Err(err) => log::info!("Render thread ended with error: {:?}", err),
+            })
+            .unwrap();
 
         RenderThread {
             join_handle: Some(join_handle),
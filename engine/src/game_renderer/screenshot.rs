@@ -0,0 +1,419 @@
+use ash::vk;
+use ash::prelude::VkResult;
+
+use ash::version::DeviceV1_0;
+
+use renderer::vulkan::{VkBuffer, VkDeviceContext, VkSwapchain, SwapchainInfo, VkQueueFamilyIndices};
+use std::path::{Path, PathBuf};
+
+/// A request to write the presented swapchain image to disk once the frame completes
+#[derive(Debug, Clone)]
+pub struct ScreenshotRequest {
+    pub path: PathBuf,
+    pub include_ui: bool,
+}
+
+/// Settings for writing every frame to a numbered image sequence. While active, simulation time
+/// advances by `fixed_timestep` per frame regardless of how long the frame took to render/write.
+#[derive(Debug, Clone)]
+pub struct FrameSequenceCapture {
+    pub directory: PathBuf,
+    pub fixed_timestep: std::time::Duration,
+    pub include_ui: bool,
+    pub next_frame_index: u32,
+}
+
+impl FrameSequenceCapture {
+    pub fn next_request(&mut self) -> ScreenshotRequest {
+        let path = self
+            .directory
+            .join(format!("frame_{:06}.png", self.next_frame_index));
+        self.next_frame_index += 1;
+
+        ScreenshotRequest {
+            path,
+            include_ui: self.include_ui,
+        }
+    }
+}
+
+/// A screenshot that has been read back from the GPU and is ready to be written to disk
+pub struct CapturedScreenshot {
+    pub request: ScreenshotRequest,
+    pub width: u32,
+    pub height: u32,
+    // Tightly packed RGBA8
+    pub pixels: Vec<u8>,
+}
+
+impl CapturedScreenshot {
+    /// Encoding a large image is slow, so the PNG is written on another thread
+    pub fn save_async(self) {
+        std::thread::spawn(move || {
+            match write_png(&self.request.path, self.width, self.height, &self.pixels) {
+                Ok(_) => log::info!("Saved screenshot to {:?}", self.request.path),
+                Err(e) => log::error!(
+                    "Failed to save screenshot to {:?}: {:?}",
+                    self.request.path,
+                    e
+                ),
+            }
+        });
+    }
+}
+
+/// Copies a swapchain image into a host-visible buffer so that it can be written out as a PNG.
+/// Inserted either after bloom combine (no UI) or after the UI pass. Like picking, the copy is
+/// read back the next time the present index is rendered, once the GPU is done with it, so that
+/// capturing every frame doesn't stall the render thread.
+pub struct VkScreenshotCopy {
+    pub device_context: VkDeviceContext,
+    pub swapchain_info: SwapchainInfo,
+
+    swapchain_images: Vec<vk::Image>,
+    surface_format: vk::Format,
+
+    // Tightly packed 4 bytes per pixel, sized for the current swapchain extents. One per present
+    // index
+    readback_buffers: Vec<VkBuffer>,
+
+    // The request copied for each present index that hasn't been read back yet
+    pending_requests: Vec<Option<ScreenshotRequest>>,
+
+    // Command pool and list of command buffers, one per present index
+    pub command_pool: vk::CommandPool,
+    pub command_buffers: Vec<vk::CommandBuffer>,
+}
+
+impl VkScreenshotCopy {
+    pub fn new(
+        device_context: &VkDeviceContext,
+        swapchain: &VkSwapchain,
+    ) -> VkResult<Self> {
+        let command_pool = Self::create_command_pool(
+            &device_context.device(),
+            &device_context.queue_family_indices(),
+        )?;
+
+        let command_buffers = Self::create_command_buffers(
+            &device_context.device(),
+            &swapchain.swapchain_info,
+            &command_pool,
+        )?;
+
+        let extents = swapchain.swapchain_info.extents;
+        let readback_buffer_size = extents.width as u64 * extents.height as u64 * 4;
+        let mut readback_buffers = Vec::with_capacity(swapchain.swapchain_info.image_count);
+        for _ in 0..swapchain.swapchain_info.image_count {
+            readback_buffers.push(VkBuffer::new(
+                device_context,
+                vk_mem::MemoryUsage::GpuToCpu,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                readback_buffer_size,
+            )?);
+        }
+
+        Ok(VkScreenshotCopy {
+            device_context: device_context.clone(),
+            swapchain_info: swapchain.swapchain_info.clone(),
+            swapchain_images: swapchain.swapchain_images.clone(),
+            surface_format: swapchain.swapchain_info.surface_format.format,
+            readback_buffers,
+            pending_requests: vec![None; swapchain.swapchain_info.image_count],
+            command_pool,
+            command_buffers,
+        })
+    }
+
+    fn create_command_pool(
+        logical_device: &ash::Device,
+        queue_family_indices: &VkQueueFamilyIndices,
+    ) -> VkResult<vk::CommandPool> {
+        log::trace!(
+            "Creating command pool with queue family index {}",
+            queue_family_indices.graphics_queue_family_index
+        );
+        let pool_create_info = vk::CommandPoolCreateInfo::builder()
+            .flags(
+                vk::CommandPoolCreateFlags::TRANSIENT
+                    | vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+            )
+            .queue_family_index(queue_family_indices.graphics_queue_family_index);
+
+        unsafe { logical_device.create_command_pool(&pool_create_info, None) }
+    }
+
+    fn create_command_buffers(
+        logical_device: &ash::Device,
+        swapchain_info: &SwapchainInfo,
+        command_pool: &vk::CommandPool,
+    ) -> VkResult<Vec<vk::CommandBuffer>> {
+        let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_buffer_count(swapchain_info.image_count as u32)
+            .command_pool(*command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY);
+
+        unsafe { logical_device.allocate_command_buffers(&command_buffer_allocate_info) }
+    }
+
+    fn update_command_buffer(
+        device_context: &VkDeviceContext,
+        swapchain_info: &SwapchainInfo,
+        command_buffer: vk::CommandBuffer,
+        image: vk::Image,
+        buffer: vk::Buffer,
+        image_layout: vk::ImageLayout,
+    ) -> VkResult<()> {
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder();
+
+        let subresource_range = vk::ImageSubresourceRange::builder()
+            .aspect_mask(vk::ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1)
+            .build();
+
+        // The image was just written as a color attachment by either bloom combine (no UI) or the
+        // UI pass. Move it to TRANSFER_SRC, copy, and put it back in the layout the next user of
+        // the image expects.
+        let src_stage = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
+        let src_access = vk::AccessFlags::COLOR_ATTACHMENT_WRITE;
+
+        let (dst_stage, dst_access) = if image_layout == vk::ImageLayout::PRESENT_SRC_KHR {
+            (
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::AccessFlags::MEMORY_READ,
+            )
+        } else {
+            (
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::AccessFlags::COLOR_ATTACHMENT_READ | vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            )
+        };
+
+        let to_transfer_barrier = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(src_access)
+            .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .old_layout(image_layout)
+            .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range)
+            .build();
+
+        let from_transfer_barrier = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .dst_access_mask(dst_access)
+            .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(image_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(subresource_range)
+            .build();
+
+        let host_read_barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE)
+            .build();
+
+        let copy_region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+            .image_extent(vk::Extent3D {
+                width: swapchain_info.extents.width,
+                height: swapchain_info.extents.height,
+                depth: 1,
+            })
+            .build();
+
+        unsafe {
+            let logical_device = device_context.device();
+            logical_device.begin_command_buffer(command_buffer, &command_buffer_begin_info)?;
+
+            logical_device.cmd_pipeline_barrier(
+                command_buffer,
+                src_stage,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_transfer_barrier],
+            );
+
+            logical_device.cmd_copy_image_to_buffer(
+                command_buffer,
+                image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer,
+                &[copy_region],
+            );
+
+            logical_device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                dst_stage | vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[host_read_barrier],
+                &[from_transfer_barrier],
+            );
+
+            logical_device.end_command_buffer(command_buffer)
+        }
+    }
+
+    /// Records the copy for the given present index. The request's `include_ui` determines where
+    /// in the frame the copy is inserted, and therefore what layout the swapchain image is in.
+    pub fn update(
+        &mut self,
+        present_index: usize,
+        request: ScreenshotRequest,
+    ) -> VkResult<()> {
+        // These must match the final layouts of bloom_combine.renderpass and ui.renderpass
+        let image_layout = if request.include_ui {
+            vk::ImageLayout::PRESENT_SRC_KHR
+        } else {
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        };
+
+        Self::update_command_buffer(
+            &self.device_context,
+            &self.swapchain_info,
+            self.command_buffers[present_index],
+            self.swapchain_images[present_index],
+            self.readback_buffers[present_index].buffer,
+            image_layout,
+        )?;
+
+        self.pending_requests[present_index] = Some(request);
+        Ok(())
+    }
+
+    /// Returns the image copied the last time this present index was rendered. The GPU must be
+    /// finished with the present index, which is the case once its swapchain image has been
+    /// acquired again.
+    pub fn read_result(
+        &mut self,
+        present_index: usize,
+    ) -> VkResult<Option<CapturedScreenshot>> {
+        let request = match self.pending_requests[present_index].take() {
+            Some(request) => request,
+            None => return Ok(None),
+        };
+
+        let extents = self.swapchain_info.extents;
+        let pixels = self.read_rgba8(present_index)?;
+        Ok(Some(CapturedScreenshot {
+            request,
+            width: extents.width,
+            height: extents.height,
+            pixels,
+        }))
+    }
+
+    /// Returns every image that hasn't been read back yet. Used when the swapchain is destroyed,
+    /// at which point the device is idle.
+    pub fn read_all_results(&mut self) -> VkResult<Vec<CapturedScreenshot>> {
+        let mut results = vec![];
+        for present_index in 0..self.pending_requests.len() {
+            if let Some(result) = self.read_result(present_index)? {
+                results.push(result);
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Call if the command buffers for the present index were not submitted, so that a stale
+    /// image isn't written out
+    pub fn cancel_frame(
+        &mut self,
+        present_index: usize,
+    ) {
+        self.pending_requests[present_index] = None;
+    }
+
+    // Reads the present index's readback buffer as tightly packed RGBA8
+    fn read_rgba8(
+        &self,
+        present_index: usize,
+    ) -> VkResult<Vec<u8>> {
+        let extents = self.swapchain_info.extents;
+        let byte_count = extents.width as usize * extents.height as usize * 4;
+
+        let allocator = self.device_context.allocator();
+        let readback_buffer = &self.readback_buffers[present_index];
+        let mut pixels = vec![0; byte_count];
+        unsafe {
+            let data = allocator
+                .map_memory(&readback_buffer.allocation)
+                .map_err(|_| vk::Result::ERROR_MEMORY_MAP_FAILED)?;
+            std::ptr::copy_nonoverlapping(data, pixels.as_mut_ptr(), byte_count);
+            allocator
+                .unmap_memory(&readback_buffer.allocation)
+                .map_err(|_| vk::Result::ERROR_MEMORY_MAP_FAILED)?;
+        }
+
+        // Swapchains are commonly BGRA, PNG wants RGBA
+        match self.surface_format {
+            vk::Format::B8G8R8A8_UNORM | vk::Format::B8G8R8A8_SRGB => {
+                for pixel in pixels.chunks_exact_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+            _ => {}
+        }
+
+        // The presentation engine ignores alpha, make sure the saved image does too
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+
+        Ok(pixels)
+    }
+}
+
+impl Drop for VkScreenshotCopy {
+    fn drop(&mut self) {
+        log::trace!("destroying VkScreenshotCopy");
+
+        unsafe {
+            let device = self.device_context.device();
+            device.destroy_command_pool(self.command_pool, None);
+        }
+
+        log::trace!("destroyed VkScreenshotCopy");
+    }
+}
+
+/// Writes an RGBA8 image to disk as a PNG. This is intended to be called off the render thread
+/// since encoding a large image is slow.
+fn write_png(
+    path: &Path,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> Result<(), image::ImageError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    image::save_buffer(path, rgba, width, height, image::ColorType::Rgba8)
+}
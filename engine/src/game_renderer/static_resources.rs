@@ -7,7 +7,7 @@ use atelier_assets::loader::LoadStatus;
 use atelier_assets::core as atelier_core;
 use ash::prelude::VkResult;
 use atelier_assets::loader::handle::AssetHandle;
-use renderer::assets::MaterialAsset;
+use renderer::assets::{MaterialAsset, ShaderAsset, ImageAsset};
 use legion::prelude::Resources;
 
 fn begin_load_asset<T>(
@@ -58,6 +58,10 @@ fn wait_for_asset_to_load<T>(
 
 pub struct GameRendererStaticResources {
     pub sprite_material: Handle<MaterialAsset>,
+    pub text_material: Handle<MaterialAsset>,
+    pub particle_material: Handle<MaterialAsset>,
+    // Used by features when an optional texture isn't set
+    pub white_image: Handle<ImageAsset>,
     pub debug_material_3d: Handle<MaterialAsset>,
     pub debug_material_3d_no_depth: Handle<MaterialAsset>,
     pub debug_material_2d: Handle<MaterialAsset>,
@@ -66,6 +70,9 @@ pub struct GameRendererStaticResources {
     pub bloom_blur_material: Handle<MaterialAsset>,
     pub bloom_combine_material: Handle<MaterialAsset>,
     pub imgui_material: Handle<MaterialAsset>,
+    pub selection_outline_material: Handle<MaterialAsset>,
+    pub picking_material: Handle<MaterialAsset>,
+    pub mesh_cull_shader: Handle<ShaderAsset>,
 }
 
 impl GameRendererStaticResources {
@@ -80,6 +87,27 @@ impl GameRendererStaticResources {
             resources,
         );
 
+        //
+        // Text resources
+        //
+        let text_material = begin_load_asset::<MaterialAsset>(
+            asset_uuid!("6d1f8b3a-4e92-47c5-b0a8-3c5e9d7f2a16"),
+            resources,
+        );
+
+        //
+        // Particle resources
+        //
+        let particle_material = begin_load_asset::<MaterialAsset>(
+            asset_uuid!("0e5c9a27-b4d1-4f83-a6e2-8d7b3f1c5a90"),
+            resources,
+        );
+
+        let white_image = begin_load_asset::<ImageAsset>(
+            asset_uuid!("be831a21-f4f6-45d4-b9eb-e1bb6fc19d22"),
+            resources,
+        );
+
         //
         // Debug resources
         //
@@ -136,12 +164,54 @@ impl GameRendererStaticResources {
             resources,
         );
 
+        //
+        // Selection outline resources
+        //
+        let selection_outline_material = begin_load_asset::<MaterialAsset>(
+            asset_uuid!("6bddb19c-1e83-40b5-867b-7f72449e49a8"),
+            resources,
+        );
+
+        //
+        // Editor picking resources
+        //
+        let picking_material = begin_load_asset::<MaterialAsset>(
+            asset_uuid!("38b0fdc5-e997-4682-be80-81892fa4ebe9"),
+            resources,
+        );
+
+        //
+        // Static mesh culling resources
+        //
+        let mesh_cull_shader = begin_load_asset::<ShaderAsset>(
+            asset_uuid!("29029805-b530-4422-b3bc-d93a083fb29c"),
+            resources,
+        );
+
         wait_for_asset_to_load(
             &sprite_material,
             resources,
             "sprite_material",
         )?;
 
+        wait_for_asset_to_load(
+            &text_material,
+            resources,
+            "text material",
+        )?;
+
+        wait_for_asset_to_load(
+            &particle_material,
+            resources,
+            "particle material",
+        )?;
+
+        wait_for_asset_to_load(
+            &white_image,
+            resources,
+            "white image",
+        )?;
+
         wait_for_asset_to_load(
             &debug_material_3d,
             resources,
@@ -184,10 +254,31 @@ impl GameRendererStaticResources {
             "imgui material",
         )?;
 
+        wait_for_asset_to_load(
+            &selection_outline_material,
+            resources,
+            "selection outline material",
+        )?;
+
+        wait_for_asset_to_load(
+            &picking_material,
+            resources,
+            "picking material",
+        )?;
+
+        wait_for_asset_to_load(
+            &mesh_cull_shader,
+            resources,
+            "mesh cull shader",
+        )?;
+
         let debug_material_2d = debug_material_3d_no_depth.clone();
 
         Ok(GameRendererStaticResources {
             sprite_material,
+            text_material,
+            particle_material,
+            white_image,
             debug_material_3d,
             debug_material_3d_no_depth,
             debug_material_2d,
@@ -196,6 +287,9 @@ impl GameRendererStaticResources {
             bloom_blur_material,
             bloom_combine_material,
             imgui_material,
+            selection_outline_material,
+            picking_material,
+            mesh_cull_shader,
         })
     }
 }
@@ -6,8 +6,11 @@ use legion::prelude::Resources;
 use ash::prelude::VkResult;
 use renderer::assets::resources::ResourceManager;
 use renderer::nodes::RenderRegistry;
-use crate::game_renderer::swapchain_resources::SwapchainResources;
+use crate::game_renderer::swapchain_resources::{SwapchainResources, SwapchainExtractResources};
 use renderer::assets::vk_description::SwapchainSurfaceInfo;
+use minimum::resources::AssetResource;
+use atelier_assets::loader::handle::AssetHandle;
+use ash::version::DeviceV1_0;
 
 pub struct SwapchainLifetimeListener<'a> {
     pub resources: &'a Resources,
@@ -44,6 +47,9 @@ impl<'a> SwapchainLifetimeListener<'a> {
         window: &dyn Window,
         game_renderer: &GameRenderer,
     ) -> VkResult<()> {
+        // The render thread may still be writing frames that use the old swapchain
+        game_renderer.wait_for_frames_in_flight();
+
         let mut surface = resources.get_mut::<VkSurface>().unwrap();
         let mut resource_manager = resources.get_mut::<ResourceManager>().unwrap();
         let render_registry = resources.get::<RenderRegistry>().unwrap();
@@ -58,9 +64,70 @@ impl<'a> SwapchainLifetimeListener<'a> {
         surface.rebuild_swapchain(window, &mut Some(&mut lifetime_listener))
     }
 
+    /// Applies a changed render scale. Only the scene targets and the passes that draw into them
+    /// are reallocated, the swapchain is left alone
+    pub fn resize_scene_targets(
+        resources: &Resources,
+        game_renderer: &GameRenderer,
+    ) -> VkResult<()> {
+        // The render thread and the GPU may still be using the old targets
+        game_renderer.wait_for_frames_in_flight();
+        let device_context = resources.get::<VkDeviceContext>().unwrap().clone();
+        unsafe { device_context.device().device_wait_idle()? };
+
+        let mut resource_manager = resources.get_mut::<ResourceManager>().unwrap();
+        let mut guard = game_renderer.inner.lock().unwrap();
+        let game_renderer_inner = &mut *guard;
+        let mut swapchain_resources = game_renderer.swapchain_resources.lock().unwrap();
+        let swapchain_resources = match swapchain_resources.as_mut() {
+            Some(swapchain_resources) => swapchain_resources,
+            None => return Ok(()),
+        };
+
+        let swapchain_surface_info = swapchain_resources.swapchain_surface_info.clone();
+        let old_scene_surface_info = swapchain_resources.scene_surface_info.clone();
+        let scene_surface_info = SwapchainSurfaceInfo {
+            extents: game_renderer_inner
+                .render_scale
+                .scaled_extents(swapchain_surface_info.extents),
+            ..swapchain_surface_info.clone()
+        };
+
+        if scene_surface_info == old_scene_surface_info {
+            return Ok(());
+        }
+
+        // Pipelines for the new size must exist before the passes are recreated, and the old
+        // ones can only be removed once the old passes are gone
+        if scene_surface_info != swapchain_surface_info {
+            resource_manager.add_swapchain(&scene_surface_info)?;
+        }
+
+        swapchain_resources.resize_scene_targets(
+            &device_context,
+            game_renderer_inner,
+            &mut *resource_manager,
+            scene_surface_info.clone(),
+        )?;
+
+        if old_scene_surface_info != swapchain_surface_info {
+            resource_manager.remove_swapchain(&old_scene_surface_info);
+        }
+
+        if let Some(swapchain_extract_resources) =
+            game_renderer_inner.swapchain_extract_resources.as_mut()
+        {
+            swapchain_extract_resources.scene_surface_info = scene_surface_info;
+        }
+
+        Ok(())
+    }
+
     pub fn tear_down(resources: &Resources) {
         let mut surface = resources.get_mut::<VkSurface>().unwrap();
         let mut game_renderer = resources.get_mut::<GameRenderer>().unwrap();
+        game_renderer.wait_for_frames_in_flight();
+
         let mut resource_manager = resources.get_mut::<ResourceManager>().unwrap();
         let render_registry = resources.get::<RenderRegistry>().unwrap();
 
@@ -94,17 +161,58 @@ impl<'a> VkSurfaceSwapchainLifetimeListener for SwapchainLifetimeListener<'a> {
             depth_format: swapchain.depth_format,
         };
 
+        // Pipelines are created per swapchain surface info, so the scaled scene targets need their
+        // own when the render scale is below 1
+        let scene_surface_info = SwapchainSurfaceInfo {
+            extents: game_renderer
+                .render_scale
+                .scaled_extents(swapchain.swapchain_info.extents),
+            ..swapchain_surface_info.clone()
+        };
+
         resource_manager.add_swapchain(&swapchain_surface_info)?;
+        if scene_surface_info != swapchain_surface_info {
+            resource_manager.add_swapchain(&scene_surface_info)?;
+        }
+
+        // Compute pipelines aren't created through materials, so get the shader module directly
+        let mesh_cull_shader_module = {
+            let asset_resource = self.resources.get::<AssetResource>().unwrap();
+            game_renderer
+                .static_resources
+                .mesh_cull_shader
+                .asset(asset_resource.storage())
+                .unwrap()
+                .shader_module
+                .get_raw()
+                .shader_module
+        };
 
         let swapchain_resources = SwapchainResources::new(
             device_context,
             swapchain,
+            game_renderer,
+            resource_manager,
+            swapchain_surface_info.clone(),
+            scene_surface_info.clone(),
+            mesh_cull_shader_module,
+        )?;
+
+        let frames_in_flight = &self.game_renderer.frames_in_flight;
+        let max_frames_in_flight =
+            frames_in_flight.on_swapchain_created(swapchain.swapchain_info.image_count);
+        log::debug!("{} frames in flight", max_frames_in_flight);
+
+        let swapchain_extract_resources = SwapchainExtractResources::new(
             game_renderer,
             resource_manager,
             swapchain_surface_info,
+            scene_surface_info,
+            frames_in_flight.frame_slot_count(),
         )?;
 
-        game_renderer.swapchain_resources = Some(swapchain_resources);
+        game_renderer.swapchain_extract_resources = Some(swapchain_extract_resources);
+        *self.game_renderer.swapchain_resources.lock().unwrap() = Some(swapchain_resources);
 
         log::debug!("game renderer swapchain_created finished");
 
@@ -113,7 +221,7 @@ impl<'a> VkSurfaceSwapchainLifetimeListener for SwapchainLifetimeListener<'a> {
 
     fn swapchain_destroyed(
         &mut self,
-        _device_context: &VkDeviceContext,
+        device_context: &VkDeviceContext,
         _swapchain: &VkSwapchain,
     ) {
         let mut guard = self.game_renderer.inner.lock().unwrap();
@@ -121,10 +229,35 @@ impl<'a> VkSurfaceSwapchainLifetimeListener for SwapchainLifetimeListener<'a> {
 
         log::debug!("game renderer swapchain destroyed");
 
-        // This will clear game_renderer.swapchain_resources and drop SwapchainResources at end of fn
-        let swapchain_resources = game_renderer.swapchain_resources.take().unwrap();
+        // This will clear the swapchain resources and drop them at end of fn
+        game_renderer.swapchain_extract_resources = None;
+        let mut swapchain_resources = self
+            .game_renderer
+            .swapchain_resources
+            .lock()
+            .unwrap()
+            .take()
+            .unwrap();
+
+        // Screenshots are read back when their present index is next rendered, which won't happen
+        // for this swapchain, so wait for the GPU to finish them and read them back now
+        if let Err(e) = unsafe { device_context.device().device_wait_idle() } {
+            log::error!("Failed to wait for the device before destroying the swapchain: {:?}", e);
+        }
+        match swapchain_resources.screenshot_copy.read_all_results() {
+            Ok(screenshots) => {
+                for screenshot in screenshots {
+                    screenshot.save_async();
+                }
+            }
+            Err(e) => log::error!("Failed to read back screenshots: {:?}", e),
+        }
 
         self.resource_manager
             .remove_swapchain(&swapchain_resources.swapchain_surface_info);
+        if swapchain_resources.scene_surface_info != swapchain_resources.swapchain_surface_info {
+            self.resource_manager
+                .remove_swapchain(&swapchain_resources.scene_surface_info);
+        }
     }
 }
@@ -1,27 +1,53 @@
 use crate::renderpass::{
     VkOpaqueRenderPass, VkMsaaRenderPass, VkBloomRenderPassResources, VkBloomExtractRenderPass,
-    VkBloomBlurRenderPass, VkBloomCombineRenderPass, VkUiRenderPass,
+    VkBloomBlurRenderPass, VkBloomCombineRenderPass, VkUiRenderPass, VkStaticMeshCullPass,
+    VkSceneTargets, VkSelectionOutlineMaskRenderPass, VkSelectionOutlineCompositeRenderPass,
+    VkPickingRenderPass,
+};
+use crate::features::selection_outline::{
+    SelectionOutlineCompositeShaderParam, SELECTION_OUTLINE_MASK_PASS_INDEX,
+    SELECTION_OUTLINE_COMPOSITE_PASS_INDEX,
 };
 use renderer::vulkan::{VkDeviceContext, VkSwapchain};
 use crate::game_renderer::GameRendererInner;
+use crate::game_renderer::screenshot::VkScreenshotCopy;
+use crate::game_renderer::gpu_profiler::VkGpuProfiler;
 use renderer::assets::resources::{ResourceManager, DynDescriptorSet};
 use renderer::assets::vk_description::SwapchainSurfaceInfo;
 use ash::prelude::VkResult;
+use ash::vk;
 
+// Swapchain-dependent state that is only used by the render thread
 pub struct SwapchainResources {
-    pub debug_material_per_frame_data: DynDescriptorSet,
     pub bloom_resources: VkBloomRenderPassResources,
     pub bloom_extract_material_dyn_set: DynDescriptorSet,
     pub bloom_combine_material_dyn_set: DynDescriptorSet,
+    pub selection_outline_composite_material_dyn_set: DynDescriptorSet,
 
     pub opaque_renderpass: VkOpaqueRenderPass,
     pub msaa_renderpass: VkMsaaRenderPass,
     pub bloom_extract_renderpass: VkBloomExtractRenderPass,
     pub bloom_blur_renderpass: VkBloomBlurRenderPass,
     pub bloom_combine_renderpass: VkBloomCombineRenderPass,
+    pub selection_outline_mask_renderpass: VkSelectionOutlineMaskRenderPass,
+    pub selection_outline_composite_renderpass: VkSelectionOutlineCompositeRenderPass,
+    pub picking_renderpass: VkPickingRenderPass,
     pub ui_renderpass: VkUiRenderPass,
+    pub screenshot_copy: VkScreenshotCopy,
+    pub static_mesh_cull_pass: VkStaticMeshCullPass,
+    pub gpu_profiler: VkGpuProfiler,
+
+    // Depth written by the depth prepass/opaque pass. It is stored and left in
+    // DEPTH_STENCIL_ATTACHMENT_OPTIMAL so that later passes (SSAO, decals, etc.) can use it
+    pub depth_image_view: vk::ImageView,
 
     pub swapchain_surface_info: SwapchainSurfaceInfo,
+
+    // Same as swapchain_surface_info, but with the extents of the scene targets
+    pub scene_surface_info: SwapchainSurfaceInfo,
+
+    // Declared last so that the renderpasses using them are dropped first
+    pub scene_targets: VkSceneTargets,
 }
 
 impl SwapchainResources {
@@ -31,28 +57,222 @@ impl SwapchainResources {
         game_renderer: &mut GameRendererInner,
         resource_manager: &mut ResourceManager,
         swapchain_surface_info: SwapchainSurfaceInfo,
+        scene_surface_info: SwapchainSurfaceInfo,
+        mesh_cull_shader_module: vk::ShaderModule,
     ) -> VkResult<SwapchainResources> {
         log::debug!("creating swapchain resources");
 
+        log::trace!("Create VkSceneTargets");
+        let scene_targets =
+            VkSceneTargets::new(device_context, swapchain, scene_surface_info.extents)?;
+
+        let ScenePasses {
+            bloom_resources,
+            bloom_extract_material_dyn_set,
+            bloom_combine_material_dyn_set,
+            opaque_renderpass,
+            msaa_renderpass,
+            bloom_extract_renderpass,
+            bloom_blur_renderpass,
+        } = ScenePasses::new(
+            device_context,
+            game_renderer,
+            resource_manager,
+            &scene_targets,
+            &scene_surface_info,
+        )?;
+
+        log::trace!("Create VkBloomCombineRenderPass");
+
+        let bloom_combine_pipeline_info = resource_manager.get_pipeline_info(
+            &game_renderer.static_resources.bloom_combine_material,
+            &swapchain_surface_info,
+            0,
+        );
+
+        let bloom_combine_renderpass =
+            VkBloomCombineRenderPass::new(device_context, swapchain, bloom_combine_pipeline_info)?;
+
+        log::trace!("Create VkSelectionOutlineMaskRenderPass");
+
+        let selection_outline_mask_pipeline_info = resource_manager.get_pipeline_info(
+            &game_renderer.static_resources.selection_outline_material,
+            &swapchain_surface_info,
+            SELECTION_OUTLINE_MASK_PASS_INDEX,
+        );
+
+        let selection_outline_mask_renderpass = VkSelectionOutlineMaskRenderPass::new(
+            device_context,
+            swapchain,
+            selection_outline_mask_pipeline_info,
+        )?;
+
+        log::trace!("Create VkSelectionOutlineCompositeRenderPass");
+
+        let selection_outline_composite_layout = resource_manager.get_descriptor_set_info(
+            &game_renderer.static_resources.selection_outline_material,
+            SELECTION_OUTLINE_COMPOSITE_PASS_INDEX,
+            0,
+        );
+
+        let selection_outline_composite_pipeline_info = resource_manager.get_pipeline_info(
+            &game_renderer.static_resources.selection_outline_material,
+            &swapchain_surface_info,
+            SELECTION_OUTLINE_COMPOSITE_PASS_INDEX,
+        );
+
+        let selection_outline_composite_renderpass = VkSelectionOutlineCompositeRenderPass::new(
+            device_context,
+            swapchain,
+            selection_outline_composite_pipeline_info,
+        )?;
+
+        log::trace!("Create VkPickingRenderPass");
+
+        let picking_pipeline_info = resource_manager.get_pipeline_info(
+            &game_renderer.static_resources.picking_material,
+            &swapchain_surface_info,
+            0,
+        );
+
+        let picking_renderpass =
+            VkPickingRenderPass::new(device_context, swapchain, picking_pipeline_info)?;
+
+        let imgui_pipeline_info = resource_manager.get_pipeline_info(
+            &game_renderer.static_resources.imgui_material,
+            &swapchain_surface_info,
+            0,
+        );
+
+        let ui_renderpass = VkUiRenderPass::new(device_context, swapchain, imgui_pipeline_info)?;
+
+        log::trace!("Create VkScreenshotCopy");
+        let screenshot_copy = VkScreenshotCopy::new(device_context, swapchain)?;
+
+        log::trace!("Create VkStaticMeshCullPass");
+        let static_mesh_cull_pass =
+            VkStaticMeshCullPass::new(device_context, swapchain, mesh_cull_shader_module)?;
+
+        log::trace!("Create VkGpuProfiler");
+        let gpu_profiler = VkGpuProfiler::new(device_context, swapchain)?;
+
+        let mut descriptor_set_allocator = resource_manager.create_descriptor_set_allocator();
+        let mut selection_outline_composite_material_dyn_set = descriptor_set_allocator
+            .create_dyn_descriptor_set_uninitialized(
+                &selection_outline_composite_layout.descriptor_set_layout,
+            )?;
+        selection_outline_composite_material_dyn_set
+            .set_image_raw(0, selection_outline_mask_renderpass.mask_image_view);
+        selection_outline_composite_material_dyn_set
+            .set_buffer_data(2, &SelectionOutlineCompositeShaderParam::new());
+        selection_outline_composite_material_dyn_set.flush(&mut descriptor_set_allocator)?;
+
+        log::debug!("game renderer swapchain_created finished");
+
+        VkResult::Ok(SwapchainResources {
+            bloom_resources,
+            bloom_extract_material_dyn_set,
+            bloom_combine_material_dyn_set,
+            selection_outline_composite_material_dyn_set,
+            opaque_renderpass,
+            msaa_renderpass,
+            bloom_extract_renderpass,
+            bloom_blur_renderpass,
+            bloom_combine_renderpass,
+            selection_outline_mask_renderpass,
+            selection_outline_composite_renderpass,
+            picking_renderpass,
+            ui_renderpass,
+            screenshot_copy,
+            static_mesh_cull_pass,
+            gpu_profiler,
+            depth_image_view: scene_targets.depth_image_view,
+            swapchain_surface_info,
+            scene_surface_info,
+            scene_targets,
+        })
+    }
+
+    /// Reallocates the scene targets at the scene surface info's extents and recreates the passes
+    /// that draw into them. Everything tied to the swapchain images is kept. The GPU must be done
+    /// with the old targets.
+    pub fn resize_scene_targets(
+        &mut self,
+        device_context: &VkDeviceContext,
+        game_renderer: &GameRendererInner,
+        resource_manager: &mut ResourceManager,
+        scene_surface_info: SwapchainSurfaceInfo,
+    ) -> VkResult<()> {
+        log::debug!(
+            "resizing scene targets to {}x{}",
+            scene_surface_info.extents.width,
+            scene_surface_info.extents.height
+        );
+
+        let scene_targets = self.scene_targets.resized(scene_surface_info.extents)?;
+        let scene_passes = ScenePasses::new(
+            device_context,
+            game_renderer,
+            resource_manager,
+            &scene_targets,
+            &scene_surface_info,
+        )?;
+
+        // The old passes are dropped before the targets they reference
+        self.bloom_resources = scene_passes.bloom_resources;
+        self.bloom_extract_material_dyn_set = scene_passes.bloom_extract_material_dyn_set;
+        self.bloom_combine_material_dyn_set = scene_passes.bloom_combine_material_dyn_set;
+        self.opaque_renderpass = scene_passes.opaque_renderpass;
+        self.msaa_renderpass = scene_passes.msaa_renderpass;
+        self.bloom_extract_renderpass = scene_passes.bloom_extract_renderpass;
+        self.bloom_blur_renderpass = scene_passes.bloom_blur_renderpass;
+        self.depth_image_view = scene_targets.depth_image_view;
+        self.scene_surface_info = scene_surface_info;
+        self.scene_targets = scene_targets;
+
+        Ok(())
+    }
+}
+
+// Passes and resources that draw into or read from the scene targets, recreated with them when
+// the render scale changes
+struct ScenePasses {
+    bloom_resources: VkBloomRenderPassResources,
+    bloom_extract_material_dyn_set: DynDescriptorSet,
+    bloom_combine_material_dyn_set: DynDescriptorSet,
+    opaque_renderpass: VkOpaqueRenderPass,
+    msaa_renderpass: VkMsaaRenderPass,
+    bloom_extract_renderpass: VkBloomExtractRenderPass,
+    bloom_blur_renderpass: VkBloomBlurRenderPass,
+}
+
+impl ScenePasses {
+    fn new(
+        device_context: &VkDeviceContext,
+        game_renderer: &GameRendererInner,
+        resource_manager: &mut ResourceManager,
+        scene_targets: &VkSceneTargets,
+        scene_surface_info: &SwapchainSurfaceInfo,
+    ) -> VkResult<ScenePasses> {
         log::trace!("Create VkOpaqueRenderPass");
         //TODO: We probably want to move to just using a pipeline here and not a specific material
         let opaque_pipeline_info = resource_manager.get_pipeline_info(
             &game_renderer.static_resources.sprite_material,
-            &swapchain_surface_info,
+            scene_surface_info,
             0,
         );
 
         let opaque_renderpass =
-            VkOpaqueRenderPass::new(device_context, swapchain, opaque_pipeline_info)?;
+            VkOpaqueRenderPass::new(device_context, scene_targets, opaque_pipeline_info)?;
 
         log::trace!("Create VkDebugRenderPass");
-        let msaa_renderpass = VkMsaaRenderPass::new(device_context, swapchain)?;
+        let msaa_renderpass = VkMsaaRenderPass::new(device_context, scene_targets)?;
 
         log::trace!("Create VkBloomExtractRenderPass");
 
         let bloom_resources = VkBloomRenderPassResources::new(
             device_context,
-            swapchain,
+            scene_targets,
             resource_manager,
             game_renderer.static_resources.bloom_blur_material.clone(),
         )?;
@@ -65,13 +285,13 @@ impl SwapchainResources {
 
         let bloom_extract_pipeline_info = resource_manager.get_pipeline_info(
             &game_renderer.static_resources.bloom_extract_material,
-            &swapchain_surface_info,
+            scene_surface_info,
             0,
         );
 
         let bloom_extract_renderpass = VkBloomExtractRenderPass::new(
             device_context,
-            swapchain,
+            scene_targets,
             bloom_extract_pipeline_info,
             &bloom_resources,
         )?;
@@ -79,80 +299,86 @@ impl SwapchainResources {
         let mut descriptor_set_allocator = resource_manager.create_descriptor_set_allocator();
         let mut bloom_extract_material_dyn_set = descriptor_set_allocator
             .create_dyn_descriptor_set_uninitialized(&bloom_extract_layout.descriptor_set_layout)?;
-        bloom_extract_material_dyn_set
-            .set_image_raw(0, swapchain.color_attachment.resolved_image_view());
+        bloom_extract_material_dyn_set.set_image_raw(0, scene_targets.color_resolved_image_view());
         bloom_extract_material_dyn_set.flush(&mut descriptor_set_allocator)?;
 
         log::trace!("Create VkBloomBlurRenderPass");
 
         let bloom_blur_pipeline_info = resource_manager.get_pipeline_info(
             &game_renderer.static_resources.bloom_blur_material,
-            &swapchain_surface_info,
+            scene_surface_info,
             0,
         );
 
         let bloom_blur_renderpass = VkBloomBlurRenderPass::new(
             device_context,
-            swapchain,
+            scene_targets,
             bloom_blur_pipeline_info,
             &bloom_resources,
         )?;
 
-        log::trace!("Create VkBloomCombineRenderPass");
-
+        // Bloom combine itself draws into the swapchain image, but reads the bloom images
         let bloom_combine_layout = resource_manager.get_descriptor_set_info(
             &game_renderer.static_resources.bloom_combine_material,
             0,
             0,
         );
 
-        let bloom_combine_pipeline_info = resource_manager.get_pipeline_info(
-            &game_renderer.static_resources.bloom_combine_material,
-            &swapchain_surface_info,
-            0,
-        );
-
-        let bloom_combine_renderpass =
-            VkBloomCombineRenderPass::new(device_context, swapchain, bloom_combine_pipeline_info)?;
-
-        let imgui_pipeline_info = resource_manager.get_pipeline_info(
-            &game_renderer.static_resources.imgui_material,
-            &swapchain_surface_info,
-            0,
-        );
-
-        let ui_renderpass = VkUiRenderPass::new(device_context, swapchain, imgui_pipeline_info)?;
-
         let mut bloom_combine_material_dyn_set = descriptor_set_allocator
             .create_dyn_descriptor_set_uninitialized(&bloom_combine_layout.descriptor_set_layout)?;
         bloom_combine_material_dyn_set.set_image_raw(0, bloom_resources.color_image_view);
         bloom_combine_material_dyn_set.set_image_raw(1, bloom_resources.bloom_image_views[0]);
         bloom_combine_material_dyn_set.flush(&mut descriptor_set_allocator)?;
 
+        Ok(ScenePasses {
+            bloom_resources,
+            bloom_extract_material_dyn_set,
+            bloom_combine_material_dyn_set,
+            opaque_renderpass,
+            msaa_renderpass,
+            bloom_extract_renderpass,
+            bloom_blur_renderpass,
+        })
+    }
+}
+
+// Swapchain-dependent state that the main thread uses while extracting. Per-frame data is a ring
+// with an entry per frame in flight so that the main thread never overwrites data that the render
+// thread is still using
+pub struct SwapchainExtractResources {
+    pub debug_material_per_frame_data: Vec<DynDescriptorSet>,
+    pub swapchain_surface_info: SwapchainSurfaceInfo,
+    pub scene_surface_info: SwapchainSurfaceInfo,
+}
+
+impl SwapchainExtractResources {
+    pub fn new(
+        game_renderer: &GameRendererInner,
+        resource_manager: &mut ResourceManager,
+        swapchain_surface_info: SwapchainSurfaceInfo,
+        scene_surface_info: SwapchainSurfaceInfo,
+        frame_slot_count: usize,
+    ) -> VkResult<SwapchainExtractResources> {
         let debug_per_frame_layout = resource_manager.get_descriptor_set_info(
             &game_renderer.static_resources.debug_material_3d,
             0,
             0,
         );
-        let debug_material_per_frame_data = descriptor_set_allocator
-            .create_dyn_descriptor_set_uninitialized(
-                &debug_per_frame_layout.descriptor_set_layout,
-            )?;
 
-        log::debug!("game renderer swapchain_created finished");
+        let mut descriptor_set_allocator = resource_manager.create_descriptor_set_allocator();
+        let mut debug_material_per_frame_data = Vec::with_capacity(frame_slot_count);
+        for _ in 0..frame_slot_count {
+            debug_material_per_frame_data.push(
+                descriptor_set_allocator.create_dyn_descriptor_set_uninitialized(
+                    &debug_per_frame_layout.descriptor_set_layout,
+                )?,
+            );
+        }
 
-        VkResult::Ok(SwapchainResources {
+        VkResult::Ok(SwapchainExtractResources {
             debug_material_per_frame_data,
-            bloom_resources,
-            bloom_extract_material_dyn_set,
-            bloom_combine_material_dyn_set,
-            opaque_renderpass,
-            msaa_renderpass,
-            bloom_extract_renderpass,
-            bloom_blur_renderpass,
-            bloom_combine_renderpass,
-            ui_renderpass,
             swapchain_surface_info,
+            scene_surface_info,
         })
     }
 }
@@ -3,19 +3,28 @@ use renderer::vulkan::{
     LogicalSize, VkContextBuilder, MsaaLevel, VkDeviceContext, VkSurface, VkContext,
 };
 use crate::features::sprite::{SpriteRenderNodeSet, SpriteRenderFeature};
-use crate::features::mesh::{MeshRenderNodeSet, MeshRenderFeature};
+use crate::features::text::{TextRenderNodeSet, TextRenderFeature, ScreenTextResource};
+use crate::features::particle::{ParticleRenderNodeSet, ParticleRenderFeature};
+use crate::features::mesh::{MeshRenderNodeSet, MeshRenderFeature, StaticMeshSet, MeshLodSelectionState};
 use renderer::visibility::{StaticVisibilityNodeSet, DynamicVisibilityNodeSet};
 use renderer_shell_vulkan_sdl2::Sdl2Window;
 use crate::game_renderer::{SwapchainLifetimeListener, GameRenderer};
-use crate::features::debug3d::{DebugDraw3DResource, Debug3dRenderFeature};
+use crate::features::debug3d::{DebugDraw3DResource, DebugDraw3DShapesResource, Debug3dRenderFeature};
 use renderer::nodes::RenderRegistry;
 use crate::assets::gltf::{GltfMaterialAsset, MeshAssetData};
+use crate::assets::sprite::SpriteSheetAsset;
+use crate::assets::font::FontAsset;
 
 use crate::game_resource_manager::GameResourceManager;
 use renderer::assets::ResourceManager;
 use crate::phases::{OpaqueRenderPhase, UiRenderPhase, PreUiRenderPhase};
-use crate::phases::TransparentRenderPhase;
-use crate::features::imgui::ImGuiRenderFeature;
+use crate::phases::{TransparentRenderPhase, DepthPrepassRenderPhase, SelectionOutlineRenderPhase};
+use crate::phases::PickingRenderPhase;
+use crate::features::imgui::{ImGuiRenderFeature, ImguiTextureResource};
+use crate::features::picking::PickingRenderFeature;
+use crate::features::selection_outline::{
+    SelectionOutlineRenderFeature, EditorHoveredEntityResource,
+};
 use minimum::resources::{AssetResource, ImguiResource, DebugDraw2DResource};
 use renderer::assets::{
     ShaderAsset, PipelineAsset, RenderpassAsset, MaterialAsset, MaterialInstanceAsset, ImageAsset,
@@ -27,6 +36,7 @@ use renderer::assets::{
 };
 use crate::assets::gltf::MeshAsset;
 use crate::asset_loader::ResourceAssetLoader;
+use crate::render_stats::RenderStatsResource;
 
 pub struct Sdl2Systems {
     pub context: sdl2::Sdl,
@@ -67,6 +77,7 @@ pub fn sdl2_init() -> Sdl2Systems {
 pub fn rendering_init(
     resources: &mut Resources,
     sdl2_window: &sdl2::video::Window,
+    max_frames_in_flight: usize,
 ) {
     // Set up imgui
     #[cfg(feature = "use_imgui")]
@@ -74,14 +85,23 @@ pub fn rendering_init(
         let sdl2_imgui_manager = minimum_sdl2::imgui::init_imgui_manager(sdl2_window);
         resources.insert(ImguiResource::new(sdl2_imgui_manager.imgui_manager()));
         resources.insert(sdl2_imgui_manager);
+        resources.insert(ImguiTextureResource::default());
     }
 
     resources.insert(SpriteRenderNodeSet::default());
+    resources.insert(TextRenderNodeSet::default());
+    resources.insert(ScreenTextResource::new());
+    resources.insert(ParticleRenderNodeSet::default());
     resources.insert(MeshRenderNodeSet::default());
+    resources.insert(StaticMeshSet::default());
+    resources.insert(MeshLodSelectionState::default());
     resources.insert(StaticVisibilityNodeSet::default());
     resources.insert(DynamicVisibilityNodeSet::default());
     resources.insert(DebugDraw2DResource::new());
     resources.insert(DebugDraw3DResource::new());
+    resources.insert(DebugDraw3DShapesResource::new());
+    resources.insert(EditorHoveredEntityResource::new());
+    resources.insert(RenderStatsResource::default());
 
     let mut msaa_level = MsaaLevel::Sample4;
     #[cfg(target_os = "ios")]
@@ -145,6 +165,8 @@ pub fn rendering_init(
             ResourceAssetLoader(game_resource_manager.create_mesh_loader()),
         ));
         asset_resource.add_storage::<GltfMaterialAsset>();
+        asset_resource.add_storage::<SpriteSheetAsset>();
+        asset_resource.add_storage::<FontAsset>();
     }
 
     resources.insert(vk_context);
@@ -154,17 +176,25 @@ pub fn rendering_init(
 
     let render_registry = renderer::nodes::RenderRegistryBuilder::default()
         .register_feature::<SpriteRenderFeature>()
+        .register_feature::<TextRenderFeature>()
+        .register_feature::<ParticleRenderFeature>()
         .register_feature::<MeshRenderFeature>()
         .register_feature::<Debug3dRenderFeature>()
         .register_feature::<ImGuiRenderFeature>()
+        .register_feature::<SelectionOutlineRenderFeature>()
+        .register_feature::<PickingRenderFeature>()
+        .register_render_phase::<DepthPrepassRenderPhase>()
         .register_render_phase::<OpaqueRenderPhase>()
         .register_render_phase::<TransparentRenderPhase>()
         .register_render_phase::<PreUiRenderPhase>()
         .register_render_phase::<UiRenderPhase>()
+        .register_render_phase::<SelectionOutlineRenderPhase>()
+        .register_render_phase::<PickingRenderPhase>()
         .build();
     resources.insert(render_registry);
 
-    let game_renderer = GameRenderer::new(&window_wrapper, &resources).unwrap();
+    let game_renderer =
+        GameRenderer::new(&window_wrapper, &resources, max_frames_in_flight).unwrap();
     resources.insert(game_renderer);
 
     let window_surface =
@@ -180,10 +210,17 @@ pub fn rendering_destroy(resources: &mut Resources) {
         resources.remove::<GameRenderer>();
         resources.remove::<VkDeviceContext>();
         resources.remove::<SpriteRenderNodeSet>();
+        resources.remove::<TextRenderNodeSet>();
+        resources.remove::<ScreenTextResource>();
+        resources.remove::<ParticleRenderNodeSet>();
         resources.remove::<MeshRenderNodeSet>();
+        resources.remove::<StaticMeshSet>();
+        resources.remove::<MeshLodSelectionState>();
         resources.remove::<StaticVisibilityNodeSet>();
         resources.remove::<DynamicVisibilityNodeSet>();
         resources.remove::<DebugDraw3DResource>();
+        resources.remove::<DebugDraw3DShapesResource>();
+        resources.remove::<RenderStatsResource>();
         resources.remove::<GameResourceManager>();
         resources.remove::<RenderRegistry>();
 
@@ -32,6 +32,7 @@ use crate::components::{
 };
 
 use crate::game_renderer::GameRenderer;
+use crate::profiler::{Profiler, ProfilerWindowState};
 use minimum::resources::editor::{
     EditorInspectRegistryResource, EditorMode, EditorStateResource, EditorSelectionResource,
     EditorSettingsResource, EditorDraw3DResource,
@@ -43,6 +44,7 @@ use atelier_assets::core::asset_uuid;
 use atelier_assets::loader::rpc_loader::RpcLoader;
 
 mod asset_loader;
+mod asset_catalog;
 pub mod assets;
 mod features;
 mod game_renderer;
@@ -54,17 +56,41 @@ mod game_asset_lookup;
 mod renderpass;
 mod phases;
 mod render_contexts;
+mod render_stats;
+mod render_jobs;
+mod render_job_benchmark;
+mod profiler;
+
+use crate::render_job_benchmark::RenderJobBenchmark;
+pub use crate::render_jobs::init_render_job_thread_pool;
 
 struct ImGuiInspectTest {
     mat4: minimum::math::Mat4,
 }
 
-pub fn run(connect_string: String) {
+#[derive(Default)]
+pub struct RunOptions {
+    // If set, spawns this many meshes and logs how long extract and prepare take on them
+    pub benchmark_mesh_count: Option<usize>,
+
+    // Frames that can be in flight between the main thread and the render thread. Defaults to
+    // DEFAULT_MAX_FRAMES_IN_FLIGHT
+    pub max_frames_in_flight: Option<usize>,
+}
+
+pub fn run(
+    connect_string: String,
+    options: RunOptions,
+) {
     let mut resources = Resources::default();
     let loader = RpcLoader::new(connect_string).unwrap();
     resources.insert(registration::create_asset_resource(loader));
     resources.insert(AppControlResource::new());
     resources.insert(TimeResource::new());
+    resources.insert(Profiler::new());
+    resources.insert(ProfilerWindowState::default());
+    #[cfg(feature = "use_imgui")]
+    resources.insert(crate::systems::AssetBrowserWindowState::default());
     resources.insert(InputResource::new());
     resources.insert(EditorStateResource::new());
     resources.insert(DebugDraw3DResource::new());
@@ -72,6 +98,11 @@ pub fn run(connect_string: String) {
     resources.insert(EditorSettingsResource::new(
         registration::create_editor_keybinds(),
     ));
+    resources.insert(crate::systems::EditorHistoryResource::new());
+    resources.insert(crate::systems::EditorCameraResource::new(
+        registration::create_editor_camera_keybinds(),
+    ));
+    resources.insert(crate::systems::EditorViewportResource::new());
     resources.insert(EditorSelectionResource::new(
         registration::create_editor_selection_registry(),
     ));
@@ -96,7 +127,10 @@ pub fn run(connect_string: String) {
     resources.insert(viewport);
 
     // This will register more rendering-specific asset types
-    init::rendering_init(&mut resources, &sdl2_systems.window);
+    let max_frames_in_flight = options
+        .max_frames_in_flight
+        .unwrap_or(game_renderer::DEFAULT_MAX_FRAMES_IN_FLIGHT);
+    init::rendering_init(&mut resources, &sdl2_systems.window, max_frames_in_flight);
 
     log::info!("Starting window event loop");
     let mut event_pump = sdl2_systems
@@ -119,6 +153,8 @@ pub fn run(connect_string: String) {
     #[cfg(feature = "use_imgui")]
     let sdl2_imgui = resources.get::<Sdl2ImguiManager>().unwrap().clone();
 
+    let profiler = resources.get::<Profiler>().unwrap().clone();
+
     //EditorStateResource::open_prefab(&mut world, &resources, asset_uuid!("12b37b66-94f7-4fa6-abb3-4050619c3e11")).unwrap();
 
 
@@ -135,6 +171,10 @@ pub fn run(connect_string: String) {
     // sponza cleaned up
     EditorStateResource::open_prefab(&mut world, &resources, asset_uuid!("df741a2e-2073-4f8c-9e4e-e52c29ad22e1")).unwrap();
 
+    let mut render_job_benchmark = options
+        .benchmark_mesh_count
+        .map(|mesh_count| RenderJobBenchmark::new(&mut resources, &mut world, mesh_count));
+
 
     // frenet
     //EditorStateResource::open_prefab(&mut world, &resources, asset_uuid!("48ea3c0f-dcfa-4e6d-aef2-7ae3b890bde4")).unwrap();
@@ -149,7 +189,12 @@ pub fn run(connect_string: String) {
 
 
     'running: loop {
-        let t0 = std::time::Instant::now();
+        profiler.begin_frame();
+        if let Some(render_job_benchmark) = &mut render_job_benchmark {
+            render_job_benchmark.update(&resources, &profiler);
+        }
+
+        let simulation_scope = profiler.scope("simulation");
 
         for event in event_pump.poll_iter() {
             //log::info!("SDL2 Event: {:?}", event);
@@ -161,9 +206,23 @@ pub fn run(connect_string: String) {
 
             let mut ignore_event = false;
 
+            // imgui wants the mouse while it is over any window, including the editor's viewport
+            // windows. Input over their scene area goes to the editor instead.
             #[cfg(feature = "use_imgui")]
             {
-                ignore_event |= sdl2_imgui.ignore_event(&event);
+                let is_mouse_event = match event {
+                    Event::MouseMotion { .. }
+                    | Event::MouseButtonDown { .. }
+                    | Event::MouseButtonUp { .. }
+                    | Event::MouseWheel { .. } => true,
+                    _ => false,
+                };
+                let scene_hovered = resources
+                    .get::<crate::systems::EditorViewportResource>()
+                    .unwrap()
+                    .is_scene_hovered();
+                ignore_event |=
+                    sdl2_imgui.ignore_event(&event) && !(is_mouse_event && scene_hovered);
             }
 
             if !ignore_event {
@@ -191,7 +250,10 @@ pub fn run(connect_string: String) {
         #[cfg(feature = "use_imgui")]
         sdl2_imgui.begin_frame(&sdl2_systems.window, &MouseState::new(&event_pump));
 
-        schedule_manager.update(&mut world, &mut resources);
+        {
+            let _scope = profiler.scope("update schedule");
+            schedule_manager.update(&mut world, &mut resources);
+        }
 
         //
         // Close imgui input for this frame and render the results to memory
@@ -199,11 +261,7 @@ pub fn run(connect_string: String) {
         #[cfg(feature = "use_imgui")]
         sdl2_imgui.render(&sdl2_systems.window);
 
-        let t1 = std::time::Instant::now();
-        log::trace!(
-            "[main] simulation took {} ms",
-            (t1 - t0).as_secs_f32() * 1000.0
-        );
+        std::mem::drop(simulation_scope);
 
         //
         // Redraw
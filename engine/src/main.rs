@@ -1,7 +1,26 @@
 // There's a decent amount of code that's just for example and isn't called
 #![allow(dead_code)]
 
+use structopt::StructOpt;
+
+#[derive(StructOpt)]
+struct Opt {
+    /// Threads that extract and prepare work is spread across, 0 uses one per logical core
+    #[structopt(long, default_value = "0")]
+    render_job_threads: usize,
+
+    /// Spawns this many meshes and logs how long extract and prepare take on them
+    #[structopt(long)]
+    benchmark_mesh_count: Option<usize>,
+
+    /// Frames the main thread can get ahead of the render thread, limited by the swapchain size
+    #[structopt(long)]
+    frames_in_flight: Option<usize>,
+}
+
 fn main() {
+    let opt = Opt::from_args();
+
     #[allow(unused_assignments)]
     let mut log_level = log::LevelFilter::Info;
     //#[cfg(debug_assertions)]
@@ -29,11 +48,20 @@ fn main() {
         // })
         .init();
 
+    // Before the daemon is started in case it uses rayon
+    engine::init_render_job_thread_pool(opt.render_job_threads);
+
     // Spawn the daemon in a background thread. This could be a different process, but
     // for simplicity we'll launch it here.
     std::thread::spawn(move || {
         minimum::daemon::run();
     });
 
-    engine::run("127.0.0.1:9999".to_string());
+    engine::run(
+        "127.0.0.1:9999".to_string(),
+        engine::RunOptions {
+            benchmark_mesh_count: opt.benchmark_mesh_count,
+            max_frames_in_flight: opt.frames_in_flight,
+        },
+    );
 }
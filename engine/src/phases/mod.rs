@@ -1,3 +1,6 @@
+mod depth_prepass_render_phase;
+pub use depth_prepass_render_phase::DepthPrepassRenderPhase;
+
 mod opaque_render_phase;
 pub use opaque_render_phase::OpaqueRenderPhase;
 
@@ -10,3 +13,8 @@ pub use pre_ui_render_phase::PreUiRenderPhase;
 mod ui_render_phase;
 pub use ui_render_phase::UiRenderPhase;
 
+mod selection_outline_render_phase;
+pub use selection_outline_render_phase::SelectionOutlineRenderPhase;
+
+mod picking_render_phase;
+pub use picking_render_phase::PickingRenderPhase;
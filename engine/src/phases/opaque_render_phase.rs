@@ -18,9 +18,14 @@ impl RenderPhase for OpaqueRenderPhase {
     }
 
     fn sort_submit_nodes(mut submit_nodes: Vec<SubmitNode>) -> Vec<SubmitNode> {
-        // Sort by feature
+        // Sort by feature, then by the feature's sort key. Features use the sort key to group
+        // draws that share state (i.e. the mesh feature sorts by material and then by mesh)
         log::trace!("Sort phase {}", Self::render_phase_debug_name());
-        submit_nodes.sort_unstable_by(|a, b| a.feature_index().cmp(&b.feature_index()));
+        submit_nodes.sort_unstable_by(|a, b| {
+            a.feature_index()
+                .cmp(&b.feature_index())
+                .then(a.sort_key().cmp(&b.sort_key()))
+        });
 
         submit_nodes
     }
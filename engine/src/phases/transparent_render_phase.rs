@@ -18,12 +18,15 @@ impl RenderPhase for TransparentRenderPhase {
     }
 
     fn sort_submit_nodes(mut submit_nodes: Vec<SubmitNode>) -> Vec<SubmitNode> {
-        // Sort by distance from camera back to front
+        // Sort by sort key (features use this for layering, i.e. sprite sorting layers), then by
+        // distance from camera back to front
         log::trace!("Sort phase {}", Self::render_phase_debug_name());
         submit_nodes.sort_unstable_by(|a, b| {
-            b.distance_from_camera()
-                .partial_cmp(&a.distance_from_camera())
-                .unwrap()
+            a.sort_key().cmp(&b.sort_key()).then(
+                b.distance_from_camera()
+                    .partial_cmp(&a.distance_from_camera())
+                    .unwrap(),
+            )
         });
 
         submit_nodes
@@ -0,0 +1,118 @@
+use super::ProfiledFrame;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::time::{Duration, Instant};
+
+// All events go in a single process. CPU threads use their thread index as the tid and GPU work
+// gets its own row after them
+const PROCESS_ID: u32 = 1;
+
+fn micros(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1_000_000.0
+}
+
+fn escape_json(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn push_event(
+    events: &mut Vec<String>,
+    name: &str,
+    category: &str,
+    thread_id: usize,
+    start: Duration,
+    duration: Duration,
+) {
+    events.push(format!(
+        r#"{{"name":"{}","cat":"{}","ph":"X","pid":{},"tid":{},"ts":{:.3},"dur":{:.3}}}"#,
+        escape_json(name),
+        category,
+        PROCESS_ID,
+        thread_id,
+        micros(start),
+        micros(duration)
+    ));
+}
+
+fn push_thread_name(
+    events: &mut Vec<String>,
+    thread_id: usize,
+    name: &str,
+) {
+    events.push(format!(
+        r#"{{"name":"thread_name","ph":"M","pid":{},"tid":{},"args":{{"name":"{}"}}}}"#,
+        PROCESS_ID,
+        thread_id,
+        escape_json(name)
+    ));
+}
+
+/// Builds a trace in the Chrome trace event format. Times are relative to `epoch`. GPU scopes are
+/// placed at the estimated CPU time their GPU frame started, so the trace shows which CPU frames
+/// the GPU work overlapped.
+pub(super) fn build_chrome_trace(
+    epoch: Instant,
+    frames: &VecDeque<ProfiledFrame>,
+    thread_names: &[String],
+) -> String {
+    let gpu_thread_id = thread_names.len();
+
+    let mut events = vec![];
+    for (thread_index, thread_name) in thread_names.iter().enumerate() {
+        push_thread_name(&mut events, thread_index, thread_name);
+    }
+    push_thread_name(&mut events, gpu_thread_id, "GPU");
+
+    for frame in frames {
+        let frame_name = format!("Frame {}", frame.frame_index);
+        push_event(
+            &mut events,
+            &frame_name,
+            "frame",
+            gpu_thread_id + 1,
+            frame.start - epoch,
+            frame.duration(),
+        );
+
+        for scope in &frame.cpu_scopes {
+            push_event(
+                &mut events,
+                scope.name,
+                "cpu",
+                scope.thread_index,
+                scope.start - epoch,
+                scope.duration(),
+            );
+        }
+
+        let gpu_start = frame.gpu_start.unwrap_or(frame.start);
+        for scope in &frame.gpu_scopes {
+            push_event(
+                &mut events,
+                scope.name,
+                "gpu",
+                gpu_thread_id,
+                gpu_start - epoch + scope.start,
+                scope.duration,
+            );
+        }
+    }
+
+    push_thread_name(&mut events, gpu_thread_id + 1, "Frames");
+
+    format!(
+        "{{\"traceEvents\":[\n{}\n],\"displayTimeUnit\":\"ms\"}}\n",
+        events.join(",\n")
+    )
+}
@@ -0,0 +1,430 @@
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+mod chrome_trace;
+
+// Number of completed frames kept for display and export
+const DEFAULT_MAX_HISTORY_FRAMES: usize = 300;
+
+thread_local! {
+    // Number of scopes currently open on this thread, used to nest scopes in the timeline
+    static SCOPE_DEPTH: Cell<u32> = Cell::new(0);
+}
+
+/// A timed CPU scope. Scopes are recorded when they end, so a scope that is still open when the
+/// frame ends (i.e. on the render thread) lands in the frame it finishes in
+#[derive(Clone, Debug)]
+pub struct CpuScope {
+    pub name: &'static str,
+    // Index into ProfilerThreads::thread_names
+    pub thread_index: usize,
+    pub depth: u32,
+    pub start: Instant,
+    pub end: Instant,
+    // True if the thread was blocked on another thread or the GPU rather than doing work, see
+    // Profiler::wait_scope
+    pub waiting: bool,
+}
+
+impl CpuScope {
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+}
+
+/// A GPU interval measured with timestamp queries, relative to the first timestamp written in the
+/// GPU frame
+#[derive(Clone, Debug)]
+pub struct GpuScope {
+    pub name: &'static str,
+    pub start: Duration,
+    pub duration: Duration,
+}
+
+/// Everything recorded between two calls to `Profiler::begin_frame`
+#[derive(Clone, Debug)]
+pub struct ProfiledFrame {
+    pub frame_index: u64,
+    pub start: Instant,
+    pub end: Instant,
+    pub cpu_scopes: Vec<CpuScope>,
+    // Timestamp queries are read back when the swapchain image is reused, so these are from the
+    // most recent GPU frame that finished, not necessarily this one
+    pub gpu_scopes: Vec<GpuScope>,
+    // Estimated CPU time at which the GPU started the frame that gpu_scopes were measured in, see
+    // VkGpuProfiler. None if no GPU scopes were recorded
+    pub gpu_start: Option<Instant>,
+}
+
+/// How much of a GPU frame ran while the main thread was busy with later frames
+#[derive(Copy, Clone, Debug, Default)]
+pub struct GpuOverlap {
+    // Main thread time spent working while the GPU frame ran
+    pub working: Duration,
+    // Main thread time spent in wait scopes while the GPU frame ran
+    pub waiting: Duration,
+}
+
+fn overlap(
+    a: (Instant, Instant),
+    b: (Instant, Instant),
+) -> Duration {
+    let start = a.0.max(b.0);
+    let end = a.1.min(b.1);
+    if end > start {
+        end - start
+    } else {
+        Duration::default()
+    }
+}
+
+impl ProfiledFrame {
+    pub fn duration(&self) -> Duration {
+        self.end - self.start
+    }
+
+    pub fn gpu_duration(&self) -> Duration {
+        self.gpu_scopes
+            .iter()
+            .map(|x| x.start + x.duration)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Estimated CPU times at which the GPU started and finished the frame of gpu_scopes
+    pub fn gpu_interval(&self) -> Option<(Instant, Instant)> {
+        self.gpu_start
+            .map(|gpu_start| (gpu_start, gpu_start + self.gpu_duration()))
+    }
+
+    /// Measures how much of this frame's GPU work overlapped with the main thread, using every
+    /// frame in the history. With multiple frames in flight the main thread should be working on
+    /// the next frame while the GPU renders this one. Time spent in wait scopes, such as waiting
+    /// for a swapchain image, is counted separately since it means the two were serialized.
+    pub fn gpu_overlap(
+        &self,
+        history: &VecDeque<ProfiledFrame>,
+        main_thread_index: usize,
+    ) -> Option<GpuOverlap> {
+        let gpu_interval = self.gpu_interval()?;
+
+        let mut result = GpuOverlap::default();
+        for frame in history {
+            let frame_overlap = overlap(gpu_interval, (frame.start, frame.end));
+            if frame_overlap == Duration::default() {
+                continue;
+            }
+
+            // Only outermost wait scopes are counted so that nested ones aren't counted twice
+            let waiting: Duration = frame
+                .cpu_scopes
+                .iter()
+                .filter(|x| x.waiting && x.thread_index == main_thread_index)
+                .filter(|x| {
+                    !frame.cpu_scopes.iter().any(|parent| {
+                        parent.waiting
+                            && parent.thread_index == main_thread_index
+                            && parent.depth < x.depth
+                            && parent.start <= x.start
+                            && parent.end >= x.end
+                    })
+                })
+                .map(|x| overlap(gpu_interval, (x.start, x.end)))
+                .sum();
+
+            result.waiting += waiting;
+            result.working += frame_overlap.checked_sub(waiting).unwrap_or_default();
+        }
+
+        Some(result)
+    }
+}
+
+struct ProfilerInner {
+    enabled: bool,
+    paused: bool,
+
+    // Chrome traces are written relative to this
+    epoch: Instant,
+
+    thread_ids: Vec<ThreadId>,
+    thread_names: Vec<String>,
+
+    // Thread that calls begin_frame
+    main_thread_index: Option<usize>,
+
+    frame_index: u64,
+    frame_start: Instant,
+    cpu_scopes: Vec<CpuScope>,
+    gpu_scopes: Vec<GpuScope>,
+    gpu_start: Option<Instant>,
+
+    history: VecDeque<ProfiledFrame>,
+    max_history_frames: usize,
+}
+
+impl ProfilerInner {
+    fn thread_index(&mut self) -> usize {
+        let thread = std::thread::current();
+        if let Some(index) = self.thread_ids.iter().position(|x| *x == thread.id()) {
+            return index;
+        }
+
+        let name = thread
+            .name()
+            .map(|x| x.to_string())
+            .unwrap_or_else(|| format!("{:?}", thread.id()));
+        self.thread_ids.push(thread.id());
+        self.thread_names.push(name);
+        self.thread_ids.len() - 1
+    }
+}
+
+/// Collects nested CPU scopes from any thread plus GPU timings from the renderer into a rolling
+/// history of frames. This is a cheap-to-clone handle, the main thread holds it as a resource and
+/// the render thread is handed a clone with each frame.
+#[derive(Clone)]
+pub struct Profiler {
+    inner: Arc<Mutex<ProfilerInner>>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        let now = Instant::now();
+        let inner = ProfilerInner {
+            enabled: true,
+            paused: false,
+            epoch: now,
+            thread_ids: Default::default(),
+            thread_names: Default::default(),
+            main_thread_index: None,
+            frame_index: 0,
+            frame_start: now,
+            cpu_scopes: Default::default(),
+            gpu_scopes: Default::default(),
+            gpu_start: None,
+            history: Default::default(),
+            max_history_frames: DEFAULT_MAX_HISTORY_FRAMES,
+        };
+
+        Profiler {
+            inner: Arc::new(Mutex::new(inner)),
+        }
+    }
+
+    /// Starts timing a scope that ends when the returned guard is dropped. Scopes opened while
+    /// another is open on the same thread are nested under it.
+    pub fn scope(
+        &self,
+        name: &'static str,
+    ) -> ProfilerScope {
+        self.start_scope(name, false)
+    }
+
+    /// Like `scope`, but marks the time as spent blocked on another thread or the GPU, i.e.
+    /// waiting for a frame in flight or a swapchain image
+    pub fn wait_scope(
+        &self,
+        name: &'static str,
+    ) -> ProfilerScope {
+        self.start_scope(name, true)
+    }
+
+    fn start_scope(
+        &self,
+        name: &'static str,
+        waiting: bool,
+    ) -> ProfilerScope {
+        if !self.is_enabled() {
+            return ProfilerScope {
+                profiler: None,
+                name,
+                depth: 0,
+                start: Instant::now(),
+                waiting,
+            };
+        }
+
+        let depth = SCOPE_DEPTH.with(|x| {
+            let depth = x.get();
+            x.set(depth + 1);
+            depth
+        });
+
+        ProfilerScope {
+            profiler: Some(self.clone()),
+            name,
+            depth,
+            start: Instant::now(),
+            waiting,
+        }
+    }
+
+    /// Ends the current frame, pushing it into the history, and starts the next one. Called once
+    /// at the top of the main loop.
+    pub fn begin_frame(&self) {
+        let now = Instant::now();
+        let mut guard = self.inner.lock().unwrap();
+        let inner = &mut *guard;
+
+        if inner.main_thread_index.is_none() {
+            inner.main_thread_index = Some(inner.thread_index());
+        }
+
+        let cpu_scopes = std::mem::replace(&mut inner.cpu_scopes, vec![]);
+        let gpu_scopes = std::mem::replace(&mut inner.gpu_scopes, vec![]);
+        let frame = ProfiledFrame {
+            frame_index: inner.frame_index,
+            start: inner.frame_start,
+            end: now,
+            cpu_scopes,
+            gpu_scopes,
+            gpu_start: inner.gpu_start.take(),
+        };
+
+        inner.frame_index += 1;
+        inner.frame_start = now;
+
+        // Leave the history alone while paused so that it can be inspected
+        if inner.enabled && !inner.paused {
+            inner.history.push_back(frame);
+            while inner.history.len() > inner.max_history_frames {
+                inner.history.pop_front();
+            }
+        }
+    }
+
+    /// Adds the GPU timings for a frame. Called from the render thread once timestamp queries
+    /// have been read back. `gpu_start` is the estimated CPU time of the frame's first timestamp
+    pub fn record_gpu_scopes(
+        &self,
+        gpu_start: Instant,
+        gpu_scopes: Vec<GpuScope>,
+    ) {
+        let mut guard = self.inner.lock().unwrap();
+        if guard.enabled {
+            guard.gpu_scopes = gpu_scopes;
+            guard.gpu_start = Some(gpu_start);
+        }
+    }
+
+    fn record_cpu_scope(
+        &self,
+        name: &'static str,
+        depth: u32,
+        start: Instant,
+        end: Instant,
+        waiting: bool,
+    ) {
+        let mut guard = self.inner.lock().unwrap();
+        let thread_index = guard.thread_index();
+        guard.cpu_scopes.push(CpuScope {
+            name,
+            thread_index,
+            depth,
+            start,
+            end,
+            waiting,
+        });
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.lock().unwrap().enabled
+    }
+
+    pub fn set_enabled(
+        &self,
+        enabled: bool,
+    ) {
+        let mut guard = self.inner.lock().unwrap();
+        guard.enabled = enabled;
+        if !enabled {
+            guard.cpu_scopes.clear();
+            guard.gpu_scopes.clear();
+            guard.gpu_start = None;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.inner.lock().unwrap().paused
+    }
+
+    /// While paused, frames are still timed but not added to the history
+    pub fn set_paused(
+        &self,
+        paused: bool,
+    ) {
+        self.inner.lock().unwrap().paused = paused;
+    }
+
+    pub fn clear_history(&self) {
+        self.inner.lock().unwrap().history.clear();
+    }
+
+    /// Calls `f` with the completed frames, oldest first, and the names of the threads that
+    /// `CpuScope::thread_index` refers to
+    pub fn with_history<F: FnOnce(&VecDeque<ProfiledFrame>, &[String]) -> R, R>(
+        &self,
+        f: F,
+    ) -> R {
+        let guard = self.inner.lock().unwrap();
+        (f)(&guard.history, &guard.thread_names)
+    }
+
+    /// Index of the thread that calls `begin_frame` into the thread names, once it has been called
+    pub fn main_thread_index(&self) -> Option<usize> {
+        self.inner.lock().unwrap().main_thread_index
+    }
+
+    /// Writes every frame in the history to a JSON file that can be opened in chrome://tracing or
+    /// https://ui.perfetto.dev
+    pub fn export_chrome_trace<P: AsRef<Path>>(
+        &self,
+        path: P,
+    ) -> std::io::Result<()> {
+        let json = {
+            let guard = self.inner.lock().unwrap();
+            chrome_trace::build_chrome_trace(guard.epoch, &guard.history, &guard.thread_names)
+        };
+
+        std::fs::write(path, json)
+    }
+}
+
+/// Guard returned by `Profiler::scope`, the scope ends when this is dropped
+pub struct ProfilerScope {
+    // None if the profiler was disabled when the scope started
+    profiler: Option<Profiler>,
+    name: &'static str,
+    depth: u32,
+    start: Instant,
+    waiting: bool,
+}
+
+impl Drop for ProfilerScope {
+    fn drop(&mut self) {
+        if let Some(profiler) = self.profiler.take() {
+            let end = Instant::now();
+            SCOPE_DEPTH.with(|x| x.set(x.get().saturating_sub(1)));
+            profiler.record_cpu_scope(self.name, self.depth, self.start, end, self.waiting);
+        }
+    }
+}
+
+/// Whether the profiler window is shown. Toggled from the Render menu
+#[derive(Default)]
+pub struct ProfilerWindowState {
+    pub open: bool,
+    // Offset from the newest frame in the history of the frame being displayed
+    pub frames_from_newest: usize,
+}
@@ -9,10 +9,11 @@ use atelier_assets::loader::rpc_loader::RpcLoader;
 
 use minimum::ComponentRegistry;
 use minimum::resources::editor::Keybinds;
-use crate::components::{MeshComponentDef, MeshComponent, SpotLightComponent, PointLightComponent, DirectionalLightComponent};
+use crate::components::{MeshComponentDef, MeshComponent, SpotLightComponent, PointLightComponent, DirectionalLightComponent, SpriteComponentDef, SpriteComponent, TextComponentDef, TextComponent, ParticleEmitterComponentDef, ParticleEmitterComponent};
 use legion::prelude::Resources;
 use renderer::assets::ResourceManager;
 use crate::game_resource_manager::GameResourceManager;
+use crate::systems::EditorCameraKeybinds;
 
 struct AssetResourceUpdateCallbackImpl;
 
@@ -46,6 +47,9 @@ pub fn create_component_registry() -> ComponentRegistry {
         // .add_spawn_mapping::<RigidBodyBallComponentDef, RigidBodyComponent>()
         // .add_spawn_mapping::<RigidBodyBoxComponentDef, RigidBodyComponent>()
         .add_spawn_mapping::<MeshComponentDef, MeshComponent>()
+        .add_spawn_mapping::<SpriteComponentDef, SpriteComponent>()
+        .add_spawn_mapping::<TextComponentDef, TextComponent>()
+        .add_spawn_mapping::<ParticleEmitterComponentDef, ParticleEmitterComponent>()
         .add_spawn_mapping_into::<TransformComponentDef, TransformComponent>()
         .build()
 }
@@ -58,6 +62,8 @@ pub fn create_editor_selection_registry() -> EditorSelectRegistry {
         // .register_transformed::<RigidBodyBallComponentDef, RigidBodyComponent>()
         //.register::<PointLightComponent>()
         .register_transformed::<MeshComponentDef, MeshComponent>()
+        .register_transformed::<SpriteComponentDef, SpriteComponent>()
+        .register_transformed::<ParticleEmitterComponentDef, ParticleEmitterComponent>()
         .register::<PointLightComponent>()
         .register::<SpotLightComponent>()
         .register::<DirectionalLightComponent>()
@@ -74,6 +80,9 @@ pub fn create_editor_inspector_registry() -> EditorInspectRegistry {
         .register::<DirectionalLightComponent>()
         .register::<EditorMetadataComponent>()
         .register::<MeshComponentDef>()
+        .register::<SpriteComponentDef>()
+        .register::<TextComponentDef>()
+        .register::<ParticleEmitterComponentDef>()
         // .register::<RigidBodyBallComponentDef>()
         // .register::<RigidBodyBoxComponentDef>()
         .build()
@@ -91,5 +100,24 @@ pub fn create_editor_keybinds() -> Keybinds {
         tool_rotate: Sdl2KeyboardKey::new(Keycode::Num3).into(),
         action_quit: Sdl2KeyboardKey::new(Keycode::Escape).into(),
         action_toggle_editor_pause: Sdl2KeyboardKey::new(Keycode::Space).into(),
+        action_undo_redo_modifier: Sdl2KeyboardKey::new(Keycode::LCtrl).into(),
+        action_undo: Sdl2KeyboardKey::new(Keycode::Z).into(),
+        action_redo: Sdl2KeyboardKey::new(Keycode::Y).into(),
+    }
+}
+
+pub fn create_editor_camera_keybinds() -> EditorCameraKeybinds {
+    use minimum_sdl2::input::Sdl2KeyboardKey;
+    use sdl2::keyboard::Keycode;
+    EditorCameraKeybinds {
+        move_forward: Sdl2KeyboardKey::new(Keycode::W).into(),
+        move_back: Sdl2KeyboardKey::new(Keycode::S).into(),
+        move_left: Sdl2KeyboardKey::new(Keycode::A).into(),
+        move_right: Sdl2KeyboardKey::new(Keycode::D).into(),
+        move_up: Sdl2KeyboardKey::new(Keycode::E).into(),
+        move_down: Sdl2KeyboardKey::new(Keycode::Q).into(),
+        move_fast: Sdl2KeyboardKey::new(Keycode::LShift).into(),
+        orbit_modifier: Sdl2KeyboardKey::new(Keycode::LAlt).into(),
+        focus: Sdl2KeyboardKey::new(Keycode::F).into(),
     }
 }
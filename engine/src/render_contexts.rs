@@ -1,59 +1,87 @@
 use ash::vk;
 use legion::prelude::*;
 use renderer::assets::{ResourceManager, DynResourceAllocatorSet};
+use renderer::nodes::RenderPhaseIndex;
 use renderer::vulkan::VkDeviceContext;
+use crate::render_stats::RenderStatsCounters;
+use std::sync::Arc;
 
-pub struct RenderJobExtractContext {
-    pub world: &'static World,
-    pub resources: &'static Resources,
-    pub resource_manager: &'static ResourceManager,
+// Borrows the world and resources for the duration of extract. Extract jobs only see the context
+// through a reference and must return 'static prepare jobs, so nothing borrowed from here can be
+// carried into prepare or write
+pub struct RenderJobExtractContext<'a> {
+    pub world: &'a World,
+    pub resources: &'a Resources,
+    pub resource_manager: &'a ResourceManager,
+    render_stats: Arc<RenderStatsCounters>,
 }
 
-impl RenderJobExtractContext {
-    pub fn new<'a>(
+impl<'a> RenderJobExtractContext<'a> {
+    pub fn new(
         world: &'a World,
         resources: &'a Resources,
         resource_manager: &'a ResourceManager,
+        render_stats: Arc<RenderStatsCounters>,
     ) -> Self {
-        unsafe {
-            RenderJobExtractContext {
-                world: force_to_static_lifetime(world),
-                resources: force_to_static_lifetime(resources),
-                resource_manager: force_to_static_lifetime(resource_manager),
-            }
+        RenderJobExtractContext {
+            world,
+            resources,
+            resource_manager,
+            render_stats,
         }
     }
+
+    pub fn render_stats(&self) -> &RenderStatsCounters {
+        &self.render_stats
+    }
 }
 
 pub struct RenderJobPrepareContext {
     pub dyn_resource_lookups: DynResourceAllocatorSet,
+    render_stats: Arc<RenderStatsCounters>,
 }
 
 impl RenderJobPrepareContext {
-    pub fn new(resource_allocators: DynResourceAllocatorSet) -> Self {
+    pub fn new(
+        resource_allocators: DynResourceAllocatorSet,
+        render_stats: Arc<RenderStatsCounters>,
+    ) -> Self {
         RenderJobPrepareContext {
             dyn_resource_lookups: resource_allocators,
+            render_stats,
         }
     }
+
+    pub fn render_stats(&self) -> &RenderStatsCounters {
+        &self.render_stats
+    }
 }
 
 // Used to produce RenderJobWriteContexts per each job
 pub struct RenderJobWriteContextFactory {
     pub device_context: VkDeviceContext,
     pub dyn_resource_lookups: DynResourceAllocatorSet,
+    render_stats: Arc<RenderStatsCounters>,
 }
 
 impl RenderJobWriteContextFactory {
     pub fn new(
         device_context: VkDeviceContext,
         resource_allocators: DynResourceAllocatorSet,
+        render_stats: Arc<RenderStatsCounters>,
     ) -> Self {
         RenderJobWriteContextFactory {
             device_context,
             dyn_resource_lookups: resource_allocators,
+            render_stats,
         }
     }
 
+    // Total draw calls recorded by all contexts created by this factory
+    pub fn draw_call_count(&self) -> u32 {
+        self.render_stats.draw_call_count()
+    }
+
     pub fn create_context(
         &self,
         command_buffer: vk::CommandBuffer,
@@ -62,6 +90,7 @@ impl RenderJobWriteContextFactory {
             self.device_context.clone(),
             self.dyn_resource_lookups.clone(),
             command_buffer,
+            self.render_stats.clone(),
         )
     }
 }
@@ -70,6 +99,7 @@ pub struct RenderJobWriteContext {
     pub device_context: VkDeviceContext,
     pub dyn_resource_lookups: DynResourceAllocatorSet,
     pub command_buffer: vk::CommandBuffer,
+    render_stats: Arc<RenderStatsCounters>,
 }
 
 impl RenderJobWriteContext {
@@ -77,15 +107,30 @@ impl RenderJobWriteContext {
         device_context: VkDeviceContext,
         resource_allocators: DynResourceAllocatorSet,
         command_buffer: vk::CommandBuffer,
+        render_stats: Arc<RenderStatsCounters>,
     ) -> Self {
         RenderJobWriteContext {
             device_context,
             dyn_resource_lookups: resource_allocators,
             command_buffer,
+            render_stats,
         }
     }
-}
 
-unsafe fn force_to_static_lifetime<T>(value: &T) -> &'static T {
-    std::mem::transmute(value)
+    // Features call this for every draw they record so that we can measure the effect of batching.
+    // Pass 0 triangles if the count isn't known on the CPU (i.e. indirect draws)
+    pub fn record_draw_call(
+        &self,
+        triangle_count: u32,
+    ) {
+        self.render_stats.record_draw_call(triangle_count);
+    }
+
+    // Features call this for every submit node they are asked to write
+    pub fn record_submit_node(
+        &self,
+        render_phase_index: RenderPhaseIndex,
+    ) {
+        self.render_stats.record_submit_node(render_phase_index);
+    }
 }
@@ -0,0 +1,132 @@
+use legion::prelude::*;
+use atelier_assets::loader::handle::Handle;
+use crate::assets::gltf::MeshAsset;
+use crate::game_resource_manager::GameResourceManager;
+use crate::profiler::{Profiler, ProfiledFrame};
+use std::time::Duration;
+
+// Frames to skip after the mesh loads so that pipelines, allocator pools, etc. are warm
+const WARMUP_FRAME_COUNT: u32 = 60;
+const MEASURED_FRAME_COUNT: u32 = 300;
+
+/// Spawns a grid of dynamic meshes and logs how long extract and prepare take on them, averaged
+/// over a few hundred frames. Run with `--benchmark-mesh-count 10000` at several values of
+/// `--render-job-threads` to see how the render jobs scale, or of `--frames-in-flight` to see how
+/// much of the frame the main and render threads overlap.
+pub struct RenderJobBenchmark {
+    mesh_count: usize,
+    mesh: Handle<MeshAsset>,
+    warmup_frames_remaining: u32,
+    measured_frame_count: u32,
+    extract_time: Duration,
+    prepare_time: Duration,
+    frame_in_flight_wait_time: Duration,
+    frame_time: Duration,
+}
+
+impl RenderJobBenchmark {
+    pub fn new(
+        resources: &mut Resources,
+        world: &mut World,
+        mesh_count: usize,
+    ) -> Self {
+        log::info!("Render job benchmark: spawning {} meshes", mesh_count);
+        let mesh =
+            crate::test_scene::populate_benchmark_mesh_entities(resources, world, mesh_count);
+
+        RenderJobBenchmark {
+            mesh_count,
+            mesh,
+            warmup_frames_remaining: WARMUP_FRAME_COUNT,
+            measured_frame_count: 0,
+            extract_time: Duration::default(),
+            prepare_time: Duration::default(),
+            frame_in_flight_wait_time: Duration::default(),
+            frame_time: Duration::default(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.measured_frame_count >= MEASURED_FRAME_COUNT
+    }
+
+    /// Call once per frame after `Profiler::begin_frame`, which makes the previous frame's scopes
+    /// available
+    pub fn update(
+        &mut self,
+        resources: &Resources,
+        profiler: &Profiler,
+    ) {
+        if self.is_finished() {
+            return;
+        }
+
+        // Until the mesh loads, the mesh nodes are skipped during extract
+        let mesh_loaded = resources
+            .get::<GameResourceManager>()
+            .map(|x| x.get_mesh_info(&self.mesh).is_some())
+            .unwrap_or(false);
+        if !mesh_loaded {
+            return;
+        }
+
+        if self.warmup_frames_remaining > 0 {
+            self.warmup_frames_remaining -= 1;
+            return;
+        }
+
+        let frame_times = profiler.with_history(|history, _| {
+            history.back().map(|frame| {
+                (
+                    Self::total_scope_time(frame, "extract jobs"),
+                    Self::total_scope_time(frame, "prepare jobs"),
+                    Self::total_scope_time(frame, "wait for frame in flight"),
+                    frame.duration(),
+                )
+            })
+        });
+
+        if let Some((extract_time, prepare_time, frame_in_flight_wait_time, frame_time)) =
+            frame_times
+        {
+            self.extract_time += extract_time;
+            self.prepare_time += prepare_time;
+            self.frame_in_flight_wait_time += frame_in_flight_wait_time;
+            self.frame_time += frame_time;
+            self.measured_frame_count += 1;
+
+            if self.is_finished() {
+                self.log_results();
+            }
+        }
+    }
+
+    // Prepare runs on the render thread, so it's attributed to whichever frame it ends in
+    fn total_scope_time(
+        frame: &ProfiledFrame,
+        name: &str,
+    ) -> Duration {
+        frame
+            .cpu_scopes
+            .iter()
+            .filter(|x| x.name == name)
+            .map(|x| x.duration())
+            .sum()
+    }
+
+    fn log_results(&self) {
+        let average_ms =
+            |total: Duration| total.as_secs_f64() * 1000.0 / self.measured_frame_count as f64;
+
+        log::info!(
+            "Render job benchmark: {} meshes, {} render job threads, averaged over {} frames: extract {:.3} ms, prepare {:.3} ms, waiting on render thread {:.3} ms, frame {:.3} ms",
+            self.mesh_count,
+            rayon::current_num_threads(),
+            self.measured_frame_count,
+            average_ms(self.extract_time),
+            average_ms(self.prepare_time),
+            average_ms(self.frame_in_flight_wait_time),
+            average_ms(self.frame_time)
+        );
+    }
+}
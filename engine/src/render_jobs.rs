@@ -0,0 +1,101 @@
+use renderer::nodes::{ExtractJob, PrepareJobSet, FramePacket, RenderView};
+use renderer::assets::resources::DescriptorSetAllocatorRef;
+use crate::render_contexts::{RenderJobExtractContext, RenderJobPrepareContext, RenderJobWriteContext};
+use crate::profiler::Profiler;
+use rayon::prelude::*;
+use std::sync::{Arc, Mutex};
+
+/// An extract job that can be handed to another thread. Every feature's `create_*_extract_job`
+/// returns one of these. The prepare job it produces can't borrow from the extract context, so
+/// the compiler rejects any job that tries to hold on to the world past extraction
+pub type RenderExtractJob<'a> = Box<
+    dyn ExtractJob<RenderJobExtractContext<'a>, RenderJobPrepareContext, RenderJobWriteContext>
+        + Send
+        + 'a,
+>;
+
+/// Sets up the global rayon pool that extract jobs and prepare work are spread across. Must be
+/// called before anything else uses rayon. A thread count of 0 uses one thread per logical core.
+/// Threads are named so that their scopes are labeled in the profiler
+pub fn init_render_job_thread_pool(thread_count: usize) {
+    let result = rayon::ThreadPoolBuilder::new()
+        .num_threads(thread_count)
+        .thread_name(|index| format!("render job {}", index))
+        .build_global();
+
+    match result {
+        Ok(_) => log::info!(
+            "Render jobs will run on {} threads",
+            rayon::current_num_threads()
+        ),
+        Err(e) => log::warn!("Failed to set up the render job thread pool: {:?}", e),
+    }
+}
+
+/// Equivalent to `ExtractJobSet`, except that each feature's extract job runs on the render job
+/// thread pool. Features only share the extract context, which is read-only, and each one owns
+/// its descriptor set allocator, so they don't need to coordinate with each other.
+pub struct ParallelExtractJobSet<'a> {
+    extract_jobs: Vec<RenderExtractJob<'a>>,
+}
+
+impl<'a> ParallelExtractJobSet<'a> {
+    pub fn new() -> Self {
+        ParallelExtractJobSet {
+            extract_jobs: Default::default(),
+        }
+    }
+
+    pub fn add_job(
+        &mut self,
+        extract_job: RenderExtractJob<'a>,
+    ) {
+        self.extract_jobs.push(extract_job);
+    }
+
+    /// Blocks until every job has finished. The returned prepare jobs are in the order the
+    /// extract jobs were added, regardless of which finished first
+    pub fn extract(
+        self,
+        profiler: &Profiler,
+        extract_context: &RenderJobExtractContext<'a>,
+        frame_packet: &FramePacket,
+        views: &[&RenderView],
+    ) -> PrepareJobSet<RenderJobPrepareContext, RenderJobWriteContext> {
+        let prepare_jobs = self
+            .extract_jobs
+            .into_par_iter()
+            .map(|extract_job| {
+                let _scope = profiler.scope(extract_job.feature_debug_name());
+                extract_job.extract(extract_context, frame_packet, views)
+            })
+            .collect();
+
+        PrepareJobSet::new(prepare_jobs)
+    }
+}
+
+/// A descriptor set allocator that can be used from several threads at once, for features that
+/// split their prepare work across the render job thread pool. Allocations are serialized by a
+/// mutex, so only the code that allocates should run while it is held.
+#[derive(Clone)]
+pub struct SharedDescriptorSetAllocator {
+    allocator: Arc<Mutex<DescriptorSetAllocatorRef>>,
+}
+
+impl SharedDescriptorSetAllocator {
+    pub fn new(allocator: DescriptorSetAllocatorRef) -> Self {
+        SharedDescriptorSetAllocator {
+            allocator: Arc::new(Mutex::new(allocator)),
+        }
+    }
+
+    /// Calls `f` with exclusive access to the allocator
+    pub fn with_allocator<F: FnOnce(&mut DescriptorSetAllocatorRef) -> R, R>(
+        &self,
+        f: F,
+    ) -> R {
+        let mut guard = self.allocator.lock().unwrap();
+        (f)(&mut *guard)
+    }
+}
@@ -0,0 +1,197 @@
+use renderer::nodes::{RenderPhase, RenderPhaseIndex, RenderFeature, RenderFeatureIndex};
+use crate::phases::{
+    DepthPrepassRenderPhase, OpaqueRenderPhase, TransparentRenderPhase, PreUiRenderPhase,
+    UiRenderPhase, SelectionOutlineRenderPhase, PickingRenderPhase,
+};
+use crate::features::sprite::SpriteRenderFeature;
+use crate::features::text::TextRenderFeature;
+use crate::features::particle::ParticleRenderFeature;
+use crate::features::mesh::MeshRenderFeature;
+use crate::game_renderer::FrameInFlightToken;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::Duration;
+
+// Upper bound on registered render phases, used to size the per-phase counters
+const MAX_RENDER_PHASES: usize = 16;
+
+/// Phases in the order they are drawn, for displaying per-phase counts
+pub fn render_phase_names() -> Vec<(RenderPhaseIndex, &'static str)> {
+    vec![
+        (
+            DepthPrepassRenderPhase::render_phase_index(),
+            DepthPrepassRenderPhase::render_phase_debug_name(),
+        ),
+        (
+            OpaqueRenderPhase::render_phase_index(),
+            OpaqueRenderPhase::render_phase_debug_name(),
+        ),
+        (
+            TransparentRenderPhase::render_phase_index(),
+            TransparentRenderPhase::render_phase_debug_name(),
+        ),
+        (
+            PreUiRenderPhase::render_phase_index(),
+            PreUiRenderPhase::render_phase_debug_name(),
+        ),
+        (
+            SelectionOutlineRenderPhase::render_phase_index(),
+            SelectionOutlineRenderPhase::render_phase_debug_name(),
+        ),
+        (
+            PickingRenderPhase::render_phase_index(),
+            PickingRenderPhase::render_phase_debug_name(),
+        ),
+        (
+            UiRenderPhase::render_phase_index(),
+            UiRenderPhase::render_phase_debug_name(),
+        ),
+    ]
+}
+
+/// Features that have render nodes and therefore show up in visibility and frame packets
+pub fn render_node_feature_names() -> Vec<(RenderFeatureIndex, &'static str)> {
+    vec![
+        (
+            SpriteRenderFeature::feature_index(),
+            SpriteRenderFeature::feature_debug_name(),
+        ),
+        (
+            TextRenderFeature::feature_index(),
+            TextRenderFeature::feature_debug_name(),
+        ),
+        (
+            ParticleRenderFeature::feature_index(),
+            ParticleRenderFeature::feature_debug_name(),
+        ),
+        (
+            MeshRenderFeature::feature_index(),
+            MeshRenderFeature::feature_debug_name(),
+        ),
+    ]
+}
+
+/// Counts for a single view, gathered on the main thread during extract
+#[derive(Default, Clone, Debug)]
+pub struct ViewRenderStats {
+    pub view_name: String,
+    pub static_visible_nodes: u32,
+    pub dynamic_visible_nodes: u32,
+    // Frame packet nodes in this view, by feature name
+    pub view_nodes_per_feature: Vec<(&'static str, u32)>,
+}
+
+/// Everything counted while rendering a frame
+#[derive(Default, Clone, Debug)]
+pub struct RenderStats {
+    pub views: Vec<ViewRenderStats>,
+    // Frame packet nodes shared by all views, by feature name
+    pub frame_nodes_per_feature: Vec<(&'static str, u32)>,
+    // Submit nodes written in each phase, summed across views
+    pub submit_nodes_per_phase: Vec<(&'static str, u32)>,
+    pub draw_calls: u32,
+    // Only counts draws with a triangle count known on the CPU, GPU-culled indirect draws are
+    // not included
+    pub triangles: u64,
+    pub descriptor_sets_allocated: u32,
+    // Bytes written to host-visible buffers and dynamic uniform data
+    pub buffer_bytes_uploaded: u64,
+    pub max_frames_in_flight: u32,
+    // Earlier frames the render thread was still working on when this frame began extracting.
+    // Zero means the main and render threads did not overlap
+    pub overlapped_frames: u32,
+    // Time the main thread was blocked waiting for the render thread to catch up
+    pub frame_in_flight_wait: Duration,
+    // Time the main thread was blocked acquiring a swapchain image, which includes waiting for
+    // the GPU to finish older frames
+    pub swapchain_image_wait: Duration,
+}
+
+/// Counters shared by the extract, prepare and write contexts of a single frame. They are atomic
+/// because jobs may record from the main thread, the render thread or worker threads.
+#[derive(Default)]
+pub struct RenderStatsCounters {
+    draw_calls: AtomicU32,
+    triangles: AtomicU64,
+    descriptor_sets_allocated: AtomicU32,
+    buffer_bytes_uploaded: AtomicU64,
+    submit_nodes_per_phase: [AtomicU32; MAX_RENDER_PHASES],
+}
+
+impl RenderStatsCounters {
+    pub fn record_draw_call(
+        &self,
+        triangle_count: u32,
+    ) {
+        self.draw_calls.fetch_add(1, Ordering::Relaxed);
+        self.triangles
+            .fetch_add(triangle_count as u64, Ordering::Relaxed);
+    }
+
+    pub fn record_submit_node(
+        &self,
+        render_phase_index: RenderPhaseIndex,
+    ) {
+        if let Some(counter) = self.submit_nodes_per_phase.get(render_phase_index as usize) {
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_descriptor_set_allocated(&self) {
+        self.descriptor_sets_allocated
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_buffer_upload(
+        &self,
+        byte_count: u64,
+    ) {
+        self.buffer_bytes_uploaded
+            .fetch_add(byte_count, Ordering::Relaxed);
+    }
+
+    pub fn draw_call_count(&self) -> u32 {
+        self.draw_calls.load(Ordering::Relaxed)
+    }
+
+    /// Combines the counters with the counts gathered during extract into the frame's stats
+    pub fn finish(
+        &self,
+        views: Vec<ViewRenderStats>,
+        frame_nodes_per_feature: Vec<(&'static str, u32)>,
+        frame_in_flight: &FrameInFlightToken,
+    ) -> RenderStats {
+        let submit_nodes_per_phase = render_phase_names()
+            .into_iter()
+            .map(|(render_phase_index, name)| {
+                let count = self
+                    .submit_nodes_per_phase
+                    .get(render_phase_index as usize)
+                    .map(|x| x.load(Ordering::Relaxed))
+                    .unwrap_or(0);
+                (name, count)
+            })
+            .collect();
+
+        RenderStats {
+            views,
+            frame_nodes_per_feature,
+            submit_nodes_per_phase,
+            draw_calls: self.draw_calls.load(Ordering::Relaxed),
+            triangles: self.triangles.load(Ordering::Relaxed),
+            descriptor_sets_allocated: self.descriptor_sets_allocated.load(Ordering::Relaxed),
+            buffer_bytes_uploaded: self.buffer_bytes_uploaded.load(Ordering::Relaxed),
+            max_frames_in_flight: frame_in_flight.max_frames_in_flight(),
+            overlapped_frames: frame_in_flight.overlapped_frames(),
+            frame_in_flight_wait: frame_in_flight.wait_duration(),
+            swapchain_image_wait: frame_in_flight.swapchain_image_wait_duration(),
+        }
+    }
+}
+
+/// Stats for the most recently completed frame, updated by the GameRenderer at the start of each
+/// render. The overlay is toggled from the Render menu
+#[derive(Default)]
+pub struct RenderStatsResource {
+    pub previous_frame: RenderStats,
+    pub overlay_visible: bool,
+}
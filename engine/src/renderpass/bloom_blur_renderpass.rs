@@ -4,12 +4,11 @@ use ash::prelude::VkResult;
 use ash::version::DeviceV1_0;
 
 use renderer::vulkan::VkDeviceContext;
-use renderer::vulkan::VkSwapchain;
 use renderer::vulkan::SwapchainInfo;
 use renderer::vulkan::VkQueueFamilyIndices;
 
 use renderer::assets::resources::PipelineSwapchainInfo;
-use crate::renderpass::VkBloomRenderPassResources;
+use crate::renderpass::{VkBloomRenderPassResources, VkSceneTargets};
 
 pub struct VkBloomBlurRenderPass {
     pub device_context: VkDeviceContext,
@@ -26,7 +25,7 @@ pub struct VkBloomBlurRenderPass {
 impl VkBloomBlurRenderPass {
     pub fn new(
         device_context: &VkDeviceContext,
-        swapchain: &VkSwapchain,
+        scene_targets: &VkSceneTargets,
         pipeline_info: PipelineSwapchainInfo,
         bloom_resources: &VkBloomRenderPassResources,
     ) -> VkResult<Self> {
@@ -45,13 +44,13 @@ impl VkBloomBlurRenderPass {
             &device_context.device(),
             //&swapchain.swapchain_image_views,
             &bloom_resources.bloom_image_views,
-            &swapchain.swapchain_info,
+            &scene_targets.swapchain_info,
             &pipeline_info.pipeline.get_raw().renderpass.get_raw(),
         )?;
 
         let command_buffers = Self::create_command_buffers(
             &device_context.device(),
-            &swapchain.swapchain_info,
+            &scene_targets.swapchain_info,
             &command_pool,
         )?;
 
@@ -64,7 +63,7 @@ impl VkBloomBlurRenderPass {
 
         Self::update_command_buffer(
             &device_context,
-            &swapchain.swapchain_info,
+            &scene_targets.swapchain_info,
             pipeline_info.pipeline.get_raw().renderpass.get_raw(),
             frame_buffers[1],
             command_buffers[0],
@@ -75,7 +74,7 @@ impl VkBloomBlurRenderPass {
 
         Self::update_command_buffer(
             &device_context,
-            &swapchain.swapchain_info,
+            &scene_targets.swapchain_info,
             pipeline_info.pipeline.get_raw().renderpass.get_raw(),
             frame_buffers[0],
             command_buffers[1],
@@ -86,7 +85,7 @@ impl VkBloomBlurRenderPass {
 
         Ok(VkBloomBlurRenderPass {
             device_context: device_context.clone(),
-            swapchain_info: swapchain.swapchain_info.clone(),
+            swapchain_info: scene_targets.swapchain_info.clone(),
             frame_buffers,
             command_pool,
             command_buffers,
@@ -5,7 +5,6 @@ use std::mem::ManuallyDrop;
 use ash::version::DeviceV1_0;
 
 use renderer::vulkan::{VkDeviceContext, MsaaLevel, RenderpassAttachmentImage};
-use renderer::vulkan::VkSwapchain;
 use renderer::vulkan::SwapchainInfo;
 use renderer::vulkan::VkQueueFamilyIndices;
 
@@ -15,6 +14,7 @@ use atelier_assets::loader::handle::Handle;
 
 use renderer::assets::resources::{PipelineSwapchainInfo, DynDescriptorSet, ResourceManager};
 use renderer::assets::MaterialAsset;
+use crate::renderpass::VkSceneTargets;
 
 pub struct VkBloomRenderPassResources {
     pub device_context: VkDeviceContext,
@@ -27,16 +27,17 @@ pub struct VkBloomRenderPassResources {
 }
 
 impl VkBloomRenderPassResources {
+    // The bloom images match the scene's scaled size
     pub fn new(
         device_context: &VkDeviceContext,
-        swapchain: &VkSwapchain,
+        scene_targets: &VkSceneTargets,
         resource_manager: &mut ResourceManager,
         bloom_blur_material: Handle<MaterialAsset>,
     ) -> VkResult<Self> {
         let (bloom_image0, bloom_image_view0) = RenderpassAttachmentImage::create_image_and_view(
             device_context,
-            &swapchain.swapchain_info,
-            swapchain.color_format,
+            &scene_targets.swapchain_info,
+            scene_targets.color_format,
             vk::ImageAspectFlags::COLOR,
             vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
             MsaaLevel::Sample1,
@@ -44,8 +45,8 @@ impl VkBloomRenderPassResources {
 
         let (bloom_image1, bloom_image_view1) = RenderpassAttachmentImage::create_image_and_view(
             device_context,
-            &swapchain.swapchain_info,
-            swapchain.color_format,
+            &scene_targets.swapchain_info,
+            scene_targets.color_format,
             vk::ImageAspectFlags::COLOR,
             vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
             MsaaLevel::Sample1,
@@ -53,8 +54,8 @@ impl VkBloomRenderPassResources {
 
         let (color_image, color_image_view) = RenderpassAttachmentImage::create_image_and_view(
             device_context,
-            &swapchain.swapchain_info,
-            swapchain.color_format,
+            &scene_targets.swapchain_info,
+            scene_targets.color_format,
             vk::ImageAspectFlags::COLOR,
             vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
             MsaaLevel::Sample1,
@@ -134,7 +135,7 @@ pub struct VkBloomExtractRenderPass {
 impl VkBloomExtractRenderPass {
     pub fn new(
         device_context: &VkDeviceContext,
-        swapchain: &VkSwapchain,
+        scene_targets: &VkSceneTargets,
         pipeline_info: PipelineSwapchainInfo,
         bloom_resources: &VkBloomRenderPassResources,
     ) -> VkResult<Self> {
@@ -153,20 +154,19 @@ impl VkBloomExtractRenderPass {
             &device_context.device(),
             bloom_resources.bloom_image_views[0],
             bloom_resources.color_image_view,
-            &swapchain.swapchain_image_views,
-            &swapchain.swapchain_info,
+            &scene_targets.swapchain_info,
             &pipeline_info.pipeline.get_raw().renderpass.get_raw(),
         )?;
 
         let command_buffers = Self::create_command_buffers(
             &device_context.device(),
-            &swapchain.swapchain_info,
+            &scene_targets.swapchain_info,
             &command_pool,
         )?;
 
         Ok(VkBloomExtractRenderPass {
             device_context: device_context.clone(),
-            swapchain_info: swapchain.swapchain_info.clone(),
+            swapchain_info: scene_targets.swapchain_info.clone(),
             pipeline_info,
             frame_buffers,
             command_pool,
@@ -196,13 +196,11 @@ impl VkBloomExtractRenderPass {
         logical_device: &ash::Device,
         bloom_image_view: vk::ImageView,
         color_image_view: vk::ImageView,
-        swapchain_image_views: &[vk::ImageView],
         swapchain_info: &SwapchainInfo,
         renderpass: &vk::RenderPass,
     ) -> VkResult<Vec<vk::Framebuffer>> {
-        swapchain_image_views
-            .iter()
-            .map(|&_swapchain_image_view| {
+        (0..swapchain_info.image_count)
+            .map(|_present_index| {
                 let framebuffer_attachments = [color_image_view, bloom_image_view];
                 let frame_buffer_create_info = vk::FramebufferCreateInfo::builder()
                     .render_pass(*renderpass)
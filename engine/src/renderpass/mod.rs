@@ -1,3 +1,6 @@
+pub mod scene_targets;
+pub use scene_targets::VkSceneTargets;
+
 pub mod msaa_renderpass;
 pub use msaa_renderpass::VkMsaaRenderPass;
 
@@ -16,3 +19,32 @@ pub use opaque_renderpass::VkOpaqueRenderPass;
 
 pub mod ui_renderpass;
 pub use ui_renderpass::VkUiRenderPass;
+
+pub mod static_mesh_cull_pass;
+pub use static_mesh_cull_pass::{VkStaticMeshCullPass, MAX_STATIC_MESH_CULL_VIEWS};
+
+pub mod selection_outline_mask_renderpass;
+pub use selection_outline_mask_renderpass::VkSelectionOutlineMaskRenderPass;
+
+pub mod selection_outline_composite_renderpass;
+pub use selection_outline_composite_renderpass::VkSelectionOutlineCompositeRenderPass;
+
+pub mod picking_renderpass;
+pub use picking_renderpass::VkPickingRenderPass;
+
+use ash::vk;
+use renderer::nodes::RenderView;
+
+/// A view of the 3D scene and the part of the window it is drawn into. Its projection maps the
+/// whole window, so the region is applied as a scissor and draws that spill out of it are clipped.
+#[derive(Clone)]
+pub struct SceneView {
+    pub view: RenderView,
+    // In swapchain pixels, for passes that draw at the output resolution
+    pub region: vk::Rect2D,
+    // In scene target pixels, for passes that draw at the scene's scaled size
+    pub scene_region: vk::Rect2D,
+    // The view's projection before it is mapped into the region. Culling uses it so that things
+    // outside the region but inside the window aren't considered visible.
+    pub cull_projection: glam::Mat4,
+}
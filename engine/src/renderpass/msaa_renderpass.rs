@@ -4,9 +4,9 @@ use ash::prelude::VkResult;
 use ash::version::DeviceV1_0;
 
 use renderer::vulkan::{VkDeviceContext, MsaaLevel};
-use renderer::vulkan::VkSwapchain;
 use renderer::vulkan::SwapchainInfo;
 use renderer::vulkan::VkQueueFamilyIndices;
+use crate::renderpass::VkSceneTargets;
 
 /// Draws sprites
 pub struct VkMsaaRenderPass {
@@ -24,7 +24,7 @@ pub struct VkMsaaRenderPass {
 impl VkMsaaRenderPass {
     pub fn new(
         device_context: &VkDeviceContext,
-        swapchain: &VkSwapchain,
+        scene_targets: &VkSceneTargets,
     ) -> VkResult<Self> {
         //
         // Command Buffers
@@ -36,16 +36,16 @@ impl VkMsaaRenderPass {
 
         let command_buffers = Self::create_command_buffers(
             &device_context.device(),
-            &swapchain.swapchain_info,
+            &scene_targets.swapchain_info,
             &command_pool,
         )?;
 
-        let color_target_image = swapchain.color_attachment.target_image();
-        let color_resolved_image = swapchain.color_attachment.resolved_image();
+        let color_target_image = scene_targets.color_target_image();
+        let color_resolved_image = scene_targets.color_resolved_image();
 
         Ok(VkMsaaRenderPass {
             device_context: device_context.clone(),
-            swapchain_info: swapchain.swapchain_info.clone(),
+            swapchain_info: scene_targets.swapchain_info.clone(),
             command_pool,
             command_buffers,
             color_target_image,
@@ -4,14 +4,14 @@ use ash::prelude::VkResult;
 use ash::version::DeviceV1_0;
 
 use renderer::vulkan::{VkDeviceContext, MAX_FRAMES_IN_FLIGHT};
-use renderer::vulkan::VkSwapchain;
 use renderer::vulkan::SwapchainInfo;
 use renderer::vulkan::VkQueueFamilyIndices;
 
 use renderer::assets::resources::PipelineSwapchainInfo;
 use renderer::nodes::{PreparedRenderData, RenderView};
-use crate::phases::OpaqueRenderPhase;
+use crate::phases::{OpaqueRenderPhase, DepthPrepassRenderPhase, TransparentRenderPhase};
 use crate::render_contexts::{RenderJobWriteContext, RenderJobWriteContextFactory};
+use crate::renderpass::{VkSceneTargets, SceneView};
 use renderer::vulkan::cleanup::VkCombinedDropSink;
 
 /// Draws sprites
@@ -34,7 +34,7 @@ pub struct VkOpaqueRenderPass {
 impl VkOpaqueRenderPass {
     pub fn new(
         device_context: &VkDeviceContext,
-        swapchain: &VkSwapchain,
+        scene_targets: &VkSceneTargets,
         pipeline_info: PipelineSwapchainInfo,
     ) -> VkResult<Self> {
         //
@@ -50,22 +50,22 @@ impl VkOpaqueRenderPass {
         //
         let frame_buffers = Self::create_framebuffers(
             &device_context.device(),
-            swapchain.color_attachment.target_image_view(),
-            &swapchain.swapchain_image_views,
-            swapchain.depth_attachment.target_image_view(),
-            &swapchain.swapchain_info,
+            scene_targets.color_target_image_view,
+            scene_targets.depth_image_view,
+            &scene_targets.swapchain_info,
             &pipeline_info.pipeline.get_raw().renderpass.get_raw(),
         )?;
 
         let command_buffers = Self::create_command_buffers(
             &device_context.device(),
-            &swapchain.swapchain_info,
+            &scene_targets.swapchain_info,
             &command_pool,
         )?;
 
+        // Draws at the scene's scaled size rather than the swapchain's
         Ok(VkOpaqueRenderPass {
             device_context: device_context.clone(),
-            swapchain_info: swapchain.swapchain_info.clone(),
+            swapchain_info: scene_targets.swapchain_info.clone(),
             frame_buffers,
             command_pool,
             command_buffers,
@@ -95,14 +95,12 @@ impl VkOpaqueRenderPass {
     fn create_framebuffers(
         logical_device: &ash::Device,
         color_image_view: vk::ImageView,
-        swapchain_image_views: &[vk::ImageView],
         depth_image_view: vk::ImageView,
         swapchain_info: &SwapchainInfo,
         renderpass: &vk::RenderPass,
     ) -> VkResult<Vec<vk::Framebuffer>> {
-        swapchain_image_views
-            .iter()
-            .map(|&_swapchain_image_view| {
+        (0..swapchain_info.image_count)
+            .map(|_present_index| {
                 let framebuffer_attachments = [color_image_view, depth_image_view];
                 let frame_buffer_create_info = vk::FramebufferCreateInfo::builder()
                     .render_pass(*renderpass)
@@ -136,7 +134,8 @@ impl VkOpaqueRenderPass {
         framebuffer: vk::Framebuffer,
         command_buffer: &vk::CommandBuffer,
         prepared_render_data: &PreparedRenderData<RenderJobWriteContext>,
-        view: &RenderView,
+        scene_views: &[SceneView],
+        screen_space_view: &RenderView,
         write_context_factory: &RenderJobWriteContextFactory,
     ) -> VkResult<()> {
         let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder();
@@ -177,7 +176,70 @@ impl VkOpaqueRenderPass {
 
             let mut write_context = write_context_factory.create_context(*command_buffer);
 
-            prepared_render_data.write_view_phase::<OpaqueRenderPhase>(&view, &mut write_context);
+            let full_region = vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: swapchain_info.extents.clone(),
+            };
+
+            for scene_view in scene_views {
+                let view = &scene_view.view;
+
+                // Viewport windows may overlap, so each view starts from a clear region rather
+                // than depth testing against the views drawn before it
+                logical_device.cmd_clear_attachments(
+                    *command_buffer,
+                    &[
+                        vk::ClearAttachment {
+                            aspect_mask: vk::ImageAspectFlags::COLOR,
+                            color_attachment: 0,
+                            clear_value: clear_values[0],
+                        },
+                        vk::ClearAttachment {
+                            aspect_mask: vk::ImageAspectFlags::DEPTH,
+                            color_attachment: 0,
+                            clear_value: clear_values[1],
+                        },
+                    ],
+                    &[vk::ClearRect {
+                        rect: scene_view.scene_region,
+                        base_array_layer: 0,
+                        layer_count: 1,
+                    }],
+                );
+
+                logical_device.cmd_set_scissor(*command_buffer, 0, &[scene_view.scene_region]);
+
+                // If the depth prepass is disabled, nothing is submitted to this phase
+                prepared_render_data
+                    .write_view_phase::<DepthPrepassRenderPhase>(view, &mut write_context);
+                prepared_render_data
+                    .write_view_phase::<OpaqueRenderPhase>(view, &mut write_context);
+                prepared_render_data
+                    .write_view_phase::<TransparentRenderPhase>(view, &mut write_context);
+            }
+
+            // Screen-space sprites are drawn over the scene, so they must not be depth tested
+            // against it
+            logical_device.cmd_clear_attachments(
+                *command_buffer,
+                &[vk::ClearAttachment {
+                    aspect_mask: vk::ImageAspectFlags::DEPTH,
+                    color_attachment: 0,
+                    clear_value: clear_values[1],
+                }],
+                &[vk::ClearRect {
+                    rect: full_region,
+                    base_array_layer: 0,
+                    layer_count: 1,
+                }],
+            );
+
+            logical_device.cmd_set_scissor(*command_buffer, 0, &[full_region]);
+
+            prepared_render_data.write_view_phase::<TransparentRenderPhase>(
+                &screen_space_view,
+                &mut write_context,
+            );
 
             logical_device.cmd_end_render_pass(*command_buffer);
             logical_device.end_command_buffer(*command_buffer)
@@ -189,7 +251,8 @@ impl VkOpaqueRenderPass {
         pipeline_info: &PipelineSwapchainInfo,
         present_index: usize,
         prepared_render_data: &PreparedRenderData<RenderJobWriteContext>,
-        view: &RenderView,
+        scene_views: &[SceneView],
+        screen_space_view: &RenderView,
         write_context_factory: &RenderJobWriteContextFactory,
     ) -> VkResult<()> {
         assert!(self.renderpass == pipeline_info.pipeline.get_raw().renderpass.get_raw());
@@ -200,7 +263,8 @@ impl VkOpaqueRenderPass {
             self.frame_buffers[present_index],
             &self.command_buffers[present_index],
             prepared_render_data,
-            view,
+            scene_views,
+            screen_space_view,
             write_context_factory,
         )
     }
@@ -0,0 +1,433 @@
+use ash::vk;
+use ash::prelude::VkResult;
+use std::mem::ManuallyDrop;
+
+use ash::version::DeviceV1_0;
+
+use renderer::vulkan::{VkDeviceContext, MsaaLevel, RenderpassAttachmentImage};
+use renderer::vulkan::VkSwapchain;
+use renderer::vulkan::SwapchainInfo;
+use renderer::vulkan::VkQueueFamilyIndices;
+use renderer::vulkan::{VkImage, VkBuffer};
+
+use renderer::assets::resources::PipelineSwapchainInfo;
+use renderer::nodes::{PreparedRenderData, RenderView};
+use crate::render_contexts::{RenderJobWriteContext, RenderJobWriteContextFactory};
+use crate::phases::PickingRenderPhase;
+use crate::features::picking::{PickingRequest, PickingResult};
+
+/// Draws meshes under the cursor with their picking IDs and copies the ID and depth of the pixel
+/// under the cursor into a host-visible buffer. Only that pixel is rasterized, the rest of the ID
+/// image is left cleared. The ID is read back the next time the present index is rendered, once
+/// the GPU is done with it.
+pub struct VkPickingRenderPass {
+    pub device_context: VkDeviceContext,
+    pub swapchain_info: SwapchainInfo,
+
+    id_image: ManuallyDrop<VkImage>,
+    id_image_view: vk::ImageView,
+    depth_image: ManuallyDrop<VkImage>,
+    depth_image_view: vk::ImageView,
+
+    // The ID and the bits of the f32 depth per present index
+    readback_buffers: Vec<VkBuffer>,
+
+    // The request rendered for each present index that hasn't been read back yet
+    pending_requests: Vec<Option<PickingRequest>>,
+
+    // Static resources for the renderpass, including a frame buffer per present index
+    pub frame_buffers: Vec<vk::Framebuffer>,
+
+    // Command pool and list of command buffers, one per present index
+    pub command_pool: vk::CommandPool,
+    pub command_buffers: Vec<vk::CommandBuffer>,
+
+    renderpass: vk::RenderPass,
+}
+
+impl VkPickingRenderPass {
+    pub fn new(
+        device_context: &VkDeviceContext,
+        swapchain: &VkSwapchain,
+        pipeline_info: PipelineSwapchainInfo,
+    ) -> VkResult<Self> {
+        let (id_image, id_image_view) = RenderpassAttachmentImage::create_image_and_view(
+            device_context,
+            &swapchain.swapchain_info,
+            vk::Format::R32G32_UINT,
+            vk::ImageAspectFlags::COLOR,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+            MsaaLevel::Sample1,
+        )?;
+
+        let (depth_image, depth_image_view) = RenderpassAttachmentImage::create_image_and_view(
+            device_context,
+            &swapchain.swapchain_info,
+            swapchain.depth_format,
+            vk::ImageAspectFlags::DEPTH,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            MsaaLevel::Sample1,
+        )?;
+
+        log::trace!("id_image: {:?}", id_image);
+        log::trace!("depth_image: {:?}", depth_image);
+
+        let mut readback_buffers = Vec::with_capacity(swapchain.swapchain_info.image_count);
+        for _ in 0..swapchain.swapchain_info.image_count {
+            readback_buffers.push(VkBuffer::new(
+                device_context,
+                vk_mem::MemoryUsage::GpuToCpu,
+                vk::BufferUsageFlags::TRANSFER_DST,
+                vk::MemoryPropertyFlags::HOST_VISIBLE | vk::MemoryPropertyFlags::HOST_COHERENT,
+                std::mem::size_of::<[u32; 2]>() as u64,
+            )?);
+        }
+
+        //
+        // Command Buffers
+        //
+        let command_pool = Self::create_command_pool(
+            &device_context.device(),
+            &device_context.queue_family_indices(),
+        )?;
+
+        //
+        // Renderpass Resources
+        //
+        let frame_buffers = Self::create_framebuffers(
+            &device_context.device(),
+            id_image_view,
+            depth_image_view,
+            &swapchain.swapchain_image_views,
+            &swapchain.swapchain_info,
+            &pipeline_info.pipeline.get_raw().renderpass.get_raw(),
+        )?;
+
+        let command_buffers = Self::create_command_buffers(
+            &device_context.device(),
+            &swapchain.swapchain_info,
+            &command_pool,
+        )?;
+
+        Ok(VkPickingRenderPass {
+            device_context: device_context.clone(),
+            swapchain_info: swapchain.swapchain_info.clone(),
+            id_image,
+            id_image_view,
+            depth_image,
+            depth_image_view,
+            readback_buffers,
+            pending_requests: vec![None; swapchain.swapchain_info.image_count],
+            frame_buffers,
+            command_pool,
+            command_buffers,
+            renderpass: pipeline_info.pipeline.get_raw().renderpass.get_raw(),
+        })
+    }
+
+    fn create_command_pool(
+        logical_device: &ash::Device,
+        queue_family_indices: &VkQueueFamilyIndices,
+    ) -> VkResult<vk::CommandPool> {
+        log::trace!(
+            "Creating command pool with queue family index {}",
+            queue_family_indices.graphics_queue_family_index
+        );
+        let pool_create_info = vk::CommandPoolCreateInfo::builder()
+            .flags(
+                vk::CommandPoolCreateFlags::TRANSIENT
+                    | vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+            )
+            .queue_family_index(queue_family_indices.graphics_queue_family_index);
+
+        unsafe { logical_device.create_command_pool(&pool_create_info, None) }
+    }
+
+    fn create_framebuffers(
+        logical_device: &ash::Device,
+        id_image_view: vk::ImageView,
+        depth_image_view: vk::ImageView,
+        swapchain_image_views: &[vk::ImageView],
+        swapchain_info: &SwapchainInfo,
+        renderpass: &vk::RenderPass,
+    ) -> VkResult<Vec<vk::Framebuffer>> {
+        swapchain_image_views
+            .iter()
+            .map(|&_swapchain_image_view| {
+                let framebuffer_attachments = [id_image_view, depth_image_view];
+                let frame_buffer_create_info = vk::FramebufferCreateInfo::builder()
+                    .render_pass(*renderpass)
+                    .attachments(&framebuffer_attachments)
+                    .width(swapchain_info.extents.width)
+                    .height(swapchain_info.extents.height)
+                    .layers(1);
+
+                unsafe { logical_device.create_framebuffer(&frame_buffer_create_info, None) }
+            })
+            .collect()
+    }
+
+    fn create_command_buffers(
+        logical_device: &ash::Device,
+        swapchain_info: &SwapchainInfo,
+        command_pool: &vk::CommandPool,
+    ) -> VkResult<Vec<vk::CommandBuffer>> {
+        let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_buffer_count(swapchain_info.image_count as u32)
+            .command_pool(*command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY);
+
+        unsafe { logical_device.allocate_command_buffers(&command_buffer_allocate_info) }
+    }
+
+    fn update_command_buffer(
+        device_context: &VkDeviceContext,
+        swapchain_info: &SwapchainInfo,
+        renderpass: &vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        command_buffer: &vk::CommandBuffer,
+        id_image: vk::Image,
+        readback_buffer: vk::Buffer,
+        pixel: (u32, u32),
+        prepared_render_data: &PreparedRenderData<RenderJobWriteContext>,
+        view: &RenderView,
+        write_context_factory: &RenderJobWriteContextFactory,
+    ) -> VkResult<()> {
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder();
+
+        // 0 is never a picking ID, so pixels with no mesh read back as nothing at the far plane
+        let clear_values = [
+            vk::ClearValue {
+                color: vk::ClearColorValue {
+                    uint32: [0, 1.0f32.to_bits(), 0, 0],
+                },
+            },
+            vk::ClearValue {
+                depth_stencil: vk::ClearDepthStencilValue {
+                    depth: 1.0,
+                    stencil: 0,
+                },
+            },
+        ];
+
+        let render_pass_begin_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(*renderpass)
+            .framebuffer(framebuffer)
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: swapchain_info.extents.clone(),
+            })
+            .clear_values(&clear_values);
+
+        // picking.pipeline has a dynamic scissor, only the pixel under the cursor is drawn
+        let scissor = vk::Rect2D {
+            offset: vk::Offset2D {
+                x: pixel.0 as i32,
+                y: pixel.1 as i32,
+            },
+            extent: vk::Extent2D {
+                width: 1,
+                height: 1,
+            },
+        };
+
+        // The renderpass leaves the ID image in TRANSFER_SRC_OPTIMAL
+        let copy_region = vk::BufferImageCopy::builder()
+            .buffer_offset(0)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            })
+            .image_offset(vk::Offset3D {
+                x: pixel.0 as i32,
+                y: pixel.1 as i32,
+                z: 0,
+            })
+            .image_extent(vk::Extent3D {
+                width: 1,
+                height: 1,
+                depth: 1,
+            })
+            .build();
+
+        let host_read_barrier = vk::BufferMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::HOST_READ)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .buffer(readback_buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE)
+            .build();
+
+        // Implicitly resets the command buffer
+        unsafe {
+            let logical_device = device_context.device();
+            logical_device.begin_command_buffer(*command_buffer, &command_buffer_begin_info)?;
+
+            logical_device.cmd_begin_render_pass(
+                *command_buffer,
+                &render_pass_begin_info,
+                vk::SubpassContents::INLINE,
+            );
+
+            logical_device.cmd_set_scissor(*command_buffer, 0, &[scissor]);
+
+            let mut write_context = write_context_factory.create_context(*command_buffer);
+
+            prepared_render_data.write_view_phase::<PickingRenderPhase>(&view, &mut write_context);
+
+            logical_device.cmd_end_render_pass(*command_buffer);
+
+            logical_device.cmd_copy_image_to_buffer(
+                *command_buffer,
+                id_image,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                readback_buffer,
+                &[copy_region],
+            );
+
+            logical_device.cmd_pipeline_barrier(
+                *command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::HOST,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[host_read_barrier],
+                &[],
+            );
+
+            logical_device.end_command_buffer(*command_buffer)
+        }
+    }
+
+    /// Records the picking pass for the present index. Returns false if nothing needs to be
+    /// drawn, in which case the command buffer must not be submitted and the request reads back
+    /// as no entity.
+    pub fn update(
+        &mut self,
+        pipeline_info: &PipelineSwapchainInfo,
+        present_index: usize,
+        request: PickingRequest,
+        prepared_render_data: &PreparedRenderData<RenderJobWriteContext>,
+        view: &RenderView,
+        write_context_factory: &RenderJobWriteContextFactory,
+    ) -> VkResult<bool> {
+        assert!(self.renderpass == pipeline_info.pipeline.get_raw().renderpass.get_raw());
+
+        let has_entities = !request.entities.is_empty();
+        if has_entities {
+            let extents = self.swapchain_info.extents;
+            let pixel = (
+                request.pixel.0.min(extents.width - 1),
+                request.pixel.1.min(extents.height - 1),
+            );
+
+            Self::update_command_buffer(
+                &self.device_context,
+                &self.swapchain_info,
+                &pipeline_info.pipeline.get_raw().renderpass.get_raw(),
+                self.frame_buffers[present_index],
+                &self.command_buffers[present_index],
+                self.id_image.image,
+                self.readback_buffers[present_index].buffer,
+                pixel,
+                prepared_render_data,
+                view,
+                write_context_factory,
+            )?;
+        }
+
+        self.pending_requests[present_index] = Some(request);
+        Ok(has_entities)
+    }
+
+    /// Returns the entity under the cursor the last time this present index was rendered. The GPU
+    /// must be finished with the present index, which is the case once its swapchain image has
+    /// been acquired again.
+    pub fn read_result(
+        &mut self,
+        present_index: usize,
+    ) -> VkResult<Option<PickingResult>> {
+        let request = match self.pending_requests[present_index].take() {
+            Some(request) => request,
+            None => return Ok(None),
+        };
+
+        if request.entities.is_empty() {
+            return Ok(Some(PickingResult {
+                request_id: request.request_id,
+                camera: request.camera,
+                cursor_position: request.cursor_position,
+                entity: None,
+                depth: 1.0,
+            }));
+        }
+
+        let allocator = self.device_context.allocator();
+        let mut pixel = [0u32; 2];
+        unsafe {
+            let data = allocator
+                .map_memory(&self.readback_buffers[present_index].allocation)
+                .map_err(|_| vk::Result::ERROR_MEMORY_MAP_FAILED)?;
+            std::ptr::copy_nonoverlapping(
+                data,
+                pixel.as_mut_ptr() as *mut u8,
+                std::mem::size_of::<[u32; 2]>(),
+            );
+            allocator
+                .unmap_memory(&self.readback_buffers[present_index].allocation)
+                .map_err(|_| vk::Result::ERROR_MEMORY_MAP_FAILED)?;
+        }
+
+        let [picking_id, depth_bits] = pixel;
+        let entity = (picking_id as usize)
+            .checked_sub(1)
+            .and_then(|index| request.entities.get(index))
+            .cloned();
+
+        Ok(Some(PickingResult {
+            request_id: request.request_id,
+            camera: request.camera,
+            cursor_position: request.cursor_position,
+            entity,
+            depth: f32::from_bits(depth_bits),
+        }))
+    }
+
+    /// Call if the command buffers for the present index were not submitted, so that a stale ID
+    /// isn't read back
+    pub fn cancel_frame(
+        &mut self,
+        present_index: usize,
+    ) {
+        self.pending_requests[present_index] = None;
+    }
+}
+
+impl Drop for VkPickingRenderPass {
+    fn drop(&mut self) {
+        log::trace!("destroying VkPickingRenderPass");
+
+        unsafe {
+            let device = self.device_context.device();
+
+            device.destroy_command_pool(self.command_pool, None);
+
+            for frame_buffer in &self.frame_buffers {
+                device.destroy_framebuffer(*frame_buffer, None);
+            }
+
+            device.destroy_image_view(self.id_image_view, None);
+            ManuallyDrop::drop(&mut self.id_image);
+            device.destroy_image_view(self.depth_image_view, None);
+            ManuallyDrop::drop(&mut self.depth_image);
+        }
+
+        log::trace!("destroyed VkPickingRenderPass");
+    }
+}
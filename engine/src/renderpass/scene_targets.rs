@@ -0,0 +1,180 @@
+use ash::vk;
+use ash::prelude::VkResult;
+use std::mem::ManuallyDrop;
+
+use ash::version::DeviceV1_0;
+
+use renderer::vulkan::{VkDeviceContext, MsaaLevel, RenderpassAttachmentImage};
+use renderer::vulkan::VkSwapchain;
+use renderer::vulkan::SwapchainInfo;
+use renderer::vulkan::VkImage;
+
+/// Color and depth targets that the 3D scene is drawn into. These replace the swapchain's own
+/// attachments so that the scene can be rendered at a fraction of the output resolution. The
+/// MSAA resolve and bloom passes also run at this size, and bloom combine upscales to the
+/// swapchain image. The passes that draw into these targets only depend on them and not on the
+/// swapchain, so they can be reallocated at a new size without recreating the swapchain.
+pub struct VkSceneTargets {
+    pub device_context: VkDeviceContext,
+
+    // Copy of the swapchain's info with the extents replaced by the scaled size. Passed to
+    // renderpasses that draw into these targets in place of the swapchain's info
+    pub swapchain_info: SwapchainInfo,
+    pub color_format: vk::Format,
+    pub depth_format: vk::Format,
+
+    color_target_image: ManuallyDrop<VkImage>,
+    pub color_target_image_view: vk::ImageView,
+
+    // Without MSAA, the target is used directly and there is nothing to resolve into
+    color_resolved_image: Option<ManuallyDrop<VkImage>>,
+    color_resolved_image_view: Option<vk::ImageView>,
+
+    depth_image: ManuallyDrop<VkImage>,
+    pub depth_image_view: vk::ImageView,
+}
+
+impl VkSceneTargets {
+    pub fn new(
+        device_context: &VkDeviceContext,
+        swapchain: &VkSwapchain,
+        extents: vk::Extent2D,
+    ) -> VkResult<Self> {
+        Self::create(
+            device_context,
+            &swapchain.swapchain_info,
+            swapchain.color_format,
+            swapchain.depth_format,
+            extents,
+        )
+    }
+
+    /// Allocates targets for the same swapchain at a different size, i.e. when the render scale
+    /// changes
+    pub fn resized(
+        &self,
+        extents: vk::Extent2D,
+    ) -> VkResult<Self> {
+        Self::create(
+            &self.device_context,
+            &self.swapchain_info,
+            self.color_format,
+            self.depth_format,
+            extents,
+        )
+    }
+
+    fn create(
+        device_context: &VkDeviceContext,
+        swapchain_info: &SwapchainInfo,
+        color_format: vk::Format,
+        depth_format: vk::Format,
+        extents: vk::Extent2D,
+    ) -> VkResult<Self> {
+        let mut swapchain_info = swapchain_info.clone();
+        swapchain_info.extents = extents;
+
+        let msaa_level = swapchain_info.msaa_level;
+
+        let (color_target_image, color_target_image_view) =
+            RenderpassAttachmentImage::create_image_and_view(
+                device_context,
+                &swapchain_info,
+                color_format,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::TRANSFER_SRC
+                    | vk::ImageUsageFlags::SAMPLED,
+                msaa_level,
+            )?;
+
+        let (color_resolved_image, color_resolved_image_view) = if msaa_level != MsaaLevel::Sample1
+        {
+            let (image, image_view) = RenderpassAttachmentImage::create_image_and_view(
+                device_context,
+                &swapchain_info,
+                color_format,
+                vk::ImageAspectFlags::COLOR,
+                vk::ImageUsageFlags::COLOR_ATTACHMENT
+                    | vk::ImageUsageFlags::TRANSFER_DST
+                    | vk::ImageUsageFlags::SAMPLED,
+                MsaaLevel::Sample1,
+            )?;
+            (Some(image), Some(image_view))
+        } else {
+            (None, None)
+        };
+
+        let (depth_image, depth_image_view) = RenderpassAttachmentImage::create_image_and_view(
+            device_context,
+            &swapchain_info,
+            depth_format,
+            vk::ImageAspectFlags::DEPTH,
+            vk::ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            msaa_level,
+        )?;
+
+        log::debug!(
+            "Created scene targets at {}x{}",
+            extents.width,
+            extents.height
+        );
+
+        Ok(VkSceneTargets {
+            device_context: device_context.clone(),
+            swapchain_info,
+            color_format,
+            depth_format,
+            color_target_image,
+            color_target_image_view,
+            color_resolved_image,
+            color_resolved_image_view,
+            depth_image,
+            depth_image_view,
+        })
+    }
+
+    pub fn extents(&self) -> vk::Extent2D {
+        self.swapchain_info.extents
+    }
+
+    pub fn color_target_image(&self) -> vk::Image {
+        self.color_target_image.image
+    }
+
+    /// The single-sampled scene color, read by bloom extract
+    pub fn color_resolved_image(&self) -> vk::Image {
+        self.color_resolved_image
+            .as_ref()
+            .map(|x| x.image)
+            .unwrap_or(self.color_target_image.image)
+    }
+
+    pub fn color_resolved_image_view(&self) -> vk::ImageView {
+        self.color_resolved_image_view
+            .unwrap_or(self.color_target_image_view)
+    }
+}
+
+impl Drop for VkSceneTargets {
+    fn drop(&mut self) {
+        log::trace!("destroying VkSceneTargets");
+
+        unsafe {
+            let device = self.device_context.device();
+            device.destroy_image_view(self.color_target_image_view, None);
+            device.destroy_image_view(self.depth_image_view, None);
+            if let Some(color_resolved_image_view) = self.color_resolved_image_view {
+                device.destroy_image_view(color_resolved_image_view, None);
+            }
+
+            ManuallyDrop::drop(&mut self.color_target_image);
+            ManuallyDrop::drop(&mut self.depth_image);
+            if let Some(color_resolved_image) = &mut self.color_resolved_image {
+                ManuallyDrop::drop(color_resolved_image);
+            }
+        }
+
+        log::trace!("destroyed VkSceneTargets");
+    }
+}
@@ -0,0 +1,245 @@
+use ash::vk;
+use ash::prelude::VkResult;
+use std::mem::ManuallyDrop;
+
+use ash::version::DeviceV1_0;
+
+use renderer::vulkan::{VkDeviceContext, MsaaLevel, RenderpassAttachmentImage};
+use renderer::vulkan::VkSwapchain;
+use renderer::vulkan::SwapchainInfo;
+use renderer::vulkan::VkQueueFamilyIndices;
+use renderer::vulkan::VkImage;
+
+use renderer::assets::resources::PipelineSwapchainInfo;
+use renderer::nodes::PreparedRenderData;
+use crate::render_contexts::{RenderJobWriteContext, RenderJobWriteContextFactory};
+use crate::renderpass::SceneView;
+use crate::phases::SelectionOutlineRenderPhase;
+
+/// Draws selected and hovered meshes into a mask at the swapchain's resolution. The mask is
+/// sampled by VkSelectionOutlineCompositeRenderPass to find the edges of the objects, so it is
+/// kept at full resolution even when the scene is rendered at a lower scale.
+pub struct VkSelectionOutlineMaskRenderPass {
+    pub device_context: VkDeviceContext,
+    pub swapchain_info: SwapchainInfo,
+
+    mask_image: ManuallyDrop<VkImage>,
+    pub mask_image_view: vk::ImageView,
+
+    // Static resources for the renderpass, including a frame buffer per present index
+    pub frame_buffers: Vec<vk::Framebuffer>,
+
+    // Command pool and list of command buffers, one per present index
+    pub command_pool: vk::CommandPool,
+    pub command_buffers: Vec<vk::CommandBuffer>,
+
+    renderpass: vk::RenderPass,
+}
+
+impl VkSelectionOutlineMaskRenderPass {
+    pub fn new(
+        device_context: &VkDeviceContext,
+        swapchain: &VkSwapchain,
+        pipeline_info: PipelineSwapchainInfo,
+    ) -> VkResult<Self> {
+        let (mask_image, mask_image_view) = RenderpassAttachmentImage::create_image_and_view(
+            device_context,
+            &swapchain.swapchain_info,
+            swapchain.color_format,
+            vk::ImageAspectFlags::COLOR,
+            vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::SAMPLED,
+            MsaaLevel::Sample1,
+        )?;
+
+        log::trace!("mask_image: {:?}", mask_image);
+
+        //
+        // Command Buffers
+        //
+        let command_pool = Self::create_command_pool(
+            &device_context.device(),
+            &device_context.queue_family_indices(),
+        )?;
+
+        //
+        // Renderpass Resources
+        //
+        let frame_buffers = Self::create_framebuffers(
+            &device_context.device(),
+            mask_image_view,
+            &swapchain.swapchain_image_views,
+            &swapchain.swapchain_info,
+            &pipeline_info.pipeline.get_raw().renderpass.get_raw(),
+        )?;
+
+        let command_buffers = Self::create_command_buffers(
+            &device_context.device(),
+            &swapchain.swapchain_info,
+            &command_pool,
+        )?;
+
+        Ok(VkSelectionOutlineMaskRenderPass {
+            device_context: device_context.clone(),
+            swapchain_info: swapchain.swapchain_info.clone(),
+            mask_image,
+            mask_image_view,
+            frame_buffers,
+            command_pool,
+            command_buffers,
+            renderpass: pipeline_info.pipeline.get_raw().renderpass.get_raw(),
+        })
+    }
+
+    fn create_command_pool(
+        logical_device: &ash::Device,
+        queue_family_indices: &VkQueueFamilyIndices,
+    ) -> VkResult<vk::CommandPool> {
+        log::trace!(
+            "Creating command pool with queue family index {}",
+            queue_family_indices.graphics_queue_family_index
+        );
+        let pool_create_info = vk::CommandPoolCreateInfo::builder()
+            .flags(
+                vk::CommandPoolCreateFlags::TRANSIENT
+                    | vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+            )
+            .queue_family_index(queue_family_indices.graphics_queue_family_index);
+
+        unsafe { logical_device.create_command_pool(&pool_create_info, None) }
+    }
+
+    fn create_framebuffers(
+        logical_device: &ash::Device,
+        mask_image_view: vk::ImageView,
+        swapchain_image_views: &[vk::ImageView],
+        swapchain_info: &SwapchainInfo,
+        renderpass: &vk::RenderPass,
+    ) -> VkResult<Vec<vk::Framebuffer>> {
+        swapchain_image_views
+            .iter()
+            .map(|&_swapchain_image_view| {
+                let framebuffer_attachments = [mask_image_view];
+                let frame_buffer_create_info = vk::FramebufferCreateInfo::builder()
+                    .render_pass(*renderpass)
+                    .attachments(&framebuffer_attachments)
+                    .width(swapchain_info.extents.width)
+                    .height(swapchain_info.extents.height)
+                    .layers(1);
+
+                unsafe { logical_device.create_framebuffer(&frame_buffer_create_info, None) }
+            })
+            .collect()
+    }
+
+    fn create_command_buffers(
+        logical_device: &ash::Device,
+        swapchain_info: &SwapchainInfo,
+        command_pool: &vk::CommandPool,
+    ) -> VkResult<Vec<vk::CommandBuffer>> {
+        let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_buffer_count(swapchain_info.image_count as u32)
+            .command_pool(*command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY);
+
+        unsafe { logical_device.allocate_command_buffers(&command_buffer_allocate_info) }
+    }
+
+    fn update_command_buffer(
+        device_context: &VkDeviceContext,
+        swapchain_info: &SwapchainInfo,
+        renderpass: &vk::RenderPass,
+        framebuffer: vk::Framebuffer,
+        command_buffer: &vk::CommandBuffer,
+        prepared_render_data: &PreparedRenderData<RenderJobWriteContext>,
+        scene_views: &[SceneView],
+        write_context_factory: &RenderJobWriteContextFactory,
+    ) -> VkResult<()> {
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder();
+
+        // Nothing is selected or hovered where the mask is cleared
+        let clear_values = [vk::ClearValue {
+            color: vk::ClearColorValue {
+                float32: [0.0, 0.0, 0.0, 0.0],
+            },
+        }];
+
+        let render_pass_begin_info = vk::RenderPassBeginInfo::builder()
+            .render_pass(*renderpass)
+            .framebuffer(framebuffer)
+            .render_area(vk::Rect2D {
+                offset: vk::Offset2D { x: 0, y: 0 },
+                extent: swapchain_info.extents.clone(),
+            })
+            .clear_values(&clear_values);
+
+        // Implicitly resets the command buffer
+        unsafe {
+            let logical_device = device_context.device();
+            logical_device.begin_command_buffer(*command_buffer, &command_buffer_begin_info)?;
+
+            logical_device.cmd_begin_render_pass(
+                *command_buffer,
+                &render_pass_begin_info,
+                vk::SubpassContents::INLINE,
+            );
+
+            let mut write_context = write_context_factory.create_context(*command_buffer);
+
+            // Without the scissor, objects that extend past a view's region would be outlined
+            // over the other viewports
+            for scene_view in scene_views {
+                logical_device.cmd_set_scissor(*command_buffer, 0, &[scene_view.region]);
+
+                prepared_render_data.write_view_phase::<SelectionOutlineRenderPhase>(
+                    &scene_view.view,
+                    &mut write_context,
+                );
+            }
+
+            logical_device.cmd_end_render_pass(*command_buffer);
+            logical_device.end_command_buffer(*command_buffer)
+        }
+    }
+
+    pub fn update(
+        &mut self,
+        pipeline_info: &PipelineSwapchainInfo,
+        present_index: usize,
+        prepared_render_data: &PreparedRenderData<RenderJobWriteContext>,
+        scene_views: &[SceneView],
+        write_context_factory: &RenderJobWriteContextFactory,
+    ) -> VkResult<()> {
+        assert!(self.renderpass == pipeline_info.pipeline.get_raw().renderpass.get_raw());
+        Self::update_command_buffer(
+            &self.device_context,
+            &self.swapchain_info,
+            &pipeline_info.pipeline.get_raw().renderpass.get_raw(),
+            self.frame_buffers[present_index],
+            &self.command_buffers[present_index],
+            prepared_render_data,
+            scene_views,
+            write_context_factory,
+        )
+    }
+}
+
+impl Drop for VkSelectionOutlineMaskRenderPass {
+    fn drop(&mut self) {
+        log::trace!("destroying VkSelectionOutlineMaskRenderPass");
+
+        unsafe {
+            let device = self.device_context.device();
+
+            device.destroy_command_pool(self.command_pool, None);
+
+            for frame_buffer in &self.frame_buffers {
+                device.destroy_framebuffer(*frame_buffer, None);
+            }
+
+            device.destroy_image_view(self.mask_image_view, None);
+            ManuallyDrop::drop(&mut self.mask_image);
+        }
+
+        log::trace!("destroyed VkSelectionOutlineMaskRenderPass");
+    }
+}
@@ -0,0 +1,380 @@
+use ash::vk;
+use ash::prelude::VkResult;
+
+use ash::version::DeviceV1_0;
+
+use renderer::vulkan::{VkDeviceContext, VkSwapchain, SwapchainInfo, VkQueueFamilyIndices};
+use crate::features::mesh::StaticMeshFrameData;
+use std::ffi::CString;
+
+const CULL_WORKGROUP_SIZE: u32 = 64;
+
+/// Static meshes are culled for at most this many views per frame, one for each editor viewport
+pub const MAX_STATIC_MESH_CULL_VIEWS: usize = 4;
+
+/// Frustum culls static meshes with a compute shader, producing the indirect draw commands and
+/// per-instance transforms that the mesh feature draws from. Each view is culled into its own
+/// buffers. Must be submitted before the opaque renderpass.
+pub struct VkStaticMeshCullPass {
+    pub device_context: VkDeviceContext,
+    pub swapchain_info: SwapchainInfo,
+
+    descriptor_set_layout: vk::DescriptorSetLayout,
+    pipeline_layout: vk::PipelineLayout,
+    pipeline: vk::Pipeline,
+
+    // Descriptor sets are rewritten every frame to point at that frame's buffers. Indexed by
+    // present index, then by view
+    descriptor_pool: vk::DescriptorPool,
+    descriptor_sets: Vec<Vec<vk::DescriptorSet>>,
+
+    // Command pool and list of command buffers, one per present index
+    pub command_pool: vk::CommandPool,
+    pub command_buffers: Vec<vk::CommandBuffer>,
+}
+
+impl VkStaticMeshCullPass {
+    pub fn new(
+        device_context: &VkDeviceContext,
+        swapchain: &VkSwapchain,
+        cull_shader_module: vk::ShaderModule,
+    ) -> VkResult<Self> {
+        let logical_device = device_context.device();
+
+        let descriptor_set_layout = Self::create_descriptor_set_layout(logical_device)?;
+
+        let descriptor_set_layouts = [descriptor_set_layout];
+        let pipeline_layout_create_info =
+            vk::PipelineLayoutCreateInfo::builder().set_layouts(&descriptor_set_layouts);
+        let pipeline_layout =
+            unsafe { logical_device.create_pipeline_layout(&pipeline_layout_create_info, None)? };
+
+        let entry_name = CString::new("main").unwrap();
+        let stage = vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::COMPUTE)
+            .module(cull_shader_module)
+            .name(&entry_name);
+        let pipeline_create_info = vk::ComputePipelineCreateInfo::builder()
+            .stage(*stage)
+            .layout(pipeline_layout);
+        let pipeline = unsafe {
+            logical_device
+                .create_compute_pipelines(
+                    vk::PipelineCache::null(),
+                    &[*pipeline_create_info],
+                    None,
+                )
+                .map_err(|(_, e)| e)?[0]
+        };
+
+        let image_count = swapchain.swapchain_info.image_count;
+        let set_count = (image_count * MAX_STATIC_MESH_CULL_VIEWS) as u32;
+        let pool_sizes = [
+            vk::DescriptorPoolSize::builder()
+                .ty(vk::DescriptorType::UNIFORM_BUFFER)
+                .descriptor_count(set_count)
+                .build(),
+            vk::DescriptorPoolSize::builder()
+                .ty(vk::DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(set_count * 3)
+                .build(),
+        ];
+        let descriptor_pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+            .max_sets(set_count)
+            .pool_sizes(&pool_sizes);
+        let descriptor_pool =
+            unsafe { logical_device.create_descriptor_pool(&descriptor_pool_create_info, None)? };
+
+        let set_layouts = vec![descriptor_set_layout; MAX_STATIC_MESH_CULL_VIEWS];
+        let descriptor_set_allocate_info = vk::DescriptorSetAllocateInfo::builder()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&set_layouts);
+        let mut descriptor_sets = Vec::with_capacity(image_count);
+        for _ in 0..image_count {
+            descriptor_sets.push(unsafe {
+                logical_device.allocate_descriptor_sets(&descriptor_set_allocate_info)?
+            });
+        }
+
+        let command_pool =
+            Self::create_command_pool(logical_device, &device_context.queue_family_indices())?;
+
+        let command_buffers =
+            Self::create_command_buffers(logical_device, &swapchain.swapchain_info, &command_pool)?;
+
+        Ok(VkStaticMeshCullPass {
+            device_context: device_context.clone(),
+            swapchain_info: swapchain.swapchain_info.clone(),
+            descriptor_set_layout,
+            pipeline_layout,
+            pipeline,
+            descriptor_pool,
+            descriptor_sets,
+            command_pool,
+            command_buffers,
+        })
+    }
+
+    fn create_descriptor_set_layout(
+        logical_device: &ash::Device
+    ) -> VkResult<vk::DescriptorSetLayout> {
+        // Matches mesh_cull.comp: cull params, instances, draw commands, per-instance output
+        let descriptor_types = [
+            vk::DescriptorType::UNIFORM_BUFFER,
+            vk::DescriptorType::STORAGE_BUFFER,
+            vk::DescriptorType::STORAGE_BUFFER,
+            vk::DescriptorType::STORAGE_BUFFER,
+        ];
+
+        let bindings: Vec<_> = descriptor_types
+            .iter()
+            .enumerate()
+            .map(|(binding, descriptor_type)| {
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(binding as u32)
+                    .descriptor_type(*descriptor_type)
+                    .descriptor_count(1)
+                    .stage_flags(vk::ShaderStageFlags::COMPUTE)
+                    .build()
+            })
+            .collect();
+
+        let create_info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
+        unsafe { logical_device.create_descriptor_set_layout(&create_info, None) }
+    }
+
+    fn create_command_pool(
+        logical_device: &ash::Device,
+        queue_family_indices: &VkQueueFamilyIndices,
+    ) -> VkResult<vk::CommandPool> {
+        log::trace!(
+            "Creating command pool with queue family index {}",
+            queue_family_indices.graphics_queue_family_index
+        );
+        let pool_create_info = vk::CommandPoolCreateInfo::builder()
+            .flags(
+                vk::CommandPoolCreateFlags::TRANSIENT
+                    | vk::CommandPoolCreateFlags::RESET_COMMAND_BUFFER,
+            )
+            .queue_family_index(queue_family_indices.graphics_queue_family_index);
+
+        unsafe { logical_device.create_command_pool(&pool_create_info, None) }
+    }
+
+    fn create_command_buffers(
+        logical_device: &ash::Device,
+        swapchain_info: &SwapchainInfo,
+        command_pool: &vk::CommandPool,
+    ) -> VkResult<Vec<vk::CommandBuffer>> {
+        let command_buffer_allocate_info = vk::CommandBufferAllocateInfo::builder()
+            .command_buffer_count(swapchain_info.image_count as u32)
+            .command_pool(*command_pool)
+            .level(vk::CommandBufferLevel::PRIMARY);
+
+        unsafe { logical_device.allocate_command_buffers(&command_buffer_allocate_info) }
+    }
+
+    fn update_descriptor_set(
+        logical_device: &ash::Device,
+        descriptor_set: vk::DescriptorSet,
+        frame_data: &StaticMeshFrameData,
+    ) {
+        let buffers = [
+            (
+                vk::DescriptorType::UNIFORM_BUFFER,
+                frame_data.cull_params_buffer.get_raw().buffer,
+            ),
+            (
+                vk::DescriptorType::STORAGE_BUFFER,
+                frame_data.gpu_data.instance_buffer.get_raw().buffer,
+            ),
+            (
+                vk::DescriptorType::STORAGE_BUFFER,
+                frame_data.draw_command_buffer.get_raw().buffer,
+            ),
+            (
+                vk::DescriptorType::STORAGE_BUFFER,
+                frame_data.per_instance_buffer.get_raw().buffer,
+            ),
+        ];
+
+        let buffer_infos: Vec<_> = buffers
+            .iter()
+            .map(|(_, buffer)| {
+                [vk::DescriptorBufferInfo::builder()
+                    .buffer(*buffer)
+                    .offset(0)
+                    .range(vk::WHOLE_SIZE)
+                    .build()]
+            })
+            .collect();
+
+        let writes: Vec<_> = buffers
+            .iter()
+            .zip(&buffer_infos)
+            .enumerate()
+            .map(|(binding, ((descriptor_type, _), buffer_info))| {
+                vk::WriteDescriptorSet::builder()
+                    .dst_set(descriptor_set)
+                    .dst_binding(binding as u32)
+                    .descriptor_type(*descriptor_type)
+                    .buffer_info(buffer_info)
+                    .build()
+            })
+            .collect();
+
+        unsafe {
+            logical_device.update_descriptor_sets(&writes, &[]);
+        }
+    }
+
+    fn update_command_buffer(
+        logical_device: &ash::Device,
+        command_buffer: vk::CommandBuffer,
+        pipeline: vk::Pipeline,
+        pipeline_layout: vk::PipelineLayout,
+        descriptor_sets: &[vk::DescriptorSet],
+        frame_data: &[StaticMeshFrameData],
+    ) -> VkResult<()> {
+        let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder();
+
+        let buffer_barrier = |buffer: vk::Buffer, src_access_mask, dst_access_mask| {
+            vk::BufferMemoryBarrier::builder()
+                .src_access_mask(src_access_mask)
+                .dst_access_mask(dst_access_mask)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .buffer(buffer)
+                .offset(0)
+                .size(vk::WHOLE_SIZE)
+                .build()
+        };
+
+        // Every view's results are made visible to the draws with a single barrier at the end
+        let mut to_draw_barriers = Vec::with_capacity(frame_data.len() * 2);
+
+        unsafe {
+            logical_device.begin_command_buffer(command_buffer, &command_buffer_begin_info)?;
+            logical_device.cmd_bind_pipeline(
+                command_buffer,
+                vk::PipelineBindPoint::COMPUTE,
+                pipeline,
+            );
+
+            for (view_frame_data, descriptor_set) in frame_data.iter().zip(descriptor_sets) {
+                let draw_command_buffer = view_frame_data.draw_command_buffer.get_raw().buffer;
+                let draw_command_buffer_size = (view_frame_data.gpu_data.draws.len()
+                    * std::mem::size_of::<vk::DrawIndexedIndirectCommand>())
+                    as u64;
+
+                logical_device.cmd_copy_buffer(
+                    command_buffer,
+                    view_frame_data
+                        .gpu_data
+                        .draw_command_template_buffer
+                        .get_raw()
+                        .buffer,
+                    draw_command_buffer,
+                    &[vk::BufferCopy {
+                        src_offset: 0,
+                        dst_offset: 0,
+                        size: draw_command_buffer_size,
+                    }],
+                );
+
+                // Draw commands with instance_count = 0 must land before the shader starts counting
+                logical_device.cmd_pipeline_barrier(
+                    command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::COMPUTE_SHADER,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[buffer_barrier(
+                        draw_command_buffer,
+                        vk::AccessFlags::TRANSFER_WRITE,
+                        vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE,
+                    )],
+                    &[],
+                );
+
+                let group_count = (view_frame_data.gpu_data.instance_count + CULL_WORKGROUP_SIZE
+                    - 1)
+                    / CULL_WORKGROUP_SIZE;
+
+                logical_device.cmd_bind_descriptor_sets(
+                    command_buffer,
+                    vk::PipelineBindPoint::COMPUTE,
+                    pipeline_layout,
+                    0,
+                    &[*descriptor_set],
+                    &[],
+                );
+                logical_device.cmd_dispatch(command_buffer, group_count, 1, 1);
+
+                to_draw_barriers.push(buffer_barrier(
+                    draw_command_buffer,
+                    vk::AccessFlags::SHADER_WRITE,
+                    vk::AccessFlags::INDIRECT_COMMAND_READ,
+                ));
+                to_draw_barriers.push(buffer_barrier(
+                    view_frame_data.per_instance_buffer.get_raw().buffer,
+                    vk::AccessFlags::SHADER_WRITE,
+                    vk::AccessFlags::SHADER_READ,
+                ));
+            }
+
+            logical_device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::DRAW_INDIRECT | vk::PipelineStageFlags::VERTEX_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &to_draw_barriers,
+                &[],
+            );
+
+            logical_device.end_command_buffer(command_buffer)
+        }
+    }
+
+    // Culls static meshes for each view in frame_data, at most MAX_STATIC_MESH_CULL_VIEWS
+    pub fn update(
+        &mut self,
+        present_index: usize,
+        frame_data: &[StaticMeshFrameData],
+    ) -> VkResult<()> {
+        assert!(frame_data.len() <= MAX_STATIC_MESH_CULL_VIEWS);
+        let logical_device = self.device_context.device();
+        let descriptor_sets = &self.descriptor_sets[present_index];
+
+        for (view_frame_data, descriptor_set) in frame_data.iter().zip(descriptor_sets) {
+            Self::update_descriptor_set(logical_device, *descriptor_set, view_frame_data);
+        }
+
+        Self::update_command_buffer(
+            logical_device,
+            self.command_buffers[present_index],
+            self.pipeline,
+            self.pipeline_layout,
+            descriptor_sets,
+            frame_data,
+        )
+    }
+}
+
+impl Drop for VkStaticMeshCullPass {
+    fn drop(&mut self) {
+        log::trace!("destroying VkStaticMeshCullPass");
+
+        unsafe {
+            let device = self.device_context.device();
+            device.destroy_command_pool(self.command_pool, None);
+            device.destroy_descriptor_pool(self.descriptor_pool, None);
+            device.destroy_pipeline(self.pipeline, None);
+            device.destroy_pipeline_layout(self.pipeline_layout, None);
+            device.destroy_descriptor_set_layout(self.descriptor_set_layout, None);
+        }
+
+        log::trace!("destroyed VkStaticMeshCullPass");
+    }
+}
@@ -9,8 +9,9 @@ use renderer::vulkan::SwapchainInfo;
 use renderer::vulkan::VkQueueFamilyIndices;
 
 use renderer::assets::resources::PipelineSwapchainInfo;
-use renderer::nodes::{PreparedRenderData, RenderView};
+use renderer::nodes::PreparedRenderData;
 use crate::render_contexts::{RenderJobWriteContext, RenderJobWriteContextFactory};
+use crate::renderpass::SceneView;
 use renderer::vulkan::cleanup::VkCombinedDropSink;
 use crate::phases::{UiRenderPhase, PreUiRenderPhase};
 
@@ -132,7 +133,7 @@ impl VkUiRenderPass {
         framebuffer: vk::Framebuffer,
         command_buffer: &vk::CommandBuffer,
         prepared_render_data: &PreparedRenderData<RenderJobWriteContext>,
-        view: &RenderView,
+        scene_views: &[SceneView],
         write_context_factory: &RenderJobWriteContextFactory,
     ) -> VkResult<()> {
         let command_buffer_begin_info = vk::CommandBufferBeginInfo::builder();
@@ -158,8 +159,16 @@ impl VkUiRenderPass {
 
             let mut write_context = write_context_factory.create_context(*command_buffer);
 
-            prepared_render_data.write_view_phase::<PreUiRenderPhase>(&view, &mut write_context);
-            prepared_render_data.write_view_phase::<UiRenderPhase>(&view, &mut write_context);
+            // Debug draws that ignore depth (i.e. gizmos) are drawn over each view's region
+            for scene_view in scene_views {
+                logical_device.cmd_set_scissor(*command_buffer, 0, &[scene_view.region]);
+                prepared_render_data
+                    .write_view_phase::<PreUiRenderPhase>(&scene_view.view, &mut write_context);
+            }
+
+            // imgui is not associated with a view, its nodes are the same in all of them
+            prepared_render_data
+                .write_view_phase::<UiRenderPhase>(&scene_views[0].view, &mut write_context);
 
             logical_device.cmd_end_render_pass(*command_buffer);
             logical_device.end_command_buffer(*command_buffer)
@@ -171,7 +180,7 @@ impl VkUiRenderPass {
         pipeline_info: &PipelineSwapchainInfo,
         present_index: usize,
         prepared_render_data: &PreparedRenderData<RenderJobWriteContext>,
-        view: &RenderView,
+        scene_views: &[SceneView],
         write_context_factory: &RenderJobWriteContextFactory,
     ) -> VkResult<()> {
         assert!(self.renderpass == pipeline_info.pipeline.get_raw().renderpass.get_raw());
@@ -182,7 +191,7 @@ impl VkUiRenderPass {
             self.frame_buffers[present_index],
             &self.command_buffers[present_index],
             prepared_render_data,
-            view,
+            scene_views,
             write_context_factory,
         )
     }
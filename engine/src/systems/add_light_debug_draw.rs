@@ -5,24 +5,26 @@ use crate::components::{
     DirectionalLightComponent, PointLightComponent, SpotLightComponent,
 };
 use minimum::resources::{DebugDraw3DResource, DebugDraw3DDepthBehavior};
+use crate::features::debug3d::DebugDraw3DShapesResource;
 use minimum::components::{TransformComponentDef, TransformComponent};
 
 pub fn add_light_debug_draw() -> Box<dyn Schedulable> {
     SystemBuilder::new("quit_if_escape_pressed")
         .write_resource::<DebugDraw3DResource>()
+        .write_resource::<DebugDraw3DShapesResource>()
         .with_query(<Read<DirectionalLightComponent>>::query())
         .with_query(<(Read<TransformComponent>, Read<PointLightComponent>)>::query())
         .with_query(<(Read<TransformComponent>, Read<SpotLightComponent>)>::query())
         .build(
             |_,
              world,
-             debug_draw,
+             (debug_draw, debug_draw_shapes),
              (directional_light_query, point_light_query, spot_light_query)| {
                 for light in directional_light_query.iter(world) {
                     let light_from = glam::Vec3::new(0.0, 0.0, 0.0);
                     let light_to = light.direction;
 
-                    debug_draw.add_line(light_from, *light_to, light.color.extend(1.0), DebugDraw3DDepthBehavior::Normal);
+                    debug_draw_shapes.add_arrow(light_from, *light_to, light.color.extend(1.0), DebugDraw3DDepthBehavior::Normal, 0.0);
                 }
 
                 for (position, light) in point_light_query.iter(world) {
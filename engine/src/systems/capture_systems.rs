@@ -0,0 +1,73 @@
+use legion::prelude::*;
+use sdl2::keyboard::Keycode;
+use minimum::resources::InputResource;
+use minimum::resources::TimeResource;
+use minimum_sdl2::input::Sdl2KeyboardKey;
+use crate::game_renderer::GameRenderer;
+use std::time::{Duration, Instant};
+
+const SCREENSHOT_DIRECTORY: &str = "screenshots";
+const FRAME_SEQUENCE_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+
+// F12 takes a screenshot including UI, shift+F12 takes one without UI. F11 starts/stops writing
+// every frame to a numbered image sequence at a fixed 60hz timestep
+pub fn capture_keybinds() -> Box<dyn Schedulable> {
+    SystemBuilder::new("capture_keybinds")
+        .read_resource::<InputResource>()
+        .read_resource::<GameRenderer>()
+        .build(|_, _, (input_state, game_renderer), _| {
+            let timestamp = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|x| x.as_millis())
+                .unwrap_or(0);
+
+            if input_state.is_key_just_down(Sdl2KeyboardKey::new(Keycode::F12).into()) {
+                let include_ui =
+                    !input_state.is_key_down(Sdl2KeyboardKey::new(Keycode::LShift).into());
+                let path = std::path::Path::new(SCREENSHOT_DIRECTORY)
+                    .join(format!("screenshot_{}.png", timestamp));
+                game_renderer.request_screenshot(path, include_ui);
+            }
+
+            if input_state.is_key_just_down(Sdl2KeyboardKey::new(Keycode::F11).into()) {
+                if game_renderer.frame_sequence_capture_timestep().is_some() {
+                    game_renderer.end_frame_sequence_capture();
+                } else {
+                    let directory = std::path::Path::new(SCREENSHOT_DIRECTORY)
+                        .join(format!("sequence_{}", timestamp));
+                    game_renderer.begin_frame_sequence_capture(
+                        directory,
+                        FRAME_SEQUENCE_TIMESTEP,
+                        true,
+                    );
+                }
+            }
+        })
+}
+
+// Replaces minimum's advance_time. While a frame sequence capture is running, simulation time
+// advances by the capture's fixed timestep instead of wall-clock time so that the written frames
+// play back at the correct speed no matter how long each frame took to render and save.
+pub fn advance_time_with_frame_capture() -> Box<dyn Schedulable> {
+    let mut capture_instant: Option<Instant> = None;
+    SystemBuilder::new("advance_time_with_frame_capture")
+        .read_resource::<GameRenderer>()
+        .write_resource::<TimeResource>()
+        .build(move |_, _, (game_renderer, time_resource), _| {
+            match game_renderer.frame_sequence_capture_timestep() {
+                Some(timestep) => {
+                    let instant = capture_instant.get_or_insert_with(Instant::now);
+                    *instant += timestep;
+
+                    time_resource.time_state.update();
+                    if !time_resource.is_simulation_paused() {
+                        time_resource.simulation_time.update_with_time(*instant);
+                    }
+                }
+                None => {
+                    capture_instant = None;
+                    time_resource.advance_time();
+                }
+            }
+        })
+}
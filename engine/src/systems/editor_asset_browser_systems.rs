@@ -0,0 +1,397 @@
+use legion::prelude::*;
+use atelier_assets::core::{AssetTypeId, AssetUuid};
+use atelier_assets::loader::handle::Handle;
+use atelier_assets::loader::{LoadStatus, Loader};
+use minimum::resources::{AssetResource, ImguiResource, UniverseResource};
+use minimum::resources::editor::{EditorStateResource, PostCommitSelection};
+use minimum::components::TransformComponentDef;
+use minimum::pipeline::PrefabAsset;
+use minimum::ComponentRegistryResource;
+use renderer::assets::{ImageAsset, ImageAssetData};
+use type_uuid::TypeUuid;
+use imgui::ImString;
+use crate::asset_catalog::{asset_type_name, AssetCatalog, AssetCatalogEntry, ASSET_DRAG_DROP_TYPE};
+use crate::assets::gltf::{MeshAsset, MeshAssetData};
+use crate::components::MeshComponentDef;
+use crate::features::imgui::ImguiTextureResource;
+
+const THUMBNAIL_SIZE: f32 = 128.0;
+
+/// Whether the asset browser is shown and what is selected in it. Toggled from the Assets menu
+pub struct AssetBrowserWindowState {
+    pub open: bool,
+    search: ImString,
+    selected_asset: Option<AssetUuid>,
+    // Kept loaded while an image is selected so that it can be drawn in the details pane
+    thumbnail: Option<(AssetUuid, Handle<ImageAsset>)>,
+}
+
+impl Default for AssetBrowserWindowState {
+    fn default() -> Self {
+        AssetBrowserWindowState {
+            open: false,
+            search: ImString::with_capacity(128),
+            selected_asset: None,
+            thumbnail: None,
+        }
+    }
+}
+
+// Actions that need the world or exclusive access to resources, applied after the UI is drawn
+enum AssetBrowserAction {
+    OpenPrefab(AssetUuid),
+    InstantiateMesh(AssetUuid),
+}
+
+fn load_asset<T>(
+    asset_id: AssetUuid,
+    asset_resource: &AssetResource,
+) -> Handle<T> {
+    let load_handle = asset_resource.loader().add_ref(asset_id);
+    Handle::<T>::new(asset_resource.tx().clone(), load_handle)
+}
+
+fn type_display_name(asset_type: AssetTypeId) -> String {
+    asset_type_name(asset_type)
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| format!("{:?}", asset_type))
+}
+
+fn entry_matches_search(
+    entry: &AssetCatalogEntry,
+    search: &str,
+) -> bool {
+    if search.is_empty() {
+        return true;
+    }
+
+    let tags = entry
+        .search_tags
+        .iter()
+        .filter_map(|(_, value)| value.as_ref());
+    std::iter::once(&entry.path)
+        .chain(entry.name.iter())
+        .chain(tags)
+        .any(|x| x.to_lowercase().contains(search))
+}
+
+// Lists the assets in the daemon's metadata grouped by type, with details and actions for the
+// selected asset. Assets can be dragged from the list onto asset fields in the inspector.
+pub fn editor_asset_browser_window(
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    let mut action = None;
+
+    {
+        let mut imgui_resource = resources.get_mut::<ImguiResource>().unwrap();
+        let mut window_state = resources.get_mut::<AssetBrowserWindowState>().unwrap();
+        let mut imgui_textures = resources.get_mut::<ImguiTextureResource>().unwrap();
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+
+        imgui_resource.with_ui(|ui| {
+            ui.main_menu_bar(|| {
+                ui.menu(imgui::im_str!("Assets"), true, || {
+                    let clicked = imgui::MenuItem::new(imgui::im_str!("Asset Browser"))
+                        .selected(window_state.open)
+                        .build(ui);
+                    if clicked {
+                        window_state.open = !window_state.open;
+                    }
+                });
+            });
+
+            if !window_state.open {
+                // Don't keep the thumbnail loaded while the window is closed
+                window_state.thumbnail = None;
+                return;
+            }
+
+            let mut open = window_state.open;
+            imgui::Window::new(imgui::im_str!("Asset Browser"))
+                .opened(&mut open)
+                .size([700.0, 450.0], imgui::Condition::FirstUseEver)
+                .build(ui, || {
+                    AssetCatalog::with(|catalog| {
+                        action = draw_asset_browser(
+                            ui,
+                            catalog,
+                            &mut *window_state,
+                            &*asset_resource,
+                            &mut *imgui_textures,
+                        );
+                    });
+                });
+            window_state.open = open;
+        });
+    }
+
+    match action {
+        Some(AssetBrowserAction::OpenPrefab(prefab_id)) => {
+            if let Err(e) = EditorStateResource::open_prefab(world, resources, prefab_id) {
+                log::error!("Could not open prefab {:?}: {:?}", prefab_id, e);
+            }
+        }
+        Some(AssetBrowserAction::InstantiateMesh(mesh_id)) => {
+            instantiate_mesh(resources, mesh_id);
+        }
+        None => {}
+    }
+}
+
+fn draw_asset_browser(
+    ui: &imgui::Ui,
+    catalog: &mut AssetCatalog,
+    window_state: &mut AssetBrowserWindowState,
+    asset_resource: &AssetResource,
+    imgui_textures: &mut ImguiTextureResource,
+) -> Option<AssetBrowserAction> {
+    if ui.button(imgui::im_str!("Refresh"), [0.0, 0.0]) {
+        catalog.request_rescan();
+    }
+    ui.same_line(0.0);
+    ui.input_text(imgui::im_str!("Search"), &mut window_state.search)
+        .build();
+    let search = window_state.search.to_str().to_lowercase();
+
+    ui.columns(2, imgui::im_str!("asset_browser_columns"), true);
+
+    //
+    // Asset list, grouped by type
+    //
+    let mut asset_types: Vec<AssetTypeId> = vec![];
+    for entry in catalog.entries() {
+        if !asset_types.contains(&entry.asset_type) {
+            asset_types.push(entry.asset_type);
+        }
+    }
+    asset_types.sort_by_key(|asset_type| type_display_name(*asset_type));
+
+    let drag_drop_type = ImString::new(ASSET_DRAG_DROP_TYPE);
+    let mut dragged_asset = None;
+    imgui::ChildWindow::new(imgui::im_str!("asset_list")).build(ui, || {
+        for asset_type in asset_types {
+            let entries: Vec<_> = catalog
+                .entries()
+                .iter()
+                .filter(|entry| entry.asset_type == asset_type)
+                .filter(|entry| entry_matches_search(entry, &search))
+                .collect();
+            if entries.is_empty() {
+                continue;
+            }
+
+            let header = imgui::im_str!("{} ({})", type_display_name(asset_type), entries.len());
+            if !ui.collapsing_header(&header).build() {
+                continue;
+            }
+
+            for entry in entries {
+                let clicked = imgui::Selectable::new(&imgui::im_str!(
+                    "{}##{:?}",
+                    entry.display_name(),
+                    entry.id
+                ))
+                .selected(window_state.selected_asset == Some(entry.id))
+                .build(ui);
+                if clicked {
+                    window_state.selected_asset = Some(entry.id);
+                }
+
+                if let Some(tooltip) = imgui::DragDropSource::new(&drag_drop_type).begin(ui) {
+                    dragged_asset = Some(entry.id);
+                    ui.text(&imgui::im_str!("{}", entry.display_name()));
+                    tooltip.end();
+                }
+            }
+        }
+    });
+
+    if let Some(dragged_asset) = dragged_asset {
+        catalog.set_dragged_asset(dragged_asset);
+    }
+
+    ui.next_column();
+
+    //
+    // Details of the selected asset
+    //
+    let mut action = None;
+    let selected_entry = window_state
+        .selected_asset
+        .and_then(|asset_id| catalog.entry(asset_id));
+    match selected_entry {
+        Some(entry) => {
+            action = draw_asset_details(
+                ui,
+                catalog,
+                entry,
+                window_state,
+                asset_resource,
+                imgui_textures,
+            );
+        }
+        None => {
+            ui.text_disabled(imgui::im_str!("No asset selected"));
+            window_state.thumbnail = None;
+        }
+    }
+
+    ui.columns(1, imgui::im_str!("asset_browser_columns"), false);
+    action
+}
+
+fn draw_asset_details(
+    ui: &imgui::Ui,
+    catalog: &AssetCatalog,
+    entry: &AssetCatalogEntry,
+    window_state: &mut AssetBrowserWindowState,
+    asset_resource: &AssetResource,
+    imgui_textures: &mut ImguiTextureResource,
+) -> Option<AssetBrowserAction> {
+    let mut action = None;
+    let loader = asset_resource.loader();
+
+    ui.text(imgui::im_str!(
+        "{}",
+        entry.name.as_ref().unwrap_or(&entry.path)
+    ));
+    ui.text(imgui::im_str!(
+        "Type: {}",
+        type_display_name(entry.asset_type)
+    ));
+    ui.text(imgui::im_str!("Source: {}", entry.path));
+    ui.text(imgui::im_str!("ID: {:?}", entry.id));
+
+    let load_status = loader
+        .get_load(entry.id)
+        .map(|load_handle| format!("{:?}", loader.get_load_status(load_handle)))
+        .unwrap_or_else(|| "Not loaded".to_string());
+    ui.text(imgui::im_str!("Load status: {}", load_status));
+
+    if !entry.search_tags.is_empty() {
+        ui.separator();
+        for (key, value) in &entry.search_tags {
+            match value {
+                Some(value) => ui.text(imgui::im_str!("{}: {}", key, value)),
+                None => ui.text(imgui::im_str!("{}", key)),
+            }
+        }
+    }
+
+    //
+    // Thumbnail
+    //
+    if entry.asset_type.0 == ImageAssetData::UUID {
+        let thumbnail_is_current = window_state
+            .thumbnail
+            .as_ref()
+            .map(|(asset_id, _)| *asset_id == entry.id)
+            .unwrap_or(false);
+        if !thumbnail_is_current {
+            let handle = load_asset::<ImageAsset>(entry.id, asset_resource);
+            window_state.thumbnail = Some((entry.id, handle));
+        }
+
+        ui.separator();
+        let (_, handle) = window_state.thumbnail.as_ref().unwrap();
+        if let LoadStatus::Loaded = handle.load_status(loader) {
+            let texture_id = imgui_textures.add_image(handle);
+            imgui::Image::new(texture_id, [THUMBNAIL_SIZE, THUMBNAIL_SIZE]).build(ui);
+        } else {
+            ui.text_disabled(imgui::im_str!("Loading thumbnail..."));
+        }
+    } else {
+        window_state.thumbnail = None;
+    }
+
+    //
+    // Actions
+    //
+    ui.separator();
+    if entry.asset_type.0 == PrefabAsset::UUID {
+        if ui.button(imgui::im_str!("Open Prefab"), [0.0, 0.0]) {
+            action = Some(AssetBrowserAction::OpenPrefab(entry.id));
+        }
+    }
+
+    if entry.asset_type.0 == MeshAssetData::UUID {
+        if ui.button(imgui::im_str!("Add to Prefab"), [0.0, 0.0]) {
+            action = Some(AssetBrowserAction::InstantiateMesh(entry.id));
+        }
+    }
+
+    //
+    // Dependencies in both directions. Clicking one selects it.
+    //
+    let mut select_asset = None;
+    ui.separator();
+    ui.text(imgui::im_str!("Dependencies ({})", entry.load_deps.len()));
+    for dep in &entry.load_deps {
+        let name = catalog
+            .entry(*dep)
+            .map(|dep_entry| dep_entry.display_name())
+            .unwrap_or_else(|| format!("{:?}", dep));
+        if imgui::Selectable::new(&imgui::im_str!("  {}##dep{:?}", name, dep)).build(ui) {
+            select_asset = Some(*dep);
+        }
+    }
+
+    let dependents: Vec<_> = catalog
+        .entries()
+        .iter()
+        .filter(|x| x.load_deps.contains(&entry.id))
+        .collect();
+    ui.text(imgui::im_str!("Used by ({})", dependents.len()));
+    for dependent in dependents {
+        let label = imgui::im_str!("  {}##user{:?}", dependent.display_name(), dependent.id);
+        if imgui::Selectable::new(&label).build(ui) {
+            select_asset = Some(dependent.id);
+        }
+    }
+
+    if select_asset.is_some() {
+        window_state.selected_asset = select_asset;
+    }
+
+    action
+}
+
+// Adds an entity with the mesh at the origin of the opened prefab. This goes through an editor
+// transaction so that it can be undone.
+fn instantiate_mesh(
+    resources: &Resources,
+    mesh_id: AssetUuid,
+) {
+    let mesh = {
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+        load_asset::<MeshAsset>(mesh_id, &*asset_resource)
+    };
+
+    let universe = resources.get::<UniverseResource>().unwrap();
+    let component_registry = resources.get::<ComponentRegistryResource>().unwrap();
+    let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
+
+    let mut transaction = match editor_state.create_empty_transaction(&*universe) {
+        Some(transaction) => transaction,
+        None => {
+            log::warn!("Can't add a mesh, no prefab is open");
+            return;
+        }
+    };
+
+    let mesh_component = MeshComponentDef {
+        mesh: Some(mesh.into()),
+        ..Default::default()
+    };
+    transaction.world_mut().insert(
+        (),
+        (0..1).map(|_| (TransformComponentDef::default(), mesh_component.clone())),
+    );
+
+    if let Some(diffs) =
+        transaction.create_transaction_diffs(component_registry.components_by_uuid())
+    {
+        editor_state.enqueue_apply_diffs(diffs, PostCommitSelection::SelectAllInTransaction);
+    }
+}
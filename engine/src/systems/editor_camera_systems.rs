@@ -0,0 +1,417 @@
+use legion::prelude::*;
+use glam::{Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+use minimum::input::{KeyboardKey, MouseButton};
+use minimum::resources::{InputResource, TimeResource};
+use minimum::resources::editor::{EditorSelectionResource, EditorSettingsResource, EditorStateResource};
+use ncollide3d::bounding_volume::{BoundingVolume, AABB};
+use super::editor_viewport_systems::{EditorViewportResource, EditorViewportKind};
+
+// Flying moves with the move keys while the right mouse is down, see editor_camera_controls
+pub struct EditorCameraKeybinds {
+    pub move_forward: KeyboardKey,
+    pub move_back: KeyboardKey,
+    pub move_left: KeyboardKey,
+    pub move_right: KeyboardKey,
+    pub move_up: KeyboardKey,
+    pub move_down: KeyboardKey,
+    pub move_fast: KeyboardKey,
+    pub orbit_modifier: KeyboardKey,
+    pub focus: KeyboardKey,
+}
+
+const DEFAULT_FLY_SPEED: f32 = 5.0;
+const MIN_FLY_SPEED: f32 = 0.25;
+const MAX_FLY_SPEED: f32 = 200.0;
+// Each wheel notch scales the fly speed by this much
+const FLY_SPEED_WHEEL_MULTIPLIER: f32 = 1.2;
+// Each wheel notch moves the camera this fraction of the way to the focus point
+const DOLLY_WHEEL_FRACTION: f32 = 0.1;
+const MIN_DISTANCE: f32 = 0.1;
+// Radians per pixel of mouse movement
+const LOOK_SENSITIVITY: f32 = 0.005;
+// Pitch is limited to slightly less than straight up/down so that the view matrix is defined
+const MAX_PITCH: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+// When focusing on the selection, how many times the selection's radius to back away
+const FOCUS_DISTANCE_MULTIPLIER: f32 = 2.5;
+
+// Position and orientation of the editor camera. The camera looks at focus from distance away,
+// so orbiting rotates around focus and flying moves focus along with the camera. Z is up.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct EditorCameraState {
+    focus: [f32; 3],
+    // Radians counterclockwise from +X, looking down on the XY plane
+    yaw: f32,
+    // Radians above the XY plane
+    pitch: f32,
+    distance: f32,
+    fly_speed: f32,
+}
+
+impl Default for EditorCameraState {
+    fn default() -> Self {
+        EditorCameraState::look_at(Vec3::new(-8.0, -1.0, 3.0), Vec3::zero())
+    }
+}
+
+impl EditorCameraState {
+    pub fn look_at(
+        eye: Vec3,
+        focus: Vec3,
+    ) -> Self {
+        let mut state = EditorCameraState {
+            focus: [focus.x(), focus.y(), focus.z()],
+            yaw: 0.0,
+            pitch: 0.0,
+            distance: 1.0,
+            fly_speed: DEFAULT_FLY_SPEED,
+        };
+        state.set_eye_keeping_focus(eye);
+        state
+    }
+
+    pub fn focus(&self) -> Vec3 {
+        Vec3::from(self.focus)
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        Vec3::new(
+            self.pitch.cos() * self.yaw.cos(),
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+        )
+    }
+
+    pub fn right(&self) -> Vec3 {
+        Vec3::new(self.yaw.sin(), -self.yaw.cos(), 0.0)
+    }
+
+    pub fn up(&self) -> Vec3 {
+        self.right().cross(self.forward())
+    }
+
+    pub fn eye_position(&self) -> Vec3 {
+        self.focus() - self.forward() * self.distance
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        Mat4::look_at_rh(self.eye_position(), self.focus(), Vec3::new(0.0, 0.0, 1.0))
+    }
+
+    fn set_focus(
+        &mut self,
+        focus: Vec3,
+    ) {
+        self.focus = [focus.x(), focus.y(), focus.z()];
+    }
+
+    // Points the camera at the current focus from the given position
+    fn set_eye_keeping_focus(
+        &mut self,
+        eye: Vec3,
+    ) {
+        let offset = self.focus() - eye;
+        self.distance = offset.length().max(MIN_DISTANCE);
+        let forward = offset / self.distance;
+        self.yaw = forward.y().atan2(forward.x());
+        self.pitch = forward
+            .z()
+            .max(-1.0)
+            .min(1.0)
+            .asin()
+            .max(-MAX_PITCH)
+            .min(MAX_PITCH);
+    }
+
+    // Moves the camera without changing where it looks
+    fn translate(
+        &mut self,
+        offset: Vec3,
+    ) {
+        self.set_focus(self.focus() + offset);
+    }
+
+    // Turns the camera in place
+    fn look(
+        &mut self,
+        delta_yaw: f32,
+        delta_pitch: f32,
+    ) {
+        let eye = self.eye_position();
+        self.rotate(delta_yaw, delta_pitch);
+        self.set_focus(eye + self.forward() * self.distance);
+    }
+
+    // Moves the camera around the focus point
+    fn orbit(
+        &mut self,
+        delta_yaw: f32,
+        delta_pitch: f32,
+    ) {
+        self.rotate(delta_yaw, delta_pitch);
+    }
+
+    fn rotate(
+        &mut self,
+        delta_yaw: f32,
+        delta_pitch: f32,
+    ) {
+        self.yaw += delta_yaw;
+        self.pitch = (self.pitch + delta_pitch).max(-MAX_PITCH).min(MAX_PITCH);
+    }
+
+    fn dolly(
+        &mut self,
+        amount: f32,
+    ) {
+        self.distance = (self.distance - amount).max(MIN_DISTANCE);
+    }
+}
+
+// The camera the editor views the scene with. It is separate from any camera in the scene and is
+// not part of the prefab. GameRenderer renders the main view from it. The camera placed in each
+// prefab is stored in the editor settings (see EditorSettingsResource) and saved with them.
+pub struct EditorCameraResource {
+    keybinds: EditorCameraKeybinds,
+    state: EditorCameraState,
+    // The editor settings key of the prefab the camera state belongs to
+    settings_key: Option<String>,
+    previous_mouse_position: Option<Vec2>,
+    moved_last_update: bool,
+}
+
+impl EditorCameraResource {
+    pub fn new(keybinds: EditorCameraKeybinds) -> Self {
+        EditorCameraResource {
+            keybinds,
+            state: EditorCameraState::default(),
+            settings_key: None,
+            previous_mouse_position: None,
+            moved_last_update: false,
+        }
+    }
+
+    pub fn keybinds(&self) -> &EditorCameraKeybinds {
+        &self.keybinds
+    }
+
+    pub fn state(&self) -> &EditorCameraState {
+        &self.state
+    }
+
+    pub fn eye_position(&self) -> Vec3 {
+        self.state.eye_position()
+    }
+
+    pub fn view_matrix(&self) -> Mat4 {
+        self.state.view_matrix()
+    }
+
+    pub fn fly_speed(&self) -> f32 {
+        self.state.fly_speed
+    }
+
+    // Restores the camera that was saved for the prefab when a different one is opened
+    fn set_opened_prefab(
+        &mut self,
+        editor_settings: &EditorSettingsResource,
+        prefab_id: Option<[u8; 16]>,
+    ) {
+        let settings_key = prefab_id
+            .map(|prefab_id| format!("editor_camera/{}", uuid::Uuid::from_bytes(prefab_id)));
+        if settings_key == self.settings_key {
+            return;
+        }
+
+        self.state = settings_key
+            .as_ref()
+            .and_then(|key| editor_settings.get_setting::<EditorCameraState>(key))
+            .unwrap_or_default();
+        self.settings_key = settings_key;
+    }
+
+    fn save_state(
+        &mut self,
+        editor_settings: &mut EditorSettingsResource,
+    ) {
+        if let Some(settings_key) = &self.settings_key {
+            editor_settings.set_setting(settings_key, &self.state);
+        }
+    }
+}
+
+// Center and radius of the bounds of the selected entities' selection shapes
+pub(super) fn selection_bounds(editor_selection: &EditorSelectionResource) -> Option<(Vec3, f32)> {
+    let selected_entities = editor_selection.selected_entities();
+    let mut aabb: Option<AABB<f32>> = None;
+    for (_, object) in editor_selection
+        .editor_selection_world()
+        .collision_objects()
+    {
+        if !selected_entities.contains(object.data()) {
+            continue;
+        }
+
+        let object_aabb = object.shape().aabb(object.position());
+        aabb = Some(match aabb {
+            Some(aabb) => aabb.merged(&object_aabb),
+            None => object_aabb,
+        });
+    }
+
+    aabb.map(|aabb| {
+        let center = aabb.center();
+        (
+            Vec3::new(center.x, center.y, center.z),
+            aabb.half_extents().norm(),
+        )
+    })
+}
+
+// Keys are from EditorCameraKeybinds, see create_editor_camera_keybinds for the defaults.
+// Right mouse: look around, and fly with the move keys (move_fast to go faster). The mouse wheel
+// changes the fly speed while flying and dollies otherwise.
+// Orbit modifier+right mouse: orbit around the selection, or the focus point if nothing is selected
+// Middle mouse: pan. Orbit modifier+middle mouse: dolly
+// Focus key: focus on the selection
+// Mouse input is ignored while another viewport is under the mouse, see EditorViewportResource
+pub fn editor_camera_controls() -> Box<dyn Schedulable> {
+    SystemBuilder::new("editor_camera_controls")
+        .read_resource::<InputResource>()
+        .read_resource::<TimeResource>()
+        .read_resource::<EditorStateResource>()
+        .read_resource::<EditorSelectionResource>()
+        .read_resource::<EditorViewportResource>()
+        .write_resource::<EditorSettingsResource>()
+        .write_resource::<EditorCameraResource>()
+        .build(
+            |_,
+             _,
+             (
+                input_state,
+                time_state,
+                editor_state,
+                editor_selection,
+                viewports,
+                editor_settings,
+                camera,
+            ),
+             _| {
+                let prefab_id = editor_state
+                    .opened_prefab()
+                    .map(|opened_prefab| opened_prefab.uncooked_prefab().prefab_id());
+                camera.set_opened_prefab(&*editor_settings, prefab_id);
+
+                // Borrow the resource directly so the keybinds and state can be borrowed apart
+                let camera = &mut **camera;
+                let keybinds = &camera.keybinds;
+                let key_down = |key: KeyboardKey| input_state.is_key_down(key);
+                let alt_down = key_down(keybinds.orbit_modifier);
+                let mouse_active = viewports.active_viewport() == EditorViewportKind::Perspective;
+                let right_down = mouse_active && input_state.is_mouse_down(MouseButton::RIGHT);
+                let middle_down = mouse_active && input_state.is_mouse_down(MouseButton::MIDDLE);
+
+                let mouse_position = input_state.mouse_position();
+                let mouse_delta = camera
+                    .previous_mouse_position
+                    .map(|previous| mouse_position - previous)
+                    .unwrap_or_else(Vec2::zero);
+                camera.previous_mouse_position = Some(mouse_position);
+
+                let dt = time_state.system_time.previous_update_dt();
+                let wheel_delta = if mouse_active {
+                    input_state.mouse_wheel_delta().y as f32
+                } else {
+                    0.0
+                };
+                let state = &mut camera.state;
+                let mut moved = false;
+
+                if right_down && alt_down {
+                    // Orbit around the selection, keeping the camera where it is
+                    if input_state.is_mouse_just_down(MouseButton::RIGHT) {
+                        if let Some((center, _)) = selection_bounds(&*editor_selection) {
+                            let eye = state.eye_position();
+                            state.set_focus(center);
+                            state.set_eye_keeping_focus(eye);
+                        }
+                    }
+
+                    state.orbit(
+                        -mouse_delta.x() * LOOK_SENSITIVITY,
+                        -mouse_delta.y() * LOOK_SENSITIVITY,
+                    );
+                    moved = true;
+                } else if right_down {
+                    state.look(
+                        -mouse_delta.x() * LOOK_SENSITIVITY,
+                        -mouse_delta.y() * LOOK_SENSITIVITY,
+                    );
+
+                    let mut direction = Vec3::zero();
+                    if key_down(keybinds.move_forward) {
+                        direction += state.forward();
+                    }
+                    if key_down(keybinds.move_back) {
+                        direction -= state.forward();
+                    }
+                    if key_down(keybinds.move_right) {
+                        direction += state.right();
+                    }
+                    if key_down(keybinds.move_left) {
+                        direction -= state.right();
+                    }
+                    if key_down(keybinds.move_up) {
+                        direction += Vec3::new(0.0, 0.0, 1.0);
+                    }
+                    if key_down(keybinds.move_down) {
+                        direction -= Vec3::new(0.0, 0.0, 1.0);
+                    }
+
+                    if direction.length_squared() > 0.0 {
+                        let mut speed = state.fly_speed;
+                        if key_down(keybinds.move_fast) {
+                            speed *= 4.0;
+                        }
+                        state.translate(direction.normalize() * speed * dt);
+                    }
+
+                    if wheel_delta != 0.0 {
+                        state.fly_speed = (state.fly_speed
+                            * FLY_SPEED_WHEEL_MULTIPLIER.powf(wheel_delta))
+                        .max(MIN_FLY_SPEED)
+                        .min(MAX_FLY_SPEED);
+                    }
+
+                    moved = true;
+                } else if middle_down && alt_down {
+                    state.dolly(-mouse_delta.y() * state.distance * LOOK_SENSITIVITY);
+                    moved = true;
+                } else if middle_down {
+                    // Pan so that the point at the focus distance follows the mouse
+                    let units_per_pixel = state.distance * LOOK_SENSITIVITY * 0.25;
+                    let offset = state.right() * -mouse_delta.x() * units_per_pixel
+                        + state.up() * mouse_delta.y() * units_per_pixel;
+                    state.translate(offset);
+                    moved = true;
+                } else if wheel_delta != 0.0 {
+                    state.dolly(wheel_delta * state.distance * DOLLY_WHEEL_FRACTION);
+                    moved = true;
+                }
+
+                if input_state.is_key_just_down(keybinds.focus) {
+                    if let Some((center, radius)) = selection_bounds(&*editor_selection) {
+                        state.set_focus(center);
+                        state.distance = radius.max(MIN_DISTANCE) * FOCUS_DISTANCE_MULTIPLIER;
+                        moved = true;
+                    }
+                }
+
+                // Save once the camera comes to rest rather than every frame it moves
+                if camera.moved_last_update && !moved {
+                    camera.save_state(&mut *editor_settings);
+                }
+                camera.moved_last_update = moved;
+            },
+        )
+}
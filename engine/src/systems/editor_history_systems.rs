@@ -0,0 +1,511 @@
+use legion::prelude::*;
+use legion_prefab::Prefab;
+use std::sync::Arc;
+use serde::Serialize;
+use atelier_assets::loader::handle::SerdeContext;
+use minimum::resources::{AssetResource, InputResource};
+use minimum::resources::editor::{EditorSettingsResource, EditorStateResource};
+use minimum::editor::systems::{
+    editor_process_edit_diffs, editor_process_editor_ops, reload_editor_state_if_file_changed,
+};
+use minimum::components::TransformComponentDef;
+use crate::components::{
+    MeshComponentDef, SpriteComponentDef, TextComponentDef, ParticleEmitterComponentDef,
+    PointLightComponent, SpotLightComponent, DirectionalLightComponent,
+};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+enum HistoryStep {
+    Undo,
+    Redo,
+}
+
+// Descriptions of the transactions committed to the opened prefab, so that they can be listed and
+// undone/redone to any point. The editor keeps the actual undo chain. Entries are recorded when
+// the editor commits edit diffs, and undo/redo moves one step per update so that each step can be
+// checked against the editor's chain, see the thread-local functions below.
+pub struct EditorHistoryResource {
+    // Oldest first
+    entries: Vec<String>,
+    // Number of entries that are currently applied. Entries after this can be redone.
+    applied_count: usize,
+    // Number of entries that undo/redo is moving towards
+    target_count: usize,
+    // Undo/redo enqueued in the editor for this update
+    pending_step: Option<HistoryStep>,
+    prefab_id: Option<[u8; 16]>,
+    pub window_open: bool,
+}
+
+impl EditorHistoryResource {
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        EditorHistoryResource {
+            entries: vec![],
+            applied_count: 0,
+            target_count: 0,
+            pending_step: None,
+            prefab_id: None,
+            window_open: false,
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn applied_count(&self) -> usize {
+        self.applied_count
+    }
+
+    // Undoes or redoes transactions until the given number of entries are applied. This takes
+    // one update per transaction.
+    pub fn jump_to(
+        &mut self,
+        applied_count: usize,
+    ) {
+        self.target_count = applied_count.min(self.entries.len());
+    }
+
+    pub fn undo(&mut self) {
+        if self.target_count > 0 {
+            self.jump_to(self.target_count - 1);
+        }
+    }
+
+    pub fn redo(&mut self) {
+        self.jump_to(self.target_count + 1);
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.applied_count = 0;
+        self.target_count = 0;
+        self.pending_step = None;
+    }
+
+    // Clears the history if a different prefab (or none) was opened. Returns false if it was.
+    fn follow_opened_prefab(
+        &mut self,
+        prefab: Option<&Arc<Prefab>>,
+    ) -> bool {
+        let prefab_id = prefab.map(|prefab| prefab.prefab_id());
+        if prefab_id.is_some() && prefab_id == self.prefab_id {
+            return true;
+        }
+
+        self.prefab_id = prefab_id;
+        self.clear();
+        false
+    }
+
+    fn next_step(&self) -> Option<HistoryStep> {
+        if self.target_count < self.applied_count {
+            Some(HistoryStep::Undo)
+        } else if self.target_count > self.applied_count {
+            Some(HistoryStep::Redo)
+        } else {
+            None
+        }
+    }
+
+    fn record_transaction(
+        &mut self,
+        description: String,
+    ) {
+        self.entries.truncate(self.applied_count);
+        self.entries.push(description);
+        self.applied_count += 1;
+        self.target_count = self.applied_count;
+    }
+
+    // Called once the editor has processed the pending step. If the prefab didn't change, the
+    // editor's chain had nothing to undo/redo at this point, so the entries past that end of the
+    // chain are dropped.
+    fn finish_step(
+        &mut self,
+        stepped: bool,
+    ) {
+        match (self.pending_step.take(), stepped) {
+            (Some(HistoryStep::Undo), true) => self.applied_count -= 1,
+            (Some(HistoryStep::Redo), true) => self.applied_count += 1,
+            (Some(HistoryStep::Undo), false) => {
+                self.entries.drain(..self.applied_count);
+                self.applied_count = 0;
+                self.target_count = 0;
+            }
+            (Some(HistoryStep::Redo), false) => {
+                self.entries.truncate(self.applied_count);
+                self.target_count = self.applied_count;
+            }
+            (None, _) => {}
+        }
+    }
+}
+
+fn opened_prefab(resources: &Resources) -> Option<Arc<Prefab>> {
+    resources
+        .get::<EditorStateResource>()
+        .unwrap()
+        .opened_prefab()
+        .map(|opened_prefab| opened_prefab.uncooked_prefab().clone())
+}
+
+//
+// Describing transactions
+//
+struct ComponentChange {
+    component_name: &'static str,
+    // Top-level fields of the component that differ. Empty if the component was added/removed.
+    fields: Vec<String>,
+}
+
+fn short_type_name<T>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+// Names of the fields that differ between two values of a struct, found by serializing them
+fn changed_fields<T: Serialize>(
+    old: &T,
+    new: &T,
+) -> Vec<String> {
+    let to_value = |value: &T| {
+        ron::ser::to_string(value)
+            .ok()
+            .and_then(|x| ron::de::from_str::<ron::Value>(&x).ok())
+    };
+
+    match (to_value(old), to_value(new)) {
+        (Some(ron::Value::Map(old)), Some(ron::Value::Map(new))) => old
+            .iter()
+            .filter(|(key, value)| new.get(key) != Some(value))
+            .filter_map(|(key, _)| match key {
+                ron::Value::String(key) => Some(key.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    }
+}
+
+fn compare_component<T: legion::storage::Component + PartialEq + Serialize>(
+    (old_world, old_entity): (&World, Entity),
+    (new_world, new_entity): (&World, Entity),
+    changes: &mut Vec<ComponentChange>,
+) {
+    let old = old_world.get_component::<T>(old_entity);
+    let new = new_world.get_component::<T>(new_entity);
+    let fields = match (&old, &new) {
+        (Some(old), Some(new)) if **old != **new => changed_fields(&**old, &**new),
+        (Some(_), None) | (None, Some(_)) => vec![],
+        _ => return,
+    };
+
+    changes.push(ComponentChange {
+        component_name: short_type_name::<T>(),
+        fields,
+    });
+}
+
+// The components registered with the inspector in create_editor_inspector_registry
+fn changed_components(
+    old: (&World, Entity),
+    new: (&World, Entity),
+) -> Vec<ComponentChange> {
+    let mut changes = vec![];
+    compare_component::<TransformComponentDef>(old, new, &mut changes);
+    compare_component::<MeshComponentDef>(old, new, &mut changes);
+    compare_component::<SpriteComponentDef>(old, new, &mut changes);
+    compare_component::<TextComponentDef>(old, new, &mut changes);
+    compare_component::<ParticleEmitterComponentDef>(old, new, &mut changes);
+    compare_component::<PointLightComponent>(old, new, &mut changes);
+    compare_component::<SpotLightComponent>(old, new, &mut changes);
+    compare_component::<DirectionalLightComponent>(old, new, &mut changes);
+    changes
+}
+
+fn entity_count(count: usize) -> String {
+    if count == 1 {
+        "entity".to_string()
+    } else {
+        format!("{} entities", count)
+    }
+}
+
+// e.g. "Move 3 entities", "Edit PointLightComponent.range" or "Add entity". Returns None if no
+// difference was found in the entities or the components that are compared.
+fn describe_prefab_change(
+    old: &Prefab,
+    new: &Prefab,
+) -> Option<String> {
+    let old_entities = &old.prefab_meta.entities;
+    let new_entities = &new.prefab_meta.entities;
+
+    let added = new_entities
+        .keys()
+        .filter(|uuid| !old_entities.contains_key(*uuid))
+        .count();
+    let removed = old_entities
+        .keys()
+        .filter(|uuid| !new_entities.contains_key(*uuid))
+        .count();
+
+    let mut edited_entities = vec![];
+    for (uuid, new_entity) in new_entities {
+        if let Some(old_entity) = old_entities.get(uuid) {
+            let changes = changed_components((&old.world, *old_entity), (&new.world, *new_entity));
+            if !changes.is_empty() {
+                edited_entities.push(changes);
+            }
+        }
+    }
+
+    let mut parts = vec![];
+    if added > 0 {
+        parts.push(format!("Add {}", entity_count(added)));
+    }
+
+    if removed > 0 {
+        parts.push(format!("Delete {}", entity_count(removed)));
+    }
+
+    if !edited_entities.is_empty() {
+        let changes = edited_entities.iter().flatten();
+        let transform_name = short_type_name::<TransformComponentDef>();
+        let only_transforms = changes
+            .clone()
+            .all(|change| change.component_name == transform_name);
+        let transform_fields_contain = |s: &str| {
+            changes
+                .clone()
+                .any(|change| change.fields.iter().any(|x| x.contains(s)))
+        };
+
+        let part = if only_transforms && transform_fields_contain("position") {
+            format!("Move {}", entity_count(edited_entities.len()))
+        } else if only_transforms && transform_fields_contain("rotation") {
+            format!("Rotate {}", entity_count(edited_entities.len()))
+        } else if only_transforms && transform_fields_contain("scale") {
+            format!("Scale {}", entity_count(edited_entities.len()))
+        } else if edited_entities.len() == 1 && edited_entities[0].len() == 1 {
+            let change = &edited_entities[0][0];
+            match change.fields.as_slice() {
+                [field] => format!("Edit {}.{}", change.component_name, field),
+                _ => format!("Edit {}", change.component_name),
+            }
+        } else {
+            format!("Edit {}", entity_count(edited_entities.len()))
+        };
+
+        parts.push(part);
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(", "))
+    }
+}
+
+//
+// Systems
+//
+pub fn editor_history_keybinds() -> Box<dyn Schedulable> {
+    SystemBuilder::new("editor_history_keybinds")
+        .read_resource::<InputResource>()
+        .read_resource::<EditorSettingsResource>()
+        .write_resource::<EditorHistoryResource>()
+        .build(|_, _, (input_state, editor_settings, history), _| {
+            let keybinds = editor_settings.keybinds();
+            if !input_state.is_key_down(keybinds.action_undo_redo_modifier) {
+                return;
+            }
+
+            if input_state.is_key_just_down(keybinds.action_redo) {
+                history.redo();
+            } else if input_state.is_key_just_down(keybinds.action_undo) {
+                history.undo();
+            }
+        })
+}
+
+// Replaces reload_editor_state_if_file_changed. The editor reopens the prefab from its file, so the
+// history no longer applies to it.
+pub fn editor_reload_if_file_changed(
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    let previous_prefab = opened_prefab(resources);
+    reload_editor_state_if_file_changed(world, resources);
+    let prefab = opened_prefab(resources);
+
+    let mut history = resources.get_mut::<EditorHistoryResource>().unwrap();
+    let reloaded = match (&previous_prefab, &prefab) {
+        (Some(previous_prefab), Some(prefab)) => !Arc::ptr_eq(previous_prefab, prefab),
+        _ => false,
+    };
+    if reloaded {
+        history.clear();
+    }
+    history.follow_opened_prefab(prefab.as_ref());
+}
+
+// Replaces editor_process_edit_diffs. Committing diffs is how the editor adds a transaction to its
+// undo chain, so a changed prefab here is a new entry.
+pub fn editor_process_edit_diffs_with_history(
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    let previous_prefab = opened_prefab(resources);
+    editor_process_edit_diffs(world, resources);
+    let prefab = opened_prefab(resources);
+
+    let mut history = resources.get_mut::<EditorHistoryResource>().unwrap();
+    if !history.follow_opened_prefab(prefab.as_ref()) {
+        return;
+    }
+
+    let (previous_prefab, prefab) = match (previous_prefab, prefab) {
+        (Some(previous_prefab), Some(prefab)) if !Arc::ptr_eq(&previous_prefab, &prefab) => {
+            (previous_prefab, prefab)
+        }
+        _ => return,
+    };
+
+    // Serializing components that contain handles requires a loader
+    let asset_resource = resources.get::<AssetResource>().unwrap();
+    let description = SerdeContext::with(
+        asset_resource.loader(),
+        asset_resource.tx().as_ref().clone(),
+        || describe_prefab_change(&*previous_prefab, &*prefab),
+    );
+
+    // Every commit is a transaction in the editor's undo chain, even if the change is in something
+    // that isn't compared here. Skipping it would leave undo/redo off by one.
+    let description = description.unwrap_or_else(|| "Edit entity".to_string());
+    history.record_transaction(description);
+}
+
+// Replaces editor_process_editor_ops, which is where the editor undoes/redoes. Enqueues the next
+// step towards the history's target and checks that the editor took it.
+pub fn editor_process_editor_ops_with_history(
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    {
+        let mut history = resources.get_mut::<EditorHistoryResource>().unwrap();
+        let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
+        history.pending_step = history.next_step();
+        match history.pending_step {
+            Some(HistoryStep::Undo) => editor_state.enqueue_undo(),
+            Some(HistoryStep::Redo) => editor_state.enqueue_redo(),
+            None => {}
+        }
+    }
+
+    let previous_prefab = opened_prefab(resources);
+    editor_process_editor_ops(world, resources);
+    let prefab = opened_prefab(resources);
+
+    let mut history = resources.get_mut::<EditorHistoryResource>().unwrap();
+    if !history.follow_opened_prefab(prefab.as_ref()) {
+        return;
+    }
+
+    let stepped = match (previous_prefab, prefab) {
+        (Some(previous_prefab), Some(prefab)) => !Arc::ptr_eq(&previous_prefab, &prefab),
+        _ => false,
+    };
+    history.finish_step(stepped);
+}
+
+// Lists the history with the applied entries first. Clicking an entry undoes/redoes to just
+// after it, clicking "Opened prefab" undoes everything.
+#[cfg(feature = "use_imgui")]
+pub fn editor_history_window() -> Box<dyn Schedulable> {
+    use minimum::resources::ImguiResource;
+
+    SystemBuilder::new("editor_history_window")
+        .write_resource::<ImguiResource>()
+        .write_resource::<EditorHistoryResource>()
+        .build(|_, _, (imgui_resource, history), _| {
+            imgui_resource.with_ui(|ui| {
+                ui.main_menu_bar(|| {
+                    ui.menu(imgui::im_str!("Edit"), true, || {
+                        let clicked = imgui::MenuItem::new(imgui::im_str!("Undo"))
+                            .shortcut(imgui::im_str!("Ctrl+Z"))
+                            .enabled(history.applied_count() > 0)
+                            .build(ui);
+                        if clicked {
+                            history.undo();
+                        }
+
+                        let clicked = imgui::MenuItem::new(imgui::im_str!("Redo"))
+                            .shortcut(imgui::im_str!("Ctrl+Y"))
+                            .enabled(history.applied_count() < history.entries().len())
+                            .build(ui);
+                        if clicked {
+                            history.redo();
+                        }
+
+                        ui.separator();
+
+                        let clicked = imgui::MenuItem::new(imgui::im_str!("History"))
+                            .selected(history.window_open)
+                            .build(ui);
+                        if clicked {
+                            history.window_open = !history.window_open;
+                        }
+                    });
+                });
+
+                if !history.window_open {
+                    return;
+                }
+
+                let mut open = history.window_open;
+                let mut jump_to = None;
+                imgui::Window::new(imgui::im_str!("History"))
+                    .opened(&mut open)
+                    .size([300.0, 400.0], imgui::Condition::FirstUseEver)
+                    .build(ui, || {
+                        let clicked = imgui::Selectable::new(imgui::im_str!("Opened prefab"))
+                            .selected(history.applied_count() == 0)
+                            .build(ui);
+                        if clicked {
+                            jump_to = Some(0);
+                        }
+
+                        for (index, description) in history.entries().iter().enumerate() {
+                            let label = imgui::im_str!("{}##{}", description, index);
+                            let applied = index < history.applied_count();
+                            let selectable = imgui::Selectable::new(&label)
+                                .selected(index + 1 == history.applied_count());
+
+                            // Entries that would be redone are greyed out
+                            let clicked = if applied {
+                                selectable.build(ui)
+                            } else {
+                                let style = ui.push_style_color(
+                                    imgui::StyleColor::Text,
+                                    ui.style_color(imgui::StyleColor::TextDisabled),
+                                );
+                                let clicked = selectable.build(ui);
+                                style.pop(ui);
+                                clicked
+                            };
+
+                            if clicked {
+                                jump_to = Some(index + 1);
+                            }
+                        }
+                    });
+                history.window_open = open;
+
+                if let Some(applied_count) = jump_to {
+                    history.jump_to(applied_count);
+                }
+            });
+        })
+}
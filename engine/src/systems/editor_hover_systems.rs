@@ -0,0 +1,29 @@
+use legion::prelude::*;
+use minimum::resources::{InputResource, ViewportResource};
+use minimum::resources::editor::EditorSelectionResource;
+use ncollide3d::pipeline::CollisionGroups;
+use crate::features::selection_outline::EditorHoveredEntityResource;
+use crate::features::picking::viewport_space_ray;
+
+// Casts a ray under the mouse cursor against the editor's selection shapes and stores the nearest
+// entity it hits so that it can be outlined
+pub fn editor_update_hovered_entity() -> Box<dyn Schedulable> {
+    SystemBuilder::new("editor_update_hovered_entity")
+        .read_resource::<InputResource>()
+        .read_resource::<ViewportResource>()
+        .read_resource::<EditorSelectionResource>()
+        .write_resource::<EditorHoveredEntityResource>()
+        .build(
+            |_, _, (input_state, viewport, editor_selection, hovered_entity), _| {
+                let ray = viewport_space_ray(viewport, input_state.mouse_position());
+
+                let nearest = editor_selection
+                    .editor_selection_world()
+                    .interferences_with_ray(&ray, &CollisionGroups::new())
+                    .min_by(|(_, _, a), (_, _, b)| a.toi.partial_cmp(&b.toi).unwrap())
+                    .map(|(_, object, _)| *object.data());
+
+                hovered_entity.set_hovered_entity(nearest);
+            },
+        )
+}
@@ -0,0 +1,151 @@
+use legion::prelude::*;
+use minimum::input::MouseButton;
+use minimum::resources::InputResource;
+use minimum::resources::editor::{EditorSelectionResource, EditorSettingsResource};
+use ncollide3d::pipeline::CollisionGroups;
+use crate::components::MeshComponent;
+use crate::game_renderer::GameRenderer;
+
+// How a click changes the selection, the same as editor_handle_selection
+#[derive(Copy, Clone)]
+enum SelectionMode {
+    Set,
+    Add,
+    Subtract,
+    Toggle,
+}
+
+// A click waiting for the picking result of the frame it happened in
+struct PendingClick {
+    request_id: u64,
+    mode: SelectionMode,
+    // The selection before editor_handle_selection applied the click
+    previous_selection: Vec<Entity>,
+}
+
+fn apply_selection_mode(
+    mode: SelectionMode,
+    mut selection: Vec<Entity>,
+    picked: Option<Entity>,
+) -> Vec<Entity> {
+    match (mode, picked) {
+        (SelectionMode::Set, picked) => picked.into_iter().collect(),
+        (SelectionMode::Add, Some(picked)) => {
+            if !selection.contains(&picked) {
+                selection.push(picked);
+            }
+            selection
+        }
+        (SelectionMode::Subtract, Some(picked)) => {
+            selection.retain(|entity| *entity != picked);
+            selection
+        }
+        (SelectionMode::Toggle, Some(picked)) => {
+            if selection.contains(&picked) {
+                selection.retain(|entity| *entity != picked);
+            } else {
+                selection.push(picked);
+            }
+            selection
+        }
+        (_, None) => selection,
+    }
+}
+
+// Replaces the selection made by a click with the mesh drawn under the cursor, so that clicking
+// empty space inside a mesh's selection shape doesn't select it. Entities without meshes (lights,
+// etc.) are still selected with their shapes if they are in front of the mesh. Runs after
+// editor_handle_selection, which still handles box selection. The selection keybinds add, subtract
+// or toggle the picked entity the same way they do there.
+//
+// The picking result lags a few frames behind the cursor, so the click waits for the result of
+// the picking request made in the frame it happened. If that request was never read back (i.e. the
+// frame was skipped) the selection from editor_handle_selection is left as is.
+pub fn editor_pick_entity_on_click() -> Box<dyn Schedulable> {
+    let mut pending_click: Option<PendingClick> = None;
+
+    SystemBuilder::new("editor_pick_entity_on_click")
+        .read_resource::<InputResource>()
+        .read_resource::<EditorSettingsResource>()
+        .read_resource::<GameRenderer>()
+        .write_resource::<EditorSelectionResource>()
+        .read_component::<MeshComponent>()
+        .build(
+            move |_, world, (input_state, editor_settings, game_renderer, editor_selection), _| {
+                if input_state
+                    .mouse_button_just_clicked_position(MouseButton::LEFT)
+                    .is_some()
+                {
+                    // Checked in the same order as editor_handle_selection
+                    let keybinds = editor_settings.keybinds();
+                    let mode = if input_state.is_key_down(keybinds.selection_add) {
+                        SelectionMode::Add
+                    } else if input_state.is_key_down(keybinds.selection_subtract) {
+                        SelectionMode::Subtract
+                    } else if input_state.is_key_down(keybinds.selection_toggle) {
+                        SelectionMode::Toggle
+                    } else {
+                        SelectionMode::Set
+                    };
+
+                    // Selection ops are processed at the end of the frame, so this doesn't include
+                    // the click yet
+                    let previous_selection = editor_selection
+                        .selected_entities()
+                        .iter()
+                        .cloned()
+                        .collect();
+
+                    pending_click = Some(PendingClick {
+                        request_id: game_renderer.next_picking_request_id(),
+                        mode,
+                        previous_selection,
+                    });
+                }
+
+                let request_id = match &pending_click {
+                    Some(pending_click) => pending_click.request_id,
+                    None => return,
+                };
+
+                let picking_result = match game_renderer.picking_result() {
+                    Some(result) if result.request_id == request_id => result,
+                    Some(result) if result.request_id > request_id => {
+                        pending_click = None;
+                        return;
+                    }
+                    _ => return,
+                };
+
+                let pending_click = pending_click.take().unwrap();
+
+                // Cast with the camera the request was rendered with, the current one may have
+                // moved since
+                let ray = picking_result.ray();
+                let nearest_non_mesh = editor_selection
+                    .editor_selection_world()
+                    .interferences_with_ray(&ray, &CollisionGroups::new())
+                    .filter(|(_, object, _)| {
+                        world
+                            .get_component::<MeshComponent>(*object.data())
+                            .is_none()
+                    })
+                    .min_by(|(_, _, a), (_, _, b)| a.toi.partial_cmp(&b.toi).unwrap())
+                    .map(|(_, object, intersection)| (*object.data(), intersection.toi));
+
+                // The non-mesh hit is only used if it's nearer than the mesh drawn under the cursor
+                let picked = match (nearest_non_mesh, picking_result.toi()) {
+                    (Some((entity, toi)), Some(picked_toi)) if toi < picked_toi => Some(entity),
+                    (Some((entity, _)), None) => Some(entity),
+                    _ => picking_result.entity,
+                };
+
+                let selection = apply_selection_mode(
+                    pending_click.mode,
+                    pending_click.previous_selection,
+                    picked,
+                );
+                editor_selection.enqueue_set_selection(selection);
+            },
+        )
+}
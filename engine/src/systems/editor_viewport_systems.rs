@@ -0,0 +1,486 @@
+use legion::prelude::*;
+use glam::{Mat4, Vec2, Vec3};
+use minimum::input::MouseButton;
+use minimum::resources::InputResource;
+use minimum::resources::editor::EditorSelectionResource;
+use super::editor_camera_systems::{EditorCameraState, EditorCameraResource, selection_bounds};
+
+const PERSPECTIVE_FOV: f32 = std::f32::consts::FRAC_PI_4;
+const PERSPECTIVE_NEAR_CLIP: f32 = 0.1;
+const PERSPECTIVE_FAR_CLIP: f32 = 25.0;
+
+// Orthographic views are placed this far back from their center so that the scene is in front of
+// them, and see twice as far
+const ORTHO_EYE_DISTANCE: f32 = 50.0;
+const ORTHO_NEAR_CLIP: f32 = 0.1;
+const ORTHO_FAR_CLIP: f32 = ORTHO_EYE_DISTANCE * 2.0;
+
+const DEFAULT_ORTHO_HALF_HEIGHT: f32 = 10.0;
+const MIN_ORTHO_HALF_HEIGHT: f32 = 0.1;
+const MAX_ORTHO_HALF_HEIGHT: f32 = 500.0;
+// Each wheel notch scales the visible area of an orthographic view by this much
+const ORTHO_ZOOM_WHEEL_MULTIPLIER: f32 = 1.2;
+// When focusing on the selection, how many times the selection's radius is visible above and
+// below it
+const ORTHO_FOCUS_MULTIPLIER: f32 = 1.5;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EditorViewportKind {
+    Perspective,
+    Top,
+    Front,
+    Side,
+}
+
+impl EditorViewportKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            EditorViewportKind::Perspective => "Perspective",
+            EditorViewportKind::Top => "Top",
+            EditorViewportKind::Front => "Front",
+            EditorViewportKind::Side => "Side",
+        }
+    }
+
+    // The direction an orthographic view looks in and its up direction. Z is up.
+    fn ortho_axes(self) -> (Vec3, Vec3) {
+        match self {
+            EditorViewportKind::Top => (Vec3::new(0.0, 0.0, -1.0), Vec3::new(0.0, 1.0, 0.0)),
+            EditorViewportKind::Front => (Vec3::new(0.0, 1.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+            EditorViewportKind::Side => (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0)),
+            EditorViewportKind::Perspective => unreachable!(),
+        }
+    }
+}
+
+// Part of the window a viewport is drawn in, in framebuffer pixels (not imgui's points)
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct EditorViewportRegion {
+    pub position: Vec2,
+    pub size: Vec2,
+}
+
+impl EditorViewportRegion {
+    fn contains(
+        &self,
+        point: Vec2,
+    ) -> bool {
+        let max = self.position + self.size;
+        point.x() >= self.position.x()
+            && point.y() >= self.position.y()
+            && point.x() < max.x()
+            && point.y() < max.y()
+    }
+}
+
+// The camera a viewport is rendered with this frame
+pub struct EditorViewportView {
+    pub kind: EditorViewportKind,
+    pub region: EditorViewportRegion,
+    pub eye_position: Vec3,
+    pub view: Mat4,
+    // OpenGL-style projection with the region's aspect ratio. The renderer corrects it for vulkan
+    // and maps it into the region.
+    pub projection: Mat4,
+}
+
+pub struct EditorViewport {
+    kind: EditorViewportKind,
+    pub open: bool,
+    // Set while the viewport's window is shown
+    region: Option<EditorViewportRegion>,
+    // Orthographic views look at this point
+    ortho_center: Vec3,
+    // Half the height of the area shown by an orthographic view, in world units
+    ortho_half_height: f32,
+}
+
+impl EditorViewport {
+    fn new(kind: EditorViewportKind) -> Self {
+        EditorViewport {
+            kind,
+            open: true,
+            region: None,
+            ortho_center: Vec3::zero(),
+            ortho_half_height: DEFAULT_ORTHO_HALF_HEIGHT,
+        }
+    }
+
+    pub fn kind(&self) -> EditorViewportKind {
+        self.kind
+    }
+
+    fn view(
+        &self,
+        camera: &EditorCameraState,
+        region: EditorViewportRegion,
+    ) -> EditorViewportView {
+        let aspect_ratio = region.size.x() / region.size.y();
+        let (eye_position, view, projection) = match self.kind {
+            EditorViewportKind::Perspective => {
+                let projection = Mat4::perspective_rh_gl(
+                    PERSPECTIVE_FOV,
+                    aspect_ratio,
+                    PERSPECTIVE_NEAR_CLIP,
+                    PERSPECTIVE_FAR_CLIP,
+                );
+                (camera.eye_position(), camera.view_matrix(), projection)
+            }
+            kind => {
+                let (direction, up) = kind.ortho_axes();
+                let eye_position = self.ortho_center - direction * ORTHO_EYE_DISTANCE;
+                let view = Mat4::look_at_rh(eye_position, self.ortho_center, up);
+                let half_width = self.ortho_half_height * aspect_ratio;
+                let projection = Mat4::orthographic_rh_gl(
+                    -half_width,
+                    half_width,
+                    -self.ortho_half_height,
+                    self.ortho_half_height,
+                    ORTHO_NEAR_CLIP,
+                    ORTHO_FAR_CLIP,
+                );
+                (eye_position, view, projection)
+            }
+        };
+
+        EditorViewportView {
+            kind: self.kind,
+            region,
+            eye_position,
+            view,
+            projection,
+        }
+    }
+
+    fn ortho_pan(
+        &mut self,
+        mouse_delta: Vec2,
+    ) {
+        let region = match self.region {
+            Some(region) => region,
+            None => return,
+        };
+
+        // Move so that the point under the mouse follows it
+        let (direction, up) = self.kind.ortho_axes();
+        let right = direction.cross(up);
+        let units_per_pixel = self.ortho_half_height * 2.0 / region.size.y();
+        self.ortho_center += (up * mouse_delta.y() - right * mouse_delta.x()) * units_per_pixel;
+    }
+
+    fn ortho_zoom(
+        &mut self,
+        wheel_delta: f32,
+    ) {
+        self.ortho_half_height = (self.ortho_half_height
+            / ORTHO_ZOOM_WHEEL_MULTIPLIER.powf(wheel_delta))
+        .max(MIN_ORTHO_HALF_HEIGHT)
+        .min(MAX_ORTHO_HALF_HEIGHT);
+    }
+}
+
+// The editor can show the scene in a single perspective view that fills the window, or in a
+// perspective view and top/front/side orthographic views, each in its own imgui window. Mouse
+// input goes to the viewport under the mouse: the renderer points ViewportResource at its camera
+// so that selection and gizmos use it, and the camera controls only move it.
+pub struct EditorViewportResource {
+    multiple_viewports: bool,
+    viewports: Vec<EditorViewport>,
+    active_viewport: EditorViewportKind,
+    // Whether the mouse is over a viewport window's scene area. imgui would otherwise keep mouse
+    // input from reaching the editor there.
+    scene_hovered: bool,
+    previous_mouse_position: Option<Vec2>,
+}
+
+impl EditorViewportResource {
+    pub fn new() -> Self {
+        EditorViewportResource {
+            multiple_viewports: false,
+            viewports: vec![
+                EditorViewport::new(EditorViewportKind::Perspective),
+                EditorViewport::new(EditorViewportKind::Top),
+                EditorViewport::new(EditorViewportKind::Front),
+                EditorViewport::new(EditorViewportKind::Side),
+            ],
+            active_viewport: EditorViewportKind::Perspective,
+            scene_hovered: false,
+            previous_mouse_position: None,
+        }
+    }
+
+    pub fn multiple_viewports(&self) -> bool {
+        self.multiple_viewports
+    }
+
+    pub fn set_multiple_viewports(
+        &mut self,
+        multiple_viewports: bool,
+    ) {
+        self.multiple_viewports = multiple_viewports;
+        if !multiple_viewports {
+            self.scene_hovered = false;
+            for viewport in &mut self.viewports {
+                viewport.region = None;
+            }
+        }
+    }
+
+    pub fn viewports(&self) -> &[EditorViewport] {
+        &self.viewports
+    }
+
+    pub fn viewports_mut(&mut self) -> &mut [EditorViewport] {
+        &mut self.viewports
+    }
+
+    pub fn active_viewport(&self) -> EditorViewportKind {
+        self.active_viewport
+    }
+
+    pub fn is_scene_hovered(&self) -> bool {
+        self.scene_hovered
+    }
+
+    // Called by the viewport windows every frame with the scene area of each window that is shown
+    pub fn set_region(
+        &mut self,
+        kind: EditorViewportKind,
+        region: Option<EditorViewportRegion>,
+    ) {
+        if let Some(viewport) = self.viewports.iter_mut().find(|x| x.kind == kind) {
+            viewport.region =
+                region.filter(|region| region.size.x() >= 1.0 && region.size.y() >= 1.0);
+        }
+    }
+
+    // The cameras to render with, the main view first. With a single viewport, or if every
+    // viewport window is closed, the perspective view fills the window.
+    pub fn views(
+        &self,
+        camera: &EditorCameraState,
+        window_size: Vec2,
+    ) -> Vec<EditorViewportView> {
+        let views: Vec<_> = self
+            .visible_viewports()
+            .map(|(viewport, region)| viewport.view(camera, region))
+            .collect();
+
+        if !views.is_empty() {
+            return views;
+        }
+
+        let full_window = EditorViewportRegion {
+            position: Vec2::zero(),
+            size: window_size,
+        };
+        vec![self.viewports[0].view(camera, full_window)]
+    }
+
+    fn visible_viewports(
+        &self
+    ) -> impl Iterator<Item = (&EditorViewport, EditorViewportRegion)> + '_ {
+        let multiple_viewports = self.multiple_viewports;
+        self.viewports
+            .iter()
+            .filter(move |_| multiple_viewports)
+            .filter_map(|viewport| viewport.region.map(|region| (viewport, region)))
+    }
+
+    // The viewport under the mouse becomes active. It stays active while a button is held so that
+    // drags that leave its region aren't handed to another viewport.
+    fn update_active_viewport(
+        &mut self,
+        mouse_position: Vec2,
+        any_button_down: bool,
+    ) {
+        if any_button_down {
+            return;
+        }
+
+        let hovered_viewport = self
+            .visible_viewports()
+            .find(|(_, region)| region.contains(mouse_position))
+            .map(|(viewport, _)| viewport.kind);
+
+        self.active_viewport = if self.visible_viewports().next().is_some() {
+            hovered_viewport.unwrap_or(self.active_viewport)
+        } else {
+            EditorViewportKind::Perspective
+        };
+    }
+}
+
+// Picks the viewport that receives mouse input. When it is orthographic, the right or middle mouse
+// pans it and the mouse wheel zooms it. The camera's focus key focuses the orthographic views on
+// the selection (the perspective camera focuses itself, see editor_camera_controls).
+pub fn editor_viewport_controls() -> Box<dyn Schedulable> {
+    SystemBuilder::new("editor_viewport_controls")
+        .read_resource::<InputResource>()
+        .read_resource::<EditorSelectionResource>()
+        .read_resource::<EditorCameraResource>()
+        .write_resource::<EditorViewportResource>()
+        .build(
+            |_, _, (input_state, editor_selection, camera, viewports), _| {
+                let right_down = input_state.is_mouse_down(MouseButton::RIGHT);
+                let middle_down = input_state.is_mouse_down(MouseButton::MIDDLE);
+                let any_button_down =
+                    input_state.is_mouse_down(MouseButton::LEFT) || right_down || middle_down;
+
+                let mouse_position = input_state.mouse_position();
+                let mouse_delta = viewports
+                    .previous_mouse_position
+                    .map(|previous| mouse_position - previous)
+                    .unwrap_or_else(Vec2::zero);
+                viewports.previous_mouse_position = Some(mouse_position);
+
+                viewports.update_active_viewport(mouse_position, any_button_down);
+
+                let active_viewport = viewports.active_viewport;
+                if active_viewport != EditorViewportKind::Perspective {
+                    let wheel_delta = input_state.mouse_wheel_delta().y as f32;
+                    let viewport = viewports
+                        .viewports
+                        .iter_mut()
+                        .find(|x| x.kind == active_viewport)
+                        .unwrap();
+
+                    if right_down || middle_down {
+                        viewport.ortho_pan(mouse_delta);
+                    }
+
+                    if wheel_delta != 0.0 {
+                        viewport.ortho_zoom(wheel_delta);
+                    }
+                }
+
+                if input_state.is_key_just_down(camera.keybinds().focus) {
+                    if let Some((center, radius)) = selection_bounds(&*editor_selection) {
+                        let half_height = (radius * ORTHO_FOCUS_MULTIPLIER)
+                            .max(MIN_ORTHO_HALF_HEIGHT)
+                            .min(MAX_ORTHO_HALF_HEIGHT);
+                        for viewport in &mut viewports.viewports {
+                            viewport.ortho_center = center;
+                            viewport.ortho_half_height = half_height;
+                        }
+                    }
+                }
+            },
+        )
+}
+
+// Adds a Viewports menu and, when multiple viewports are enabled, a window for each viewport. The
+// windows have no background and the scene is rendered beneath their content area. This version
+// of imgui has no docking support, so the windows start out tiling the screen and can be moved,
+// resized and closed like any other window.
+#[cfg(feature = "use_imgui")]
+pub fn editor_viewport_windows() -> Box<dyn Schedulable> {
+    use minimum::resources::ImguiResource;
+
+    SystemBuilder::new("editor_viewport_windows")
+        .write_resource::<ImguiResource>()
+        .write_resource::<EditorViewportResource>()
+        .build(|_, _, (imgui_resource, viewports), _| {
+            imgui_resource.with_ui(|ui| {
+                ui.main_menu_bar(|| {
+                    ui.menu(imgui::im_str!("Viewports"), true, || {
+                        let multiple_viewports = viewports.multiple_viewports();
+                        let clicked = imgui::MenuItem::new(imgui::im_str!("Single Viewport"))
+                            .selected(!multiple_viewports)
+                            .build(ui);
+                        if clicked {
+                            viewports.set_multiple_viewports(false);
+                        }
+
+                        let clicked = imgui::MenuItem::new(imgui::im_str!("Four Viewports"))
+                            .selected(multiple_viewports)
+                            .build(ui);
+                        if clicked {
+                            viewports.set_multiple_viewports(true);
+                        }
+
+                        ui.separator();
+
+                        for viewport in viewports.viewports_mut() {
+                            let clicked =
+                                imgui::MenuItem::new(&imgui::im_str!("{}", viewport.kind().name()))
+                                    .selected(viewport.open)
+                                    .enabled(multiple_viewports)
+                                    .build(ui);
+                            if clicked {
+                                viewport.open = !viewport.open;
+                            }
+                        }
+                    });
+                });
+
+                if !viewports.multiple_viewports() {
+                    return;
+                }
+
+                // Quadrants of the area below the main menu bar
+                let display_size = ui.io().display_size;
+                let framebuffer_scale = ui.io().display_framebuffer_scale;
+                let menu_bar_height = ui.frame_height();
+                let quadrant_size = [
+                    display_size[0] / 2.0,
+                    (display_size[1] - menu_bar_height) / 2.0,
+                ];
+
+                let mut scene_hovered = false;
+                let mut regions = vec![];
+                for (index, viewport) in viewports.viewports_mut().iter_mut().enumerate() {
+                    let kind = viewport.kind();
+                    let mut region = None;
+                    if viewport.open {
+                        let position = [
+                            quadrant_size[0] * (index % 2) as f32,
+                            menu_bar_height + quadrant_size[1] * (index / 2) as f32,
+                        ];
+
+                        imgui::Window::new(&imgui::im_str!("{}", kind.name()))
+                            .opened(&mut viewport.open)
+                            .position(position, imgui::Condition::FirstUseEver)
+                            .size(quadrant_size, imgui::Condition::FirstUseEver)
+                            .bg_alpha(0.0)
+                            .scroll_bar(false)
+                            .scrollable(false)
+                            .build(ui, || {
+                                let position = ui.cursor_screen_pos();
+                                let size = ui.content_region_avail();
+
+                                // imgui asserts on a zero sized button, and set_region would drop
+                                // the region anyway
+                                if size[0] < 1.0 || size[1] < 1.0 {
+                                    return;
+                                }
+
+                                // Covers the scene area so that clicking and dragging in it
+                                // doesn't move the window
+                                ui.invisible_button(imgui::im_str!("##scene"), size);
+                                scene_hovered |= ui.is_item_hovered() || ui.is_item_active();
+
+                                // imgui lays out in points, the renderer works in pixels
+                                region = Some(EditorViewportRegion {
+                                    position: Vec2::new(
+                                        position[0] * framebuffer_scale[0],
+                                        position[1] * framebuffer_scale[1],
+                                    ),
+                                    size: Vec2::new(
+                                        size[0] * framebuffer_scale[0],
+                                        size[1] * framebuffer_scale[1],
+                                    ),
+                                });
+                            });
+                    }
+
+                    regions.push((kind, region));
+                }
+
+                for (kind, region) in regions {
+                    viewports.set_region(kind, region);
+                }
+                viewports.scene_hovered = scene_hovered;
+            });
+        })
+}
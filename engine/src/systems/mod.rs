@@ -10,8 +10,66 @@ pub use add_light_debug_draw::add_light_debug_draw;
 mod temp_logic;
 pub use temp_logic::imgui_draw_mouse_coordinates;
 
+mod capture_systems;
+pub use capture_systems::capture_keybinds;
+pub use capture_systems::advance_time_with_frame_capture;
+
+mod render_debug_systems;
+pub use render_debug_systems::render_debug_keybinds;
+#[cfg(feature = "use_imgui")]
+pub use render_debug_systems::render_debug_imgui_menu;
+#[cfg(feature = "use_imgui")]
+pub use render_debug_systems::render_stats_overlay;
+
+mod particle_systems;
+pub use particle_systems::simulate_particles;
+
+mod profiler_systems;
+pub use profiler_systems::profiler_keybinds;
+#[cfg(feature = "use_imgui")]
+pub use profiler_systems::profiler_window;
+
+mod editor_hover_systems;
+pub use editor_hover_systems::editor_update_hovered_entity;
+
+mod editor_picking_systems;
+pub use editor_picking_systems::editor_pick_entity_on_click;
+
+mod editor_history_systems;
+pub use editor_history_systems::editor_history_keybinds;
+pub use editor_history_systems::editor_reload_if_file_changed;
+pub use editor_history_systems::editor_process_edit_diffs_with_history;
+pub use editor_history_systems::editor_process_editor_ops_with_history;
+#[cfg(feature = "use_imgui")]
+pub use editor_history_systems::editor_history_window;
+pub use editor_history_systems::EditorHistoryResource;
+
+mod editor_camera_systems;
+pub use editor_camera_systems::editor_camera_controls;
+pub use editor_camera_systems::EditorCameraResource;
+pub use editor_camera_systems::EditorCameraKeybinds;
+pub use editor_camera_systems::EditorCameraState;
+
+mod editor_viewport_systems;
+pub use editor_viewport_systems::editor_viewport_controls;
+#[cfg(feature = "use_imgui")]
+pub use editor_viewport_systems::editor_viewport_windows;
+pub use editor_viewport_systems::EditorViewportResource;
+pub use editor_viewport_systems::EditorViewportKind;
+pub use editor_viewport_systems::EditorViewportView;
+
+#[cfg(feature = "use_imgui")]
+mod editor_asset_browser_systems;
+#[cfg(feature = "use_imgui")]
+pub use editor_asset_browser_systems::editor_asset_browser_window;
+#[cfg(feature = "use_imgui")]
+pub use editor_asset_browser_systems::AssetBrowserWindowState;
+
 use minimum::systems::*;
 
+#[cfg(feature = "use_imgui")]
+use crate::asset_catalog::editor_update_asset_catalog;
+
 use legion::prelude::*;
 
 use minimum::editor::resources::EditorMode;
@@ -169,11 +227,15 @@ impl ScheduleManager {
 
         let mut builder = ScheduleBuilder::new(criteria)
             .always(update_input_resource)
-            .always(advance_time)
+            .always(advance_time_with_frame_capture)
             .always(quit_if_escape_pressed)
+            .always(capture_keybinds)
+            .always(render_debug_keybinds)
+            .always(profiler_keybinds)
             .always_thread_local(update_asset_manager)
             //.always(update_resource_manager)
             .always(add_light_debug_draw)
+            .simulation_unpaused_only(simulate_particles)
             //.always(imgui_draw_mouse_coordinates)
             //.always(update_fps_text)
             //.always(update_physics)
@@ -182,26 +244,38 @@ impl ScheduleManager {
             // Prepare to handle editor input
             .always_thread_local(editor_refresh_selection_world)
             // Editor input
-            .always_thread_local(reload_editor_state_if_file_changed)
+            .always_thread_local(editor_reload_if_file_changed)
             .always(editor_keybinds)
+            .always(editor_history_keybinds)
             .always(editor_mouse_input)
+            .editor_only(editor_viewport_controls)
+            .editor_only(editor_camera_controls)
             .always(editor_update_editor_draw)
             .always(editor_gizmos)
-            .always(editor_handle_selection);
+            .always(editor_handle_selection)
+            .always(editor_pick_entity_on_click)
+            .always(editor_update_hovered_entity);
 
 
         #[cfg(feature = "use_imgui")]
         {
             builder = builder
                 .always(editor_imgui_menu)
+                .always(render_debug_imgui_menu)
+                .always(profiler_window)
+                .always(editor_viewport_windows)
+                .always(render_stats_overlay)
                 .always(editor_entity_list_window)
+                .always(editor_history_window)
+                .always_thread_local(editor_asset_browser_window)
+                .always_thread_local(editor_update_asset_catalog)
                 .always_thread_local(editor_inspector_window);
         }
 
         // Editor processing
-        builder.always_thread_local(editor_process_edit_diffs)
+        builder.always_thread_local(editor_process_edit_diffs_with_history)
             .always_thread_local(editor_process_selection_ops)
-            .always_thread_local(editor_process_editor_ops)
+            .always_thread_local(editor_process_editor_ops_with_history)
             // Editor output
             .always(draw_selection_shapes) //TODO: Requires pushing 3d debug draw down
             // --- End editor stuff ---
@@ -0,0 +1,20 @@
+use legion::prelude::*;
+use minimum::resources::TimeResource;
+use minimum::components::TransformComponent;
+use crate::components::ParticleEmitterComponent;
+
+// Only scheduled while the simulation is running, so particles freeze when it is paused
+pub fn simulate_particles() -> Box<dyn Schedulable> {
+    SystemBuilder::new("simulate_particles")
+        .read_resource::<TimeResource>()
+        .with_query(<(Read<TransformComponent>, Write<ParticleEmitterComponent>)>::query())
+        .build(|_, world, time_resource, query| {
+            let dt = time_resource.simulation_time.previous_update_dt();
+            for (transform, mut emitter) in query.iter_mut(world) {
+                let emitter = &mut *emitter;
+                emitter
+                    .state
+                    .simulate(&emitter.settings, &transform.transform(), dt);
+            }
+        })
+}
@@ -0,0 +1,313 @@
+use legion::prelude::*;
+use sdl2::keyboard::Keycode;
+use minimum::resources::InputResource;
+use minimum_sdl2::input::Sdl2KeyboardKey;
+use crate::profiler::Profiler;
+#[cfg(feature = "use_imgui")]
+use crate::profiler::{ProfilerWindowState, ProfiledFrame};
+use std::time::Duration;
+
+const PROFILE_DIRECTORY: &str = "profiles";
+
+const ROW_HEIGHT: f32 = 18.0;
+const LANE_HEADER_HEIGHT: f32 = 16.0;
+
+// Scopes get a stable color picked from their name so the same scope is easy to follow between
+// frames
+const SCOPE_COLORS: [[f32; 4]; 8] = [
+    [0.85, 0.37, 0.35, 1.0],
+    [0.36, 0.62, 0.85, 1.0],
+    [0.45, 0.75, 0.42, 1.0],
+    [0.88, 0.65, 0.30, 1.0],
+    [0.62, 0.45, 0.82, 1.0],
+    [0.35, 0.75, 0.72, 1.0],
+    [0.82, 0.48, 0.68, 1.0],
+    [0.65, 0.68, 0.35, 1.0],
+];
+
+fn scope_color(name: &str) -> [f32; 4] {
+    let hash = name
+        .bytes()
+        .fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32));
+    SCOPE_COLORS[hash as usize % SCOPE_COLORS.len()]
+}
+
+fn as_ms(duration: Duration) -> f32 {
+    duration.as_secs_f32() * 1000.0
+}
+
+// Draws the profiler window with a frame time graph and a timeline of the selected frame. Opened
+// from the Render menu
+#[cfg(feature = "use_imgui")]
+pub fn profiler_window() -> Box<dyn Schedulable> {
+    use minimum::resources::ImguiResource;
+
+    SystemBuilder::new("profiler_window")
+        .write_resource::<ImguiResource>()
+        .read_resource::<Profiler>()
+        .write_resource::<ProfilerWindowState>()
+        .build(|_, _, (imgui_resource, profiler, window_state), _| {
+            if !window_state.open {
+                return;
+            }
+
+            imgui_resource.with_ui(|ui| {
+                let mut open = window_state.open;
+                imgui::Window::new(imgui::im_str!("Profiler"))
+                    .opened(&mut open)
+                    .size([900.0, 400.0], imgui::Condition::FirstUseEver)
+                    .build(ui, || {
+                        draw_profiler_window(ui, &*profiler, &mut *window_state);
+                    });
+                window_state.open = open;
+            });
+        })
+}
+
+#[cfg(feature = "use_imgui")]
+fn draw_profiler_window(
+    ui: &imgui::Ui,
+    profiler: &Profiler,
+    window_state: &mut ProfilerWindowState,
+) {
+    //
+    // Controls
+    //
+    let mut paused = profiler.is_paused();
+    if ui.checkbox(imgui::im_str!("Paused"), &mut paused) {
+        profiler.set_paused(paused);
+        window_state.frames_from_newest = 0;
+    }
+
+    ui.same_line(0.0);
+    if ui.button(imgui::im_str!("Clear"), [0.0, 0.0]) {
+        profiler.clear_history();
+        window_state.frames_from_newest = 0;
+    }
+
+    ui.same_line(0.0);
+    if ui.button(imgui::im_str!("Export Chrome Trace"), [0.0, 0.0]) {
+        export_chrome_trace(profiler);
+    }
+
+    let main_thread_index = profiler.main_thread_index();
+    profiler.with_history(|history, thread_names| {
+        if history.is_empty() {
+            ui.text(imgui::im_str!("No frames recorded"));
+            return;
+        }
+
+        //
+        // Frame selection, only meaningful while paused since the history moves otherwise
+        //
+        if paused {
+            ui.same_line(0.0);
+            if ui.button(imgui::im_str!("<"), [0.0, 0.0]) {
+                window_state.frames_from_newest += 1;
+            }
+            ui.same_line(0.0);
+            if ui.button(imgui::im_str!(">"), [0.0, 0.0]) {
+                window_state.frames_from_newest = window_state.frames_from_newest.saturating_sub(1);
+            }
+        } else {
+            window_state.frames_from_newest = 0;
+        }
+
+        window_state.frames_from_newest = window_state.frames_from_newest.min(history.len() - 1);
+        let frame = &history[history.len() - 1 - window_state.frames_from_newest];
+
+        //
+        // Frame time graph
+        //
+        let frame_times: Vec<f32> = history.iter().map(|x| as_ms(x.duration())).collect();
+        let average = frame_times.iter().sum::<f32>() / frame_times.len() as f32;
+        let overlay = imgui::im_str!("avg {:.2} ms", average);
+        let graph_width = ui.content_region_avail()[0];
+        imgui::PlotHistogram::new(ui, imgui::im_str!("##frame_times"), &frame_times)
+            .overlay_text(&overlay)
+            .scale_min(0.0)
+            .graph_size([graph_width, 60.0])
+            .build();
+
+        ui.text(imgui::im_str!(
+            "Frame {}: {:.2} ms CPU, {:.2} ms GPU (most recent completed)",
+            frame.frame_index,
+            as_ms(frame.duration()),
+            as_ms(frame.gpu_duration())
+        ));
+
+        // With multiple frames in flight the main thread should be working on later frames while
+        // the GPU renders this one. Waiting means the two were serialized, i.e. on a swapchain
+        // image
+        let gpu_overlap = main_thread_index
+            .and_then(|main_thread_index| frame.gpu_overlap(history, main_thread_index));
+        if let Some(gpu_overlap) = gpu_overlap {
+            ui.text(imgui::im_str!(
+                "GPU frame overlapped {:.2} ms of main thread work, {:.2} ms of main thread waits",
+                as_ms(gpu_overlap.working),
+                as_ms(gpu_overlap.waiting)
+            ));
+        }
+
+        ui.separator();
+
+        draw_timeline(ui, frame, thread_names);
+    });
+}
+
+#[cfg(feature = "use_imgui")]
+fn draw_timeline(
+    ui: &imgui::Ui,
+    frame: &ProfiledFrame,
+    thread_names: &[String],
+) {
+    // Render thread scopes can start before the frame does, and GPU work is from an earlier frame,
+    // so widen the range to fit everything
+    let gpu_interval = frame.gpu_interval();
+    let range_start = frame
+        .cpu_scopes
+        .iter()
+        .map(|x| x.start)
+        .chain(gpu_interval.map(|x| x.0))
+        .min()
+        .map_or(frame.start, |x| x.min(frame.start));
+    let range_end = frame
+        .cpu_scopes
+        .iter()
+        .map(|x| x.end)
+        .chain(gpu_interval.map(|x| x.1))
+        .max()
+        .map_or(frame.end, |x| x.max(frame.end));
+    let range_ms = as_ms(range_end - range_start).max(0.001);
+
+    // One lane per thread that recorded something, plus the GPU
+    let mut lanes: Vec<(String, Vec<(&'static str, u32, f32, f32)>)> = vec![];
+    for (thread_index, thread_name) in thread_names.iter().enumerate() {
+        let scopes: Vec<_> = frame
+            .cpu_scopes
+            .iter()
+            .filter(|x| x.thread_index == thread_index)
+            .map(|x| {
+                (
+                    x.name,
+                    x.depth,
+                    as_ms(x.start - range_start),
+                    as_ms(x.duration()),
+                )
+            })
+            .collect();
+
+        if !scopes.is_empty() {
+            lanes.push((thread_name.clone(), scopes));
+        }
+    }
+
+    // GPU timings are placed at the estimated CPU time their GPU frame started
+    let gpu_offset_ms = as_ms(frame.gpu_start.unwrap_or(frame.start) - range_start);
+    lanes.push((
+        "GPU".to_string(),
+        frame
+            .gpu_scopes
+            .iter()
+            .map(|x| (x.name, 0, gpu_offset_ms + as_ms(x.start), as_ms(x.duration)))
+            .collect(),
+    ));
+
+    let lane_heights: Vec<f32> = lanes
+        .iter()
+        .map(|(_, scopes)| {
+            let max_depth = scopes.iter().map(|x| x.1).max().unwrap_or(0);
+            LANE_HEADER_HEIGHT + (max_depth + 1) as f32 * ROW_HEIGHT
+        })
+        .collect();
+
+    let origin = ui.cursor_screen_pos();
+    let width = ui.content_region_avail()[0].max(1.0);
+    let height: f32 = lane_heights.iter().sum();
+
+    // Reserve the space so the window scrolls and hovering can be detected
+    ui.invisible_button(imgui::im_str!("##timeline"), [width, height]);
+    let timeline_hovered = ui.is_item_hovered();
+    let mouse_position = ui.io().mouse_pos;
+
+    let mut hovered_scope = None;
+    {
+        let draw_list = ui.get_window_draw_list();
+        let text_color = [1.0, 1.0, 1.0, 1.0];
+        let frame_end_x = origin[0] + as_ms(frame.end - range_start) / range_ms * width;
+        let frame_start_x = origin[0] + as_ms(frame.start - range_start) / range_ms * width;
+
+        let mut lane_y = origin[1];
+        for ((lane_name, scopes), lane_height) in lanes.iter().zip(&lane_heights) {
+            draw_list.add_text([origin[0], lane_y], [0.7, 0.7, 0.7, 1.0], lane_name);
+
+            for (name, depth, start_ms, duration_ms) in scopes {
+                let x0 = origin[0] + start_ms / range_ms * width;
+                let x1 = (origin[0] + (start_ms + duration_ms) / range_ms * width).max(x0 + 1.0);
+                let y0 = lane_y + LANE_HEADER_HEIGHT + *depth as f32 * ROW_HEIGHT;
+                let y1 = y0 + ROW_HEIGHT - 1.0;
+
+                draw_list
+                    .add_rect([x0, y0], [x1, y1], scope_color(name))
+                    .filled(true)
+                    .build();
+
+                // Only label scopes wide enough to fit some text
+                if x1 - x0 > 40.0 {
+                    draw_list.add_text([x0 + 2.0, y0 + 2.0], text_color, *name);
+                }
+
+                let hovered = mouse_position[0] >= x0
+                    && mouse_position[0] <= x1
+                    && mouse_position[1] >= y0
+                    && mouse_position[1] <= y1;
+                if timeline_hovered && hovered {
+                    hovered_scope = Some((*name, *duration_ms));
+                }
+            }
+
+            lane_y += lane_height;
+        }
+
+        // Mark where the main thread's frame begins and ends
+        let marker_color = [1.0, 1.0, 1.0, 0.4];
+        draw_list
+            .add_line([frame_start_x, origin[1]], [frame_start_x, origin[1] + height], marker_color)
+            .build();
+        draw_list
+            .add_line([frame_end_x, origin[1]], [frame_end_x, origin[1] + height], marker_color)
+            .build();
+    }
+
+    if let Some((name, duration_ms)) = hovered_scope {
+        ui.tooltip_text(format!("{}: {:.3} ms", name, duration_ms));
+    }
+}
+
+fn export_chrome_trace(profiler: &Profiler) {
+    let timestamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|x| x.as_millis())
+        .unwrap_or(0);
+
+    let path = std::path::Path::new(PROFILE_DIRECTORY).join(format!("profile_{}.json", timestamp));
+    let result = std::fs::create_dir_all(PROFILE_DIRECTORY)
+        .and_then(|_| profiler.export_chrome_trace(&path));
+
+    match result {
+        Ok(_) => log::info!("Saved profile to {:?}", path),
+        Err(e) => log::error!("Failed to save profile to {:?}: {:?}", path, e),
+    }
+}
+
+// F9 exports the profiler history as a Chrome trace
+pub fn profiler_keybinds() -> Box<dyn Schedulable> {
+    SystemBuilder::new("profiler_keybinds")
+        .read_resource::<InputResource>()
+        .read_resource::<Profiler>()
+        .build(|_, _, (input_state, profiler), _| {
+            if input_state.is_key_just_down(Sdl2KeyboardKey::new(Keycode::F9).into()) {
+                export_chrome_trace(&*profiler);
+            }
+        })
+}
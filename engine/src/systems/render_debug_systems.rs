@@ -0,0 +1,208 @@
+use legion::prelude::*;
+use sdl2::keyboard::Keycode;
+use minimum::resources::InputResource;
+use minimum_sdl2::input::Sdl2KeyboardKey;
+use crate::game_renderer::GameRenderer;
+#[cfg(feature = "use_imgui")]
+use crate::game_renderer::RenderScaleMode;
+use crate::features::mesh::MeshDebugViewMode;
+#[cfg(feature = "use_imgui")]
+use crate::profiler::ProfilerWindowState;
+#[cfg(feature = "use_imgui")]
+use crate::render_stats::RenderStatsResource;
+
+// F8 toggles tinting meshes by the LOD they are drawn with
+pub fn render_debug_keybinds() -> Box<dyn Schedulable> {
+    SystemBuilder::new("render_debug_keybinds")
+        .read_resource::<InputResource>()
+        .read_resource::<GameRenderer>()
+        .build(|_, _, (input_state, game_renderer), _| {
+            if input_state.is_key_just_down(Sdl2KeyboardKey::new(Keycode::F8).into()) {
+                let enabled = !game_renderer.mesh_lod_debug_view_enabled();
+                log::info!("Mesh LOD debug view enabled: {}", enabled);
+                game_renderer.set_mesh_lod_debug_view_enabled(enabled);
+            }
+        })
+}
+
+// Choices offered in the Render Scale menu
+#[cfg(feature = "use_imgui")]
+fn render_scale_menu_items() -> Vec<(&'static str, RenderScaleMode)> {
+    vec![
+        ("100%", RenderScaleMode::Fixed(1.0)),
+        ("75%", RenderScaleMode::Fixed(0.75)),
+        ("50%", RenderScaleMode::Fixed(0.5)),
+        (
+            "Automatic (60 FPS)",
+            RenderScaleMode::Automatic {
+                target_frame_time: std::time::Duration::from_micros(16_667),
+            },
+        ),
+        (
+            "Automatic (30 FPS)",
+            RenderScaleMode::Automatic {
+                target_frame_time: std::time::Duration::from_micros(33_333),
+            },
+        ),
+    ]
+}
+
+// Adds a "Render" menu to the main menu bar for picking the mesh debug view and render scale, and
+// opening the profiler and render stats overlay
+#[cfg(feature = "use_imgui")]
+pub fn render_debug_imgui_menu() -> Box<dyn Schedulable> {
+    use minimum::resources::ImguiResource;
+
+    SystemBuilder::new("render_debug_imgui_menu")
+        .write_resource::<ImguiResource>()
+        .read_resource::<GameRenderer>()
+        .write_resource::<ProfilerWindowState>()
+        .write_resource::<RenderStatsResource>()
+        .build(|_, _, (imgui_resource, game_renderer, profiler_window, render_stats), _| {
+            imgui_resource.with_ui(|ui| {
+                ui.main_menu_bar(|| {
+                    ui.menu(imgui::im_str!("Render"), true, || {
+                        ui.menu(imgui::im_str!("Debug View"), true, || {
+                            let current_mode = game_renderer.mesh_debug_view_mode();
+                            for mode in MeshDebugViewMode::ALL.iter() {
+                                let label = imgui::ImString::new(mode.display_name());
+                                let clicked = imgui::MenuItem::new(&label)
+                                    .selected(*mode == current_mode)
+                                    .build(ui);
+                                if clicked {
+                                    log::info!("Mesh debug view: {:?}", mode);
+                                    game_renderer.set_mesh_debug_view_mode(*mode);
+                                }
+                            }
+                        });
+
+                        ui.menu(imgui::im_str!("Render Scale"), true, || {
+                            let current_mode = game_renderer.render_scale_mode();
+                            for (name, mode) in render_scale_menu_items() {
+                                let clicked = imgui::MenuItem::new(&imgui::ImString::new(name))
+                                    .selected(mode == current_mode)
+                                    .build(ui);
+                                if clicked {
+                                    game_renderer.set_render_scale_mode(mode);
+                                }
+                            }
+
+                            ui.separator();
+                            ui.text_disabled(imgui::im_str!(
+                                "Current: {:.0}%",
+                                game_renderer.render_scale() * 100.0
+                            ));
+                        });
+
+                        let lod_debug_view_enabled = game_renderer.mesh_lod_debug_view_enabled();
+                        let clicked = imgui::MenuItem::new(imgui::im_str!("Tint Mesh LODs"))
+                            .shortcut(imgui::im_str!("F8"))
+                            .selected(lod_debug_view_enabled)
+                            .build(ui);
+                        if clicked {
+                            game_renderer.set_mesh_lod_debug_view_enabled(!lod_debug_view_enabled);
+                        }
+
+                        let selection_outline_enabled = game_renderer.selection_outline_enabled();
+                        let clicked = imgui::MenuItem::new(imgui::im_str!("Selection Outline"))
+                            .selected(selection_outline_enabled)
+                            .build(ui);
+                        if clicked {
+                            game_renderer.set_selection_outline_enabled(!selection_outline_enabled);
+                        }
+
+                        ui.separator();
+
+                        let clicked = imgui::MenuItem::new(imgui::im_str!("Profiler"))
+                            .selected(profiler_window.open)
+                            .build(ui);
+                        if clicked {
+                            profiler_window.open = !profiler_window.open;
+                        }
+
+                        let clicked = imgui::MenuItem::new(imgui::im_str!("Render Stats"))
+                            .selected(render_stats.overlay_visible)
+                            .build(ui);
+                        if clicked {
+                            render_stats.overlay_visible = !render_stats.overlay_visible;
+                        }
+                    });
+                });
+            });
+        })
+}
+
+// Shows the previous frame's RenderStatsResource in a small window in the corner of the screen
+#[cfg(feature = "use_imgui")]
+pub fn render_stats_overlay() -> Box<dyn Schedulable> {
+    use minimum::resources::ImguiResource;
+
+    SystemBuilder::new("render_stats_overlay")
+        .write_resource::<ImguiResource>()
+        .read_resource::<RenderStatsResource>()
+        .build(|_, _, (imgui_resource, render_stats_resource), _| {
+            if !render_stats_resource.overlay_visible {
+                return;
+            }
+
+            let stats = &render_stats_resource.previous_frame;
+            imgui_resource.with_ui(|ui| {
+                imgui::Window::new(imgui::im_str!("Render Stats"))
+                    .position([10.0, 30.0], imgui::Condition::FirstUseEver)
+                    .always_auto_resize(true)
+                    .bg_alpha(0.6)
+                    .build(ui, || {
+                        ui.text(imgui::im_str!("Draw calls: {}", stats.draw_calls));
+                        ui.text(imgui::im_str!("Triangles: {}", stats.triangles));
+                        ui.text(imgui::im_str!(
+                            "Descriptor sets allocated: {}",
+                            stats.descriptor_sets_allocated
+                        ));
+                        ui.text(imgui::im_str!(
+                            "Buffer upload: {:.1} KiB",
+                            stats.buffer_bytes_uploaded as f32 / 1024.0
+                        ));
+
+                        ui.separator();
+                        ui.text(imgui::im_str!(
+                            "Frames in flight: {} overlapped, {} max",
+                            stats.overlapped_frames,
+                            stats.max_frames_in_flight
+                        ));
+                        ui.text(imgui::im_str!(
+                            "Waited for render thread: {:.2} ms",
+                            stats.frame_in_flight_wait.as_secs_f32() * 1000.0
+                        ));
+                        ui.text(imgui::im_str!(
+                            "Waited for swapchain image: {:.2} ms",
+                            stats.swapchain_image_wait.as_secs_f32() * 1000.0
+                        ));
+
+                        ui.separator();
+                        ui.text(imgui::im_str!("Frame nodes"));
+                        for (feature_name, count) in &stats.frame_nodes_per_feature {
+                            ui.text(imgui::im_str!("  {}: {}", feature_name, count));
+                        }
+
+                        for view in &stats.views {
+                            ui.separator();
+                            ui.text(imgui::im_str!(
+                                "View '{}': {} static, {} dynamic visible",
+                                view.view_name,
+                                view.static_visible_nodes,
+                                view.dynamic_visible_nodes
+                            ));
+                            for (feature_name, count) in &view.view_nodes_per_feature {
+                                ui.text(imgui::im_str!("  {}: {}", feature_name, count));
+                            }
+                        }
+
+                        ui.separator();
+                        ui.text(imgui::im_str!("Submit nodes"));
+                        for (phase_name, count) in &stats.submit_nodes_per_phase {
+                            ui.text(imgui::im_str!("  {}: {}", phase_name, count));
+                        }
+                    });
+            });
+        })
+}
@@ -1,10 +1,10 @@
 use legion::prelude::{Resources, World, EntityStore};
 use minimum::resources::AssetResource;
-use glam::f32::Vec3;
+use glam::f32::{Vec2, Vec3, Vec4};
 use crate::features::sprite::{SpriteRenderNodeSet, SpriteRenderNode};
 use renderer::visibility::{DynamicVisibilityNodeSet, DynamicAabbVisibilityNode};
 use crate::components::{
-    SpriteComponent, PointLightComponent, SpotLightComponent,
+    SpriteComponent, SpriteSpace, PointLightComponent, SpotLightComponent,
     DirectionalLightComponent,
 };
 use renderer::assets::ImageAsset;
@@ -72,8 +72,16 @@ pub fn populate_test_sprite_entities(
             let sprite_component = SpriteComponent {
                 sprite_handle,
                 visibility_handle,
-                alpha,
-                image: sprite_image.clone(),
+                image: Some(sprite_image.clone()),
+                sprite_sheet: None,
+                frame: 0,
+                space: SpriteSpace::Screen,
+                size: Vec2::new(50.0, 50.0),
+                pivot: Vec2::new(0.5, 0.5),
+                tint: Vec4::new(1.0, 1.0, 1.0, alpha),
+                flip_x: false,
+                flip_y: false,
+                sorting_layer: 0,
             };
 
             let entity = world.insert(
@@ -128,9 +136,10 @@ pub fn populate_test_mesh_entities(
 
             let transform_component = TransformComponent::from_position(position);
             let mesh_component = MeshComponent {
-                mesh_handle,
-                visibility_handle,
+                mesh_handle: Some(mesh_handle),
+                visibility_handle: Some(visibility_handle),
                 mesh: Some(mesh.clone()),
+                is_static: false,
             };
 
             let entity = world.insert((), vec![(transform_component, mesh_component)])[0];
@@ -142,6 +151,57 @@ pub fn populate_test_mesh_entities(
     }
 }
 
+// Spawns a square grid of dynamic meshes so that every one of them goes through extract and
+// prepare each frame. Returns the mesh so the caller can tell when it has loaded
+pub fn populate_benchmark_mesh_entities(
+    resources: &mut Resources,
+    world: &mut World,
+    mesh_count: usize,
+) -> atelier_assets::loader::handle::Handle<MeshAsset> {
+    let mesh = {
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+        begin_load_asset::<MeshAsset>(
+            asset_uuid!("ffc9b240-0a17-4ff4-bb7d-72d13cc6e261"),
+            &asset_resource,
+        )
+    };
+
+    let grid_size = (mesh_count as f32).sqrt().ceil().max(1.0) as usize;
+
+    let mut mesh_render_nodes = resources.get_mut::<MeshRenderNodeSet>().unwrap();
+    let mut dynamic_visibility_node_set = resources.get_mut::<DynamicVisibilityNodeSet>().unwrap();
+
+    for i in 0..mesh_count {
+        let position = Vec3::new(
+            ((i % grid_size) * 3) as f32,
+            ((i / grid_size) * 3) as f32,
+            0.0,
+        );
+
+        mesh_render_nodes.register_mesh_with_handle(|mesh_handle| {
+            let aabb_info = DynamicAabbVisibilityNode {
+                handle: mesh_handle.into(),
+            };
+
+            let visibility_handle = dynamic_visibility_node_set.register_dynamic_aabb(aabb_info);
+
+            let transform_component = TransformComponent::from_position(position);
+            let mesh_component = MeshComponent {
+                mesh_handle: Some(mesh_handle),
+                visibility_handle: Some(visibility_handle),
+                mesh: Some(mesh.clone()),
+                is_static: false,
+            };
+
+            let entity = world.insert((), vec![(transform_component, mesh_component)])[0];
+
+            MeshRenderNode { entity }
+        });
+    }
+
+    mesh
+}
+
 pub fn populate_test_lights(
     resources: &mut Resources,
     world: &mut World,